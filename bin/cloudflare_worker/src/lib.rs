@@ -24,6 +24,11 @@ async fn transform_req(url: &Url, mut req: Request) -> Result<ConductorHttpReque
     }
   }
 
+  // Workers are always behind Cloudflare's edge, which sets the address of the connecting client.
+  let peer_addr = req
+    .headers()
+    .get("cf-connecting-ip")?
+    .and_then(|ip| ip.parse().ok());
   let body = req.bytes().await?;
   let uri = url.to_string();
   let query_string = url.query().unwrap_or_default().to_string();
@@ -35,6 +40,7 @@ async fn transform_req(url: &Url, mut req: Request) -> Result<ConductorHttpReque
     query_string,
     method,
     headers: headers_map,
+    peer_addr,
   })
 }
 
@@ -65,17 +71,22 @@ async fn run_flow(
 
   match conductor_config_str {
     Ok(conductor_config_str) => {
-      let conductor_config = parse_config_contents(
+      let conductor_config = match parse_config_contents(
+        "CONDUCTOR_CONFIG",
         conductor_config_str,
         conductor_config::ConfigFormat::Yaml,
         get_env_value,
-      );
+      ) {
+        Ok(conductor_config) => conductor_config,
+        Err(e) => return Response::error(e.to_string(), 500),
+      };
 
       let logger_config = conductor_config.logger.clone().unwrap_or_default();
-      let logger = conductor_logger::logger_layer::build_logger(
+      let logger = conductor_logger::logger_layer::build_loggers(
         &logger_config.format,
         &logger_config.filter,
         logger_config.print_performance_info,
+        &logger_config.sinks,
       )
       .unwrap_or_else(|e| panic!("failed to build logger: {}", e));
 
@@ -101,9 +112,7 @@ async fn run_flow(
 
               http_response
             }
-            Err(GatewayError::MissingEndpoint(_)) => {
-              Response::error("failed to locate endpoint".to_string(), 404)
-            }
+            Err(GatewayError::MissingEndpoint(_)) => transform_res(gw.not_found_response()),
             Err(e) => Response::error(e.to_string(), 500),
           }
         }
@@ -0,0 +1,189 @@
+use std::{collections::HashSet, path::PathBuf, sync::Arc, time::Duration};
+
+use conductor_config::{
+  is_remote_config, load_and_merge_configs, ConfigContentsCache, STDIN_CONFIG_PATH,
+};
+use conductor_engine::gateway::ConductorGateway;
+use conductor_tracing::minitrace_mgr::MinitraceManager;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tracing::{debug, error, info};
+
+use crate::SharedGateway;
+
+/// Editors usually emit a few events for a single save, so they are collected for a short while before reloading.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watches the config files, and swaps the gateway with one built from the new config when any of them changes.
+///
+/// The configs read from stdin or fetched from a URL are not watched: the reloaded config reuses their contents from the `cache` filled when the gateway started.
+///
+/// The returned watcher must be kept alive for as long as the files should be watched.
+pub fn watch_config_files(
+  config_file_paths: &[PathBuf],
+  cache: ConfigContentsCache,
+  gateway: SharedGateway,
+) -> notify::Result<RecommendedWatcher> {
+  // Only local files can be watched.
+  let watched_files = config_file_paths
+    .iter()
+    .filter(|path| {
+      let path = path.to_string_lossy();
+      path != STDIN_CONFIG_PATH && !is_remote_config(&path)
+    })
+    .map(|path| path.canonicalize())
+    .collect::<Result<HashSet<_>, _>>()?;
+  let (sender, receiver) = unbounded_channel();
+
+  let files = watched_files.clone();
+  let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+    Ok(event) => {
+      let is_change = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+
+      if is_change && event.paths.iter().any(|path| files.contains(path)) {
+        let _ = sender.send(());
+      }
+    }
+    Err(e) => error!("failed to watch config files: {}", e),
+  })?;
+
+  // Directories are watched instead of the files, so files replaced by a rename (like many editors do) are still tracked.
+  let directories = watched_files
+    .iter()
+    .filter_map(|path| path.parent())
+    .collect::<HashSet<_>>();
+
+  for directory in directories {
+    watcher.watch(directory, RecursiveMode::NonRecursive)?;
+  }
+
+  actix_web::rt::spawn(reload_on_change(
+    config_file_paths.to_vec(),
+    cache,
+    gateway,
+    receiver,
+  ));
+
+  Ok(watcher)
+}
+
+async fn reload_on_change(
+  config_file_paths: Vec<PathBuf>,
+  mut cache: ConfigContentsCache,
+  gateway: SharedGateway,
+  mut receiver: UnboundedReceiver<()>,
+) {
+  while receiver.recv().await.is_some() {
+    actix_web::rt::time::sleep(RELOAD_DEBOUNCE).await;
+    while receiver.try_recv().is_ok() {}
+
+    reload(&config_file_paths, &mut cache, &gateway).await;
+  }
+}
+
+async fn reload(
+  config_file_paths: &[PathBuf],
+  cache: &mut ConfigContentsCache,
+  gateway: &SharedGateway,
+) {
+  debug!(
+    "config files changed, reloading: {:?}",
+    config_file_paths
+      .iter()
+      .map(|path| path.display())
+      .collect::<Vec<_>>()
+  );
+
+  let config =
+    match load_and_merge_configs(config_file_paths, cache, |key| std::env::var(key).ok()).await {
+      Ok(config) => config,
+      Err(e) => {
+        error!(
+          "failed to reload config, keeping the previous config: {}",
+          e
+        );
+        return;
+      }
+    };
+
+  // Tracing reporters are only installed on startup, so the manager of the reloaded gateway is not used afterwards.
+  let mut tracing_manager = MinitraceManager::default();
+
+  match ConductorGateway::new(&config, &mut tracing_manager).await {
+    Ok(new_gateway) => {
+      gateway.store(Arc::new(new_gateway));
+      info!("config reloaded");
+    }
+    Err(e) => error!(
+      "failed to initialize gateway with the reloaded config, keeping the previous config: {}",
+      e
+    ),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use arc_swap::ArcSwap;
+
+  use super::*;
+
+  fn config(path: &str) -> String {
+    format!(
+      r#"
+sources:
+  - type: graphql
+    id: upstream
+    config:
+      endpoint: http://localhost:4000/graphql
+endpoints:
+  - path: {}
+    from: upstream
+"#,
+      path
+    )
+  }
+
+  fn base_path(gateway: &SharedGateway) -> String {
+    gateway.load().routes[0].base_path.clone()
+  }
+
+  async fn wait_for_base_path(gateway: &SharedGateway, expected: &str) -> bool {
+    for _ in 0..50 {
+      if base_path(gateway) == expected {
+        return true;
+      }
+
+      actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    false
+  }
+
+  #[actix_web::test]
+  async fn reloads_changed_config() {
+    let dir = std::env::temp_dir().join(format!("conductor-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.yaml");
+    std::fs::write(&path, config("/graphql")).unwrap();
+
+    let mut cache = ConfigContentsCache::default();
+    let initial_config = load_and_merge_configs(&[path.clone()], &mut cache, |_| None)
+      .await
+      .unwrap();
+    let initial_gateway = ConductorGateway::new(&initial_config, &mut MinitraceManager::default())
+      .await
+      .unwrap();
+    let gateway: SharedGateway = Arc::new(ArcSwap::from_pointee(initial_gateway));
+    let _watcher = watch_config_files(&[path.clone()], cache, gateway.clone()).unwrap();
+
+    std::fs::write(&path, config("/v2")).unwrap();
+    assert!(wait_for_base_path(&gateway, "/v2").await);
+
+    // An invalid config is ignored, and the previous config is kept.
+    std::fs::write(&path, "sources: []\nendpoints: 1\n").unwrap();
+    actix_web::rt::time::sleep(RELOAD_DEBOUNCE * 3).await;
+    assert_eq!(base_path(&gateway), "/v2");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -1,31 +1,119 @@
+mod config_watcher;
 mod minitrace_actix;
+mod sse;
+mod tls;
+mod websocket;
 
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use actix_web::{
-  dev::Response,
+  dev::{Decompress, Response},
+  error::PayloadError,
+  guard,
+  http::header::CONTENT_LENGTH,
   middleware::Compat,
   route,
-  web::{self, Bytes},
+  web::{self, Bytes, BytesMut},
   App, HttpRequest, HttpResponse, HttpServer, Responder, Scope,
 };
+use arc_swap::ArcSwap;
 use conductor_common::http::{ConductorHttpRequest, ConductorHttpResponse, HttpHeadersMap};
-use conductor_config::load_config;
+use conductor_config::{load_and_merge_configs, ConfigContentsCache};
 use conductor_engine::gateway::{ConductorGateway, ConductorGatewayRouteData};
 use conductor_tracing::minitrace_mgr::MinitraceManager;
+use futures_util::StreamExt;
 use minitrace::{collector::Config, trace};
 use tracing::{debug, error};
 use tracing_subscriber::{layer::SubscriberExt, registry};
 
-use crate::minitrace_actix::MinitraceTransform;
+use crate::{
+  config_watcher::watch_config_files,
+  minitrace_actix::MinitraceTransform,
+  sse::{accepts_event_stream, event_stream_response},
+  tls::build_tls_config,
+  websocket::{is_websocket_upgrade, websocket_handler},
+};
+
+/// The gateway serving the requests, swapped as a whole when the config is reloaded.
+pub(crate) type SharedGateway = Arc<ArcSwap<ConductorGateway>>;
+
+/// Finds the endpoint serving the path, the endpoint's path is a prefix of all the paths it serves.
+pub(crate) fn find_route(
+  gateway: &ConductorGateway,
+  path: &str,
+) -> Option<Arc<ConductorGatewayRouteData>> {
+  gateway
+    .routes
+    .iter()
+    .find(|route| {
+      path
+        .strip_prefix(route.base_path.trim_end_matches('/'))
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+    .map(|route| route.route_data.clone())
+}
+
+/// The maximum size of the request bodies of the endpoints without a `max_body_size` plugin, the default limit of actix.
+const DEFAULT_BODY_LIMIT: usize = 256 * 1024;
+
+/// Reads the request body, and rejects it with a `413` status code as soon as it exceeds `limit`, before it's buffered.
+///
+/// Like the `Bytes` extractor, compressed bodies are decompressed, and the limit applies to the decompressed body.
+async fn read_body(
+  req: &HttpRequest,
+  payload: web::Payload,
+  limit: usize,
+) -> Result<Bytes, HttpResponse> {
+  let content_length = req
+    .headers()
+    .get(CONTENT_LENGTH)
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.trim().parse::<usize>().ok());
+
+  if content_length.is_some_and(|content_length| content_length > limit) {
+    return Err(HttpResponse::from_error(PayloadError::Overflow));
+  }
+
+  let mut payload = Decompress::from_headers(payload, req.headers());
+  let mut body = BytesMut::new();
+
+  while let Some(chunk) = payload.next().await {
+    let chunk = chunk.map_err(HttpResponse::from_error)?;
+
+    if body.len() + chunk.len() > limit {
+      return Err(HttpResponse::from_error(PayloadError::Overflow));
+    }
+
+    body.extend_from_slice(&chunk);
+  }
+
+  Ok(body.freeze())
+}
 
 pub async fn run_services(config_file_path: &String) -> std::io::Result<()> {
-  let config = load_config(config_file_path, |key| std::env::var(key).ok()).await;
+  run_services_with_config_files(&[PathBuf::from(config_file_path)]).await
+}
+
+/// Runs the gateway with the config merged from all of the specified files, see `load_and_merge_configs`.
+pub async fn run_services_with_config_files(config_file_paths: &[PathBuf]) -> std::io::Result<()> {
+  let mut config_cache = ConfigContentsCache::default();
+  let config = match load_and_merge_configs(config_file_paths, &mut config_cache, |key| {
+    std::env::var(key).ok()
+  })
+  .await
+  {
+    Ok(config) => config,
+    Err(e) => {
+      error!("{}", e);
+      std::process::exit(1);
+    }
+  };
   let logger_config = config.logger.clone().unwrap_or_default();
-  let logger = conductor_logger::logger_layer::build_logger(
+  let logger = conductor_logger::logger_layer::build_loggers(
     &logger_config.format,
     &logger_config.filter,
     logger_config.print_performance_info,
+    &logger_config.sinks,
   )
   .unwrap_or_else(|e| panic!("failed to build logger: {}", e));
   let mut tracing_manager = MinitraceManager::default();
@@ -38,33 +126,49 @@ pub async fn run_services(config_file_path: &String) -> std::io::Result<()> {
       let tracing_reporter = tracing_manager.build_root_reporter();
       minitrace::set_reporter(tracing_reporter, Config::default());
 
-      let gateway = Arc::new(gw);
-      let http_server = HttpServer::new(move || {
-        let mut router = App::new();
-
-        for conductor_route in gateway.routes.iter() {
-          let child_router = Scope::new(conductor_route.base_path.as_str())
-            .wrap(Compat::new(MinitraceTransform::new()))
-            .app_data(web::Data::new(conductor_route.route_data.clone()))
-            .service(Scope::new("").default_service(
-              web::route().to(handler), // handle all requests with this handler
-            ));
+      let gateway: SharedGateway = Arc::new(ArcSwap::from_pointee(gw));
+      let server_config = config.server.clone().unwrap_or_default();
 
-          router = router.service(child_router)
-        }
+      let _config_watcher = match server_config.watch {
+        true => Some(
+          watch_config_files(config_file_paths, config_cache, gateway.clone())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?,
+        ),
+        false => None,
+      };
 
-        router.service(health_handler)
+      let http_server = HttpServer::new(move || {
+        App::new()
+          .app_data(web::Data::new(gateway.clone()))
+          .service(health_handler)
+          .service(
+            web::resource("/{path:.*}")
+              .guard(guard::fn_guard(|ctx| is_websocket_upgrade(ctx.head())))
+              .to(websocket_handler),
+          )
+          .service(
+            Scope::new("")
+              .wrap(Compat::new(MinitraceTransform::new()))
+              .default_service(
+                web::route().to(handler), // handle all requests with this handler
+              ),
+          )
       });
 
-      let server_config = config.server.clone().unwrap_or_default();
-
-      let server_address = format!("{}:{}", server_config.host, server_config.port);
+      let server_address = format!("{}:{}", server_config.host(), server_config.port());
       debug!("server is trying to listen on {:?}", server_address);
 
-      let server_instance = http_server
-        .bind((server_config.host, server_config.port))?
-        .run()
-        .await;
+      let http_server = match &server_config.tls {
+        Some(tls_config) => {
+          let tls_config = build_tls_config(tls_config)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+          http_server.bind_rustls_0_22((server_config.host(), server_config.port()), tls_config)?
+        }
+        None => http_server.bind((server_config.host(), server_config.port()))?,
+      };
+
+      let server_instance = http_server.run().await;
 
       tracing_manager.shutdown().await;
 
@@ -87,8 +191,10 @@ async fn health_handler() -> impl Responder {
 fn transform_req(req: HttpRequest, body: Bytes) -> ConductorHttpRequest {
   let mut headers_map = HttpHeadersMap::new();
 
+  // Repeated headers are kept, so plugins can decide how to handle multiple values.
+  // Note that `HeaderMap::get` returns the first value of a repeated header (it used to be the last one, when only the last value was kept), plugins that care about the other values must use `get_all`.
   for (key, value) in req.headers().into_iter() {
-    headers_map.insert(key, value.clone());
+    headers_map.append(key, value.clone());
   }
 
   let conductor_request = ConductorHttpRequest {
@@ -97,6 +203,7 @@ fn transform_req(req: HttpRequest, body: Bytes) -> ConductorHttpRequest {
     method: req.method().clone(),
     uri: req.uri().to_string(),
     query_string: req.query_string().to_string(),
+    peer_addr: req.peer_addr().map(|addr| addr.ip()),
   };
 
   conductor_request
@@ -106,8 +213,9 @@ fn transform_req(req: HttpRequest, body: Bytes) -> ConductorHttpRequest {
 fn transform_res(conductor_response: ConductorHttpResponse) -> HttpResponse {
   let mut response = HttpResponse::build(conductor_response.status);
 
+  // Repeated headers (for example, `Set-Cookie` or `Vary`) are sent with all of their values.
   for (key, value) in conductor_response.headers.iter() {
-    response.insert_header((key, value));
+    response.append_header((key, value));
   }
 
   response.body(conductor_response.body)
@@ -115,12 +223,206 @@ fn transform_res(conductor_response: ConductorHttpResponse) -> HttpResponse {
 
 async fn handler(
   req: HttpRequest,
-  body: Bytes,
-  route_data: web::Data<Arc<ConductorGatewayRouteData>>,
+  payload: web::Payload,
+  gateway: web::Data<SharedGateway>,
 ) -> impl Responder {
+  let gateway = gateway.load_full();
+  let route_data = match find_route(&gateway, req.path()) {
+    Some(route_data) => route_data,
+    None => return transform_res(gateway.not_found_response()),
+  };
+
+  // The limit is read from the endpoint serving the request, so it follows the reloaded configs.
+  let body_limit = route_data.max_body_bytes.unwrap_or(DEFAULT_BODY_LIMIT);
+  let body = match read_body(&req, payload, body_limit).await {
+    Ok(body) => body,
+    Err(response) => return response,
+  };
+
+  if accepts_event_stream(&req) {
+    let conductor_request = transform_req(req, body);
+
+    return match ConductorGateway::subscribe(conductor_request, &route_data).await {
+      Ok(results) => event_stream_response(results),
+      Err(response) => transform_res(response),
+    };
+  }
+
   let conductor_request = transform_req(req, body);
-  let conductor_response: ConductorHttpResponse =
-    ConductorGateway::execute(conductor_request, &route_data).await;
+  let conductor_response = ConductorGateway::execute(conductor_request, &route_data).await;
 
   transform_res(conductor_response)
 }
+
+#[cfg(test)]
+mod tests {
+  use actix_web::{
+    http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, ORIGIN, VARY},
+    test::{call_service, init_service, TestRequest},
+  };
+  use conductor_common::http::StatusCode;
+  use conductor_config::ConductorConfig;
+
+  use super::*;
+
+  async fn test_gateway(config: serde_json::Value) -> SharedGateway {
+    let config: ConductorConfig = serde_json::from_value(config).unwrap();
+    let gateway = ConductorGateway::new(&config, &mut MinitraceManager::default())
+      .await
+      .unwrap();
+
+    Arc::new(ArcSwap::from_pointee(gateway))
+  }
+
+  fn body_size_config(max_body_bytes: usize) -> serde_json::Value {
+    serde_json::json!({
+      "sources": [{ "type": "graphql", "id": "upstream", "config": { "endpoint": "http://localhost:4000/graphql" } }],
+      "endpoints": [
+        {
+          "path": "/limited",
+          "from": "upstream",
+          "plugins": [{ "type": "max_body_size", "config": { "max_body_bytes": max_body_bytes } }],
+        },
+        { "path": "/graphql", "from": "upstream" },
+      ],
+    })
+  }
+
+  #[actix_web::test]
+  async fn limits_bodies_by_endpoint() {
+    let gateway = test_gateway(body_size_config(10)).await;
+    let app = init_service(
+      App::new()
+        .app_data(web::Data::new(gateway.clone()))
+        .default_service(web::route().to(handler)),
+    )
+    .await;
+    let send = |path: &str, body: Vec<u8>| {
+      call_service(
+        &app,
+        TestRequest::post().uri(path).set_payload(body).to_request(),
+      )
+    };
+
+    assert_eq!(
+      send("/limited", vec![b' '; 20]).await.status(),
+      StatusCode::PAYLOAD_TOO_LARGE
+    );
+    // Endpoints without the plugin keep the default limit.
+    assert_ne!(
+      send("/graphql", vec![b' '; 20]).await.status(),
+      StatusCode::PAYLOAD_TOO_LARGE
+    );
+    assert_eq!(
+      send("/graphql", vec![b' '; DEFAULT_BODY_LIMIT + 1])
+        .await
+        .status(),
+      StatusCode::PAYLOAD_TOO_LARGE
+    );
+
+    // A reloaded config applies its limits to the next requests.
+    gateway.store(test_gateway(body_size_config(100)).await.load_full());
+    assert_ne!(
+      send("/limited", vec![b' '; 20]).await.status(),
+      StatusCode::PAYLOAD_TOO_LARGE
+    );
+  }
+
+  #[test]
+  fn single_value_headers() {
+    let request = transform_req(
+      TestRequest::post()
+        .uri("/graphql")
+        .insert_header((CONTENT_TYPE, "application/json"))
+        .insert_header(("authorization", "Bearer token"))
+        .to_http_request(),
+      Bytes::new(),
+    );
+
+    assert_eq!(request.headers.len(), 2);
+    assert_eq!(
+      request.headers.get(CONTENT_TYPE).unwrap(),
+      "application/json"
+    );
+    assert_eq!(
+      request.headers.get("authorization").unwrap(),
+      "Bearer token"
+    );
+  }
+
+  #[test]
+  fn repeated_headers() {
+    let request = transform_req(
+      TestRequest::post()
+        .uri("/graphql")
+        .append_header(("x-tenant", "first"))
+        .append_header(("x-tenant", "second"))
+        .to_http_request(),
+      Bytes::new(),
+    );
+
+    assert_eq!(request.headers.get("x-tenant").unwrap(), "first");
+    assert_eq!(
+      request
+        .headers
+        .get_all("x-tenant")
+        .iter()
+        .collect::<Vec<_>>(),
+      vec!["first", "second"]
+    );
+  }
+
+  #[actix_web::test]
+  async fn handler_sends_repeated_response_headers() {
+    let gateway = test_gateway(serde_json::json!({
+      "sources": [{ "type": "graphql", "id": "upstream", "config": { "endpoint": "http://localhost:4000/graphql" } }],
+      "endpoints": [{ "path": "/graphql", "from": "upstream" }],
+      "plugins": [
+        { "type": "cors", "config": { "allowed_origin": "reflect" } },
+        { "type": "compression", "config": { "min_size": 0 } },
+      ],
+    }))
+    .await;
+    let app = init_service(
+      App::new()
+        .app_data(web::Data::new(gateway))
+        .default_service(web::route().to(handler)),
+    )
+    .await;
+
+    // The invalid body is rejected by the gateway, the error response still goes through the plugins.
+    let response = call_service(
+      &app,
+      TestRequest::post()
+        .uri("/graphql")
+        .insert_header((ORIGIN, "https://example.com"))
+        .insert_header((ACCEPT_ENCODING, "gzip"))
+        .set_payload("{")
+        .to_request(),
+    )
+    .await;
+
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+    let mut vary = response.headers().get_all(VARY).collect::<Vec<_>>();
+    vary.sort_by_key(|value| value.as_bytes().to_ascii_lowercase());
+    assert_eq!(vary, vec!["accept-encoding", "Origin"]);
+  }
+
+  #[test]
+  fn repeated_response_headers() {
+    let mut headers = HttpHeadersMap::new();
+    headers.append("set-cookie", "first=1".parse().unwrap());
+    headers.append("set-cookie", "second=2".parse().unwrap());
+
+    let response = transform_res(ConductorHttpResponse {
+      body: Bytes::new(),
+      status: StatusCode::OK,
+      headers,
+    });
+
+    assert_eq!(
+      response.headers().get_all("set-cookie").collect::<Vec<_>>(),
+      vec!["first=1", "second=2"]
+    );
+  }
+}
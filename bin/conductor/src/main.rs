@@ -1,4 +1,6 @@
-use conductor::run_services;
+use std::path::PathBuf;
+
+use conductor::run_services_with_config_files;
 use conductor_config::LoggerConfig;
 use tracing::subscriber::set_global_default;
 use tracing_subscriber::layer::SubscriberExt;
@@ -15,9 +17,14 @@ async fn main() -> std::io::Result<()> {
   set_global_default(tracing_subscriber::registry().with(global_logger))
     .expect("failed to set global default logger");
 
-  let config_file_path = std::env::args()
-    .nth(1)
-    .unwrap_or("./config.json".to_string());
+  let mut config_file_paths = std::env::args()
+    .skip(1)
+    .map(PathBuf::from)
+    .collect::<Vec<_>>();
+
+  if config_file_paths.is_empty() {
+    config_file_paths.push(PathBuf::from("./config.json"));
+  }
 
-  run_services(&config_file_path).await
+  run_services_with_config_files(&config_file_paths).await
 }
@@ -16,6 +16,8 @@ use std::{
 };
 use ulid::Ulid;
 
+use crate::{find_route, SharedGateway};
+
 pub struct MinitraceTransform;
 
 impl MinitraceTransform {
@@ -41,12 +43,19 @@ where
   }
 }
 
-#[inline]
-fn build_request_root_span(req: &ServiceRequest) -> Span {
-  let endpoint_data = req
-    .app_data::<web::Data<Arc<ConductorGatewayRouteData>>>()
-    .expect("endpoint data not found, failed to setup tracing");
+fn endpoint_data(req: &ServiceRequest) -> Option<Arc<ConductorGatewayRouteData>> {
+  let gateway = req
+    .app_data::<web::Data<SharedGateway>>()
+    .expect("gateway not found, failed to setup tracing");
 
+  find_route(&gateway.load(), req.path())
+}
+
+#[inline]
+fn build_request_root_span(
+  req: &ServiceRequest,
+  endpoint_data: &ConductorGatewayRouteData,
+) -> Span {
   let span_name = format!("HTTP {} {}", req.method(), req.path());
   let mut properties: Vec<(&str, String)> = build_request_properties(req);
   properties.push((CONDUCTOR_ENDPOINT, endpoint_data.endpoint.clone()));
@@ -206,7 +215,11 @@ where
   forward_ready!(service);
 
   fn call(&self, req: ServiceRequest) -> Self::Future {
-    let root_span = build_request_root_span(&req);
+    // Requests that don't match any endpoint are not traced.
+    let root_span = match endpoint_data(&req) {
+      Some(endpoint_data) => build_request_root_span(&req, &endpoint_data),
+      None => return Box::pin(self.service.call(req)),
+    };
     let fut = self.service.call(req);
 
     Box::pin(async move {
@@ -0,0 +1,97 @@
+use std::convert::Infallible;
+
+use actix_web::{
+  http::header::{ACCEPT, CACHE_CONTROL, CONTENT_TYPE},
+  web::Bytes,
+  HttpRequest, HttpResponse,
+};
+use conductor_common::source::GraphQLResponseStream;
+use futures_util::{future::ready, stream, StreamExt};
+
+static TEXT_EVENT_STREAM: &str = "text/event-stream";
+static COMPLETE_EVENT: &str = "event: complete\ndata:\n\n";
+
+/// Returns `true` when the client lists `text/event-stream` in the `Accept` header.
+pub(crate) fn accepts_event_stream(req: &HttpRequest) -> bool {
+  req
+    .headers()
+    .get_all(ACCEPT)
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','))
+    .filter_map(|media_range| media_range.split(';').next())
+    .any(|media_type| media_type.trim().eq_ignore_ascii_case(TEXT_EVENT_STREAM))
+}
+
+fn next_event(data: &[u8]) -> Bytes {
+  let mut event = Vec::with_capacity(data.len() + 20);
+  event.extend_from_slice(b"event: next\ndata: ");
+  event.extend_from_slice(data);
+  event.extend_from_slice(b"\n\n");
+
+  event.into()
+}
+
+/// Streams the results in the "distinct connections mode" of the GraphQL over SSE protocol: a `next` event for every result, followed by a `complete` event.
+///
+/// See https://github.com/enisdenjo/graphql-sse/blob/master/PROTOCOL.md
+pub(crate) fn event_stream_response(results: GraphQLResponseStream) -> HttpResponse {
+  let events = results
+    .map(|result| next_event(&Bytes::from(result)))
+    .chain(stream::once(ready(Bytes::from_static(
+      COMPLETE_EVENT.as_bytes(),
+    ))))
+    .map(Ok::<_, Infallible>);
+
+  HttpResponse::Ok()
+    .insert_header((CONTENT_TYPE, TEXT_EVENT_STREAM))
+    .insert_header((CACHE_CONTROL, "no-cache"))
+    .streaming(events)
+}
+
+#[cfg(test)]
+mod tests {
+  use actix_web::{body::to_bytes, test::TestRequest};
+  use conductor_common::graphql::GraphQLResponse;
+
+  use super::*;
+
+  #[test]
+  fn detects_event_stream_requests() {
+    assert!(accepts_event_stream(
+      &TestRequest::default()
+        .insert_header((ACCEPT, "application/json, text/event-stream;q=0.9"))
+        .to_http_request()
+    ));
+    assert!(!accepts_event_stream(
+      &TestRequest::default()
+        .insert_header((ACCEPT, "application/graphql-response+json"))
+        .to_http_request()
+    ));
+    assert!(!accepts_event_stream(
+      &TestRequest::default().to_http_request()
+    ));
+  }
+
+  #[actix_web::test]
+  async fn streams_events() {
+    let results = stream::iter(vec![
+      GraphQLResponse::new_error("first"),
+      GraphQLResponse::new_error("second"),
+    ])
+    .boxed();
+    let response = event_stream_response(results);
+
+    assert_eq!(
+      response.headers().get(CONTENT_TYPE).unwrap(),
+      "text/event-stream"
+    );
+    assert_eq!(
+      to_bytes(response.into_body()).await.unwrap(),
+      concat!(
+        "event: next\ndata: {\"errors\":[{\"message\":\"first\"}]}\n\n",
+        "event: next\ndata: {\"errors\":[{\"message\":\"second\"}]}\n\n",
+        "event: complete\ndata:\n\n"
+      )
+    );
+  }
+}
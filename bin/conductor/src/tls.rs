@@ -0,0 +1,169 @@
+use conductor_config::{ServerTlsConfig, TlsVersion};
+use rustls::{version, ServerConfig, SupportedProtocolVersion};
+
+#[derive(thiserror::Error, Debug)]
+pub enum TlsConfigError {
+  #[error("failed to parse certificate chain from \"{0}\": {1}")]
+  InvalidCertificate(String, std::io::Error),
+  #[error("no certificates found in \"{0}\"")]
+  MissingCertificate(String),
+  #[error("failed to parse private key from \"{0}\": {1}")]
+  InvalidPrivateKey(String, std::io::Error),
+  #[error("no private key found in \"{0}\"")]
+  MissingPrivateKey(String),
+  #[error("invalid tls configuration: {0}")]
+  Rustls(#[from] rustls::Error),
+}
+
+/// rustls does not implement TLS 1.0 and TLS 1.1, so these are always rejected.
+fn protocol_versions(min_tls_version: TlsVersion) -> &'static [&'static SupportedProtocolVersion] {
+  match min_tls_version {
+    TlsVersion::Tls12 => &[&version::TLS13, &version::TLS12],
+    TlsVersion::Tls13 => &[&version::TLS13],
+  }
+}
+
+pub fn build_tls_config(config: &ServerTlsConfig) -> Result<ServerConfig, TlsConfigError> {
+  let certs = rustls_pemfile::certs(&mut config.cert.contents.as_bytes())
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| TlsConfigError::InvalidCertificate(config.cert.path.clone(), e))?;
+
+  if certs.is_empty() {
+    return Err(TlsConfigError::MissingCertificate(config.cert.path.clone()));
+  }
+
+  let key = rustls_pemfile::private_key(&mut config.key.contents.as_bytes())
+    .map_err(|e| TlsConfigError::InvalidPrivateKey(config.key.path.clone(), e))?
+    .ok_or_else(|| TlsConfigError::MissingPrivateKey(config.key.path.clone()))?;
+
+  let tls_config =
+    ServerConfig::builder_with_protocol_versions(protocol_versions(config.min_tls_version))
+      .with_no_client_auth()
+      .with_single_cert(certs, key)?;
+
+  Ok(tls_config)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use conductor_common::serde_utils::LocalFileReference;
+  use rustls::{ClientConfig, ClientConnection, ProtocolVersion, RootCertStore, ServerConnection};
+
+  use super::*;
+
+  struct TestCertificate {
+    cert_pem: String,
+    key_pem: String,
+  }
+
+  impl TestCertificate {
+    fn generate() -> Self {
+      let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+      Self {
+        cert_pem: cert.serialize_pem().unwrap(),
+        key_pem: cert.serialize_private_key_pem(),
+      }
+    }
+
+    fn server_config(&self, min_tls_version: TlsVersion) -> ServerTlsConfig {
+      ServerTlsConfig {
+        cert: LocalFileReference {
+          path: "cert.pem".to_string(),
+          contents: self.cert_pem.clone(),
+        },
+        key: LocalFileReference {
+          path: "key.pem".to_string(),
+          contents: self.key_pem.clone(),
+        },
+        min_tls_version,
+      }
+    }
+
+    fn client_config(&self, versions: &[&'static SupportedProtocolVersion]) -> ClientConfig {
+      let mut roots = RootCertStore::empty();
+
+      for cert in rustls_pemfile::certs(&mut self.cert_pem.as_bytes()) {
+        roots.add(cert.unwrap()).unwrap();
+      }
+
+      ClientConfig::builder_with_protocol_versions(versions)
+        .with_root_certificates(roots)
+        .with_no_client_auth()
+    }
+  }
+
+  /// Runs an in-memory handshake and returns the negotiated protocol version.
+  fn handshake(
+    server_config: ServerConfig,
+    client_config: ClientConfig,
+  ) -> Result<ProtocolVersion, rustls::Error> {
+    let mut server = ServerConnection::new(Arc::new(server_config))?;
+    let mut client =
+      ClientConnection::new(Arc::new(client_config), "localhost".try_into().unwrap())?;
+
+    // A full handshake takes a few round trips, a stuck handshake is reported as a failure.
+    for _ in 0..10 {
+      if !client.is_handshaking() && !server.is_handshaking() {
+        return Ok(server.protocol_version().unwrap());
+      }
+
+      let mut buffer = Vec::new();
+      client.write_tls(&mut buffer).unwrap();
+      server.read_tls(&mut buffer.as_slice()).unwrap();
+      server.process_new_packets()?;
+
+      let mut buffer = Vec::new();
+      server.write_tls(&mut buffer).unwrap();
+      client.read_tls(&mut buffer.as_slice()).unwrap();
+      client.process_new_packets()?;
+    }
+
+    Err(rustls::Error::General(
+      "handshake did not complete".to_string(),
+    ))
+  }
+
+  #[test]
+  fn tls12_handshake_is_accepted_by_default() {
+    let certificate = TestCertificate::generate();
+    let server_config =
+      build_tls_config(&certificate.server_config(TlsVersion::default())).unwrap();
+    let client_config = certificate.client_config(&[&version::TLS12]);
+
+    assert_eq!(
+      handshake(server_config, client_config).unwrap(),
+      ProtocolVersion::TLSv1_2
+    );
+  }
+
+  #[test]
+  fn tls12_handshake_is_rejected_with_min_tls13() {
+    let certificate = TestCertificate::generate();
+    let server_config = build_tls_config(&certificate.server_config(TlsVersion::Tls13)).unwrap();
+
+    assert!(handshake(
+      server_config.clone(),
+      certificate.client_config(&[&version::TLS12])
+    )
+    .is_err());
+    assert_eq!(
+      handshake(server_config, certificate.client_config(&[&version::TLS13])).unwrap(),
+      ProtocolVersion::TLSv1_3
+    );
+  }
+
+  #[test]
+  fn missing_private_key() {
+    let certificate = TestCertificate::generate();
+    let mut config = certificate.server_config(TlsVersion::Tls12);
+    config.key.contents = String::new();
+
+    assert!(matches!(
+      build_tls_config(&config),
+      Err(TlsConfigError::MissingPrivateKey(_))
+    ));
+  }
+}
@@ -0,0 +1,377 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use actix_web::{
+  dev::RequestHead,
+  http::header::{HeaderValue as ActixHeaderValue, SEC_WEBSOCKET_PROTOCOL, UPGRADE},
+  web::{self, Bytes},
+  HttpRequest, HttpResponse,
+};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use conductor_common::{
+  graphql::{GraphQLError, GraphQLRequest, GraphQLResponse},
+  graphql_ws::{close_code, GraphQLWsMessage, GRAPHQL_TRANSPORT_WS_PROTOCOL},
+  http::{
+    ConductorHttpRequest, ConductorHttpResponse, HeaderName, HeaderValue, Method, CONTENT_TYPE,
+  },
+};
+use conductor_engine::gateway::{ConductorGateway, ConductorGatewayRouteData};
+use futures_util::{
+  future::{AbortHandle, Abortable},
+  StreamExt,
+};
+use serde_json::{Map, Value};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tracing::debug;
+
+use crate::{find_route, transform_req, transform_res, SharedGateway};
+
+/// How long a client has to send `connection_init` after opening the connection.
+static CONNECTION_INIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) fn is_websocket_upgrade(head: &RequestHead) -> bool {
+  head
+    .headers()
+    .get(UPGRADE)
+    .and_then(|value| value.to_str().ok())
+    .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+fn requests_graphql_transport_ws(req: &HttpRequest) -> bool {
+  req
+    .headers()
+    .get_all(SEC_WEBSOCKET_PROTOCOL)
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','))
+    .any(|protocol| protocol.trim() == GRAPHQL_TRANSPORT_WS_PROTOCOL)
+}
+
+/// The string values of the `connection_init` payload are set as headers of the requests of the connection, so plugins (like `jwt_auth`) can handle them as they handle HTTP requests.
+///
+/// The requests are handled like POST requests, so plugins that handle GET requests (like `http_get` and `graphiql`) don't intercept them.
+fn connection_request(
+  mut request: ConductorHttpRequest,
+  payload: Option<Map<String, Value>>,
+) -> ConductorHttpRequest {
+  request.method = Method::POST;
+
+  for (key, value) in payload.into_iter().flatten() {
+    let header = match value {
+      Value::String(value) => HeaderName::from_bytes(key.as_bytes())
+        .ok()
+        .zip(HeaderValue::from_str(&value).ok()),
+      _ => None,
+    };
+
+    match header {
+      Some((name, value)) => {
+        request.headers.insert(name, value);
+      }
+      None => debug!("ignoring connection parameter \"{}\"", key),
+    }
+  }
+
+  request
+}
+
+fn subscription_request(
+  connection_request: &ConductorHttpRequest,
+  payload: GraphQLRequest,
+) -> ConductorHttpRequest {
+  let mut request = connection_request.clone();
+  request.body = payload.into();
+  request
+    .headers
+    .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+  request
+}
+
+/// The errors of a rejected operation, the body of the response is not always a GraphQL response (for example, when set by a plugin).
+fn response_errors(response: &ConductorHttpResponse) -> Vec<GraphQLError> {
+  serde_json::from_slice::<GraphQLResponse>(&response.body)
+    .ok()
+    .and_then(|response| response.errors)
+    .unwrap_or_else(|| {
+      vec![GraphQLError::new(
+        response
+          .status
+          .canonical_reason()
+          .unwrap_or("operation rejected"),
+      )]
+    })
+}
+
+fn close_reason(code: u16, description: impl Into<String>) -> CloseReason {
+  CloseReason {
+    code: CloseCode::Other(code),
+    description: Some(description.into()),
+  }
+}
+
+/// Upgrades the request to a WebSocket connection that speaks the `graphql-transport-ws` protocol.
+pub(crate) async fn websocket_handler(
+  req: HttpRequest,
+  body: web::Payload,
+  gateway: web::Data<SharedGateway>,
+) -> Result<HttpResponse, actix_web::Error> {
+  let gateway = gateway.load_full();
+
+  let route_data = match find_route(&gateway, req.path()) {
+    Some(route_data) => route_data,
+    None => return Ok(transform_res(gateway.not_found_response())),
+  };
+
+  if !requests_graphql_transport_ws(&req) {
+    return Ok(HttpResponse::BadRequest().body(format!(
+      "WebSocket connections must use the \"{}\" subprotocol",
+      GRAPHQL_TRANSPORT_WS_PROTOCOL
+    )));
+  }
+
+  let (mut response, session, messages) = actix_ws::handle(&req, body)?;
+  response.headers_mut().insert(
+    SEC_WEBSOCKET_PROTOCOL,
+    ActixHeaderValue::from_static(GRAPHQL_TRANSPORT_WS_PROTOCOL),
+  );
+
+  let request = transform_req(req, Bytes::new());
+  actix_web::rt::spawn(serve_connection(request, route_data, session, messages));
+
+  Ok(response)
+}
+
+async fn serve_connection(
+  request: ConductorHttpRequest,
+  route_data: Arc<ConductorGatewayRouteData>,
+  mut session: Session,
+  mut messages: MessageStream,
+) {
+  let (completed_sender, mut completed) = unbounded_channel::<String>();
+  let mut subscriptions: HashMap<String, AbortHandle> = HashMap::new();
+  // The request of the operations, set once the connection is acknowledged.
+  let mut accepted_request: Option<ConductorHttpRequest> = None;
+  let init_timeout = tokio::time::sleep(CONNECTION_INIT_TIMEOUT);
+  tokio::pin!(init_timeout);
+
+  let reason = loop {
+    let message = tokio::select! {
+      _ = &mut init_timeout, if accepted_request.is_none() => {
+        break Some(close_reason(
+          close_code::CONNECTION_INITIALISATION_TIMEOUT,
+          "Connection initialisation timeout",
+        ));
+      }
+      Some(id) = completed.recv() => {
+        subscriptions.remove(&id);
+        continue;
+      }
+      message = messages.next() => message,
+    };
+
+    let text = match message {
+      Some(Ok(Message::Text(text))) => text,
+      Some(Ok(Message::Ping(bytes))) => {
+        let _ = session.pong(&bytes).await;
+        continue;
+      }
+      Some(Ok(Message::Close(reason))) => break reason,
+      Some(Ok(_)) => continue,
+      Some(Err(e)) => {
+        debug!("websocket protocol error: {}", e);
+        break None;
+      }
+      None => break None,
+    };
+
+    let message = match serde_json::from_str::<GraphQLWsMessage>(&text) {
+      Ok(message) => message,
+      Err(e) => {
+        break Some(close_reason(
+          close_code::BAD_REQUEST,
+          format!("Invalid message received: {}", e),
+        ))
+      }
+    };
+
+    match message {
+      GraphQLWsMessage::ConnectionInit { payload } => {
+        if accepted_request.is_some() {
+          break Some(close_reason(
+            close_code::TOO_MANY_INITIALISATION_REQUESTS,
+            "Too many initialisation requests",
+          ));
+        }
+
+        let connection_request = connection_request(request.clone(), payload);
+
+        if let Err(response) =
+          ConductorGateway::accept_connection(connection_request.clone(), &route_data).await
+        {
+          debug!(
+            "websocket connection rejected with status {}",
+            response.status
+          );
+
+          break Some(close_reason(close_code::FORBIDDEN, "Forbidden"));
+        }
+
+        accepted_request = Some(connection_request);
+
+        if session
+          .text(GraphQLWsMessage::ConnectionAck { payload: None }.to_text())
+          .await
+          .is_err()
+        {
+          break None;
+        }
+      }
+      GraphQLWsMessage::Ping { .. } => {
+        if session
+          .text(GraphQLWsMessage::Pong { payload: None }.to_text())
+          .await
+          .is_err()
+        {
+          break None;
+        }
+      }
+      GraphQLWsMessage::Pong { .. } => {}
+      GraphQLWsMessage::Subscribe { id, payload } => {
+        let connection_request = match &accepted_request {
+          Some(connection_request) => connection_request,
+          None => break Some(close_reason(close_code::UNAUTHORIZED, "Unauthorized")),
+        };
+
+        if subscriptions.contains_key(&id) {
+          break Some(close_reason(
+            close_code::SUBSCRIBER_ALREADY_EXISTS,
+            format!("Subscriber for {} already exists", id),
+          ));
+        }
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        subscriptions.insert(id.clone(), abort_handle);
+
+        actix_web::rt::spawn(Abortable::new(
+          forward_results(
+            id,
+            subscription_request(connection_request, payload),
+            route_data.clone(),
+            session.clone(),
+            completed_sender.clone(),
+          ),
+          abort_registration,
+        ));
+      }
+      // The client is not notified when it completes a subscription.
+      GraphQLWsMessage::Complete { id } => {
+        if let Some(abort_handle) = subscriptions.remove(&id) {
+          abort_handle.abort();
+        }
+      }
+      message => {
+        break Some(close_reason(
+          close_code::BAD_REQUEST,
+          format!("Unexpected message received: {:?}", message),
+        ))
+      }
+    }
+  };
+
+  for abort_handle in subscriptions.values() {
+    abort_handle.abort();
+  }
+
+  let _ = session.close(reason).await;
+}
+
+async fn forward_results(
+  id: String,
+  request: ConductorHttpRequest,
+  route_data: Arc<ConductorGatewayRouteData>,
+  mut session: Session,
+  completed: UnboundedSender<String>,
+) {
+  let last_message = match ConductorGateway::subscribe(request, &route_data).await {
+    Ok(mut results) => {
+      while let Some(payload) = results.next().await {
+        let next = GraphQLWsMessage::Next {
+          id: id.clone(),
+          payload,
+        };
+
+        if session.text(next.to_text()).await.is_err() {
+          return;
+        }
+      }
+
+      GraphQLWsMessage::Complete { id: id.clone() }
+    }
+    Err(response) => GraphQLWsMessage::Error {
+      id: id.clone(),
+      payload: response_errors(&response),
+    },
+  };
+
+  let _ = session.text(last_message.to_text()).await;
+  let _ = completed.send(id);
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::http::StatusCode;
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn connection_params_are_set_as_headers() {
+    let mut request = ConductorHttpRequest {
+      body: Bytes::new(),
+      headers: Default::default(),
+      method: Method::GET,
+      uri: "/graphql".to_string(),
+      query_string: "".to_string(),
+      peer_addr: None,
+    };
+    request
+      .headers
+      .insert("authorization", HeaderValue::from_static("Bearer old"));
+
+    let request = connection_request(
+      request,
+      json!({
+        "Authorization": "Bearer token",
+        "x-tenant": "acme",
+        "retries": 3,
+        "invalid header": "value"
+      })
+      .as_object()
+      .cloned(),
+    );
+
+    assert_eq!(request.method, Method::POST);
+    assert_eq!(request.headers.len(), 2);
+    assert_eq!(
+      request.headers.get("authorization").unwrap(),
+      "Bearer token"
+    );
+    assert_eq!(request.headers.get("x-tenant").unwrap(), "acme");
+  }
+
+  #[test]
+  fn errors_of_rejected_operations() {
+    let errors = response_errors(
+      &GraphQLResponse::new_error("unauthenticated request")
+        .into_with_status_code(StatusCode::UNAUTHORIZED),
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "unauthenticated request");
+
+    let errors = response_errors(&ConductorHttpResponse {
+      body: "Too Many Requests".into(),
+      status: StatusCode::TOO_MANY_REQUESTS,
+      headers: Default::default(),
+    });
+    assert_eq!(errors[0].message, "Too Many Requests");
+  }
+}
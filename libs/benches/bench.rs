@@ -80,18 +80,22 @@ fn criterion_benchmark(c: &mut Criterion) {
       sources: vec![SourceDefinition::GraphQL {
         id: String::from("s"),
         config: GraphQLSourceConfig {
-          endpoint: String::from("http://localhost:4444/graphql"),
-          schema_awareness: None,
+          endpoint: String::from("http://localhost:4444/graphql").into(),
+          ..Default::default()
         },
       }],
       endpoints: vec![EndpointDefinition {
         from: String::from("s"),
         path: String::from("/"),
         plugins: None,
+        require_auth: None,
+        default_response_content_type: None,
+        max_batch_size: None,
       }],
       logger: None,
       server: None,
       plugins: None,
+      not_found: None,
     };
 
     let mut tracing_mgr = MinitraceManager::default();
@@ -113,6 +117,7 @@ fn criterion_benchmark(c: &mut Criterion) {
         })
         .to_string()
         .into(),
+        peer_addr: None,
       };
       let response = rt.block_on(ConductorGateway::execute(request, route_data));
 
@@ -174,6 +179,45 @@ fn criterion_benchmark(c: &mut Criterion) {
   );
 }
 
+/// Compares sending upstream requests with a new client every time, which opens a new connection for every request, with a single client that reuses pooled connections.
+fn upstream_client_benchmark(c: &mut Criterion) {
+  start_server();
+  thread::sleep(std::time::Duration::from_secs(5));
+
+  let rt = Runtime::new().unwrap();
+  let body = json!({ "query": "query { __typename }" }).to_string();
+  let send = |client: &reqwest::Client| {
+    client
+      .post("http://localhost:4444/graphql")
+      .header("content-type", "application/json")
+      .body(body.clone())
+      .send()
+  };
+
+  c.bench_function("upstream request with a client per request", |b| {
+    b.iter(|| {
+      let client = reqwest::Client::new();
+      let response = rt.block_on(send(&client));
+
+      black_box(response)
+    })
+  });
+
+  c.bench_function("upstream request with a shared pooled client", |b| {
+    let client = reqwest::Client::builder()
+      .pool_max_idle_per_host(CONCURRENCY_LEVEL)
+      .pool_idle_timeout(Duration::from_secs(90))
+      .build()
+      .unwrap();
+
+    b.iter(|| {
+      let response = rt.block_on(send(&client));
+
+      black_box(response)
+    })
+  });
+}
+
 fn configure_benchmark() -> Criterion {
   Criterion::default()
     .sample_size(SAMPLE_SIZE)
@@ -183,6 +227,6 @@ fn configure_benchmark() -> Criterion {
 criterion_group! {
     name = benches;
     config = configure_benchmark();
-    targets = criterion_benchmark
+    targets = criterion_benchmark, upstream_client_benchmark
 }
 criterion_main!(benches);
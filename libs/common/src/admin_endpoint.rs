@@ -0,0 +1,191 @@
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+
+use crate::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{ConductorHttpResponse, HeaderValue, HttpHeadersMap, Method, StatusCode, Uri},
+};
+
+static BEARER_PREFIX: &str = "Bearer ";
+
+/// A `GET` path exposed by a plugin under the path of the endpoint (for example: `GET /graphql/metrics`), to serve the data the plugin collected.
+///
+/// The data is collected from the requests of all callers, so it's only served to requests that send the configured `secret` as a bearer token (`Authorization: Bearer <secret>`). When no secret is configured, the request must be authenticated by an authentication plugin (like `jwt_auth`) that runs before the plugin.
+#[derive(Debug)]
+pub struct AdminEndpoint {
+  path: String,
+  secret: Option<String>,
+}
+
+impl AdminEndpoint {
+  pub fn new(path: &str, secret: Option<String>) -> Self {
+    Self {
+      path: format!("/{}", path.trim_matches('/')),
+      secret,
+    }
+  }
+
+  /// Returns `true` if the request is a `GET` request to the exact path, relative to the endpoint handling the request.
+  pub fn matches(&self, ctx: &RequestExecutionContext) -> bool {
+    if ctx.downstream_http_request.method != Method::GET {
+      return false;
+    }
+
+    // Some runtimes (like Cloudflare Worker) provide the full URL instead of the path.
+    let Ok(uri) = ctx.downstream_http_request.uri.parse::<Uri>() else {
+      return false;
+    };
+    let endpoint = ctx.endpoint().unwrap_or_default().trim_end_matches('/');
+
+    uri
+      .path()
+      .trim_end_matches('/')
+      .strip_prefix(endpoint)
+      .is_some_and(|path| path == self.path)
+  }
+
+  fn is_authorized(&self, ctx: &RequestExecutionContext) -> bool {
+    match &self.secret {
+      Some(secret) => ctx
+        .downstream_http_request
+        .headers
+        .get_all(AUTHORIZATION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.strip_prefix(BEARER_PREFIX))
+        .any(|token| constant_time_eq(token.trim().as_bytes(), secret.as_bytes())),
+      None => ctx.is_authenticated(),
+    }
+  }
+
+  /// Short-circuits the request with the rendered response if it matches the path, or with a `401` response if it's not authorized. Returns `true` if the request was handled.
+  pub fn handle(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    render: impl FnOnce() -> ConductorHttpResponse,
+  ) -> bool {
+    if !self.matches(ctx) {
+      return false;
+    }
+
+    let response = match self.is_authorized(ctx) {
+      true => render(),
+      false => {
+        let mut headers = HttpHeadersMap::new();
+        headers.insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+
+        GraphQLResponse::new_error("unauthorized")
+          .into_with_status_code_and_headers(StatusCode::UNAUTHORIZED, headers)
+      }
+    };
+
+    ctx.short_circuit(response);
+
+    true
+  }
+}
+
+/// Compares the secrets without returning early, so the time it takes doesn't tell how much of the secret was guessed.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{execute::ENDPOINT_CONTEXT_KEY, http::ConductorHttpRequest};
+
+  use super::*;
+
+  fn context(method: Method, uri: &str, authorization: Option<&str>) -> RequestExecutionContext {
+    let mut headers = HttpHeadersMap::new();
+
+    if let Some(authorization) = authorization {
+      headers.insert(AUTHORIZATION, authorization.parse().unwrap());
+    }
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      body: Default::default(),
+      headers,
+      method,
+      uri: uri.to_string(),
+      query_string: "".to_string(),
+      peer_addr: None,
+    });
+    ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, "/graphql");
+
+    ctx
+  }
+
+  #[test]
+  fn exact_path() {
+    let endpoint = AdminEndpoint::new("/metrics/", None);
+
+    assert!(endpoint.matches(&context(Method::GET, "/graphql/metrics", None)));
+    assert!(endpoint.matches(&context(Method::GET, "/graphql/metrics/?a=1", None)));
+    assert!(!endpoint.matches(&context(Method::POST, "/graphql/metrics", None)));
+    assert!(!endpoint.matches(&context(Method::GET, "/graphql/foo/metrics", None)));
+    assert!(!endpoint.matches(&context(Method::GET, "/other/metrics", None)));
+    assert!(!endpoint.matches(&context(Method::GET, "/graphql/metrics2", None)));
+    assert!(endpoint.matches(&context(
+      Method::GET,
+      "https://example.com/graphql/metrics",
+      None
+    )));
+  }
+
+  #[test]
+  fn secret() {
+    let endpoint = AdminEndpoint::new("/metrics", Some("s3cret".to_string()));
+    let render = || ConductorHttpResponse {
+      body: "data".into(),
+      status: StatusCode::OK,
+      headers: Default::default(),
+    };
+
+    let mut ctx = context(Method::GET, "/graphql/metrics", Some("Bearer s3cret"));
+    assert!(endpoint.handle(&mut ctx, render));
+    assert_eq!(ctx.short_circuit_response.unwrap().status, StatusCode::OK);
+
+    let mut ctx = context(Method::GET, "/graphql/metrics", Some("Bearer wrong"));
+    assert!(endpoint.handle(&mut ctx, render));
+    assert_eq!(
+      ctx.short_circuit_response.unwrap().status,
+      StatusCode::UNAUTHORIZED
+    );
+
+    // Authenticated requests still need the secret, when it's configured.
+    let mut ctx = context(Method::GET, "/graphql/metrics", None);
+    ctx.mark_authenticated();
+    assert!(endpoint.handle(&mut ctx, render));
+    assert_eq!(
+      ctx.short_circuit_response.unwrap().status,
+      StatusCode::UNAUTHORIZED
+    );
+  }
+
+  #[test]
+  fn authenticated_requests() {
+    let endpoint = AdminEndpoint::new("/metrics", None);
+    let render = || ConductorHttpResponse {
+      body: "data".into(),
+      status: StatusCode::OK,
+      headers: Default::default(),
+    };
+
+    let mut ctx = context(Method::GET, "/graphql/metrics", None);
+    assert!(endpoint.handle(&mut ctx, render));
+    assert_eq!(
+      ctx.short_circuit_response.unwrap().status,
+      StatusCode::UNAUTHORIZED
+    );
+
+    let mut ctx = context(Method::GET, "/graphql/metrics", None);
+    ctx.mark_authenticated();
+    assert!(endpoint.handle(&mut ctx, render));
+    assert_eq!(ctx.short_circuit_response.unwrap().status, StatusCode::OK);
+
+    let mut ctx = context(Method::GET, "/graphql", None);
+    assert!(!endpoint.handle(&mut ctx, render));
+    assert!(ctx.short_circuit_response.is_none());
+  }
+}
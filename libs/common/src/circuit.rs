@@ -0,0 +1,292 @@
+use std::{collections::VecDeque, time::Duration};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+/// The thresholds of a circuit: when it opens, and for how long.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct CircuitBreakerConfig {
+  /// The number of failures within `window` that opens the circuit.
+  #[serde(default = "default_failure_threshold")]
+  pub failure_threshold: u32,
+  /// The duration during which failures are counted.
+  #[serde(
+    deserialize_with = "humantime_serde::deserialize",
+    serialize_with = "humantime_serde::serialize",
+    default = "default_window"
+  )]
+  #[schemars(with = "String")]
+  pub window: Duration,
+  /// The duration during which the circuit stays open, before a probe request is allowed.
+  #[serde(
+    deserialize_with = "humantime_serde::deserialize",
+    serialize_with = "humantime_serde::serialize",
+    default = "default_cooldown"
+  )]
+  #[schemars(with = "String")]
+  pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+  fn default() -> Self {
+    Self {
+      failure_threshold: default_failure_threshold(),
+      window: default_window(),
+      cooldown: default_cooldown(),
+    }
+  }
+}
+
+impl CircuitBreakerConfig {
+  /// Returns `false` when a threshold is `0`, since the circuit would never open or never close again.
+  pub fn is_valid(&self) -> bool {
+    self.failure_threshold > 0 && !self.window.is_zero() && !self.cooldown.is_zero()
+  }
+}
+
+fn default_failure_threshold() -> u32 {
+  5
+}
+
+fn default_window() -> Duration {
+  Duration::from_secs(10)
+}
+
+fn default_cooldown() -> Duration {
+  Duration::from_secs(30)
+}
+
+#[derive(Debug)]
+enum CircuitState {
+  Closed {
+    /// The instants of the failures within the window, oldest first.
+    failures: VecDeque<Instant>,
+  },
+  Open {
+    until: Instant,
+  },
+  HalfOpen {
+    /// When the probe request was allowed, if one is in flight.
+    probe_started: Option<Instant>,
+  },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CircuitCheck {
+  Allowed,
+  /// The request is allowed as the probe of a half-open circuit.
+  Probe,
+  Rejected {
+    retry_after: Duration,
+  },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CircuitTransition {
+  Opened,
+  Closed,
+}
+
+/// Tracks the failures of an upstream, and decides whether requests can be sent to it.
+#[derive(Debug)]
+pub struct Circuit {
+  state: CircuitState,
+}
+
+impl Default for Circuit {
+  fn default() -> Self {
+    Self {
+      state: CircuitState::Closed {
+        failures: VecDeque::new(),
+      },
+    }
+  }
+}
+
+impl Circuit {
+  /// Returns whether a request would be allowed, without taking the probe of a half-open circuit.
+  pub fn is_available(&self, now: Instant, cooldown: Duration) -> bool {
+    match &self.state {
+      CircuitState::Closed { .. } => true,
+      CircuitState::Open { until } => now >= *until,
+      CircuitState::HalfOpen { probe_started } => {
+        probe_started.map_or(true, |started| now.duration_since(started) >= cooldown)
+      }
+    }
+  }
+
+  pub fn check(&mut self, now: Instant, cooldown: Duration) -> CircuitCheck {
+    match &self.state {
+      CircuitState::Closed { .. } => CircuitCheck::Allowed,
+      CircuitState::Open { until } if now < *until => CircuitCheck::Rejected {
+        retry_after: *until - now,
+      },
+      // A probe that didn't report back within the cooldown is considered lost, so another one is allowed.
+      CircuitState::HalfOpen {
+        probe_started: Some(started),
+      } if now.duration_since(*started) < cooldown => CircuitCheck::Rejected {
+        retry_after: cooldown - now.duration_since(*started),
+      },
+      CircuitState::Open { .. } | CircuitState::HalfOpen { .. } => {
+        self.state = CircuitState::HalfOpen {
+          probe_started: Some(now),
+        };
+
+        CircuitCheck::Probe
+      }
+    }
+  }
+
+  /// Records the outcome of an upstream request. Returns the transition, if the circuit opened or closed.
+  pub fn record(
+    &mut self,
+    now: Instant,
+    success: bool,
+    probe: bool,
+    config: &CircuitBreakerConfig,
+  ) -> Option<CircuitTransition> {
+    match &mut self.state {
+      CircuitState::Closed { failures } => {
+        if success {
+          return None;
+        }
+
+        failures.push_back(now);
+
+        while failures
+          .front()
+          .is_some_and(|failure| now.duration_since(*failure) >= config.window)
+        {
+          failures.pop_front();
+        }
+
+        if failures.len() < config.failure_threshold as usize {
+          return None;
+        }
+
+        self.state = CircuitState::Open {
+          until: now + config.cooldown,
+        };
+
+        Some(CircuitTransition::Opened)
+      }
+      // Only the probe decides whether a half-open circuit closes, other requests were sent before the circuit opened.
+      CircuitState::HalfOpen { .. } if probe => match success {
+        true => {
+          *self = Circuit::default();
+
+          Some(CircuitTransition::Closed)
+        }
+        false => {
+          self.state = CircuitState::Open {
+            until: now + config.cooldown,
+          };
+
+          Some(CircuitTransition::Opened)
+        }
+      },
+      CircuitState::HalfOpen { .. } | CircuitState::Open { .. } => None,
+    }
+  }
+
+  /// Allows another probe, when the probe request was never sent to the upstream.
+  pub fn release_probe(&mut self) {
+    if let CircuitState::HalfOpen { probe_started } = &mut self.state {
+      *probe_started = None;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config() -> CircuitBreakerConfig {
+    CircuitBreakerConfig {
+      failure_threshold: 2,
+      window: Duration::from_secs(10),
+      cooldown: Duration::from_secs(30),
+    }
+  }
+
+  #[test]
+  fn counts_failures_within_window() {
+    let config = config();
+    let mut circuit = Circuit::default();
+    let now = Instant::now();
+
+    assert_eq!(circuit.record(now, false, false, &config), None);
+    // The first failure is out of the window.
+    assert_eq!(
+      circuit.record(now + Duration::from_secs(10), false, false, &config),
+      None
+    );
+    assert_eq!(
+      circuit.record(now + Duration::from_secs(15), false, false, &config),
+      Some(CircuitTransition::Opened)
+    );
+  }
+
+  #[test]
+  fn half_opens_after_cooldown() {
+    let config = config();
+    let mut circuit = Circuit::default();
+    let now = Instant::now();
+
+    circuit.record(now, false, false, &config);
+    circuit.record(now, false, false, &config);
+
+    assert!(!circuit.is_available(now + Duration::from_secs(10), config.cooldown));
+    assert_eq!(
+      circuit.check(now + Duration::from_secs(10), config.cooldown),
+      CircuitCheck::Rejected {
+        retry_after: Duration::from_secs(20)
+      }
+    );
+
+    // Only a single probe is allowed at a time, and a failed probe opens the circuit again.
+    let now = now + Duration::from_secs(30);
+    assert!(circuit.is_available(now, config.cooldown));
+    assert_eq!(circuit.check(now, config.cooldown), CircuitCheck::Probe);
+    assert!(!circuit.is_available(now, config.cooldown));
+    assert!(matches!(
+      circuit.check(now, config.cooldown),
+      CircuitCheck::Rejected { .. }
+    ));
+    // Requests sent before the circuit opened don't affect the probe.
+    assert_eq!(circuit.record(now, true, false, &config), None);
+    assert_eq!(
+      circuit.record(now, false, true, &config),
+      Some(CircuitTransition::Opened)
+    );
+
+    let now = now + Duration::from_secs(30);
+    assert_eq!(circuit.check(now, config.cooldown), CircuitCheck::Probe);
+    assert_eq!(
+      circuit.record(now, true, true, &config),
+      Some(CircuitTransition::Closed)
+    );
+    assert_eq!(circuit.check(now, config.cooldown), CircuitCheck::Allowed);
+  }
+
+  #[test]
+  fn releases_lost_probes() {
+    let config = config();
+    let mut circuit = Circuit {
+      state: CircuitState::HalfOpen {
+        probe_started: None,
+      },
+    };
+    let now = Instant::now();
+
+    assert_eq!(circuit.check(now, config.cooldown), CircuitCheck::Probe);
+    circuit.release_probe();
+    assert_eq!(circuit.check(now, config.cooldown), CircuitCheck::Probe);
+    // A probe that never reports back expires after the cooldown.
+    assert_eq!(
+      circuit.check(now + config.cooldown, config.cooldown),
+      CircuitCheck::Probe
+    );
+  }
+}
@@ -4,11 +4,27 @@ use crate::{
   vrl_utils::serde_value_to_vrl_value,
 };
 use anyhow::Result;
+use http::Extensions;
 use serde_json::{Map, Value};
 use vrl::compiler::state::RuntimeState;
 
 type Context = Map<String, Value>;
 
+/// The context key that `RequestExecutionContext::authenticated_claims` reads the claims of an authenticated request from, when no `AuthenticatedClaimsKey` is set.
+pub static AUTHENTICATED_CLAIMS_CONTEXT_KEY: &str = "jwt_auth:upstream:claims";
+
+/// The context key that the `request_id` plugin stores the correlation id of the request in, see `RequestExecutionContext::request_id`.
+pub static REQUEST_ID_CONTEXT_KEY: &str = "request_id:id";
+
+/// The context key that the gateway stores the path of the endpoint handling the request in, see `RequestExecutionContext::endpoint`.
+pub static ENDPOINT_CONTEXT_KEY: &str = "conductor:endpoint";
+
+/// The context key that holds the claims of the token that authenticated the request, stored by authentication plugins (like `jwt_auth`) with `RequestExecutionContext::insert`, see `RequestExecutionContext::authenticated_claims`.
+///
+/// The claims themselves are only kept in the context, so the changes made by VRL and Rhai scripts are seen by the other plugins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthenticatedClaimsKey(pub String);
+
 #[derive(Debug)]
 pub struct RequestExecutionContext {
   pub downstream_http_request: ConductorHttpRequest,
@@ -16,6 +32,8 @@ pub struct RequestExecutionContext {
   pub short_circuit_response: Option<ConductorHttpResponse>,
   vrl_shared_state: RuntimeState,
   context: Context,
+  extensions: Extensions,
+  authenticated: bool,
 }
 
 impl RequestExecutionContext {
@@ -26,6 +44,8 @@ impl RequestExecutionContext {
       short_circuit_response: None,
       vrl_shared_state: RuntimeState::default(),
       context: Context::new(),
+      extensions: Extensions::new(),
+      authenticated: false,
     }
   }
 
@@ -41,6 +61,40 @@ impl RequestExecutionContext {
     self.short_circuit_response.is_some()
   }
 
+  /// Marks the request as successfully authenticated. Should be called by authentication plugins, so endpoints with `require_auth` can accept the request.
+  pub fn mark_authenticated(&mut self) {
+    self.authenticated = true;
+  }
+
+  pub fn is_authenticated(&self) -> bool {
+    self.authenticated
+  }
+
+  /// Returns the claims of the token that authenticated the request (for example, by the `jwt_auth` plugin), so other plugins can act on the caller's identity.
+  pub fn authenticated_claims(&self) -> Option<&Value> {
+    let key = self
+      .get::<AuthenticatedClaimsKey>()
+      .map_or(AUTHENTICATED_CLAIMS_CONTEXT_KEY, |key| key.0.as_str());
+
+    self.context.get(key)
+  }
+
+  /// Returns the correlation id of the request (for example, set by the `request_id` plugin), so other plugins can include it in their logs and metrics.
+  pub fn request_id(&self) -> Option<&str> {
+    self
+      .context
+      .get(REQUEST_ID_CONTEXT_KEY)
+      .and_then(Value::as_str)
+  }
+
+  /// Returns the configured path of the endpoint handling the request (for example, `/graphql`), which can differ from the path of the request itself.
+  pub fn endpoint(&self) -> Option<&str> {
+    self
+      .context
+      .get(ENDPOINT_CONTEXT_KEY)
+      .and_then(Value::as_str)
+  }
+
   pub fn has_failed_extraction(&self) -> bool {
     self.downstream_graphql_request.is_none()
   }
@@ -53,7 +107,93 @@ impl RequestExecutionContext {
     self.context.get(&key.into())
   }
 
+  /// Stores a value by its type, and returns the value of the same type that was stored before. Unlike `ctx_insert`, the value doesn't need to be converted to JSON, and it's not visible to VRL and Rhai scripts.
+  ///
+  /// Plugins should store their own types (for example, a newtype around a `String`), so they don't overwrite the values of other plugins.
+  pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+    self.extensions.insert(value)
+  }
+
+  /// Returns the value of the type `T` stored with `insert`.
+  pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+    self.extensions.get::<T>()
+  }
+
+  pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+    self.extensions.get_mut::<T>()
+  }
+
+  pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+    self.extensions.remove::<T>()
+  }
+
   pub fn ctx_for_vrl(&self) -> Result<vrl::value::Value> {
     serde_value_to_vrl_value(&serde_json::Value::Object(self.context.clone()))
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[derive(Debug, PartialEq)]
+  struct TenantId(String);
+
+  fn context() -> RequestExecutionContext {
+    RequestExecutionContext::new(ConductorHttpRequest {
+      body: Default::default(),
+      headers: Default::default(),
+      method: http::Method::POST,
+      uri: "/graphql".to_string(),
+      query_string: "".to_string(),
+      peer_addr: None,
+    })
+  }
+
+  #[test]
+  fn typed_values() {
+    let mut ctx = context();
+
+    assert_eq!(ctx.get::<TenantId>(), None);
+    assert_eq!(ctx.insert(TenantId("a".to_string())), None);
+    assert_eq!(
+      ctx.insert(TenantId("b".to_string())),
+      Some(TenantId("a".to_string()))
+    );
+    assert_eq!(ctx.get::<TenantId>(), Some(&TenantId("b".to_string())));
+
+    ctx.get_mut::<TenantId>().unwrap().0.push('c');
+    assert_eq!(ctx.remove::<TenantId>(), Some(TenantId("bc".to_string())));
+    assert_eq!(ctx.get::<TenantId>(), None);
+  }
+
+  #[test]
+  fn authenticated_claims() {
+    let mut ctx = context();
+    assert_eq!(ctx.authenticated_claims(), None);
+
+    ctx.ctx_insert(AUTHENTICATED_CLAIMS_CONTEXT_KEY, json!({ "sub": "string" }));
+    assert_eq!(
+      ctx.authenticated_claims(),
+      Some(&json!({ "sub": "string" }))
+    );
+
+    ctx.ctx_insert("tenant_a:upstream:claims", json!({ "sub": "tenant_a" }));
+    ctx.insert(AuthenticatedClaimsKey(
+      "tenant_a:upstream:claims".to_string(),
+    ));
+    assert_eq!(
+      ctx.authenticated_claims(),
+      Some(&json!({ "sub": "tenant_a" }))
+    );
+
+    // Changes made by scripts are seen by the other plugins.
+    ctx.ctx_insert("tenant_a:upstream:claims", json!({ "sub": "edited" }));
+    assert_eq!(
+      ctx.authenticated_claims(),
+      Some(&json!({ "sub": "edited" }))
+    );
+  }
+}
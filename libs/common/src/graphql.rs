@@ -1,9 +1,15 @@
-use std::fmt::{Display, Formatter};
+use std::{
+  collections::{HashMap, HashSet},
+  fmt::{Display, Formatter},
+};
 
 use bytes::Bytes;
 use graphql_parser::{
   parse_query, parse_schema,
-  query::{Definition, Document, OperationDefinition, ParseError},
+  query::{
+    Definition, Document, FragmentDefinition, OperationDefinition, ParseError, Selection,
+    SelectionSet, VariableDefinition,
+  },
   schema::{Document as SchemaDocument, ParseError as SchemaParseError},
   Pos,
 };
@@ -18,9 +24,11 @@ use minitrace::{trace, Span};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_json::{Error as SerdeError, Map, Value};
+use sha2::{Digest, Sha256};
 
 use crate::http::{
-  extract_accept, extract_content_type, ConductorHttpRequest, ConductorHttpResponse, StatusCode,
+  extract_accept, extract_content_type, ConductorHttpRequest, ConductorHttpResponse,
+  HttpHeadersMap, StatusCode,
 };
 
 pub const APPLICATION_GRAPHQL_JSON: &str = "application/graphql-response+json";
@@ -248,6 +256,28 @@ pub struct ParsedGraphQLRequest {
   pub parsed_operation: ParsedGraphQLDocument,
 }
 
+fn selects_introspection_field<'a>(
+  selection_set: &'a SelectionSet<'static, String>,
+  fragments: &HashMap<&'a str, &'a FragmentDefinition<'static, String>>,
+  visited_fragments: &mut HashSet<&'a str>,
+) -> bool {
+  selection_set.items.iter().any(|selection| match selection {
+    Selection::Field(field) => field.name == "__schema" || field.name == "__type",
+    Selection::InlineFragment(fragment) => {
+      selects_introspection_field(&fragment.selection_set, fragments, visited_fragments)
+    }
+    Selection::FragmentSpread(spread) => {
+      visited_fragments.insert(spread.fragment_name.as_str())
+        && fragments
+          .get(spread.fragment_name.as_str())
+          .copied()
+          .is_some_and(|fragment| {
+            selects_introspection_field(&fragment.selection_set, fragments, visited_fragments)
+          })
+    }
+  })
+}
+
 impl ParsedGraphQLRequest {
   pub fn create_and_parse(raw_request: GraphQLRequest) -> Result<Self, ParseError> {
     parse_graphql_operation(&raw_request.operation).map(|parsed_operation| ParsedGraphQLRequest {
@@ -256,6 +286,16 @@ impl ParsedGraphQLRequest {
     })
   }
 
+  /// Prints the parsed operation in a canonical format, so documents that only differ in whitespace, commas or comments are normalized to the same string.
+  pub fn normalized_operation(&self) -> String {
+    self.parsed_operation.to_string()
+  }
+
+  /// A hex-encoded SHA-256 hash of the normalized operation.
+  pub fn normalized_operation_hash(&self) -> String {
+    format!("{:x}", Sha256::digest(self.normalized_operation()))
+  }
+
   pub fn executable_operation(&self) -> Option<&Definition<'static, String>> {
     match &self.request.operation_name {
       Some(op_name) => self.parsed_operation.definitions.iter().find(|v| {
@@ -284,42 +324,83 @@ impl ParsedGraphQLRequest {
     }
   }
 
-  pub fn is_introspection_query(&self) -> bool {
-    let operation_to_execute = self.executable_operation();
-    let root_level_selections = match operation_to_execute {
-      Some(Definition::Operation(OperationDefinition::SelectionSet(s))) => Some(s),
-      Some(Definition::Operation(OperationDefinition::Query(q))) => Some(&q.selection_set),
-      _ => None,
-    };
-
-    if let Some(selections) = root_level_selections {
-      let all_typename = selections.items.iter().all(|v| {
-        // TODO: Should we handle Fragments here as well?
-        if let graphql_parser::query::Selection::Field(field) = v {
-          return field.name == "__typename";
-        }
-
-        false
-      });
+  /// The root selection set of the executed operation.
+  pub fn executable_selection_set(&self) -> Option<&SelectionSet<'static, String>> {
+    match self.executable_operation()? {
+      Definition::Operation(OperationDefinition::SelectionSet(selection_set)) => {
+        Some(selection_set)
+      }
+      Definition::Operation(OperationDefinition::Query(query)) => Some(&query.selection_set),
+      Definition::Operation(OperationDefinition::Mutation(mutation)) => {
+        Some(&mutation.selection_set)
+      }
+      Definition::Operation(OperationDefinition::Subscription(subscription)) => {
+        Some(&subscription.selection_set)
+      }
+      Definition::Fragment(_) => None,
+    }
+  }
 
-      if all_typename {
-        return true;
+  /// The variables declared by the executed operation.
+  pub fn executable_variable_definitions(&self) -> &[VariableDefinition<'static, String>] {
+    match self.executable_operation() {
+      Some(Definition::Operation(OperationDefinition::Query(query))) => &query.variable_definitions,
+      Some(Definition::Operation(OperationDefinition::Mutation(mutation))) => {
+        &mutation.variable_definitions
       }
+      Some(Definition::Operation(OperationDefinition::Subscription(subscription))) => {
+        &subscription.variable_definitions
+      }
+      _ => &[],
+    }
+  }
 
-      let has_some_introspection_fields = selections.items.iter().any(|v| {
-        if let graphql_parser::query::Selection::Field(field) = v {
-          return field.name == "__schema" || field.name == "__type";
-        }
+  /// The fragments defined in the document, by name.
+  pub fn fragment_definitions(&self) -> HashMap<&str, &FragmentDefinition<'static, String>> {
+    self
+      .parsed_operation
+      .definitions
+      .iter()
+      .filter_map(|definition| match definition {
+        Definition::Fragment(fragment) => Some((fragment.name.as_str(), fragment)),
+        Definition::Operation(_) => None,
+      })
+      .collect()
+  }
 
-        false
-      });
+  /// Returns `true` when the executed query selects the `__schema` or `__type` introspection fields at its root, directly or through fragments.
+  ///
+  /// `__typename` is not considered introspection, since it's commonly selected by regular operations and only exposes the name of a type.
+  pub fn is_introspection_query(&self) -> bool {
+    if !self.is_running_query() {
+      return false;
+    }
 
-      if has_some_introspection_fields {
-        return true;
-      }
+    match self.executable_selection_set() {
+      Some(selection_set) => selects_introspection_field(
+        selection_set,
+        &self.fragment_definitions(),
+        &mut HashSet::new(),
+      ),
+      None => false,
     }
+  }
 
-    false
+  /// Returns `true` when the executed operation is a query, rather than a mutation or a subscription.
+  pub fn is_running_query(&self) -> bool {
+    matches!(
+      self.executable_operation(),
+      Some(Definition::Operation(
+        OperationDefinition::Query(_) | OperationDefinition::SelectionSet(_)
+      ))
+    )
+  }
+
+  pub fn is_running_subscription(&self) -> bool {
+    matches!(
+      self.executable_operation(),
+      Some(Definition::Operation(OperationDefinition::Subscription(_)))
+    )
   }
 
   pub fn is_running_mutation(&self) -> bool {
@@ -396,11 +477,26 @@ impl GraphQLResponse {
     }
   }
 
+  /// Sets the status code used when converting this response into a `ConductorHttpResponse`.
+  pub fn with_status_code(mut self, status_code: StatusCode) -> Self {
+    self.downstream_http_code = Some(status_code);
+    self
+  }
+
   pub fn into_with_status_code(self, code: StatusCode) -> ConductorHttpResponse {
+    self.into_with_status_code_and_headers(code, Default::default())
+  }
+
+  /// Same as `into_with_status_code`, for responses that need to carry additional headers (for example, `WWW-Authenticate`).
+  pub fn into_with_status_code_and_headers(
+    self,
+    code: StatusCode,
+    headers: HttpHeadersMap,
+  ) -> ConductorHttpResponse {
     ConductorHttpResponse {
       body: self.into(),
       status: code,
-      headers: Default::default(),
+      headers,
     }
   }
 }
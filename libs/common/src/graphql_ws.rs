@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::graphql::{GraphQLError, GraphQLRequest, GraphQLResponse};
+
+/// The WebSocket subprotocol of GraphQL over WebSocket, see https://github.com/enisdenjo/graphql-ws/blob/master/PROTOCOL.md
+pub static GRAPHQL_TRANSPORT_WS_PROTOCOL: &str = "graphql-transport-ws";
+
+/// The close codes defined by the `graphql-transport-ws` protocol.
+pub mod close_code {
+  pub const BAD_REQUEST: u16 = 4400;
+  pub const UNAUTHORIZED: u16 = 4401;
+  pub const FORBIDDEN: u16 = 4403;
+  pub const CONNECTION_INITIALISATION_TIMEOUT: u16 = 4408;
+  pub const SUBSCRIBER_ALREADY_EXISTS: u16 = 4409;
+  pub const TOO_MANY_INITIALISATION_REQUESTS: u16 = 4429;
+}
+
+/// A message of the `graphql-transport-ws` protocol, sent by either the client or the server.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GraphQLWsMessage {
+  ConnectionInit {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<Map<String, Value>>,
+  },
+  ConnectionAck {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<Map<String, Value>>,
+  },
+  Ping {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<Map<String, Value>>,
+  },
+  Pong {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<Map<String, Value>>,
+  },
+  Subscribe {
+    id: String,
+    payload: GraphQLRequest,
+  },
+  Next {
+    id: String,
+    payload: GraphQLResponse,
+  },
+  Error {
+    id: String,
+    payload: Vec<GraphQLError>,
+  },
+  Complete {
+    id: String,
+  },
+}
+
+impl GraphQLWsMessage {
+  pub fn to_text(&self) -> String {
+    // @expected: the messages only contain JSON values and strings, so serialization can't fail.
+    serde_json::to_string(self).unwrap()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn messages() {
+    let message = serde_json::from_value::<GraphQLWsMessage>(json!({
+      "type": "subscribe",
+      "id": "1",
+      "payload": {
+        "query": "subscription { reviewAdded { body } }",
+        "variables": { "limit": 1 }
+      }
+    }))
+    .unwrap();
+
+    match message {
+      GraphQLWsMessage::Subscribe { id, payload } => {
+        assert_eq!(id, "1");
+        assert_eq!(payload.operation, "subscription { reviewAdded { body } }");
+        assert_eq!(
+          payload.variables,
+          Some(json!({ "limit": 1 }).as_object().cloned().unwrap())
+        );
+      }
+      message => panic!("unexpected message: {:?}", message),
+    }
+
+    assert!(matches!(
+      serde_json::from_str::<GraphQLWsMessage>(r#"{"type":"connection_init"}"#).unwrap(),
+      GraphQLWsMessage::ConnectionInit { payload: None }
+    ));
+    assert_eq!(
+      GraphQLWsMessage::ConnectionAck { payload: None }.to_text(),
+      r#"{"type":"connection_ack"}"#
+    );
+    assert_eq!(
+      GraphQLWsMessage::Error {
+        id: "1".to_string(),
+        payload: vec![GraphQLError::new("unauthenticated request")],
+      }
+      .to_text(),
+      r#"{"type":"error","id":"1","payload":[{"message":"unauthenticated request"}]}"#
+    );
+    assert!(serde_json::from_str::<GraphQLWsMessage>(r#"{"type":"subscribe"}"#).is_err());
+  }
+}
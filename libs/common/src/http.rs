@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::str::FromStr;
 
 use anyhow::{anyhow, Ok, Result};
@@ -61,6 +62,8 @@ pub struct ConductorHttpRequest {
   pub uri: String,
   pub query_string: String,
   pub body: Bytes,
+  /// The IP address of the directly connected peer, when known by the runtime.
+  pub peer_addr: Option<IpAddr>,
 }
 
 #[cfg(feature = "test_utils")]
@@ -76,6 +79,7 @@ impl Default for ConductorHttpRequest {
       })
       .to_string()
       .into(),
+      peer_addr: None,
     }
   }
 }
@@ -10,3 +10,53 @@ pub fn parse_and_extract_json_map_value(value: &str) -> Result<Map<String, Value
     Err(e) => Err(e),
   }
 }
+
+/// Checks the nesting depth of the arrays and objects in a JSON document, without deserializing it.
+///
+/// The document is not validated, strings are skipped so brackets inside of them are not counted.
+pub fn exceeds_max_depth(value: &[u8], max_depth: usize) -> bool {
+  let mut depth = 0usize;
+  let mut in_string = false;
+  let mut escaped = false;
+
+  for byte in value {
+    if in_string {
+      match byte {
+        _ if escaped => escaped = false,
+        b'\\' => escaped = true,
+        b'"' => in_string = false,
+        _ => {}
+      }
+
+      continue;
+    }
+
+    match byte {
+      b'"' => in_string = true,
+      b'{' | b'[' => {
+        depth += 1;
+
+        if depth > max_depth {
+          return true;
+        }
+      }
+      b'}' | b']' => depth = depth.saturating_sub(1),
+      _ => {}
+    }
+  }
+
+  false
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn max_depth() {
+    assert!(!exceeds_max_depth(br#"{"a":[1,{"b":2}]}"#, 3));
+    assert!(exceeds_max_depth(br#"{"a":[1,{"b":2}]}"#, 2));
+    assert!(!exceeds_max_depth(br#"{"a":"[[[{{{\"]]]"}"#, 1));
+    assert!(!exceeds_max_depth(br#"{}"#, 1));
+  }
+}
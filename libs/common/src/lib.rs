@@ -1,15 +1,23 @@
+pub mod admin_endpoint;
+pub mod circuit;
 pub mod execute;
 pub mod graphql;
+pub mod graphql_ws;
 pub mod http;
 pub mod introspection;
 pub mod json;
+pub mod lru;
+pub mod metrics;
 pub mod plugin;
 pub mod plugin_manager;
 pub mod serde_utils;
 pub mod source;
 pub mod vrl_functions;
 pub mod vrl_utils;
-pub use graphql_parser::query::{Definition, Document, OperationDefinition, ParseError};
+pub use graphql_parser::query::{
+  Definition, Document, Field, FragmentDefinition, OperationDefinition, ParseError, Selection,
+  SelectionSet, Value as GraphQLValue,
+};
 pub use graphql_parser::schema::{
   parse_schema, Document as SchemaDocument, ParseError as SchemaParseError, SchemaDefinition,
 };
@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug)]
+struct Entry<V> {
+  value: V,
+  /// The key of the entry in `LruMap::order`, instants are not precise enough for that.
+  last_used: u64,
+}
+
+/// A map that holds at most `capacity` entries: inserting a new key into a full map evicts the least recently used entry.
+///
+/// Entries are used when they are inserted or read with [`LruMap::get_mut`]. A map with a capacity of `0` never holds any entry.
+///
+/// Evicting an entry doesn't scan the map, so a full map stays cheap to update.
+#[derive(Debug)]
+pub struct LruMap<V> {
+  capacity: usize,
+  entries: HashMap<String, Entry<V>>,
+  /// The keys of the entries, from the least to the most recently used.
+  order: BTreeMap<u64, String>,
+  sequence: u64,
+}
+
+impl<V> LruMap<V> {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      capacity,
+      entries: HashMap::new(),
+      order: BTreeMap::new(),
+      sequence: 0,
+    }
+  }
+
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// Returns `true` if inserting a new key would evict an entry.
+  pub fn is_full(&self) -> bool {
+    self.entries.len() >= self.capacity
+  }
+
+  pub fn contains_key(&self, key: &str) -> bool {
+    self.entries.contains_key(key)
+  }
+
+  /// Returns the value without marking it as used.
+  pub fn get(&self, key: &str) -> Option<&V> {
+    self.entries.get(key).map(|entry| &entry.value)
+  }
+
+  /// Returns the value, and marks it as the most recently used entry.
+  pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+    let entry = self.entries.get_mut(key)?;
+    self.sequence += 1;
+
+    if let Some(key) = self.order.remove(&entry.last_used) {
+      self.order.insert(self.sequence, key);
+    }
+    entry.last_used = self.sequence;
+
+    Some(&mut entry.value)
+  }
+
+  /// Inserts or replaces the value, as the most recently used entry.
+  pub fn insert(&mut self, key: String, value: V) {
+    if self.capacity == 0 {
+      return;
+    }
+
+    if !self.entries.contains_key(&key) && self.is_full() {
+      if let Some((_, least_recently_used)) = self.order.pop_first() {
+        self.entries.remove(&least_recently_used);
+      }
+    }
+
+    self.sequence += 1;
+    self.order.insert(self.sequence, key.clone());
+
+    let replaced = self.entries.insert(
+      key,
+      Entry {
+        value,
+        last_used: self.sequence,
+      },
+    );
+
+    if let Some(replaced) = replaced {
+      self.order.remove(&replaced.last_used);
+    }
+  }
+
+  pub fn remove(&mut self, key: &str) -> Option<V> {
+    let entry = self.entries.remove(key)?;
+    self.order.remove(&entry.last_used);
+
+    Some(entry.value)
+  }
+
+  /// Keeps only the entries for which `keep` returns `true`, for example to drop expired entries before evicting used ones.
+  ///
+  /// This visits every entry, so it's not meant to be called on every update.
+  pub fn retain(&mut self, mut keep: impl FnMut(&str, &V) -> bool) {
+    let order = &mut self.order;
+
+    self.entries.retain(|key, entry| {
+      let kept = keep(key, &entry.value);
+
+      if !kept {
+        order.remove(&entry.last_used);
+      }
+
+      kept
+    });
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&String, &V)> {
+    self.entries.iter().map(|(key, entry)| (key, &entry.value))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn evicts_least_recently_used() {
+    let mut map = LruMap::new(2);
+    map.insert("a".to_string(), 1);
+    map.insert("b".to_string(), 2);
+    map.get_mut("a");
+    map.insert("c".to_string(), 3);
+
+    assert_eq!(map.get("a"), Some(&1));
+    assert_eq!(map.get("b"), None);
+    assert_eq!(map.get("c"), Some(&3));
+
+    // Replacing an entry doesn't evict another one.
+    map.insert("c".to_string(), 4);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get("a"), Some(&1));
+    assert_eq!(map.get("c"), Some(&4));
+  }
+
+  #[test]
+  fn evicts_in_order_after_removals() {
+    let mut map = LruMap::new(3);
+    map.insert("a".to_string(), 1);
+    map.insert("b".to_string(), 2);
+    map.insert("c".to_string(), 3);
+    map.remove("a");
+    map.retain(|key, _| key != "b");
+    map.insert("d".to_string(), 4);
+    map.insert("e".to_string(), 5);
+    map.insert("f".to_string(), 6);
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get("c"), None);
+    assert!(map.contains_key("d") && map.contains_key("e") && map.contains_key("f"));
+  }
+
+  #[test]
+  fn zero_capacity() {
+    let mut map = LruMap::new(0);
+    map.insert("a".to_string(), 1);
+
+    assert!(map.is_empty());
+  }
+}
@@ -0,0 +1,86 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use once_cell::sync::Lazy;
+
+/// The values of a counter, keyed by the values of its labels.
+#[derive(Debug, Clone)]
+pub struct Counter {
+  pub help: &'static str,
+  pub values: BTreeMap<Vec<(&'static str, String)>, u64>,
+}
+
+/// Counters recorded by the parts of the gateway that can't reach the `metrics` plugin through the request context (for example, the shadow traffic comparisons that run in the background), keyed by name.
+///
+/// The counters are shared by the whole process, and exposed by the `metrics` plugin along with the metrics it records.
+static COUNTERS: Lazy<Mutex<BTreeMap<&'static str, Counter>>> =
+  Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Increments the counter with the given name and label values.
+pub fn increment_counter(name: &'static str, help: &'static str, labels: &[(&'static str, &str)]) {
+  let mut counters = match COUNTERS.lock() {
+    Ok(counters) => counters,
+    Err(_) => return,
+  };
+  let labels = labels
+    .iter()
+    .map(|(name, value)| (*name, value.to_string()))
+    .collect();
+
+  *counters
+    .entry(name)
+    .or_insert_with(|| Counter {
+      help,
+      values: BTreeMap::new(),
+    })
+    .values
+    .entry(labels)
+    .or_default() += 1;
+}
+
+/// Returns a copy of the recorded counters, keyed by name.
+pub fn counters() -> BTreeMap<&'static str, Counter> {
+  COUNTERS
+    .lock()
+    .map(|counters| counters.clone())
+    .unwrap_or_default()
+}
+
+/// Returns the value of a counter, for the given label values.
+pub fn counter_value(name: &str, labels: &[(&'static str, &str)]) -> u64 {
+  let labels = labels
+    .iter()
+    .map(|(name, value)| (*name, value.to_string()))
+    .collect::<Vec<_>>();
+
+  COUNTERS
+    .lock()
+    .ok()
+    .and_then(|counters| counters.get(name)?.values.get(&labels).copied())
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn counts_by_labels() {
+    increment_counter("test_counts_by_labels", "help", &[("result", "a")]);
+    increment_counter("test_counts_by_labels", "help", &[("result", "a")]);
+    increment_counter("test_counts_by_labels", "help", &[("result", "b")]);
+
+    assert_eq!(
+      counter_value("test_counts_by_labels", &[("result", "a")]),
+      2
+    );
+    assert_eq!(
+      counter_value("test_counts_by_labels", &[("result", "b")]),
+      1
+    );
+    assert_eq!(
+      counter_value("test_counts_by_labels", &[("result", "c")]),
+      0
+    );
+    assert_eq!(counters()["test_counts_by_labels"].help, "help");
+  }
+}
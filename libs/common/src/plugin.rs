@@ -1,13 +1,11 @@
 use std::{fmt::Debug, sync::Arc};
 
+use crate::execute::RequestExecutionContext;
 use crate::{
   graphql::GraphQLRequest,
   http::{ConductorHttpRequest, ConductorHttpResponse},
   source::SourceRuntime,
 };
-use reqwest::Response;
-
-use crate::execute::RequestExecutionContext;
 
 #[derive(Debug, thiserror::Error)]
 pub enum PluginError {
@@ -46,11 +44,11 @@ pub trait Plugin: Sync + Send + Debug {
     _req: &mut ConductorHttpRequest,
   ) {
   }
-  // Step 5: We got a response from the upstream server
+  // Step 5: We got a response from the upstream server, before it's processed by Conductor
   async fn on_upstream_http_response(
     &self,
     _ctx: &mut RequestExecutionContext,
-    _res: &Result<Response, reqwest_middleware::Error>,
+    _response: &mut ConductorHttpResponse,
   ) {
   }
   // Step 6: A final HTTP response send from Conductor to the client
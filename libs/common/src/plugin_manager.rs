@@ -6,7 +6,6 @@ use crate::{
   http::{ConductorHttpRequest, ConductorHttpResponse},
   source::SourceRuntime,
 };
-use reqwest::Response;
 
 #[async_trait::async_trait(?Send)]
 pub trait PluginManager: std::fmt::Debug + Send + Sync {
@@ -30,6 +29,6 @@ pub trait PluginManager: std::fmt::Debug + Send + Sync {
   async fn on_upstream_http_response<'a>(
     &self,
     ctx: &mut RequestExecutionContext,
-    response: &Result<Response, reqwest_middleware::Error>,
+    response: &mut ConductorHttpResponse,
   );
 }
@@ -84,6 +84,18 @@ impl<'de> Deserialize<'de> for LocalFileReference {
   }
 }
 
+/// Resolves a path against the location of the root configuration file, like `LocalFileReference` does.
+/// Use it for paths that are not read when the configuration is loaded (for example, a directory).
+pub fn deserialize_relative_path<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+  D: serde::Deserializer<'de>,
+{
+  let path = String::deserialize(deserializer)?;
+  let base_path = BASE_PATH.with(|e| e.borrow().clone());
+
+  Ok(base_path.join(path).to_string_lossy().into_owned())
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct JsonSchemaExample<T: Serialize> {
   #[serde(rename = "$metadata")]
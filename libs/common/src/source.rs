@@ -1,4 +1,6 @@
-use std::{fmt::Debug, future::Future, pin::Pin, sync::Arc};
+use std::{fmt::Debug, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use futures::Stream;
 
 use crate::{
   execute::RequestExecutionContext,
@@ -15,6 +17,9 @@ pub enum GraphQLSourceInitError {
   FetcherError { source: reqwest::Error },
 }
 
+/// The results of a subscription, in the order they were published by the upstream. The subscription is cancelled when the stream is dropped.
+pub type GraphQLResponseStream = Pin<Box<dyn Stream<Item = GraphQLResponse> + Send>>;
+
 pub trait SourceRuntime: Debug + Send + Sync + 'static {
   fn execute<'a>(
     &'a self,
@@ -22,6 +27,15 @@ pub trait SourceRuntime: Debug + Send + Sync + 'static {
     _request_context: &'a mut RequestExecutionContext,
   ) -> Pin<Box<(dyn Future<Output = Result<GraphQLResponse, SourceError>> + 'a)>>;
 
+  /// Starts a subscription, and returns the stream of its results. Sources that don't support subscriptions fail with `SourceError::SubscriptionsNotSupported`.
+  fn subscribe<'a>(
+    &'a self,
+    _plugin_manager: Arc<Box<dyn PluginManager>>,
+    _request_context: &'a mut RequestExecutionContext,
+  ) -> Pin<Box<(dyn Future<Output = Result<GraphQLResponseStream, SourceError>> + 'a)>> {
+    Box::pin(async { Err(SourceError::SubscriptionsNotSupported) })
+  }
+
   fn name(&self) -> &str;
   fn schema(&self) -> Option<Arc<ParsedGraphQLSchema>>;
   fn sdl(&self) -> Option<Arc<String>>;
@@ -37,6 +51,16 @@ pub enum SourceError {
   NetworkError(reqwest_middleware::Error),
   #[error("upstream planning error: {0}")]
   UpstreamPlanningError(anyhow::Error),
+  #[error("upstream response exceeds the maximum nesting depth of {0}")]
+  ResponseTooDeep(usize),
+  #[error("upstream request timed out after {0:?}")]
+  Timeout(Duration),
+  #[error("no upstream endpoint is available")]
+  NoAvailableEndpoint,
+  #[error("the source does not support subscriptions")]
+  SubscriptionsNotSupported,
+  #[error("upstream subscription failed: {0}")]
+  SubscriptionFailed(anyhow::Error),
 }
 
 impl SourceError {
@@ -46,6 +70,11 @@ impl SourceError {
       Self::ShortCircuit => StatusCode::INTERNAL_SERVER_ERROR,
       Self::NetworkError(_) => StatusCode::BAD_GATEWAY,
       Self::UpstreamPlanningError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+      Self::ResponseTooDeep(_) => StatusCode::BAD_GATEWAY,
+      Self::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+      Self::NoAvailableEndpoint => StatusCode::SERVICE_UNAVAILABLE,
+      Self::SubscriptionsNotSupported => StatusCode::BAD_REQUEST,
+      Self::SubscriptionFailed(_) => StatusCode::BAD_GATEWAY,
     }
   }
 }
@@ -55,3 +84,38 @@ impl From<SourceError> for GraphQLResponse {
     GraphQLResponse::new_error_with_code(&error.to_string(), error.http_status_code())
   }
 }
+
+/// A source that doesn't execute anything, for testing plugins that only need the source of the request (for example, its name).
+#[cfg(feature = "test_utils")]
+#[derive(Debug)]
+pub struct TestSource(pub &'static str);
+
+#[cfg(feature = "test_utils")]
+impl Default for TestSource {
+  fn default() -> Self {
+    Self("test")
+  }
+}
+
+#[cfg(feature = "test_utils")]
+impl SourceRuntime for TestSource {
+  fn execute<'a>(
+    &'a self,
+    _plugin_manager: Arc<Box<dyn PluginManager>>,
+    _request_context: &'a mut RequestExecutionContext,
+  ) -> Pin<Box<(dyn Future<Output = Result<GraphQLResponse, SourceError>> + 'a)>> {
+    Box::pin(async { Err(SourceError::ShortCircuit) })
+  }
+
+  fn name(&self) -> &str {
+    self.0
+  }
+
+  fn schema(&self) -> Option<Arc<ParsedGraphQLSchema>> {
+    None
+  }
+
+  fn sdl(&self) -> Option<Arc<String>> {
+    None
+  }
+}
@@ -144,7 +144,11 @@ pub fn serde_value_to_vrl_value(value: &serde_json::Value) -> Result<Value> {
         .map_err(|e| anyhow!("Failed to convert f64 to NotNan<f64>: {}", e))?;
       Value::Float(not_nan_float)
     }
-    serde_json::Value::Number(v) => Value::Integer(v.as_i64().unwrap_or(i64::MAX)),
+    // VRL integers are signed 64-bit, so larger values can't be represented without losing precision.
+    serde_json::Value::Number(v) => Value::Integer(
+      v.as_i64()
+        .ok_or_else(|| anyhow!("integer {} is out of the supported range", v))?,
+    ),
     serde_json::Value::String(v) => Value::Bytes(v.to_owned().into()),
     serde_json::Value::Array(v) => Value::Array(
       v.iter()
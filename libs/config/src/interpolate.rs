@@ -10,8 +10,8 @@ pub fn interpolate(
   let empty_string = String::with_capacity(0);
   let env_var_interpolation_regex: Regex = Regex::new(
     r"(?x)
-        \\\$|\$([[:word:].]+)|\$
-        \{([[:word:].]+?)(?::([^}]*))?\}
+        \\\$|\$\$|\$([[:word:].]+)|\$
+        \{([[:word:].]+?)(?::-?([^}]*))?\}
         ",
   )
   // @expected: statically defined regex pattern, we know it works ;)
@@ -25,8 +25,8 @@ pub fn interpolate(
         .replace_all(input, |caps: &Captures| {
             if let Some(matched) = caps.get(0) {
                 let entire_match = matched.as_str();
-                if entire_match == "\\$" {
-                    return "$".to_string(); // Return single dollar sign for escaped "\$"
+                if entire_match == "\\$" || entire_match == "$$" {
+                    return "$".to_string(); // Return single dollar sign for escaped "\$" and "$$"
                 }
             }
 
@@ -109,6 +109,36 @@ mod tests {
       assert_eq!(res.0, "name: $snaky");
     }
   }
+
+  #[test]
+  fn should_interpolate_with_double_dollar_sign() {
+    let env_vars = HashMap::<&str, &str>::new();
+    let input = "name: $$snaky, price: $${PRICE}";
+    let env_fn = |key: &str| env_vars.get(key).map(|s| s.to_string());
+
+    let result = interpolate(input, env_fn);
+
+    assert!(result.is_ok());
+    if let Ok(res) = result {
+      assert_eq!(res.0, "name: $snaky, price: ${PRICE}");
+    }
+  }
+
+  #[test]
+  fn should_interpolate_with_shell_style_default_value() {
+    let mut env_vars = HashMap::<&str, &str>::new();
+    env_vars.insert("API_KEY", "12345");
+    let input = "endpoint: ${API_ENDPOINT:-https://api.example.com/}, key: ${API_KEY:-default}";
+    let env_fn = |key: &str| env_vars.get(key).map(|s| s.to_string());
+
+    let result = interpolate(input, env_fn);
+
+    assert!(result.is_ok());
+    if let Ok(res) = result {
+      assert_eq!(res.0, "endpoint: https://api.example.com/, key: 12345");
+    }
+  }
+
   #[test]
   fn should_prioritize_environment_variable_over_default_value() {
     let mut env_vars = HashMap::<&str, &str>::new();
@@ -14,6 +14,24 @@ use serde_json::json;
 pub struct TestSuite {
   pub plugins: Vec<Box<dyn Plugin>>,
   pub mock_server: Option<MockServer>,
+  /// Overrides the configuration of the test source, the `endpoint` is always set to the mock server.
+  pub source_config: Option<GraphQLSourceConfig>,
+  /// Sets `require_auth` on the test endpoint.
+  pub require_auth: bool,
+  /// Sets `default_response_content_type` on the test endpoint.
+  pub default_response_content_type: Option<&'static str>,
+  /// Sets `max_batch_size` on the test endpoint.
+  pub max_batch_size: Option<usize>,
+}
+
+fn source_config(
+  source_config: Option<GraphQLSourceConfig>,
+  mock_server: &MockServer,
+) -> GraphQLSourceConfig {
+  let mut source_config = source_config.unwrap_or_default();
+  source_config.endpoint = mock_server.url("/graphql").into();
+
+  source_config
 }
 
 impl TestSuite {
@@ -27,16 +45,20 @@ impl TestSuite {
 
     let source = GraphQLSourceRuntime::new(
       "test".to_string(),
-      GraphQLSourceConfig {
-        endpoint: mock_server.url("/graphql"),
-        schema_awareness: None,
-      },
+      source_config(self.source_config, &mock_server),
     )
     .await
     .expect("failed to create source");
 
-    let response =
-      ConductorGateway::execute_test(Arc::new(Box::new(source)), self.plugins, request).await;
+    let response = ConductorGateway::execute_test(
+      Arc::new(Box::new(source)),
+      self.plugins,
+      request,
+      self.require_auth,
+      self.default_response_content_type,
+      self.max_batch_size,
+    )
+    .await;
 
     mock.assert();
     response
@@ -63,15 +85,20 @@ impl TestSuite {
 
     let source = GraphQLSourceRuntime::new(
       "test".to_string(),
-      GraphQLSourceConfig {
-        endpoint: mock_server.url("/graphql"),
-        schema_awareness: None,
-      },
+      source_config(self.source_config, &mock_server),
     )
     .await
     .expect("failed to create source");
 
-    ConductorGateway::execute_test(Arc::new(Box::new(source)), self.plugins, request).await
+    ConductorGateway::execute_test(
+      Arc::new(Box::new(source)),
+      self.plugins,
+      request,
+      self.require_auth,
+      self.default_response_content_type,
+      self.max_batch_size,
+    )
+    .await
   }
 
   pub async fn run_graphql_request(self, request: GraphQLRequest) -> ConductorHttpResponse {
@@ -83,6 +110,7 @@ impl TestSuite {
       uri: "/graphql".to_string(),
       body: request.to_string().into(),
       headers,
+      peer_addr: None,
     };
 
     self.run_http_request(request).await
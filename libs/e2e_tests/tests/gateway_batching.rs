@@ -0,0 +1,290 @@
+use std::sync::Arc;
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  http::{
+    header::{RETRY_AFTER, SET_COOKIE},
+    ConductorHttpRequest, ConductorHttpResponse, HttpHeadersMap, Method, StatusCode, CONTENT_TYPE,
+  },
+  plugin::Plugin,
+};
+use conductor_config::GraphQLSourceConfig;
+use conductor_engine::{gateway::ConductorGateway, source::graphql_source::GraphQLSourceRuntime};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use tokio::test;
+
+fn request(body: Value) -> ConductorHttpRequest {
+  let mut headers = HttpHeadersMap::new();
+  headers.append(CONTENT_TYPE, "application/json".parse().unwrap());
+
+  ConductorHttpRequest {
+    method: Method::POST,
+    uri: "/graphql".to_string(),
+    body: body.to_string().into(),
+    headers,
+    ..Default::default()
+  }
+}
+
+async fn source(mock_server: &MockServer) -> GraphQLSourceRuntime {
+  GraphQLSourceRuntime::new(
+    "test".to_string(),
+    GraphQLSourceConfig {
+      endpoint: mock_server.url("/graphql").into(),
+      ..Default::default()
+    },
+  )
+  .await
+  .unwrap()
+}
+
+async fn execute(
+  mock_server: &MockServer,
+  body: Value,
+  max_batch_size: Option<usize>,
+) -> ConductorHttpResponse {
+  ConductorGateway::execute_test(
+    Arc::new(Box::new(source(mock_server).await)),
+    vec![],
+    request(body),
+    false,
+    None,
+    max_batch_size,
+  )
+  .await
+}
+
+/// Authenticates the operations named `First`, and sets a cookie and a `Retry-After` header named after the operation on every response.
+#[derive(Debug)]
+struct TestOperationPlugin;
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for TestOperationPlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    if operation_name(ctx) == "First" {
+      ctx.mark_authenticated();
+    }
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    let (cookie, retry_after) = match operation_name(ctx) {
+      "First" => ("operation=first", "1"),
+      _ => ("operation=second", "5"),
+    };
+
+    response.headers.append(SET_COOKIE, cookie.parse().unwrap());
+    response
+      .headers
+      .insert(RETRY_AFTER, retry_after.parse().unwrap());
+  }
+}
+
+fn operation_name(ctx: &RequestExecutionContext) -> &str {
+  match std::str::from_utf8(&ctx.downstream_http_request.body) {
+    Ok(body) if body.contains("query First") => "First",
+    _ => "Second",
+  }
+}
+
+async fn execute_with_auth(mock_server: &MockServer, body: Value) -> ConductorHttpResponse {
+  ConductorGateway::execute_test(
+    Arc::new(Box::new(source(mock_server).await)),
+    vec![Box::new(TestOperationPlugin)],
+    request(body),
+    true,
+    None,
+    Some(2),
+  )
+  .await
+}
+
+#[test]
+async fn executes_batched_operations_in_order() {
+  let mock_server = MockServer::start();
+  let first = mock_server.mock(|when, then| {
+    when
+      .method(POST)
+      .path("/graphql")
+      .body_contains("query First");
+    then
+      .status(200)
+      .header("content-type", "application/json")
+      .body(json!({ "data": { "first": 1 } }).to_string());
+  });
+  let second = mock_server.mock(|when, then| {
+    when
+      .method(POST)
+      .path("/graphql")
+      .body_contains("query Second");
+    then
+      .status(200)
+      .header("content-type", "application/json")
+      .body(json!({ "data": { "second": 2 } }).to_string());
+  });
+
+  let response = execute(
+    &mock_server,
+    json!([
+      { "query": "query First { first }" },
+      { "query": "query Second { second }" },
+      { "query": "query Invalid {" }
+    ]),
+    Some(3),
+  )
+  .await;
+
+  first.assert();
+  second.assert();
+  assert_eq!(response.status, StatusCode::OK);
+  assert_eq!(
+    response.headers.get(CONTENT_TYPE).unwrap(),
+    "application/json"
+  );
+  assert_eq!(
+    serde_json::from_slice::<Value>(&response.body).unwrap(),
+    json!([
+      { "data": { "first": 1 } },
+      { "data": { "second": 2 } },
+      { "errors": [{ "message": "failed to parse GraphQL operation" }] }
+    ])
+  );
+}
+
+#[test]
+async fn rejects_invalid_batches() {
+  let mock_server = MockServer::start();
+  let upstream = mock_server.mock(|when, then| {
+    when.method(POST).path("/graphql");
+    then.status(200).body(json!({ "data": {} }).to_string());
+  });
+
+  let response = execute(
+    &mock_server,
+    json!([
+      { "query": "query { __typename }" },
+      { "query": "query { __typename }" }
+    ]),
+    Some(1),
+  )
+  .await;
+
+  assert_eq!(response.status, StatusCode::BAD_REQUEST);
+  assert_eq!(
+    std::str::from_utf8(&response.body).unwrap(),
+    r#"{"errors":[{"message":"batched request contains 2 operations, the maximum is 1"}]}"#
+  );
+
+  let response = execute(&mock_server, json!([]), Some(1)).await;
+
+  assert_eq!(response.status, StatusCode::BAD_REQUEST);
+  assert_eq!(
+    std::str::from_utf8(&response.body).unwrap(),
+    r#"{"errors":[{"message":"batched request is empty"}]}"#
+  );
+
+  upstream.assert_hits(0);
+}
+
+#[test]
+async fn batching_is_disabled_by_default() {
+  let mock_server = MockServer::start();
+  let upstream = mock_server.mock(|when, then| {
+    when.method(POST).path("/graphql");
+    then.status(200).body(json!({ "data": {} }).to_string());
+  });
+
+  let response = execute(
+    &mock_server,
+    json!([{ "query": "query { __typename }" }]),
+    None,
+  )
+  .await;
+
+  assert_eq!(
+    std::str::from_utf8(&response.body).unwrap(),
+    r#"{"errors":[{"message":"invalid body json format"}]}"#
+  );
+  upstream.assert_hits(0);
+}
+
+#[test]
+async fn exposes_the_status_of_failed_operations() {
+  let mock_server = MockServer::start();
+  let upstream = mock_server.mock(|when, then| {
+    when.method(POST).path("/graphql");
+    then
+      .status(200)
+      .header("content-type", "application/json")
+      .body(json!({ "data": { "first": 1 } }).to_string());
+  });
+
+  let response = execute_with_auth(
+    &mock_server,
+    json!([
+      { "query": "query First { first }" },
+      { "query": "query Second { second }" }
+    ]),
+  )
+  .await;
+
+  upstream.assert_hits(1);
+  assert_eq!(response.status, StatusCode::OK);
+  assert_eq!(
+    serde_json::from_slice::<Value>(&response.body).unwrap(),
+    json!([
+      { "data": { "first": 1 } },
+      {
+        "errors": [{ "message": "unauthenticated request" }],
+        "extensions": { "http": { "status": 401 } }
+      }
+    ])
+  );
+  assert_eq!(
+    response
+      .headers
+      .get_all(SET_COOKIE)
+      .iter()
+      .collect::<Vec<_>>(),
+    vec!["operation=first", "operation=second"]
+  );
+  assert_eq!(response.headers.get(RETRY_AFTER).unwrap(), "5");
+}
+
+#[test]
+async fn fails_when_every_operation_failed() {
+  let mock_server = MockServer::start();
+  let upstream = mock_server.mock(|when, then| {
+    when.method(POST).path("/graphql");
+    then.status(200).body(json!({ "data": {} }).to_string());
+  });
+
+  let response = execute_with_auth(
+    &mock_server,
+    json!([
+      { "query": "query Second { second }" },
+      { "query": "query Second { second }" }
+    ]),
+  )
+  .await;
+
+  upstream.assert_hits(0);
+  assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+  assert_eq!(
+    serde_json::from_slice::<Value>(&response.body).unwrap(),
+    json!([
+      {
+        "errors": [{ "message": "unauthenticated request" }],
+        "extensions": { "http": { "status": 401 } }
+      },
+      {
+        "errors": [{ "message": "unauthenticated request" }],
+        "extensions": { "http": { "status": 401 } }
+      }
+    ])
+  );
+}
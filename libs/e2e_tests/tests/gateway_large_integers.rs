@@ -0,0 +1,36 @@
+use conductor_common::{
+  graphql::GraphQLRequest,
+  http::{ConductorHttpRequest, HttpHeadersMap, Method, StatusCode, CONTENT_TYPE},
+};
+use e2e::suite::TestSuite;
+use httpmock::prelude::*;
+use tokio::test;
+
+#[test]
+async fn large_integers_pass_through_unchanged() {
+  let mut headers = HttpHeadersMap::new();
+  headers.append(CONTENT_TYPE, "application/json".parse().unwrap());
+  let request = ConductorHttpRequest {
+    method: Method::POST,
+    uri: "/graphql".to_string(),
+    body: GraphQLRequest::default().into(),
+    headers,
+    ..Default::default()
+  };
+
+  // 2^53 + 1 is the first integer that can't be represented as f64.
+  let upstream_body = r#"{"data":{"user":{"id":9007199254740993,"max":18446744073709551615,"min":-9223372036854775808}}}"#;
+
+  let response = TestSuite::default()
+    .run_with_mock(request, |when, then| {
+      when.method(POST).path("/graphql");
+      then
+        .status(200)
+        .header("content-type", "application/json")
+        .body(upstream_body);
+    })
+    .await;
+
+  assert_eq!(response.status, StatusCode::OK);
+  assert_eq!(std::str::from_utf8(&response.body).unwrap(), upstream_body);
+}
@@ -0,0 +1,121 @@
+use conductor_common::{
+  http::{header::LOCATION, StatusCode, Url, CONTENT_TYPE},
+  serde_utils::LocalFileReference,
+};
+use conductor_config::{
+  ConductorConfig, EndpointDefinition, MockedSourceConfig, NotFoundResponseConfig,
+  NotFoundResponseFormat, SourceDefinition,
+};
+use conductor_engine::gateway::{ConductorGateway, GatewayError};
+use conductor_tracing::minitrace_mgr::MinitraceManager;
+use serde_json::json;
+use tokio::test;
+
+async fn gateway_with(not_found: Option<NotFoundResponseConfig>) -> ConductorGateway {
+  let config = ConductorConfig {
+    server: None,
+    logger: None,
+    plugins: None,
+    sources: vec![SourceDefinition::Mock {
+      id: "mock".to_string(),
+      config: MockedSourceConfig {
+        response_data: LocalFileReference {
+          path: "mock.json".to_string(),
+          contents: json!({ "data": { "__typename": "Query" } }).to_string(),
+        },
+      },
+    }],
+    endpoints: vec![EndpointDefinition {
+      path: "/graphql".to_string(),
+      from: "mock".to_string(),
+      plugins: None,
+      require_auth: None,
+      default_response_content_type: None,
+      max_batch_size: None,
+    }],
+    not_found,
+  };
+
+  ConductorGateway::new(&config, &mut MinitraceManager::default())
+    .await
+    .unwrap()
+}
+
+fn assert_unknown_path(gw: &ConductorGateway) {
+  assert!(matches!(
+    gw.match_route(&Url::parse("http://localhost/unknown").unwrap()),
+    Err(GatewayError::MissingEndpoint(_))
+  ));
+}
+
+#[test]
+async fn default_not_found_response() {
+  let gw = gateway_with(None).await;
+  assert_unknown_path(&gw);
+
+  let response = gw.not_found_response();
+  assert_eq!(response.status, StatusCode::NOT_FOUND);
+  assert_eq!(
+    response.headers.get(CONTENT_TYPE),
+    Some(&"application/json".parse().unwrap())
+  );
+  assert_eq!(
+    serde_json::from_slice::<serde_json::Value>(&response.body).unwrap(),
+    json!({ "errors": [{ "message": "failed to locate endpoint" }] })
+  );
+}
+
+#[test]
+async fn custom_graphql_not_found_response() {
+  let gw = gateway_with(Some(NotFoundResponseConfig {
+    status: 410,
+    message: "this endpoint has moved, see https://docs.example.com".to_string(),
+    ..Default::default()
+  }))
+  .await;
+  assert_unknown_path(&gw);
+
+  let response = gw.not_found_response();
+  assert_eq!(response.status, StatusCode::GONE);
+  assert_eq!(
+    serde_json::from_slice::<serde_json::Value>(&response.body).unwrap(),
+    json!({ "errors": [{ "message": "this endpoint has moved, see https://docs.example.com" }] })
+  );
+}
+
+#[test]
+async fn custom_text_not_found_response() {
+  let gw = gateway_with(Some(NotFoundResponseConfig {
+    format: NotFoundResponseFormat::Text,
+    message: "nothing here".to_string(),
+    ..Default::default()
+  }))
+  .await;
+  assert_unknown_path(&gw);
+
+  let response = gw.not_found_response();
+  assert_eq!(response.status, StatusCode::NOT_FOUND);
+  assert_eq!(
+    response.headers.get(CONTENT_TYPE),
+    Some(&"text/plain".parse().unwrap())
+  );
+  assert_eq!(response.body, "nothing here");
+}
+
+#[test]
+async fn redirect_not_found_response() {
+  let gw = gateway_with(Some(NotFoundResponseConfig {
+    redirect: Some("https://docs.example.com".to_string()),
+    ..Default::default()
+  }))
+  .await;
+  assert_unknown_path(&gw);
+
+  let response = gw.not_found_response();
+  assert_eq!(response.status, StatusCode::FOUND);
+  assert_eq!(
+    response.headers.get(LOCATION),
+    Some(&"https://docs.example.com".parse().unwrap())
+  );
+  assert!(response.body.is_empty());
+}
@@ -0,0 +1,77 @@
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLRequest,
+  http::{ConductorHttpRequest, HttpHeadersMap, Method, StatusCode, CONTENT_TYPE},
+  plugin::Plugin,
+};
+use e2e::suite::TestSuite;
+use httpmock::prelude::*;
+use tokio::test;
+
+/// Authenticates every request that carries the `x-test-auth` header.
+#[derive(Debug)]
+struct TestAuthPlugin;
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for TestAuthPlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    if ctx
+      .downstream_http_request
+      .headers
+      .contains_key("x-test-auth")
+    {
+      ctx.mark_authenticated();
+    }
+  }
+}
+
+fn request(authenticated: bool) -> ConductorHttpRequest {
+  let mut headers = HttpHeadersMap::new();
+  headers.append(CONTENT_TYPE, "application/json".parse().unwrap());
+
+  if authenticated {
+    headers.append("x-test-auth", "1".parse().unwrap());
+  }
+
+  ConductorHttpRequest {
+    method: Method::POST,
+    uri: "/graphql".to_string(),
+    body: GraphQLRequest::default().into(),
+    headers,
+    ..Default::default()
+  }
+}
+
+fn test_suite() -> TestSuite {
+  TestSuite {
+    plugins: vec![Box::new(TestAuthPlugin)],
+    require_auth: true,
+    ..Default::default()
+  }
+}
+
+#[test]
+async fn require_auth_rejects_anonymous_request() {
+  let response = test_suite().run_http_request(request(false)).await;
+
+  assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+  assert_eq!(
+    std::str::from_utf8(&response.body).unwrap(),
+    r#"{"errors":[{"message":"unauthenticated request"}]}"#
+  );
+}
+
+#[test]
+async fn require_auth_allows_authenticated_request() {
+  let response = test_suite()
+    .run_with_mock(request(true), |when, then| {
+      when.method(POST).path("/graphql");
+      then
+        .status(200)
+        .header("content-type", "application/json")
+        .json_body(serde_json::json!({ "data": { "__typename": "Query" } }));
+    })
+    .await;
+
+  assert_eq!(response.status, StatusCode::OK);
+}
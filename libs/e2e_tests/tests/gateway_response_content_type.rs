@@ -0,0 +1,91 @@
+use conductor_common::{
+  graphql::GraphQLRequest,
+  http::{ConductorHttpRequest, HttpHeadersMap, Method, StatusCode, ACCEPT, CONTENT_TYPE},
+};
+use e2e::suite::TestSuite;
+use httpmock::prelude::*;
+use serde_json::json;
+use tokio::test;
+
+async fn response_content_type(
+  default_response_content_type: Option<&'static str>,
+  accept: Option<&str>,
+) -> String {
+  let mut headers = HttpHeadersMap::new();
+  headers.append(CONTENT_TYPE, "application/json".parse().unwrap());
+
+  if let Some(accept) = accept {
+    headers.append(ACCEPT, accept.parse().unwrap());
+  }
+
+  let request = ConductorHttpRequest {
+    method: Method::POST,
+    uri: "/graphql".to_string(),
+    body: GraphQLRequest::default().into(),
+    headers,
+    ..Default::default()
+  };
+
+  let response = TestSuite {
+    default_response_content_type,
+    ..Default::default()
+  }
+  .run_with_mock(request, |when, then| {
+    when.method(POST).path("/graphql");
+    then
+      .status(200)
+      .json_body(json!({ "data": { "__typename": "Query" } }));
+  })
+  .await;
+
+  assert_eq!(response.status, StatusCode::OK);
+
+  response
+    .headers
+    .get(CONTENT_TYPE)
+    .expect("expected a content type")
+    .to_str()
+    .unwrap()
+    .to_string()
+}
+
+#[test]
+async fn no_accept_uses_default_content_type() {
+  assert_eq!(response_content_type(None, None).await, "application/json");
+  assert_eq!(
+    response_content_type(Some("application/graphql-response+json"), None).await,
+    "application/graphql-response+json"
+  );
+}
+
+#[test]
+async fn wildcard_accept_uses_default_content_type() {
+  assert_eq!(
+    response_content_type(None, Some("*/*")).await,
+    "application/json"
+  );
+  assert_eq!(
+    response_content_type(Some("application/graphql-response+json"), Some("*/*")).await,
+    "application/graphql-response+json"
+  );
+}
+
+#[test]
+async fn accepted_graphql_content_type_takes_precedence() {
+  assert_eq!(
+    response_content_type(
+      None,
+      Some("application/graphql-response+json, application/json;q=0.9")
+    )
+    .await,
+    "application/graphql-response+json"
+  );
+  assert_eq!(
+    response_content_type(
+      Some("application/graphql-response+json"),
+      Some("application/json")
+    )
+    .await,
+    "application/json"
+  );
+}
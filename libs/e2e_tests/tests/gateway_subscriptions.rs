@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLRequest,
+  http::{
+    header::SEC_WEBSOCKET_PROTOCOL, ConductorHttpRequest, HttpHeadersMap, Method, StatusCode,
+    CONTENT_TYPE,
+  },
+  plugin::Plugin,
+  serde_utils::LocalFileReference,
+  source::SourceRuntime,
+};
+use conductor_config::{GraphQLSourceConfig, MockedSourceConfig};
+use conductor_engine::{
+  gateway::ConductorGateway,
+  source::{graphql_source::GraphQLSourceRuntime, mock_source::MockedSourceRuntime},
+};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+  net::{TcpListener, TcpStream},
+  task::JoinHandle,
+  test,
+};
+use tokio_tungstenite::{
+  accept_hdr_async,
+  tungstenite::{
+    handshake::server::{Request, Response},
+    Message,
+  },
+  WebSocketStream,
+};
+
+/// Sets a header on the upstream requests.
+#[derive(Debug)]
+struct TestHeaderPlugin;
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for TestHeaderPlugin {
+  async fn on_upstream_http_request(
+    &self,
+    _ctx: &mut RequestExecutionContext,
+    req: &mut ConductorHttpRequest,
+  ) {
+    req.headers.insert("x-upstream", "1".parse().unwrap());
+  }
+}
+
+fn request(operation: &str) -> ConductorHttpRequest {
+  let mut headers = HttpHeadersMap::new();
+  headers.append(CONTENT_TYPE, "application/json".parse().unwrap());
+
+  ConductorHttpRequest {
+    method: Method::POST,
+    uri: "/graphql".to_string(),
+    body: GraphQLRequest {
+      operation: operation.to_string(),
+      operation_name: None,
+      variables: None,
+      extensions: None,
+    }
+    .into(),
+    headers,
+    ..Default::default()
+  }
+}
+
+async fn receive(socket: &mut WebSocketStream<TcpStream>) -> Value {
+  match socket.next().await {
+    Some(Ok(Message::Text(text))) => serde_json::from_str::<Value>(&text).unwrap(),
+    message => panic!("unexpected message: {:?}", message),
+  }
+}
+
+/// Serves a single `graphql-transport-ws` connection, that publishes the events and completes. Returns the `x-upstream` header of the handshake and the subscribe message.
+async fn upstream(events: Vec<Value>) -> (String, JoinHandle<(Option<String>, Value)>) {
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let endpoint = format!("http://{}/graphql", listener.local_addr().unwrap());
+
+  let handle = tokio::spawn(async move {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut upstream_header = None;
+    let mut socket = accept_hdr_async(stream, |request: &Request, mut response: Response| {
+      assert_eq!(
+        request.headers().get(SEC_WEBSOCKET_PROTOCOL).unwrap(),
+        "graphql-transport-ws"
+      );
+      upstream_header = request
+        .headers()
+        .get("x-upstream")
+        .map(|value| value.to_str().unwrap().to_string());
+      response.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        "graphql-transport-ws".parse().unwrap(),
+      );
+
+      Ok(response)
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(
+      receive(&mut socket).await,
+      json!({ "type": "connection_init" })
+    );
+    socket
+      .send(Message::Text(
+        json!({ "type": "connection_ack" }).to_string(),
+      ))
+      .await
+      .unwrap();
+
+    let subscribe = receive(&mut socket).await;
+    let id = subscribe["id"].clone();
+
+    for event in events {
+      socket
+        .send(Message::Text(
+          json!({ "type": "next", "id": id, "payload": event }).to_string(),
+        ))
+        .await
+        .unwrap();
+    }
+
+    socket
+      .send(Message::Text(
+        json!({ "type": "complete", "id": id }).to_string(),
+      ))
+      .await
+      .unwrap();
+
+    (upstream_header, subscribe)
+  });
+
+  (endpoint, handle)
+}
+
+fn graphql_source(endpoint: String) -> GraphQLSourceConfig {
+  GraphQLSourceConfig {
+    endpoint: endpoint.into(),
+    ..Default::default()
+  }
+}
+
+fn mock_source() -> Arc<Box<dyn SourceRuntime>> {
+  Arc::new(Box::new(MockedSourceRuntime::new(
+    "mock".to_string(),
+    MockedSourceConfig {
+      response_data: LocalFileReference {
+        path: "mock.json".to_string(),
+        contents: json!({ "data": { "__typename": "Query" } }).to_string(),
+      },
+    },
+  )))
+}
+
+#[test]
+async fn proxies_subscription_events() {
+  let (endpoint, upstream) = upstream(vec![
+    json!({ "data": { "reviewAdded": { "body": "Great" } } }),
+    json!({ "data": { "reviewAdded": { "body": "Meh" } } }),
+  ])
+  .await;
+  let source = GraphQLSourceRuntime::new("test".to_string(), graphql_source(endpoint))
+    .await
+    .unwrap();
+
+  let results = ConductorGateway::subscribe_test(
+    Arc::new(Box::new(source)),
+    vec![Box::new(TestHeaderPlugin)],
+    request("subscription { reviewAdded { body } }"),
+    false,
+  )
+  .await
+  .unwrap()
+  .map(|result| serde_json::to_value(result).unwrap())
+  .collect::<Vec<_>>()
+  .await;
+
+  assert_eq!(
+    results,
+    vec![
+      json!({ "data": { "reviewAdded": { "body": "Great" } } }),
+      json!({ "data": { "reviewAdded": { "body": "Meh" } } }),
+    ]
+  );
+
+  let (upstream_header, subscribe) = upstream.await.unwrap();
+  assert_eq!(upstream_header.as_deref(), Some("1"));
+  assert_eq!(subscribe["type"], "subscribe");
+  assert_eq!(
+    subscribe["payload"]["query"],
+    "subscription { reviewAdded { body } }"
+  );
+}
+
+#[test]
+async fn executes_other_operations_as_single_result() {
+  let results = ConductorGateway::subscribe_test(
+    mock_source(),
+    vec![],
+    request("query { __typename }"),
+    false,
+  )
+  .await
+  .unwrap()
+  .map(|result| serde_json::to_value(result).unwrap())
+  .collect::<Vec<_>>()
+  .await;
+
+  assert_eq!(results, vec![json!({ "data": { "__typename": "Query" } })]);
+}
+
+#[test]
+async fn rejects_subscriptions() {
+  let response = ConductorGateway::subscribe_test(
+    mock_source(),
+    vec![],
+    request("subscription { reviewAdded { body } }"),
+    false,
+  )
+  .await
+  .err()
+  .unwrap();
+
+  assert_eq!(response.status, StatusCode::BAD_REQUEST);
+  assert_eq!(
+    std::str::from_utf8(&response.body).unwrap(),
+    r#"{"errors":[{"message":"the source does not support subscriptions"}]}"#
+  );
+
+  let response = ConductorGateway::subscribe_test(
+    mock_source(),
+    vec![],
+    request("subscription { reviewAdded { body } }"),
+    true,
+  )
+  .await
+  .err()
+  .unwrap();
+
+  assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+}
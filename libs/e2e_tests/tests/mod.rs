@@ -1,4 +1,15 @@
+pub mod gateway_batching;
+pub mod gateway_large_integers;
+pub mod gateway_not_found;
+pub mod gateway_require_auth;
+pub mod gateway_response_content_type;
+pub mod gateway_subscriptions;
 pub mod plugin_cors;
 pub mod plugin_disable_introspection;
+pub mod plugin_graphiql;
+pub mod plugin_graphql_validation;
+pub mod plugin_rhai;
 pub mod plugin_telemetry;
 pub mod plugin_vrl;
+pub mod source_graphql;
+pub mod source_rest;
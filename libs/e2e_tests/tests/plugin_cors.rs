@@ -234,3 +234,69 @@ async fn reflects_origin() {
     Some(&"*".parse().unwrap())
   );
 }
+
+#[test]
+async fn origin_list() {
+  let test = TestSuite {
+    plugins: vec![cors_plugin::Plugin::create(cors_plugin::Config {
+      allowed_origin: Some("http://my-server.com, http://other-server.com".to_string()),
+      ..Default::default()
+    })
+    .await
+    .unwrap()],
+    ..Default::default()
+  };
+  let preflight = |origin: &str| {
+    let mut req_headers = HttpHeadersMap::new();
+    req_headers.insert(ORIGIN, origin.parse().unwrap());
+
+    ConductorHttpRequest {
+      method: Method::OPTIONS,
+      uri: "/graphql".to_string(),
+      headers: req_headers,
+      ..Default::default()
+    }
+  };
+
+  let response = test
+    .run_http_request(preflight("http://other-server.com"))
+    .await;
+  assert_eq!(response.status, StatusCode::OK);
+  assert_eq!(
+    response.headers.get(ACCESS_CONTROL_ALLOW_ORIGIN),
+    Some(&"http://other-server.com".parse().unwrap())
+  );
+  assert_eq!(response.headers.get(VARY), Some(&"Origin".parse().unwrap()));
+
+  let response = test
+    .run_http_request(preflight("http://unknown-server.com"))
+    .await;
+  assert_eq!(response.status, StatusCode::OK);
+  assert_eq!(response.headers.get(ACCESS_CONTROL_ALLOW_ORIGIN), None);
+}
+
+#[test]
+async fn allow_private_network() {
+  let test = TestSuite {
+    plugins: vec![cors_plugin::Plugin::create(cors_plugin::Config {
+      allow_private_network: Some(true),
+      ..Default::default()
+    })
+    .await
+    .unwrap()],
+    ..Default::default()
+  };
+
+  let response = test
+    .run_http_request(ConductorHttpRequest {
+      method: Method::OPTIONS,
+      uri: "/graphql".to_string(),
+      ..Default::default()
+    })
+    .await;
+  assert_eq!(response.status, StatusCode::OK);
+  assert_eq!(
+    response.headers.get("Access-Control-Allow-Private-Network"),
+    Some(&"true".parse().unwrap())
+  );
+}
@@ -1,10 +1,11 @@
 use conductor_common::{
   graphql::GraphQLRequest,
-  http::{ConductorHttpRequest, HttpHeadersMap, Method},
+  http::{ConductorHttpRequest, ConductorHttpResponse, HttpHeadersMap, Method},
   introspection::INTROSPECTION_QUERY,
   plugin::CreatablePlugin,
   vrl_utils::VrlConfigReference,
 };
+use disable_introspection_plugin::config::IntrospectionBypassHeader;
 use e2e::suite::TestSuite;
 use tokio::test;
 
@@ -120,7 +121,7 @@ async fn disable_minimal_introspection_query_alias() {
 }
 
 #[test]
-async fn disable_type_name_only() {
+async fn allow_type_name_only() {
   let test = TestSuite {
     plugins: vec![
       disable_introspection_plugin::Plugin::create(Default::default())
@@ -137,14 +138,11 @@ async fn disable_type_name_only() {
     .await;
 
   assert_eq!(response.status, 200);
-  assert_eq!(
-    response.body,
-    "{\"errors\":[{\"message\":\"Introspection is disabled\"}]}"
-  );
+  assert_eq!(response.body, "{\"data\":{\"__typename\":\"Query\"}}");
 }
 
 #[test]
-async fn disable_typename_only_aliased() {
+async fn allow_typename_only_aliased() {
   let test = TestSuite {
     plugins: vec![
       disable_introspection_plugin::Plugin::create(Default::default())
@@ -160,10 +158,7 @@ async fn disable_typename_only_aliased() {
     })
     .await;
   assert_eq!(response.status, 200);
-  assert_eq!(
-    response.body,
-    "{\"errors\":[{\"message\":\"Introspection is disabled\"}]}"
-  );
+  assert_eq!(response.body, "{\"data\":{\"__typename\":\"Query\"}}");
 }
 
 #[test]
@@ -218,6 +213,7 @@ async fn should_allow_to_provide_simple_condition() {
         condition: Some(VrlConfigReference::Inline {
           content: "true".to_string(),
         }),
+        ..Default::default()
       },
     )
     .await
@@ -246,6 +242,7 @@ async fn should_allow_to_provide_complex_condition() {
         condition: Some(VrlConfigReference::Inline {
           content: "%downstream_http_req.method == \"POST\"".to_string(),
         }),
+        ..Default::default()
       },
     )
     .await
@@ -274,6 +271,7 @@ async fn should_allow_condition_to_bypass() {
         condition: Some(VrlConfigReference::Inline {
           content: "%downstream_http_req.headers.\"bypass-introspection\" != \"1\"".to_string(),
         }),
+        ..Default::default()
       },
     )
     .await
@@ -300,3 +298,113 @@ async fn should_allow_condition_to_bypass() {
   assert_eq!(response.status, 200);
   assert_eq!(response.body, "{\"data\":{\"__typename\":\"Query\"}}");
 }
+
+#[test]
+async fn disable_introspection_through_fragments() {
+  for operation in [
+    "query { ...Introspection } fragment Introspection on Query { __schema { queryType { name } } }",
+    "query { ... on Query { __type(name: \"Query\") { name } } }",
+  ] {
+    let test = TestSuite {
+      plugins: vec![
+        disable_introspection_plugin::Plugin::create(Default::default())
+          .await
+          .unwrap(),
+      ],
+      ..Default::default()
+    };
+    let response = test
+      .run_graphql_request(GraphQLRequest {
+        operation: operation.to_string(),
+        ..Default::default()
+      })
+      .await;
+
+    assert_eq!(response.status, 200);
+    assert_eq!(
+      response.body,
+      "{\"errors\":[{\"message\":\"Introspection is disabled\"}]}"
+    );
+  }
+}
+
+async fn run_introspection(
+  config: disable_introspection_plugin::Config,
+  peer_addr: &str,
+  headers: &[(&str, &str)],
+) -> ConductorHttpResponse {
+  let test = TestSuite {
+    plugins: vec![disable_introspection_plugin::Plugin::create(config)
+      .await
+      .unwrap()],
+    ..Default::default()
+  };
+  let mut req_headers = HttpHeadersMap::new();
+
+  for (name, value) in headers {
+    req_headers.append(*name, value.parse().unwrap());
+  }
+
+  test
+    .run_http_request(ConductorHttpRequest {
+      body: GraphQLRequest {
+        operation: "query { __schema { queryType { name } } }".to_string(),
+        ..Default::default()
+      }
+      .to_string()
+      .into(),
+      method: Method::POST,
+      headers: req_headers,
+      peer_addr: Some(peer_addr.parse().unwrap()),
+      ..Default::default()
+    })
+    .await
+}
+
+#[test]
+async fn should_allow_introspection_from_allowed_ips() {
+  let config = disable_introspection_plugin::Config {
+    allowed_ips: vec!["10.0.0.0/8".to_string(), "192.168.1.1".to_string()],
+    ..Default::default()
+  };
+
+  for (peer_addr, expected_body) in [
+    ("10.1.2.3", "{\"data\":{\"__typename\":\"Query\"}}"),
+    ("192.168.1.1", "{\"data\":{\"__typename\":\"Query\"}}"),
+    (
+      "192.168.1.2",
+      "{\"errors\":[{\"message\":\"Introspection is disabled\"}]}",
+    ),
+  ] {
+    let response = run_introspection(config.clone(), peer_addr, &[]).await;
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, expected_body, "peer address {}", peer_addr);
+  }
+}
+
+#[test]
+async fn should_allow_introspection_with_bypass_header() {
+  let config = disable_introspection_plugin::Config {
+    bypass_header: Some(IntrospectionBypassHeader {
+      name: "x-introspection-token".to_string(),
+      value: "secret".to_string(),
+    }),
+    ..Default::default()
+  };
+
+  let response = run_introspection(
+    config.clone(),
+    "127.0.0.1",
+    &[("x-introspection-token", "secret")],
+  )
+  .await;
+  assert_eq!(response.body, "{\"data\":{\"__typename\":\"Query\"}}");
+
+  let response =
+    run_introspection(config, "127.0.0.1", &[("x-introspection-token", "wrong")]).await;
+  assert_eq!(
+    response.body,
+    "{\"errors\":[{\"message\":\"Introspection is disabled\"}]}"
+  );
+}
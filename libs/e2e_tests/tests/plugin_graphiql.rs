@@ -0,0 +1,125 @@
+use conductor_common::{
+  http::{
+    header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH},
+    ConductorHttpRequest, ConductorHttpResponse, HttpHeadersMap, Method, StatusCode,
+  },
+  plugin::CreatablePlugin,
+};
+use e2e::suite::TestSuite;
+use std::collections::BTreeMap;
+use tokio::test;
+
+async fn render(config: graphiql_plugin::Config, headers: HttpHeadersMap) -> ConductorHttpResponse {
+  let test = TestSuite {
+    plugins: vec![graphiql_plugin::Plugin::create(config).await.unwrap()],
+    ..Default::default()
+  };
+
+  test
+    .run_http_request(ConductorHttpRequest {
+      method: Method::GET,
+      uri: "/graphql".to_string(),
+      headers,
+      ..Default::default()
+    })
+    .await
+}
+
+#[test]
+async fn cache_headers() {
+  let response = render(Default::default(), HttpHeadersMap::new()).await;
+  assert_eq!(response.status, StatusCode::OK);
+  assert_eq!(
+    response.headers.get(CACHE_CONTROL),
+    Some(&"no-cache".parse().unwrap())
+  );
+  assert!(response.headers.get(ETAG).is_some());
+
+  let response = render(
+    graphiql_plugin::Config {
+      cache_control: Some("public, max-age=3600".to_string()),
+      ..Default::default()
+    },
+    HttpHeadersMap::new(),
+  )
+  .await;
+  assert_eq!(
+    response.headers.get(CACHE_CONTROL),
+    Some(&"public, max-age=3600".parse().unwrap())
+  );
+}
+
+#[test]
+async fn etag_is_stable() {
+  let first = render(Default::default(), HttpHeadersMap::new()).await;
+  let second = render(Default::default(), HttpHeadersMap::new()).await;
+  assert_eq!(first.headers.get(ETAG), second.headers.get(ETAG));
+
+  // A different configuration renders a different page.
+  let other = render(
+    graphiql_plugin::Config {
+      headers_editor_enabled: Some(true),
+      ..Default::default()
+    },
+    HttpHeadersMap::new(),
+  )
+  .await;
+  assert_ne!(first.headers.get(ETAG), other.headers.get(ETAG));
+}
+
+#[test]
+async fn not_modified_on_matching_etag() {
+  let response = render(Default::default(), HttpHeadersMap::new()).await;
+  let etag = response.headers.get(ETAG).unwrap().clone();
+
+  let mut headers = HttpHeadersMap::new();
+  headers.insert(IF_NONE_MATCH, etag.clone());
+  let response = render(Default::default(), headers).await;
+  assert_eq!(response.status, StatusCode::NOT_MODIFIED);
+  assert!(response.body.is_empty());
+  assert_eq!(response.headers.get(ETAG), Some(&etag));
+
+  // Weak validators and lists of tags are also matched.
+  let mut headers = HttpHeadersMap::new();
+  headers.insert(
+    IF_NONE_MATCH,
+    format!("\"other\", W/{}", etag.to_str().unwrap())
+      .parse()
+      .unwrap(),
+  );
+  let response = render(Default::default(), headers).await;
+  assert_eq!(response.status, StatusCode::NOT_MODIFIED);
+}
+
+#[test]
+async fn full_response_on_mismatching_etag() {
+  let mut headers = HttpHeadersMap::new();
+  headers.insert(IF_NONE_MATCH, "\"outdated\"".parse().unwrap());
+  let response = render(Default::default(), headers).await;
+  assert_eq!(response.status, StatusCode::OK);
+  assert!(!response.body.is_empty());
+}
+
+#[test]
+async fn customized_page() {
+  let response = render(
+    graphiql_plugin::Config {
+      title: Some("Admin <API>".to_string()),
+      default_query: Some("query { __typename }".to_string()),
+      default_headers: Some(BTreeMap::from([(
+        "Authorization".to_string(),
+        "Bearer token".to_string(),
+      )])),
+      ..Default::default()
+    },
+    HttpHeadersMap::new(),
+  )
+  .await;
+  assert_eq!(response.status, StatusCode::OK);
+
+  let body = String::from_utf8(response.body.to_vec()).unwrap();
+  assert!(body.contains("<title>Admin &lt;API&gt;</title>"));
+  assert!(body.contains(r#""title":"Admin \u003cAPI>""#));
+  assert!(body.contains(r#""query":"query { __typename }""#));
+  assert!(body.contains(r#""headers":"{\n  \"Authorization\": \"Bearer token\"\n}""#));
+}
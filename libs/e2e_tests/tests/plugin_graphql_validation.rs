@@ -0,0 +1,72 @@
+use conductor_common::{graphql::GraphQLRequest, http::StatusCode, plugin::CreatablePlugin};
+use e2e::suite::TestSuite;
+use serde_json::{json, Map, Value};
+use tokio::test;
+
+fn variables(count: usize) -> Option<Map<String, Value>> {
+  Some(
+    (0..count)
+      .map(|i| (format!("v{}", i), json!(i)))
+      .collect::<Map<String, Value>>(),
+  )
+}
+
+async fn plugin(max_variables: Option<usize>) -> Box<graphql_validation_plugin::Plugin> {
+  graphql_validation_plugin::Plugin::create(graphql_validation_plugin::Config { max_variables })
+    .await
+    .unwrap()
+}
+
+#[test]
+async fn variables_count_within_limit() {
+  let test = TestSuite {
+    plugins: vec![plugin(Some(3)).await],
+    ..Default::default()
+  };
+
+  let response = test
+    .run_graphql_request(GraphQLRequest {
+      variables: variables(3),
+      ..Default::default()
+    })
+    .await;
+  assert_eq!(response.status, StatusCode::OK);
+}
+
+#[test]
+async fn variables_count_over_limit() {
+  let test = TestSuite {
+    plugins: vec![plugin(Some(3)).await],
+    ..Default::default()
+  };
+
+  let response = test
+    .run_graphql_request(GraphQLRequest {
+      variables: variables(4),
+      ..Default::default()
+    })
+    .await;
+  assert_eq!(response.status, StatusCode::BAD_REQUEST);
+  assert_eq!(
+    serde_json::from_slice::<Value>(&response.body).unwrap(),
+    json!({
+      "errors": [{ "message": "operation has 4 variables, exceeding the maximum of 3" }]
+    })
+  );
+}
+
+#[test]
+async fn variables_count_not_limited_by_default() {
+  let test = TestSuite {
+    plugins: vec![plugin(None).await],
+    ..Default::default()
+  };
+
+  let response = test
+    .run_graphql_request(GraphQLRequest {
+      variables: variables(100),
+      ..Default::default()
+    })
+    .await;
+  assert_eq!(response.status, StatusCode::OK);
+}
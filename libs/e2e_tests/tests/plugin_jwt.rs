@@ -64,15 +64,29 @@ pub mod jwt {
           },
         }],
         allowed_algorithms: None,
+        cookie_parse_failure: None,
+        multiple_headers: None,
+        leeway_secs: None,
+        require_expiration: None,
+        validate_nbf: None,
+        required_scopes: None,
+        scope_claim: None,
+        token_cache_size: None,
+        revoked_tokens: None,
+        context_key_prefix: None,
         audiences: None,
+        audience_match: None,
         issuers: None,
         forward_claims_to_upstream_header: Some("X-Forwarded-Claims".to_string()),
+        claim_header_mappings: vec![],
         forward_token_to_upstream_header: Some("X-Forwarded-Token".to_string()),
         lookup_locations: vec![jwt_auth_plugin::LookupLocation::Header {
           name: "Authorization".to_string(),
           prefix: Some("Bearer".to_string()),
+          prefix_exact: None,
         }],
         reject_unauthenticated_requests: Some(true),
+        allow_anonymous: None,
       })
       .await
       .unwrap()],
@@ -128,6 +142,110 @@ pub mod jwt {
     assert_eq!(response.body, "{\"data\":{\"__typename\":\"Query\"}}");
   }
 
+  #[test]
+  async fn claim_header_mappings_flow() {
+    let test = TestSuite {
+      plugins: vec![jwt_auth_plugin::Plugin::create(jwt_auth_plugin::Config {
+        jwks_providers: vec![jwt_auth_plugin::JwksProvider::Local {
+          file: LocalFileReference {
+            path: String::from("jwks.json"),
+            contents: JWKS_RSA512_2045_PUBLIC_KEY.to_string(),
+          },
+        }],
+        allowed_algorithms: None,
+        cookie_parse_failure: None,
+        multiple_headers: None,
+        leeway_secs: None,
+        require_expiration: None,
+        validate_nbf: None,
+        required_scopes: None,
+        scope_claim: None,
+        token_cache_size: None,
+        revoked_tokens: None,
+        context_key_prefix: None,
+        audiences: None,
+        audience_match: None,
+        issuers: None,
+        forward_claims_to_upstream_header: None,
+        claim_header_mappings: vec![
+          ClaimHeaderMapping {
+            claim: "sub".to_string(),
+            header: "X-User-Id".to_string(),
+          },
+          ClaimHeaderMapping {
+            claim: "roles".to_string(),
+            header: "X-User-Roles".to_string(),
+          },
+          ClaimHeaderMapping {
+            claim: "email".to_string(),
+            header: "X-User-Email".to_string(),
+          },
+        ],
+        forward_token_to_upstream_header: None,
+        lookup_locations: vec![jwt_auth_plugin::LookupLocation::Header {
+          name: "Authorization".to_string(),
+          prefix: Some("Bearer".to_string()),
+          prefix_exact: None,
+        }],
+        reject_unauthenticated_requests: Some(true),
+        allow_anonymous: None,
+      })
+      .await
+      .unwrap()],
+      ..Default::default()
+    };
+    let token = encode::<ClaimsJsonObject>(
+      &JwtHeader {
+        alg: Algorithm::RS512,
+        ..Default::default()
+      },
+      &json!({
+        "sub": "user-1",
+        "roles": ["admin"],
+        "exp": 1924942936
+      }),
+      &EncodingKey::from_rsa_pem(JWKS_RSA512_PRIVATE_PEM.as_bytes()).unwrap(),
+    )
+    .unwrap();
+
+    let formatted_token = format!("Bearer {}", token);
+    let response = test
+      .run_with_mock(
+        ConductorHttpRequest {
+          method: Method::POST,
+          uri: "/graphql".to_string(),
+          headers: vec![("Authorization", formatted_token.as_str())]
+            .to_headers_map()
+            .unwrap(),
+          ..Default::default()
+        },
+        |when, then| {
+          when
+            .method(POST)
+            .path("/graphql")
+            .header("x-user-id", "user-1")
+            .header("x-user-roles", "[\"admin\"]")
+            .header_missing("x-user-email");
+          then
+            .status(200)
+            .header("content-type", "application/json")
+            .body(
+              json!({
+                  "data": {
+                      "__typename": "Query"
+                  },
+                  "errors": null
+              })
+              .to_string(),
+            );
+        },
+      )
+      .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, "{\"data\":{\"__typename\":\"Query\"}}");
+  }
+
   #[test]
   async fn invalid_token_not_rejected() {
     let test = TestSuite {
@@ -139,15 +257,29 @@ pub mod jwt {
           },
         }],
         allowed_algorithms: None,
+        cookie_parse_failure: None,
+        multiple_headers: None,
+        leeway_secs: None,
+        require_expiration: None,
+        validate_nbf: None,
+        required_scopes: None,
+        scope_claim: None,
+        token_cache_size: None,
+        revoked_tokens: None,
+        context_key_prefix: None,
         audiences: None,
+        audience_match: None,
         issuers: None,
         forward_claims_to_upstream_header: Some("X-Forwarded-Claims".to_string()),
+        claim_header_mappings: vec![],
         forward_token_to_upstream_header: Some("X-Forwarded-Token".to_string()),
         lookup_locations: vec![jwt_auth_plugin::LookupLocation::Header {
           name: "Authorization".to_string(),
           prefix: Some("Bearer".to_string()),
+          prefix_exact: None,
         }],
         reject_unauthenticated_requests: Some(false),
+        allow_anonymous: None,
       })
       .await
       .unwrap()],
@@ -194,15 +326,29 @@ pub mod jwt {
           },
         }],
         allowed_algorithms: None,
+        cookie_parse_failure: None,
+        multiple_headers: None,
+        leeway_secs: None,
+        require_expiration: None,
+        validate_nbf: None,
+        required_scopes: None,
+        scope_claim: None,
+        token_cache_size: None,
+        revoked_tokens: None,
+        context_key_prefix: None,
         audiences: None,
+        audience_match: None,
         issuers: None,
         forward_claims_to_upstream_header: Some("X-Forwarded-Claims".to_string()),
+        claim_header_mappings: vec![],
         forward_token_to_upstream_header: Some("X-Forwarded-Token".to_string()),
         lookup_locations: vec![jwt_auth_plugin::LookupLocation::Header {
           name: "Authorization".to_string(),
           prefix: Some("Bearer".to_string()),
+          prefix_exact: None,
         }],
         reject_unauthenticated_requests: Some(true),
+        allow_anonymous: None,
       })
       .await
       .unwrap()],
@@ -236,15 +382,29 @@ pub mod jwt {
           },
         }],
         allowed_algorithms: None,
+        cookie_parse_failure: None,
+        multiple_headers: None,
+        leeway_secs: None,
+        require_expiration: None,
+        validate_nbf: None,
+        required_scopes: None,
+        scope_claim: None,
+        token_cache_size: None,
+        revoked_tokens: None,
+        context_key_prefix: None,
         audiences: None,
+        audience_match: None,
         issuers: None,
         forward_claims_to_upstream_header: Some("X-Forwarded-Claims".to_string()),
+        claim_header_mappings: vec![],
         forward_token_to_upstream_header: Some("X-Forwarded-Token".to_string()),
         lookup_locations: vec![jwt_auth_plugin::LookupLocation::Header {
           name: "Authorization".to_string(),
           prefix: Some("Bearer".to_string()),
+          prefix_exact: None,
         }],
         reject_unauthenticated_requests: Some(true),
+        allow_anonymous: None,
       })
       .await
       .unwrap()],
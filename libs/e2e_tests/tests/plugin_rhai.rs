@@ -0,0 +1,195 @@
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLRequest,
+  http::{
+    Bytes, ConductorHttpRequest, ConductorHttpResponse, HeaderValue, HttpHeadersMap, Method,
+    StatusCode,
+  },
+  plugin::{CreatablePlugin, Plugin},
+};
+use e2e::suite::TestSuite;
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use tokio::test;
+
+fn inline(content: &str) -> Option<rhai_plugin::Script> {
+  Some(rhai_plugin::Script::Inline {
+    content: content.to_string(),
+  })
+}
+
+#[test]
+async fn script_adds_headers() {
+  let plugin = rhai_plugin::Plugin::create(rhai_plugin::Config {
+    on_downstream_http_request: inline(
+      r#"
+        if "x-tenant" in request.headers {
+          context.tenant = request.headers["x-tenant"];
+        }
+      "#,
+    ),
+    on_upstream_http_request: inline(
+      r#"
+        request.headers["x-upstream-tenant"] = context.tenant;
+      "#,
+    ),
+    on_downstream_http_response: inline(
+      r#"
+        response.headers["x-powered-by"] = "conductor";
+      "#,
+    ),
+    ..Default::default()
+  })
+  .await
+  .unwrap();
+
+  let mut headers = HttpHeadersMap::default();
+  headers.append("content-type", HeaderValue::from_static("application/json"));
+  headers.append("x-tenant", HeaderValue::from_static("acme"));
+
+  let test = TestSuite {
+    plugins: vec![plugin],
+    ..Default::default()
+  };
+  let response = test
+    .run_with_mock(
+      ConductorHttpRequest {
+        body: GraphQLRequest::default().into(),
+        uri: String::from("/graphql"),
+        query_string: String::from(""),
+        method: Method::POST,
+        headers,
+        peer_addr: None,
+      },
+      |when, then| {
+        when
+          .method(POST)
+          .path("/graphql")
+          .header("x-upstream-tenant", "acme");
+        then
+          .status(200)
+          .header("content-type", "application/json")
+          .body(json!({ "data": { "__typename": "Query" } }).to_string());
+      },
+    )
+    .await;
+
+  assert_eq!(response.status, StatusCode::OK);
+  assert_eq!(
+    response.headers.get("x-powered-by"),
+    Some(&HeaderValue::from_static("conductor"))
+  );
+}
+
+#[test]
+async fn script_keeps_unchanged_fields() {
+  let plugin = rhai_plugin::Plugin::create(rhai_plugin::Config {
+    on_downstream_http_response: inline(
+      r#"
+        response.headers["x-powered-by"] = "conductor";
+        response.headers["vary"].push("origin");
+      "#,
+    ),
+    ..Default::default()
+  })
+  .await
+  .unwrap();
+
+  let mut headers = HttpHeadersMap::default();
+  headers.append("set-cookie", HeaderValue::from_static("first=1"));
+  headers.append("set-cookie", HeaderValue::from_static("second=2"));
+  headers.append("x-binary", HeaderValue::from_bytes(&[0xfe]).unwrap());
+  headers.append("vary", HeaderValue::from_static("accept-encoding"));
+  // A gzip header, which is not valid UTF-8.
+  let body = Bytes::from_static(&[0x1f, 0x8b, 0x08, 0xff]);
+  let mut response = ConductorHttpResponse {
+    body: body.clone(),
+    status: StatusCode::OK,
+    headers,
+  };
+
+  plugin.on_downstream_http_response(
+    &mut RequestExecutionContext::new(Default::default()),
+    &mut response,
+  );
+
+  assert_eq!(response.body, body);
+  assert_eq!(
+    response
+      .headers
+      .get_all("set-cookie")
+      .iter()
+      .collect::<Vec<_>>(),
+    vec!["first=1", "second=2"]
+  );
+  assert_eq!(
+    response.headers.get("x-binary").unwrap().as_bytes(),
+    &[0xfe]
+  );
+  assert_eq!(
+    response.headers.get_all("vary").iter().collect::<Vec<_>>(),
+    vec!["accept-encoding", "origin"]
+  );
+  assert_eq!(response.headers.get("x-powered-by").unwrap(), "conductor");
+}
+
+#[test]
+async fn script_short_circuit() {
+  let plugin = rhai_plugin::Plugin::create(rhai_plugin::Config {
+    on_downstream_http_request: inline(
+      r#"
+        if !("authorization" in request.headers) {
+          short_circuit(403, "Missing authorization header");
+        }
+      "#,
+    ),
+    ..Default::default()
+  })
+  .await
+  .unwrap();
+
+  let test = TestSuite {
+    plugins: vec![plugin],
+    ..Default::default()
+  };
+  let response = test.run_graphql_request(Default::default()).await;
+
+  assert_eq!(response.status, StatusCode::FORBIDDEN);
+  assert_eq!(
+    serde_json::from_slice::<Value>(&response.body).unwrap(),
+    json!({ "errors": [{ "message": "Missing authorization header" }] })
+  );
+}
+
+#[test]
+async fn script_exceeding_limits() {
+  let plugin = rhai_plugin::Plugin::create(rhai_plugin::Config {
+    on_downstream_http_request: inline("loop { }"),
+    limits: rhai_plugin::Limits {
+      max_operations: 1_000,
+      ..Default::default()
+    },
+    ..Default::default()
+  })
+  .await
+  .unwrap();
+
+  let test = TestSuite {
+    plugins: vec![plugin],
+    ..Default::default()
+  };
+  let response = test.run_graphql_request(Default::default()).await;
+
+  assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[test]
+async fn script_compilation_error() {
+  let plugin = rhai_plugin::Plugin::create(rhai_plugin::Config {
+    on_downstream_http_request: inline("let x = ;"),
+    ..Default::default()
+  })
+  .await;
+
+  assert!(plugin.is_err());
+}
@@ -1,11 +1,16 @@
 pub mod telemetry {
-  use conductor_common::{graphql::GraphQLRequest, plugin::CreatablePlugin};
+  use conductor_common::{
+    graphql::GraphQLRequest,
+    http::{ConductorHttpRequest, HeaderValue, HttpHeadersMap, Method},
+    plugin::CreatablePlugin,
+  };
   use conductor_tracing::reporters::TracingReporter;
   use conductor_tracing::routed_reporter::test_utils::TestReporter;
   use conductor_tracing::{
     minitrace_mgr::MinitraceManager, otel_attrs::*, trace_id::generate_trace_id,
   };
   use e2e::suite::TestSuite;
+  use httpmock::Method::POST;
   use minitrace::{
     collector::{Config, SpanContext, SpanId},
     future::FutureExt,
@@ -121,4 +126,51 @@ pub mod telemetry {
       (HTTP_STATUS_CODE.into(), "200".into())
     );
   }
+
+  #[test]
+  async fn propagates_trace_context() {
+    let plugin = telemetry_plugin::Plugin::create(telemetry_plugin::Config {
+      targets: vec![telemetry_plugin::Target::Stdout],
+      propagate_trace_context: true,
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let mut headers = HttpHeadersMap::default();
+    headers.append("content-type", HeaderValue::from_static("application/json"));
+    headers.append(
+      "traceparent",
+      HeaderValue::from_static("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"),
+    );
+
+    let test = TestSuite {
+      plugins: vec![plugin],
+      ..Default::default()
+    };
+    let span_context = SpanContext::new(generate_trace_id(0), SpanId::default());
+    let root_span = Span::root("root", span_context);
+    test
+      .run_with_mock(
+        ConductorHttpRequest {
+          body: GraphQLRequest::default().into(),
+          uri: String::from("/graphql"),
+          method: Method::POST,
+          headers,
+          ..Default::default()
+        },
+        |when, then| {
+          when
+            .method(POST)
+            .path("/graphql")
+            .header_exists("traceparent");
+          then
+            .status(200)
+            .header("content-type", "application/json")
+            .body("{\"data\":{\"__typename\":\"Query\"}}");
+        },
+      )
+      .in_span(root_span)
+      .await;
+  }
 }
@@ -57,6 +57,7 @@ async fn complete_flow_with_shared_state() {
     query_string: String::from(""),
     method: Method::POST,
     headers: header_map,
+    peer_addr: None,
   };
 
   let http_mock = MockServer::start();
@@ -124,6 +125,7 @@ async fn test_waterfall_of_hooks() {
     query_string: String::from(""),
     method: Method::POST,
     headers: header_map,
+    peer_addr: None,
   };
 
   let plugin = vrl_plugin::Plugin::create(vrl_plugin::Config {
@@ -233,6 +235,7 @@ async fn test_vrl_on_downstream_request_input_output() {
     query_string: String::from("test=1"),
     method: Method::POST,
     headers: header_map,
+    peer_addr: None,
   };
 
   let test = TestSuite {
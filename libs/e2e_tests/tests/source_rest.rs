@@ -0,0 +1,292 @@
+use conductor_common::source::SourceRuntime;
+use conductor_common::{
+  graphql::GraphQLRequest,
+  http::{ConductorHttpRequest, HttpHeadersMap, Method, StatusCode, CONTENT_TYPE},
+};
+use conductor_config::{OpenApiSpecSource, RestSourceConfig};
+use conductor_engine::{gateway::ConductorGateway, source::rest_source::RestSourceRuntime};
+use httpmock::prelude::*;
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::test;
+
+static SPEC: &str = r#"
+openapi: 3.0.0
+paths:
+  /pets/{petId}:
+    get:
+      operationId: getPet
+      parameters:
+        - name: petId
+          in: path
+        - name: fields
+          in: query
+  /pets:
+    post:
+      operationId: createPet
+      requestBody:
+        content:
+          application/json: {}
+"#;
+
+fn rest_config(mock_server: &MockServer) -> RestSourceConfig {
+  RestSourceConfig {
+    spec: OpenApiSpecSource::Inline {
+      content: SPEC.to_string(),
+    },
+    base_url: Some(mock_server.url("/api")),
+    headers: BTreeMap::from([(
+      "authorization".to_string(),
+      "Bearer {header.x-api-token}".to_string(),
+    )]),
+    timeout_ms: None,
+    max_requests: None,
+    max_concurrent_requests: None,
+  }
+}
+
+async fn rest_source(mock_server: &MockServer) -> RestSourceRuntime {
+  RestSourceRuntime::new("test".to_string(), rest_config(mock_server))
+    .await
+    .expect("failed to create source")
+}
+
+async fn execute(source: RestSourceRuntime, operation: &str, variables: Option<Value>) -> Value {
+  let mut headers = HttpHeadersMap::new();
+  headers.append(CONTENT_TYPE, "application/json".parse().unwrap());
+  headers.append("x-api-token", "secret".parse().unwrap());
+
+  let response = ConductorGateway::execute_test(
+    Arc::new(Box::new(source)),
+    vec![],
+    ConductorHttpRequest {
+      method: Method::POST,
+      uri: "/graphql".to_string(),
+      body: GraphQLRequest {
+        operation: operation.to_string(),
+        operation_name: None,
+        variables: variables.and_then(|variables| variables.as_object().cloned()),
+        extensions: None,
+      }
+      .to_string()
+      .into(),
+      headers,
+      ..Default::default()
+    },
+    false,
+    None,
+    None,
+  )
+  .await;
+
+  assert_eq!(response.status, StatusCode::OK);
+  serde_json::from_slice(&response.body).unwrap()
+}
+
+#[test]
+async fn resolves_queries() {
+  let mock_server = MockServer::start_async().await;
+  let mock = mock_server
+    .mock_async(|when, then| {
+      when
+        .method(GET)
+        .path("/api/pets/1")
+        .query_param("fields", "all")
+        .header("authorization", "Bearer secret");
+      then
+        .status(200)
+        .header("content-type", "application/json")
+        .json_body(json!({ "id": 1, "name": "Rex", "owner": { "id": 2, "email": "a@b.c" } }));
+    })
+    .await;
+
+  let body = execute(
+    rest_source(&mock_server).await,
+    r#"{ __typename pet: getPet(petId: 1, fields: "all") { id name owner { id } } }"#,
+    None,
+  )
+  .await;
+
+  assert_eq!(
+    body,
+    json!({
+      "data": {
+        "__typename": "Query",
+        "pet": { "id": 1, "name": "Rex", "owner": { "id": 2 } }
+      }
+    })
+  );
+  mock.assert_async().await;
+}
+
+#[test]
+async fn resolves_mutations() {
+  let mock_server = MockServer::start_async().await;
+  let mock = mock_server
+    .mock_async(|when, then| {
+      when
+        .method(POST)
+        .path("/api/pets")
+        .json_body(json!({ "name": "Rex" }));
+      then
+        .status(201)
+        .header("content-type", "application/json")
+        .json_body(json!({ "id": 1, "name": "Rex" }));
+    })
+    .await;
+
+  let body = execute(
+    rest_source(&mock_server).await,
+    "mutation ($input: JSON) { createPet(input: $input) { id } }",
+    Some(json!({ "input": { "name": "Rex" } })),
+  )
+  .await;
+
+  assert_eq!(body, json!({ "data": { "createPet": { "id": 1 } } }));
+  mock.assert_async().await;
+}
+
+#[test]
+async fn reports_field_errors() {
+  let mock_server = MockServer::start_async().await;
+  mock_server
+    .mock_async(|when, then| {
+      when.method(GET).path("/api/pets/1");
+      then.status(404);
+    })
+    .await;
+
+  let body = execute(
+    rest_source(&mock_server).await,
+    r#"{ getPet(petId: "1") { id } unknown }"#,
+    None,
+  )
+  .await;
+
+  assert_eq!(
+    body,
+    json!({
+      "data": { "getPet": null, "unknown": null },
+      "errors": [
+        {
+          "message": "Cannot query field \"unknown\" on type \"Query\".",
+          "path": ["unknown"]
+        },
+        {
+          "message": "REST API responded with HTTP status 404 Not Found",
+          "path": ["getPet"]
+        }
+      ]
+    })
+  );
+}
+
+#[test]
+async fn requires_base_url() {
+  let error = RestSourceRuntime::new(
+    "test".to_string(),
+    RestSourceConfig {
+      spec: OpenApiSpecSource::Inline {
+        content: SPEC.to_string(),
+      },
+      base_url: None,
+      headers: BTreeMap::new(),
+      timeout_ms: None,
+      max_requests: None,
+      max_concurrent_requests: None,
+    },
+  )
+  .await
+  .unwrap_err();
+
+  assert!(format!("{:?}", error).contains("base_url must be specified"));
+}
+
+#[test]
+async fn rejects_dot_segments() {
+  let mock_server = MockServer::start_async().await;
+  let mock = mock_server
+    .mock_async(|when, then| {
+      when.method(GET);
+      then.status(200).json_body(json!({ "id": 1 }));
+    })
+    .await;
+
+  let body = execute(
+    rest_source(&mock_server).await,
+    r#"{ getPet(petId: "..") { id } }"#,
+    None,
+  )
+  .await;
+
+  assert_eq!(
+    body,
+    json!({
+      "data": { "getPet": null },
+      "errors": [
+        {
+          "message": "invalid value for argument \"petId\": empty and dot segments are not allowed",
+          "path": ["getPet"]
+        }
+      ]
+    })
+  );
+  mock.assert_hits_async(0).await;
+}
+
+#[test]
+async fn limits_requests() {
+  let mock_server = MockServer::start_async().await;
+  let mock = mock_server
+    .mock_async(|when, then| {
+      when.method(GET).path_contains("/api/pets/");
+      then.status(200).json_body(json!({ "id": 1 }));
+    })
+    .await;
+  let source = RestSourceRuntime::new(
+    "test".to_string(),
+    RestSourceConfig {
+      max_requests: Some(2),
+      ..rest_config(&mock_server)
+    },
+  )
+  .await
+  .expect("failed to create source");
+
+  let body = execute(
+    source,
+    "{ a: getPet(petId: 1) { id } b: getPet(petId: 2) { id } c: getPet(petId: 3) { id } }",
+    None,
+  )
+  .await;
+
+  assert_eq!(
+    body,
+    json!({
+      "errors": [
+        { "message": "the operation sends 3 requests to the REST API, the maximum is 2" }
+      ]
+    })
+  );
+  mock.assert_hits_async(0).await;
+}
+
+#[test]
+async fn generates_schema() {
+  let mock_server = MockServer::start_async().await;
+  let source = rest_source(&mock_server).await;
+
+  assert_eq!(
+    source.sdl().unwrap().as_str(),
+    r#"scalar JSON
+
+type Query {
+  getPet(petId: JSON!, fields: JSON): JSON
+}
+
+type Mutation {
+  createPet(input: JSON): JSON
+}"#
+  );
+  assert!(source.schema().is_some());
+}
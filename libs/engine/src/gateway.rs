@@ -1,28 +1,38 @@
 use std::{collections::HashMap, fmt::Debug, sync::Arc};
 
 use conductor_common::{
-  execute::RequestExecutionContext,
+  execute::{RequestExecutionContext, ENDPOINT_CONTEXT_KEY},
   graphql::{ExtractGraphQLOperationError, GraphQLRequest, GraphQLResponse, ParsedGraphQLRequest},
-  http::{ConductorHttpRequest, ConductorHttpResponse, Url},
+  http::{
+    header::{ACCEPT_ENCODING, CONTENT_LENGTH, LOCATION, RETRY_AFTER, SET_COOKIE},
+    Bytes, ConductorHttpRequest, ConductorHttpResponse, HeaderValue, HttpHeadersMap, Url,
+    CONTENT_TYPE,
+  },
   plugin::PluginError,
   plugin_manager::PluginManager,
-  source::{GraphQLSourceInitError, SourceError, SourceRuntime},
+  source::{GraphQLResponseStream, GraphQLSourceInitError, SourceError, SourceRuntime},
+};
+use conductor_config::{
+  ConductorConfig, EndpointDefinition, NotFoundResponseConfig, NotFoundResponseFormat,
+  PluginDefinition, SourceDefinition,
 };
-use conductor_config::{ConductorConfig, EndpointDefinition, SourceDefinition};
 use conductor_tracing::{
   minitrace_mgr::MinitraceManager,
   otel_attrs::CONDUCTOR_SOURCE,
   otel_utils::{create_graphql_error_span_properties, create_graphql_span},
 };
+use futures::future::join_all;
+use match_content_type_plugin::DEFAULT_RESPONSE_CONTENT_TYPE;
 use minitrace::{future::FutureExt, trace, Span};
 use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
 use tracing::error;
 
 use crate::{
   plugin_manager::PluginManagerImpl,
   source::{
     federation_source::FederationSourceRuntime, graphql_source::GraphQLSourceRuntime,
-    mock_source::MockedSourceRuntime,
+    mock_source::MockedSourceRuntime, rest_source::RestSourceRuntime,
   },
 };
 
@@ -32,6 +42,28 @@ pub struct ConductorGatewayRouteData {
   pub tenant_id: u32,
   pub plugin_manager: Arc<Box<dyn PluginManager>>,
   pub to: Arc<Box<dyn SourceRuntime>>,
+  pub require_auth: bool,
+  pub max_batch_size: Option<usize>,
+  /// The smallest `max_body_bytes` of the enabled `max_body_size` plugins of the endpoint, so servers can stop reading larger bodies instead of buffering them.
+  pub max_body_bytes: Option<usize>,
+}
+
+/// Every enabled `max_body_size` plugin rejects the bodies above its limit, so the smallest one applies.
+fn max_body_bytes(plugin_lists: &[&Option<Vec<PluginDefinition>>]) -> Option<usize> {
+  plugin_lists
+    .iter()
+    .copied()
+    .flatten()
+    .flatten()
+    .filter_map(|plugin| match plugin {
+      PluginDefinition::MaxBodySizePlugin {
+        enabled: Some(true),
+        config,
+        ..
+      } => Some(config.clone().unwrap_or_default().max_body_bytes),
+      _ => None,
+    })
+    .min()
 }
 
 #[derive(Debug)]
@@ -43,6 +75,7 @@ pub struct ConductorGatewayRoute {
 #[derive(Debug)]
 pub struct ConductorGateway {
   pub routes: Vec<ConductorGatewayRoute>,
+  pub not_found: NotFoundResponseConfig,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -55,6 +88,23 @@ pub enum GatewayError {
   MissingSource(String),
   #[error("failed to initialize source '{0}': {1}")]
   SourceInitFailed(String, GraphQLSourceInitError),
+  #[error("invalid default response content type: \"{0}\"")]
+  InvalidResponseContentType(String),
+}
+
+/// A batched request is a `POST` request whose body is a JSON array of operations.
+fn is_batched_request(request: &ConductorHttpRequest) -> bool {
+  request.method == Method::POST
+    && request
+      .body
+      .iter()
+      .find(|byte| !byte.is_ascii_whitespace())
+      .is_some_and(|byte| *byte == b'[')
+}
+
+/// `Retry-After` headers with an HTTP date are not compared, so they never replace a delay in seconds.
+fn retry_after_seconds(value: Option<&HeaderValue>) -> Option<u64> {
+  value?.to_str().ok()?.trim().parse().ok()
 }
 
 impl ConductorGateway {
@@ -75,6 +125,53 @@ impl ConductorGateway {
     Err(GatewayError::MissingEndpoint(route.path().to_string()))
   }
 
+  /// Builds the response for requests that do not match any of the configured routes.
+  pub fn not_found_response(&self) -> ConductorHttpResponse {
+    let mut headers = HttpHeadersMap::new();
+
+    if let Some(redirect) = &self.not_found.redirect {
+      let status = StatusCode::from_u16(self.not_found.status)
+        .ok()
+        .filter(|status| status.is_redirection())
+        .unwrap_or(StatusCode::FOUND);
+
+      match HeaderValue::from_str(redirect) {
+        Ok(location) => {
+          headers.insert(LOCATION, location);
+
+          return ConductorHttpResponse {
+            body: Default::default(),
+            status,
+            headers,
+          };
+        }
+        Err(e) => error!(
+          "invalid not-found redirect location \"{}\", ignoring: {}",
+          redirect, e
+        ),
+      }
+    }
+
+    let status = StatusCode::from_u16(self.not_found.status).unwrap_or(StatusCode::NOT_FOUND);
+    let (body, content_type): (Bytes, HeaderValue) = match self.not_found.format {
+      NotFoundResponseFormat::GraphQL => (
+        GraphQLResponse::new_error(&self.not_found.message).into(),
+        HeaderValue::from_static("application/json"),
+      ),
+      NotFoundResponseFormat::Text => (
+        self.not_found.message.clone().into(),
+        HeaderValue::from_static("text/plain"),
+      ),
+    };
+    headers.insert(CONTENT_TYPE, content_type);
+
+    ConductorHttpResponse {
+      body,
+      status,
+      headers,
+    }
+  }
+
   async fn create_source(
     def: &SourceDefinition,
   ) -> Result<Box<dyn SourceRuntime>, GraphQLSourceInitError> {
@@ -88,6 +185,9 @@ impl ConductorGateway {
       SourceDefinition::Mock { id, config } => {
         Box::new(MockedSourceRuntime::new(id.clone(), config.clone()))
       }
+      SourceDefinition::Rest { id, config } => {
+        Box::new(RestSourceRuntime::new(id.clone(), config.clone()).await?)
+      }
     })
   }
 
@@ -98,24 +198,30 @@ impl ConductorGateway {
     source_runtime: Arc<Box<dyn SourceRuntime>>,
     tracing_manager: &mut MinitraceManager,
   ) -> Result<ConductorGatewayRouteData, GatewayError> {
-    let global_plugins = &config_object.plugins;
-    let combined_plugins = global_plugins
-      .iter()
-      .chain(&endpoint_config.plugins)
-      .flat_map(|vec| vec.iter())
-      .cloned()
-      .collect::<Vec<_>>();
+    let default_response_content_type = match &endpoint_config.default_response_content_type {
+      Some(content_type) => HeaderValue::from_str(content_type)
+        .map_err(|_| GatewayError::InvalidResponseContentType(content_type.clone()))?,
+      None => HeaderValue::from_static(DEFAULT_RESPONSE_CONTENT_TYPE),
+    };
 
-    let plugin_manager =
-      PluginManagerImpl::new(&Some(combined_plugins), tracing_manager, tenant_id)
-        .await
-        .map_err(GatewayError::PluginManagerInitError)?;
+    // Global plugins are applied before endpoint-specific plugins, whatever their `order`.
+    let plugin_manager = PluginManagerImpl::new(
+      &[&config_object.plugins, &endpoint_config.plugins],
+      tracing_manager,
+      tenant_id,
+      default_response_content_type,
+    )
+    .await
+    .map_err(GatewayError::PluginManagerInitError)?;
 
     let route_data = ConductorGatewayRouteData {
       endpoint: endpoint_config.path.clone(),
       to: source_runtime,
       plugin_manager: Arc::new(Box::new(plugin_manager)),
       tenant_id,
+      require_auth: endpoint_config.require_auth.unwrap_or(false),
+      max_batch_size: endpoint_config.max_batch_size,
+      max_body_bytes: max_body_bytes(&[&config_object.plugins, &endpoint_config.plugins]),
     };
 
     Ok(route_data)
@@ -141,66 +247,102 @@ impl ConductorGateway {
         .get(&endpoint_config.from)
         .ok_or_else(|| GatewayError::MissingSource(endpoint_config.from.clone()))?;
 
-      let route_data = match Self::construct_endpoint(
+      let route_data = Self::construct_endpoint(
         index.try_into().unwrap(),
         config_object,
         endpoint_config,
         upstream_source.clone(),
         tracing_manager,
       )
-      .await
-      {
-        Ok(route_data) => ConductorGatewayRoute {
-          base_path: endpoint_config.path.clone(),
-          route_data: Arc::new(route_data),
-        },
-        // @expected: if we are unable to construct the endpoints and attach them onto the gateway's http server, we have to exit
-        Err(e) => panic!("failed to construct endpoint: {:?}", e),
-      };
+      .await?;
 
-      route_mapping.push(route_data);
+      route_mapping.push(ConductorGatewayRoute {
+        base_path: endpoint_config.path.clone(),
+        route_data: Arc::new(route_data),
+      });
     }
 
     Ok(Self {
       routes: route_mapping,
+      not_found: config_object.not_found.clone().unwrap_or_default(),
     })
   }
 
   #[cfg(feature = "test_utils")]
-  pub async fn execute_test(
+  fn test_route_data(
     source: Arc<Box<dyn SourceRuntime>>,
     plugins: Vec<Box<dyn conductor_common::plugin::Plugin>>,
-    request: ConductorHttpRequest,
-  ) -> ConductorHttpResponse {
-    let plugin_manager = PluginManagerImpl::new_from_vec(plugins);
-    let route_data = ConductorGatewayRouteData {
+    require_auth: bool,
+    default_response_content_type: Option<&'static str>,
+    max_batch_size: Option<usize>,
+  ) -> ConductorGatewayRouteData {
+    let plugin_manager = PluginManagerImpl::new_from_vec(
+      plugins,
+      HeaderValue::from_static(
+        default_response_content_type.unwrap_or(DEFAULT_RESPONSE_CONTENT_TYPE),
+      ),
+    );
+
+    ConductorGatewayRouteData {
       endpoint: "/".to_string(),
       plugin_manager: Arc::new(Box::new(plugin_manager)),
       to: source,
       tenant_id: 0,
-    };
+      require_auth,
+      max_batch_size,
+      max_body_bytes: None,
+    }
+  }
+
+  #[cfg(feature = "test_utils")]
+  pub async fn execute_test(
+    source: Arc<Box<dyn SourceRuntime>>,
+    plugins: Vec<Box<dyn conductor_common::plugin::Plugin>>,
+    request: ConductorHttpRequest,
+    require_auth: bool,
+    default_response_content_type: Option<&'static str>,
+    max_batch_size: Option<usize>,
+  ) -> ConductorHttpResponse {
+    let route_data = Self::test_route_data(
+      source,
+      plugins,
+      require_auth,
+      default_response_content_type,
+      max_batch_size,
+    );
     let gw = Self {
       routes: vec![ConductorGatewayRoute {
         base_path: "/".to_string(),
         route_data: Arc::new(route_data),
       }],
+      not_found: Default::default(),
     };
 
     // @expected: we can safely index here, it's inside a test with constant defined fixtures.
     ConductorGateway::execute(request, &gw.routes[0].route_data).await
   }
 
-  #[trace(name = "execute")]
-  pub async fn execute(
+  #[cfg(feature = "test_utils")]
+  pub async fn subscribe_test(
+    source: Arc<Box<dyn SourceRuntime>>,
+    plugins: Vec<Box<dyn conductor_common::plugin::Plugin>>,
     request: ConductorHttpRequest,
-    route_data: &ConductorGatewayRouteData,
-  ) -> ConductorHttpResponse {
-    let mut request_ctx = RequestExecutionContext::new(request);
+    require_auth: bool,
+  ) -> Result<GraphQLResponseStream, ConductorHttpResponse> {
+    let route_data = Self::test_route_data(source, plugins, require_auth, None, None);
 
+    ConductorGateway::subscribe(request, &route_data).await
+  }
+
+  /// Runs the downstream plugins and extracts the GraphQL operation of the request, for both executions and subscriptions. Returns the span of the operation, or the response to send instead when the request is short-circuited or invalid.
+  async fn process_downstream_request(
+    request_ctx: &mut RequestExecutionContext,
+    route_data: &ConductorGatewayRouteData,
+  ) -> Result<Span, ConductorHttpResponse> {
     // Step 1: Trigger "on_downstream_http_request" on all plugins
     route_data
       .plugin_manager
-      .on_downstream_http_request(&mut request_ctx)
+      .on_downstream_http_request(request_ctx)
       .await;
 
     // Step 1.5: In case of short circuit, return the response right now.
@@ -208,11 +350,11 @@ impl ConductorGateway {
       if let Some(mut sc_response) = request_ctx.short_circuit_response.take() {
         route_data
           .plugin_manager
-          .on_downstream_http_response(&mut request_ctx, &mut sc_response);
+          .on_downstream_http_response(request_ctx, &mut sc_response);
 
-        return sc_response;
+        return Err(sc_response);
       } else {
-        return ExtractGraphQLOperationError::FailedToCreateResponseBody.into_response(None);
+        return Err(ExtractGraphQLOperationError::FailedToCreateResponseBody.into_response(None));
       }
     }
 
@@ -235,9 +377,9 @@ impl ConductorGateway {
               ExtractGraphQLOperationError::GraphQLParserError(e).into_response(accept);
             route_data
               .plugin_manager
-              .on_downstream_http_response(&mut request_ctx, &mut error_response);
+              .on_downstream_http_response(request_ctx, &mut error_response);
 
-            return error_response;
+            return Err(error_response);
           }
         },
         Err(e) => {
@@ -249,82 +391,372 @@ impl ConductorGateway {
           let mut error_response = e.into_response(accept);
           route_data
             .plugin_manager
-            .on_downstream_http_response(&mut request_ctx, &mut error_response);
+            .on_downstream_http_response(request_ctx, &mut error_response);
 
-          return error_response;
+          return Err(error_response);
         }
       }
     }
 
     // Verify that we have a GraphQL request at this point.
-    match request_ctx.downstream_graphql_request.as_ref() {
-      Some(gql_operation) => {
-        let mut _graphql_span = create_graphql_span(gql_operation);
+    let graphql_span = match request_ctx.downstream_graphql_request.as_ref() {
+      Some(gql_operation) => create_graphql_span(gql_operation),
+      None => {
+        // Step 2.5: In case of invalid request at this point, we can fail and return an error.
+        return Err(ConductorHttpResponse {
+          body: GraphQLResponse::new_error("failed to extract GraphQL request from HTTP request")
+            .into(),
+          status: StatusCode::BAD_REQUEST,
+          headers: Default::default(),
+        });
+      }
+    };
 
-        // Step 3: Execute plugins on the extracted GraphQL request.
+    // Step 3: Execute plugins on the extracted GraphQL request.
+    route_data
+      .plugin_manager
+      .on_downstream_graphql_request(route_data.to.clone(), request_ctx)
+      .await;
+
+    // Step 3.5: In case of short circuit, return the response right now.
+    if request_ctx.is_short_circuit() {
+      if let Some(mut sc_response) = request_ctx.short_circuit_response.take() {
         route_data
           .plugin_manager
-          .on_downstream_graphql_request(route_data.to.clone(), &mut request_ctx)
-          .await;
+          .on_downstream_http_response(request_ctx, &mut sc_response);
 
-        // Step 3.5: In case of short circuit, return the response right now.
-        if request_ctx.is_short_circuit() {
-          if let Some(mut sc_response) = request_ctx.short_circuit_response.take() {
-            route_data
-              .plugin_manager
-              .on_downstream_http_response(&mut request_ctx, &mut sc_response);
+        return Err(sc_response);
+      } else {
+        return Err(ExtractGraphQLOperationError::FailedToCreateResponseBody.into_response(None));
+      }
+    }
+
+    // Step 3.6: Reject requests that were not authenticated by any of the plugins, if required by the endpoint.
+    if route_data.require_auth && !request_ctx.is_authenticated() {
+      let mut unauthenticated_response = GraphQLResponse::new_error("unauthenticated request")
+        .into_with_status_code(StatusCode::UNAUTHORIZED);
+      route_data
+        .plugin_manager
+        .on_downstream_http_response(request_ctx, &mut unauthenticated_response);
+
+      return Err(unauthenticated_response);
+    }
+
+    Ok(graphql_span)
+  }
+
+  #[trace(name = "execute")]
+  pub async fn execute(
+    request: ConductorHttpRequest,
+    route_data: &ConductorGatewayRouteData,
+  ) -> ConductorHttpResponse {
+    match route_data.max_batch_size {
+      Some(max_batch_size) if is_batched_request(&request) => {
+        Self::execute_batch(request, route_data, max_batch_size).await
+      }
+      _ => Self::execute_operation(request, route_data).await,
+    }
+  }
+
+  /// Executes every operation of a batched request as its own request, and responds with the array of their responses, in the same order.
+  ///
+  /// The batch is answered with a `200` status code, unless every operation failed: it's then answered with the status code of the first operation.
+  async fn execute_batch(
+    request: ConductorHttpRequest,
+    route_data: &ConductorGatewayRouteData,
+    max_batch_size: usize,
+  ) -> ConductorHttpResponse {
+    let operations = match serde_json::from_slice::<Vec<Value>>(&request.body) {
+      Ok(operations) if operations.is_empty() => {
+        return Self::reject_batch(request, route_data, "batched request is empty".to_string())
+      }
+      Ok(operations) if operations.len() > max_batch_size => {
+        let message = format!(
+          "batched request contains {} operations, the maximum is {}",
+          operations.len(),
+          max_batch_size
+        );
+
+        return Self::reject_batch(request, route_data, message);
+      }
+      Ok(operations) => operations,
+      Err(e) => {
+        return Self::reject_batch(
+          request,
+          route_data,
+          ExtractGraphQLOperationError::InvalidBodyJsonFormat(e).to_string(),
+        )
+      }
+    };
+
+    let responses = join_all(operations.iter().map(|operation| {
+      let mut operation_request = request.clone();
+      operation_request.body = operation.to_string().into();
+      // The responses are combined into a single body, so they are not compressed on their own.
+      operation_request.headers.remove(ACCEPT_ENCODING);
+
+      Self::execute_operation(operation_request, route_data)
+    }))
+    .await;
+
+    let mut body = Vec::from("[");
+    let mut headers = HttpHeadersMap::new();
+    let all_failed = responses
+      .iter()
+      .all(|response| !response.status.is_success());
+    let status = match all_failed {
+      // @expected: batches are never empty at this point.
+      true => responses[0].status,
+      false => StatusCode::OK,
+    };
 
-            return sc_response;
-          } else {
-            return ExtractGraphQLOperationError::FailedToCreateResponseBody.into_response(None);
+    for (index, response) in responses.into_iter().enumerate() {
+      if index > 0 {
+        body.push(b',');
+      }
+
+      match Self::batch_operation_body(&response) {
+        Some(operation_body) => body.extend_from_slice(&operation_body),
+        None => {
+          body.extend_from_slice(&response.body);
+
+          // The content type was negotiated by the `match_content_type` plugin, the same way for every operation of the batch.
+          if let Some(content_type) = response.headers.get(CONTENT_TYPE) {
+            if !headers.contains_key(CONTENT_TYPE) {
+              headers.insert(CONTENT_TYPE, content_type.clone());
+            }
           }
         }
+      }
 
-        let upstream_span = Span::enter_with_parent("upstream_call", &_graphql_span)
-          .with_property(|| (CONDUCTOR_SOURCE, route_data.to.name().to_string()));
+      // The headers are set for the whole batch: the `Set-Cookie` headers of every operation are kept, and the longest `Retry-After` wins. For the other headers, the first operation that sets a header wins.
+      for name in response.headers.keys() {
+        if name == CONTENT_TYPE || name == CONTENT_LENGTH {
+          continue;
+        }
 
-        let upstream_response = route_data
-          .to
-          .execute(route_data.plugin_manager.clone(), &mut request_ctx)
-          .in_span(upstream_span)
-          .await;
-
-        let final_response = match upstream_response {
-          Ok(response) => response,
-          Err(e) => match e {
-            SourceError::ShortCircuit => {
-              return match request_ctx.short_circuit_response {
-                Some(e) => e,
-                None => {
-                  ExtractGraphQLOperationError::FailedToCreateResponseBody.into_response(None)
-                }
-              }
-            }
-            e => e.into(),
-          },
+        if name == SET_COOKIE || !headers.contains_key(name) {
+          for value in response.headers.get_all(name) {
+            headers.append(name.clone(), value.clone());
+          }
+        } else if name == RETRY_AFTER {
+          let retry_after = response.headers.get(RETRY_AFTER);
+
+          if retry_after_seconds(retry_after) > retry_after_seconds(headers.get(RETRY_AFTER)) {
+            headers.remove(RETRY_AFTER);
+            headers.extend(retry_after.map(|value| (RETRY_AFTER, value.clone())));
+          }
+        }
+      }
+    }
+
+    body.push(b']');
+
+    if !headers.contains_key(CONTENT_TYPE) {
+      headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(DEFAULT_RESPONSE_CONTENT_TYPE),
+      );
+    }
+
+    ConductorHttpResponse {
+      body: body.into(),
+      status,
+      headers,
+    }
+  }
+
+  /// Returns the body of a failed operation in the batch response, or `None` when the body of the operation is used as-is.
+  ///
+  /// Since the batch is answered with a single status code, the status code of a failed operation is exposed as `extensions.http.status` of its response. Bodies that are not JSON objects (for example, responses short-circuited by plugins) are replaced by an error with their text.
+  fn batch_operation_body(response: &ConductorHttpResponse) -> Option<Bytes> {
+    let body = serde_json::from_slice::<Value>(&response.body);
+
+    if response.status.is_success() && body.is_ok() {
+      return None;
+    }
+
+    let mut body = match body {
+      Ok(Value::Object(body)) => body,
+      _ => {
+        let message = match std::str::from_utf8(&response.body).map(str::trim) {
+          Ok(text) if !text.is_empty() => text,
+          _ => response
+            .status
+            .canonical_reason()
+            .unwrap_or("operation failed"),
         };
 
-        if let Some(errors) = final_response.errors.as_ref() {
-          _graphql_span =
-            _graphql_span.with_properties(|| create_graphql_error_span_properties(errors));
+        match serde_json::to_value(GraphQLResponse::new_error(message)) {
+          Ok(Value::Object(body)) => body,
+          _ => Default::default(),
+        }
+      }
+    };
+
+    if !response.status.is_success() {
+      if let Value::Object(extensions) = body
+        .entry("extensions")
+        .or_insert_with(|| Value::Object(Default::default()))
+      {
+        extensions.insert(
+          "http".to_string(),
+          json!({ "status": response.status.as_u16() }),
+        );
+      }
+    }
+
+    serde_json::to_vec(&body).ok().map(Bytes::from)
+  }
+
+  fn reject_batch(
+    request: ConductorHttpRequest,
+    route_data: &ConductorGatewayRouteData,
+    message: String,
+  ) -> ConductorHttpResponse {
+    let mut request_ctx = RequestExecutionContext::new(request);
+    request_ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, route_data.endpoint.clone());
+
+    let mut error_response =
+      GraphQLResponse::new_error(&message).into_with_status_code(StatusCode::BAD_REQUEST);
+    route_data
+      .plugin_manager
+      .on_downstream_http_response(&mut request_ctx, &mut error_response);
+
+    error_response
+  }
+
+  async fn execute_operation(
+    request: ConductorHttpRequest,
+    route_data: &ConductorGatewayRouteData,
+  ) -> ConductorHttpResponse {
+    let mut request_ctx = RequestExecutionContext::new(request);
+    request_ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, route_data.endpoint.clone());
+
+    let mut _graphql_span =
+      match Self::process_downstream_request(&mut request_ctx, route_data).await {
+        Ok(graphql_span) => graphql_span,
+        Err(response) => return response,
+      };
+
+    let upstream_span = Span::enter_with_parent("upstream_call", &_graphql_span)
+      .with_property(|| (CONDUCTOR_SOURCE, route_data.to.name().to_string()));
+
+    let upstream_response = route_data
+      .to
+      .execute(route_data.plugin_manager.clone(), &mut request_ctx)
+      .in_span(upstream_span)
+      .await;
+
+    let final_response = match upstream_response {
+      Ok(response) => response,
+      Err(e) => match e {
+        SourceError::ShortCircuit => {
+          return match request_ctx.short_circuit_response {
+            Some(e) => e,
+            None => ExtractGraphQLOperationError::FailedToCreateResponseBody.into_response(None),
+          }
         }
+        e => e.into(),
+      },
+    };
+
+    if let Some(errors) = final_response.errors.as_ref() {
+      _graphql_span =
+        _graphql_span.with_properties(|| create_graphql_error_span_properties(errors));
+    }
+
+    let mut http_response: ConductorHttpResponse = final_response.into();
+
+    route_data
+      .plugin_manager
+      .on_downstream_http_response(&mut request_ctx, &mut http_response);
+
+    http_response
+  }
+
+  /// Runs the downstream HTTP plugins (for example, authentication) on the request that opens a long-lived connection, before any operation is sent over it. Returns the response of the rejected connection.
+  pub async fn accept_connection(
+    request: ConductorHttpRequest,
+    route_data: &ConductorGatewayRouteData,
+  ) -> Result<(), ConductorHttpResponse> {
+    let mut request_ctx = RequestExecutionContext::new(request);
+    request_ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, route_data.endpoint.clone());
+
+    route_data
+      .plugin_manager
+      .on_downstream_http_request(&mut request_ctx)
+      .await;
+
+    if let Some(response) = request_ctx.short_circuit_response.take() {
+      return Err(response);
+    }
+
+    if route_data.require_auth && !request_ctx.is_authenticated() {
+      return Err(
+        GraphQLResponse::new_error("unauthenticated request")
+          .into_with_status_code(StatusCode::UNAUTHORIZED),
+      );
+    }
 
-        let mut http_response: ConductorHttpResponse = final_response.into();
+    Ok(())
+  }
 
+  /// Same as `execute`, for transports that stream the results of the operation (like WebSocket). Subscriptions are streamed from the source, and other operations are executed as a single result.
+  ///
+  /// The response hooks of the plugins only run on the response of a rejected operation, since there's no HTTP response for the results.
+  #[trace(name = "subscribe")]
+  pub async fn subscribe(
+    request: ConductorHttpRequest,
+    route_data: &ConductorGatewayRouteData,
+  ) -> Result<GraphQLResponseStream, ConductorHttpResponse> {
+    let mut request_ctx = RequestExecutionContext::new(request);
+    request_ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, route_data.endpoint.clone());
+
+    let graphql_span = Self::process_downstream_request(&mut request_ctx, route_data).await?;
+    let is_subscription = request_ctx
+      .downstream_graphql_request
+      .as_ref()
+      .is_some_and(|request| request.is_running_subscription());
+
+    let upstream_span = Span::enter_with_parent("upstream_call", &graphql_span)
+      .with_property(|| (CONDUCTOR_SOURCE, route_data.to.name().to_string()));
+
+    let result = match is_subscription {
+      true => {
+        route_data
+          .to
+          .subscribe(route_data.plugin_manager.clone(), &mut request_ctx)
+          .in_span(upstream_span)
+          .await
+      }
+      false => route_data
+        .to
+        .execute(route_data.plugin_manager.clone(), &mut request_ctx)
+        .in_span(upstream_span)
+        .await
+        .map(|response| {
+          Box::pin(futures::stream::once(futures::future::ready(response))) as GraphQLResponseStream
+        }),
+    };
+
+    match result {
+      Ok(results) => Ok(results),
+      Err(SourceError::ShortCircuit) => Err(
+        request_ctx
+          .short_circuit_response
+          .take()
+          .unwrap_or_else(|| {
+            ExtractGraphQLOperationError::FailedToCreateResponseBody.into_response(None)
+          }),
+      ),
+      Err(e) => {
+        let mut error_response: ConductorHttpResponse = GraphQLResponse::from(e).into();
         route_data
           .plugin_manager
-          .on_downstream_http_response(&mut request_ctx, &mut http_response);
+          .on_downstream_http_response(&mut request_ctx, &mut error_response);
 
-        http_response
-      }
-      None => {
-        // Step 2.5: In case of invalid request at this point, we can fail and return an error.
-        ConductorHttpResponse {
-          body: GraphQLResponse::new_error("failed to extract GraphQL request from HTTP request")
-            .into(),
-          status: StatusCode::BAD_REQUEST,
-          headers: Default::default(),
-        }
+        Err(error_response)
       }
     }
   }
@@ -1,16 +1,16 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use conductor_common::{
   execute::RequestExecutionContext,
   graphql::GraphQLRequest,
-  http::{ConductorHttpRequest, ConductorHttpResponse},
+  http::{ConductorHttpRequest, ConductorHttpResponse, HeaderValue},
   plugin::{CreatablePlugin, Plugin, PluginError},
   plugin_manager::PluginManager,
   source::SourceRuntime,
 };
 use conductor_config::PluginDefinition;
 use conductor_tracing::minitrace_mgr::MinitraceManager;
-use reqwest::Response;
+use tracing::warn;
 
 #[derive(Debug, Default)]
 pub struct PluginManagerImpl {
@@ -18,108 +18,302 @@ pub struct PluginManagerImpl {
 }
 
 impl PluginManagerImpl {
-  pub fn new_from_vec(plugins: Vec<Box<dyn Plugin>>) -> Self {
-    let mut pm = Self { plugins };
+  pub fn new_from_vec(
+    plugins: Vec<Box<dyn Plugin>>,
+    default_response_content_type: HeaderValue,
+  ) -> Self {
+    // Default plugins are registered first, so their response hooks run last and can fill in what other plugins didn't set.
+    let mut pm = Self {
+      plugins: PluginManagerImpl::default_plugins(default_response_content_type),
+    };
 
-    // We want to make sure to register default plugins last, in order to ensure it's setting the value correctly
-    for p in PluginManagerImpl::default_plugins() {
+    for p in plugins {
       pm.register_boxed_plugin(p);
     }
 
     pm
   }
 
+  /// Sorts the plugin definitions by their `order` (plugins without one have an order of `0`), keeping the declaration order of plugins with the same order.
+  ///
+  /// Warns when multiple enabled plugins declare the same `order`, since their relative order is then only decided by the declaration order.
+  fn sort_definitions(definitions: &[PluginDefinition]) -> Vec<&PluginDefinition> {
+    let mut sorted = definitions.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|definition| definition.order().unwrap_or(0));
+
+    let mut declared: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+
+    for definition in sorted.iter() {
+      if let (Some(order), Some(true)) = (definition.order(), definition.enabled()) {
+        declared
+          .entry(order)
+          .or_default()
+          .push(plugin_type(definition));
+      }
+    }
+
+    for (order, names) in declared.iter().filter(|(_, names)| names.len() > 1) {
+      warn!(
+        "plugins {} declare the same order ({}), they will run in the order they are declared",
+        names.join(", "),
+        order
+      );
+    }
+
+    sorted
+  }
+
+  /// Sorts every list of plugin definitions on its own, and concatenates them: the `order` of a plugin only applies within its list.
+  fn sort_lists<'a>(
+    plugin_lists: &[&'a Option<Vec<PluginDefinition>>],
+  ) -> Vec<&'a PluginDefinition> {
+    plugin_lists
+      .iter()
+      .copied()
+      .flatten()
+      .flat_map(|definitions| Self::sort_definitions(definitions))
+      .collect()
+  }
+
   pub async fn create_plugin<T: CreatablePlugin>(config: T::Config) -> Result<Box<T>, PluginError> {
     T::create(config).await
   }
 
+  /// Creates the plugins of every list, one list after the other.
   pub async fn new(
-    plugins_config: &Option<Vec<PluginDefinition>>,
+    plugin_lists: &[&Option<Vec<PluginDefinition>>],
     tracing_manager: &mut MinitraceManager,
     tenant_id: u32,
+    default_response_content_type: HeaderValue,
   ) -> Result<Self, PluginError> {
-    let mut instance = PluginManagerImpl::default();
-
-    if let Some(config_defs) = plugins_config {
-      for plugin_def in config_defs.iter() {
-        let plugin: Box<dyn Plugin> = match plugin_def {
-          PluginDefinition::GraphiQLPlugin {
-            enabled: Some(true),
-            config,
-          } => {
-            Self::create_plugin::<graphiql_plugin::Plugin>(config.clone().unwrap_or_default())
-              .await?
-          }
-          PluginDefinition::HttpGetPlugin {
-            enabled: Some(true),
-            config,
-          } => {
-            Self::create_plugin::<http_get_plugin::Plugin>(config.clone().unwrap_or_default())
-              .await?
-          }
-          PluginDefinition::VrlPluginConfig {
-            enabled: Some(true),
-            config,
-          } => Self::create_plugin::<vrl_plugin::Plugin>(config.clone()).await?,
-          PluginDefinition::TrustedDocumentsPlugin {
-            enabled: Some(true),
-            config,
-          } => Self::create_plugin::<trusted_documents_plugin::Plugin>(config.clone()).await?,
-          PluginDefinition::CorsPlugin {
-            enabled: Some(true),
-            config,
-          } => {
-            Self::create_plugin::<cors_plugin::Plugin>(config.clone().unwrap_or_default()).await?
-          }
-          PluginDefinition::DisableItrospectionPlugin {
-            enabled: Some(true),
-            config,
-          } => {
-            Self::create_plugin::<disable_introspection_plugin::Plugin>(
-              config.clone().unwrap_or_default(),
-            )
+    // Default plugins are registered first, so their response hooks run last and can fill in what other plugins didn't set.
+    let mut instance = PluginManagerImpl {
+      plugins: PluginManagerImpl::default_plugins(default_response_content_type),
+    };
+
+    for plugin_def in Self::sort_lists(plugin_lists) {
+      let plugin: Box<dyn Plugin> = match plugin_def {
+        PluginDefinition::GraphiQLPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<graphiql_plugin::Plugin>(config.clone().unwrap_or_default()).await?
+        }
+        PluginDefinition::HttpGetPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<http_get_plugin::Plugin>(config.clone().unwrap_or_default()).await?
+        }
+        PluginDefinition::VrlPluginConfig {
+          enabled: Some(true),
+          config,
+          ..
+        } => Self::create_plugin::<vrl_plugin::Plugin>(config.clone()).await?,
+        PluginDefinition::TrustedDocumentsPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => Self::create_plugin::<trusted_documents_plugin::Plugin>(config.clone()).await?,
+        PluginDefinition::CorsPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => Self::create_plugin::<cors_plugin::Plugin>(config.clone().unwrap_or_default()).await?,
+        PluginDefinition::DisableItrospectionPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<disable_introspection_plugin::Plugin>(
+            config.clone().unwrap_or_default(),
+          )
+          .await?
+        }
+        PluginDefinition::JwtAuthPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => Self::create_plugin::<jwt_auth_plugin::Plugin>(config.clone()).await?,
+        PluginDefinition::GraphQLValidation {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<graphql_validation_plugin::Plugin>(
+            config.clone().unwrap_or_default(),
+          )
+          .await?
+        }
+        PluginDefinition::TelemetryPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          let plugin = Self::create_plugin::<telemetry_plugin::Plugin>(config.clone()).await?;
+          plugin.configure_tracing(tenant_id, tracing_manager)?;
+
+          plugin
+        }
+        PluginDefinition::RhaiPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => Self::create_plugin::<rhai_plugin::Plugin>(config.clone()).await?,
+        PluginDefinition::LastErrorsPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<last_errors_plugin::Plugin>(config.clone().unwrap_or_default())
             .await?
-          }
-          PluginDefinition::JwtAuthPlugin {
-            enabled: Some(true),
-            config,
-          } => Self::create_plugin::<jwt_auth_plugin::Plugin>(config.clone()).await?,
-          PluginDefinition::GraphQLValidation {
-            enabled: Some(true),
-            config,
-          } => {
-            Self::create_plugin::<graphql_validation_plugin::Plugin>(
-              config.clone().unwrap_or_default(),
-            )
+        }
+        PluginDefinition::ResponseSamplingPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<response_sampling_plugin::Plugin>(
+            config.clone().unwrap_or_default(),
+          )
+          .await?
+        }
+        PluginDefinition::MaxErrorsPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<max_errors_plugin::Plugin>(config.clone().unwrap_or_default())
             .await?
-          }
-          PluginDefinition::TelemetryPlugin {
-            enabled: Some(true),
-            config,
-          } => {
-            let plugin = Self::create_plugin::<telemetry_plugin::Plugin>(config.clone()).await?;
-            plugin.configure_tracing(tenant_id, tracing_manager)?;
-
-            plugin
-          }
-          // In case plugin is not enabled, we are skipping it. Also when we don't have a match, so watch out for this one if you add a new plugin.
-          _ => continue,
-        };
-
-        instance.register_boxed_plugin(plugin)
-      }
-    };
+        }
+        PluginDefinition::OperationAllowlistPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => Self::create_plugin::<operation_allowlist_plugin::Plugin>(config.clone()).await?,
+        PluginDefinition::OmitNullsPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<omit_nulls_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        PluginDefinition::IdempotencyKeyPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<idempotency_key_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        PluginDefinition::RequestSequencePlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<request_sequence_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        PluginDefinition::RateLimitPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<rate_limit_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        PluginDefinition::ResponseCachePlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<response_cache_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        PluginDefinition::DepthLimitPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => Self::create_plugin::<depth_limit_plugin::Plugin>(config.clone()).await?,
+        PluginDefinition::CostLimitPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => Self::create_plugin::<cost_limit_plugin::Plugin>(config.clone()).await?,
+        PluginDefinition::MaxBodySizePlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<max_body_size_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        PluginDefinition::RequestIdPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<request_id_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        PluginDefinition::MetricsPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<metrics_plugin::Plugin>(config.clone().unwrap_or_default()).await?
+        }
+        PluginDefinition::MaskErrorsPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<mask_errors_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        PluginDefinition::CompressionPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<compression_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        PluginDefinition::HeaderPropagationPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<header_propagation_plugin::Plugin>(
+            config.clone().unwrap_or_default(),
+          )
+          .await?
+        }
+        PluginDefinition::CircuitBreakerPlugin {
+          enabled: Some(true),
+          config,
+          ..
+        } => {
+          Self::create_plugin::<circuit_breaker_plugin::Plugin>(config.clone().unwrap_or_default())
+            .await?
+        }
+        // In case plugin is not enabled, we are skipping it. Also when we don't have a match, so watch out for this one if you add a new plugin.
+        _ => continue,
+      };
 
-    // We want to make sure to register these last, in order to ensure it's setting the value correctly
-    for p in PluginManagerImpl::default_plugins() {
-      instance.register_boxed_plugin(p);
+      instance.register_boxed_plugin(plugin)
     }
 
     Ok(instance)
   }
 
-  fn default_plugins() -> Vec<Box<dyn Plugin>> {
-    vec![Box::new(match_content_type_plugin::Plugin {})]
+  fn default_plugins(default_response_content_type: HeaderValue) -> Vec<Box<dyn Plugin>> {
+    vec![Box::new(match_content_type_plugin::Plugin::new(
+      default_response_content_type,
+    ))]
   }
 
   pub fn register_boxed_plugin(&mut self, plugin: Box<dyn Plugin>) {
@@ -131,6 +325,14 @@ impl PluginManagerImpl {
   }
 }
 
+/// Returns the `type` of the plugin definition, as declared in the config file.
+fn plugin_type(definition: &PluginDefinition) -> String {
+  serde_json::to_value(definition)
+    .ok()
+    .and_then(|value| value.get("type")?.as_str().map(String::from))
+    .unwrap_or_default()
+}
+
 #[async_trait::async_trait(?Send)]
 impl PluginManager for PluginManagerImpl {
   #[tracing::instrument(
@@ -164,7 +366,8 @@ impl PluginManager for PluginManagerImpl {
   ) {
     let p = &self.plugins;
 
-    for plugin in p.iter() {
+    // Response hooks run in reverse order, so the first plugin sees the request first and the response last.
+    for plugin in p.iter().rev() {
       plugin.on_downstream_http_response(context, response);
 
       if context.is_short_circuit() {
@@ -238,11 +441,11 @@ impl PluginManager for PluginManagerImpl {
   async fn on_upstream_http_response<'a>(
     &self,
     ctx: &mut RequestExecutionContext,
-    response: &Result<Response, reqwest_middleware::Error>,
+    response: &mut ConductorHttpResponse,
   ) {
     let p = &self.plugins;
 
-    for plugin in p.iter() {
+    for plugin in p.iter().rev() {
       plugin.on_upstream_http_response(ctx, response).await;
 
       if ctx.is_short_circuit() {
@@ -251,3 +454,151 @@ impl PluginManager for PluginManagerImpl {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Mutex;
+
+  use conductor_common::http::StatusCode;
+
+  use super::*;
+
+  #[derive(Debug)]
+  struct RecordingPlugin {
+    name: &'static str,
+    calls: Arc<Mutex<Vec<String>>>,
+  }
+
+  impl RecordingPlugin {
+    fn record(&self, hook: &str) {
+      self
+        .calls
+        .lock()
+        .unwrap()
+        .push(format!("{}:{}", self.name, hook));
+    }
+  }
+
+  #[async_trait::async_trait(?Send)]
+  impl Plugin for RecordingPlugin {
+    async fn on_downstream_http_request(&self, _ctx: &mut RequestExecutionContext) {
+      self.record("downstream_request");
+    }
+
+    fn on_downstream_http_response(
+      &self,
+      _ctx: &mut RequestExecutionContext,
+      _response: &mut ConductorHttpResponse,
+    ) {
+      self.record("downstream_response");
+    }
+
+    async fn on_upstream_http_request(
+      &self,
+      _ctx: &mut RequestExecutionContext,
+      _req: &mut ConductorHttpRequest,
+    ) {
+      self.record("upstream_request");
+    }
+
+    async fn on_upstream_http_response(
+      &self,
+      _ctx: &mut RequestExecutionContext,
+      _response: &mut ConductorHttpResponse,
+    ) {
+      self.record("upstream_response");
+    }
+  }
+
+  fn definitions(value: serde_json::Value) -> Vec<PluginDefinition> {
+    serde_json::from_value(value).unwrap()
+  }
+
+  #[test]
+  fn sorts_definitions_by_order() {
+    let definitions = definitions(serde_json::json!([
+      { "type": "graphiql" },
+      { "type": "cors", "order": 10 },
+      { "type": "request_id", "order": -5 },
+      { "type": "http_get", "order": 10 },
+      { "type": "disable_introspection" },
+    ]));
+    let sorted = PluginManagerImpl::sort_definitions(&definitions)
+      .into_iter()
+      .map(plugin_type)
+      .collect::<Vec<_>>();
+
+    // Plugins with the same order keep the order they are declared in.
+    assert_eq!(
+      sorted,
+      vec![
+        "request_id",
+        "graphiql",
+        "disable_introspection",
+        "cors",
+        "http_get"
+      ]
+    );
+  }
+
+  #[test]
+  fn sorts_lists_on_their_own() {
+    let global = Some(definitions(serde_json::json!([
+      { "type": "cors", "order": 10 },
+      { "type": "graphiql" },
+    ])));
+    let endpoint = Some(definitions(serde_json::json!([
+      { "type": "http_get" },
+      { "type": "request_id", "order": -5 },
+    ])));
+    let sorted = PluginManagerImpl::sort_lists(&[&global, &None, &endpoint])
+      .into_iter()
+      .map(plugin_type)
+      .collect::<Vec<_>>();
+
+    // Endpoint plugins run after the global ones, even with a lower order.
+    assert_eq!(sorted, vec!["graphiql", "cors", "request_id", "http_get"]);
+  }
+
+  #[tokio::test]
+  async fn runs_response_hooks_in_reverse_order() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let plugin = |name: &'static str| -> Box<dyn Plugin> {
+      Box::new(RecordingPlugin {
+        name,
+        calls: calls.clone(),
+      })
+    };
+    let pm = PluginManagerImpl::new_from_vec(
+      vec![plugin("first"), plugin("second")],
+      HeaderValue::from_static(match_content_type_plugin::DEFAULT_RESPONSE_CONTENT_TYPE),
+    );
+
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    let mut request = ConductorHttpRequest::default();
+    let mut response = ConductorHttpResponse {
+      body: Default::default(),
+      status: StatusCode::OK,
+      headers: Default::default(),
+    };
+
+    pm.on_downstream_http_request(&mut ctx).await;
+    pm.on_upstream_http_request(&mut ctx, &mut request).await;
+    pm.on_upstream_http_response(&mut ctx, &mut response).await;
+    pm.on_downstream_http_response(&mut ctx, &mut response);
+
+    assert_eq!(
+      *calls.lock().unwrap(),
+      vec![
+        "first:downstream_request",
+        "second:downstream_request",
+        "first:upstream_request",
+        "second:upstream_request",
+        "second:upstream_response",
+        "first:upstream_response",
+        "second:downstream_response",
+        "first:downstream_response",
+      ]
+    );
+  }
+}
@@ -0,0 +1,107 @@
+use std::{net::IpAddr, str::FromStr};
+
+use conductor_common::http::{ConductorHttpRequest, HeaderName, HeaderValue, HttpHeadersMap};
+use conductor_config::{ForwardClientIpConfig, ForwardClientIpMode};
+use ipnet::IpNet;
+
+static X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ClientIpForwarderError {
+  #[error("invalid header name \"{0}\": {1}")]
+  InvalidHeaderName(String, String),
+  #[error("invalid trusted proxy \"{0}\", expected an IP address or a CIDR range")]
+  InvalidTrustedProxy(String),
+}
+
+#[derive(Debug)]
+pub struct ClientIpForwarder {
+  header: HeaderName,
+  mode: ForwardClientIpMode,
+  trusted_proxies: Vec<IpNet>,
+}
+
+impl ClientIpForwarder {
+  pub fn new(config: &ForwardClientIpConfig) -> Result<Self, ClientIpForwarderError> {
+    let header = HeaderName::from_str(&config.header).map_err(|e| {
+      ClientIpForwarderError::InvalidHeaderName(config.header.clone(), e.to_string())
+    })?;
+    let trusted_proxies = config
+      .trusted_proxies
+      .iter()
+      .map(|proxy| {
+        proxy
+          .parse::<IpNet>()
+          .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+          .map_err(|_| ClientIpForwarderError::InvalidTrustedProxy(proxy.clone()))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Self {
+      header,
+      mode: config.mode,
+      trusted_proxies,
+    })
+  }
+
+  fn is_trusted(&self, ip: &IpAddr) -> bool {
+    self.trusted_proxies.iter().any(|proxy| proxy.contains(ip))
+  }
+
+  /// Sets the configured header on the upstream request. Nothing is forwarded when the runtime doesn't know the peer address.
+  pub fn forward(
+    &self,
+    downstream_request: &ConductorHttpRequest,
+    upstream_headers: &mut HttpHeadersMap,
+  ) {
+    let peer_addr = match downstream_request.peer_addr {
+      Some(peer_addr) => peer_addr,
+      None => return,
+    };
+
+    // The incoming chain can be spoofed by the client, so it's only used when it was set by a trusted proxy.
+    let incoming_chain = match self.is_trusted(&peer_addr) {
+      true => downstream_request
+        .headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect::<Vec<_>>(),
+      false => vec![],
+    };
+
+    let value = match self.mode {
+      ForwardClientIpMode::Append => {
+        let peer_addr = peer_addr.to_string();
+        let mut chain = incoming_chain;
+        chain.push(&peer_addr);
+
+        chain.join(", ")
+      }
+      ForwardClientIpMode::ClientIp => {
+        let mut client_ip = peer_addr;
+
+        // Walk the chain from the closest hop, until reaching an address that isn't a trusted proxy.
+        for entry in incoming_chain.iter().rev() {
+          if !self.is_trusted(&client_ip) {
+            break;
+          }
+
+          match entry.parse::<IpAddr>() {
+            Ok(ip) => client_ip = ip,
+            Err(_) => break,
+          }
+        }
+
+        client_ip.to_string()
+      }
+    };
+
+    if let Ok(value) = HeaderValue::from_str(&value) {
+      upstream_headers.insert(self.header.clone(), value);
+    }
+  }
+}
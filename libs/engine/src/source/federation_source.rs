@@ -9,6 +9,7 @@ use federation_query_planner::supergraph::Supergraph;
 use federation_query_planner::FederationExecutor;
 use futures::lock::Mutex;
 use minitrace_reqwest::{traced_reqwest, TracedHttpClient};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::{future::Future, pin::Pin};
 
@@ -18,6 +19,8 @@ pub struct FederationSourceRuntime {
   pub identifier: String,
   pub config: FederationSourceConfig,
   pub schema_awareness: SchemaAwareness<Supergraph>,
+  /// Keyed by the uppercase subgraph name, like the subgraphs of the supergraph.
+  pub subgraph_urls: HashMap<String, String>,
 }
 
 impl FederationSourceRuntime {
@@ -52,8 +55,27 @@ impl FederationSourceRuntime {
       source: source.into(),
     })?;
 
+    let subgraph_urls = config
+      .subgraphs
+      .iter()
+      .map(|(name, url)| (name.to_uppercase(), url.clone()))
+      .collect::<HashMap<_, _>>();
+
+    if let Some(supergraph) = schema_awareness.processed().as_ref() {
+      for name in subgraph_urls.keys() {
+        if !supergraph.subgraphs.contains_key(name) {
+          tracing::warn!(
+            "subgraph \"{}\" of source '{}' is not part of the supergraph",
+            name,
+            identifier
+          );
+        }
+      }
+    }
+
     Ok(Self {
       schema_awareness,
+      subgraph_urls,
       client,
       identifier,
       config,
@@ -93,6 +115,7 @@ impl SourceRuntime for FederationSourceRuntime {
             client: &self.client,
             plugin_manager: plugin_manager.clone(),
             supergraph,
+            subgraph_urls: &self.subgraph_urls,
           };
 
           match executor
@@ -3,17 +3,46 @@ use std::{future::Future, pin::Pin, sync::Arc};
 use conductor_common::{
   execute::RequestExecutionContext,
   graphql::{GraphQLResponse, ParsedGraphQLSchema},
-  http::{ConductorHttpRequest, CONTENT_TYPE},
+  http::{ConductorHttpRequest, ConductorHttpResponse, HeaderName, CONTENT_TYPE},
+  json::exceeds_max_depth,
   plugin_manager::PluginManager,
 };
-use conductor_config::GraphQLSourceConfig;
+use conductor_config::{GraphQLSourceConfig, UpstreamErrorResponsePolicy};
 use minitrace_reqwest::{traced_reqwest, TracedHttpClient};
 use reqwest::{header::HeaderValue, Method, StatusCode};
-use tracing::debug;
+use tracing::{debug, warn};
+use web_time::Instant;
 
-use crate::schema_awareness::SchemaAwareness;
+use crate::{
+  schema_awareness::SchemaAwareness,
+  source::{
+    client_ip::ClientIpForwarder,
+    load_balancer::{EndpointLease, LoadBalancer},
+    retry::RetryPolicy,
+    shadow::ShadowTraffic,
+  },
+};
 
 use conductor_common::source::{GraphQLSourceInitError, SourceError, SourceRuntime};
+#[cfg(not(target_arch = "wasm32"))]
+use conductor_common::{graphql::GraphQLRequest, source::GraphQLResponseStream};
+
+static GRAPHQL_OPERATION_HEADER: &str = "x-graphql-operation";
+
+/// Headers that describe the downstream connection or body, and can't be listed in `forward_headers`.
+static RESERVED_HEADERS: &[&str] = &[
+  "host",
+  "content-length",
+  "content-type",
+  "content-encoding",
+  "accept-encoding",
+  "connection",
+  "keep-alive",
+  "transfer-encoding",
+  "te",
+  "trailer",
+  "upgrade",
+];
 
 #[derive(Debug)]
 pub struct GraphQLSourceRuntime {
@@ -21,6 +50,11 @@ pub struct GraphQLSourceRuntime {
   pub config: GraphQLSourceConfig,
   pub identifier: String,
   pub schema_awareness: Option<SchemaAwareness>,
+  pub client_ip_forwarder: Option<ClientIpForwarder>,
+  pub forwarded_headers: Vec<HeaderName>,
+  pub shadow: Option<ShadowTraffic>,
+  pub retry: Option<RetryPolicy>,
+  pub load_balancer: LoadBalancer,
 }
 
 impl GraphQLSourceRuntime {
@@ -34,11 +68,61 @@ impl GraphQLSourceRuntime {
       config
     );
 
-    let client = wasm_polyfills::create_http_client()
+    let client_builder = wasm_polyfills::create_http_client();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let client_builder = match &config.client {
+      Some(client_config) => configure_client(client_builder, client_config),
+      None => client_builder,
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let client_builder = match &config.tls {
+      Some(tls_config) => configure_tls(client_builder, tls_config)
+        .map_err(|source| GraphQLSourceInitError::SourceInitFailed { source })?,
+      None => client_builder,
+    };
+
+    let client = client_builder
       .build()
       .map_err(|source| GraphQLSourceInitError::FetcherError { source })?;
 
     let fetcher = traced_reqwest(client);
+    let shadow = match config.shadow.as_ref() {
+      Some(c) => Some(
+        ShadowTraffic::new(c, fetcher.clone(), config.timeout()).map_err(|source| {
+          GraphQLSourceInitError::SourceInitFailed {
+            source: source.into(),
+          }
+        })?,
+      ),
+      None => None,
+    };
+    let retry = match config.retry.as_ref() {
+      // Timers are not available on WASM runtime, so the retries would be sent back-to-back, without their backoff.
+      #[cfg(target_arch = "wasm32")]
+      Some(_) => {
+        warn!(
+          "retry is not supported on WASM runtime, the requests of source '{}' are not retried",
+          identifier
+        );
+
+        None
+      }
+      #[cfg(not(target_arch = "wasm32"))]
+      Some(c) => {
+        Some(
+          RetryPolicy::new(c).map_err(|source| GraphQLSourceInitError::SourceInitFailed {
+            source: source.into(),
+          })?,
+        )
+      }
+      None => None,
+    };
+    let load_balancer =
+      LoadBalancer::new(&config).map_err(|source| GraphQLSourceInitError::SourceInitFailed {
+        source: source.into(),
+      })?;
     let schema_awareness = match config.schema_awareness.as_ref() {
       Some(c) => Some(
         SchemaAwareness::new(identifier.clone(), c.to_owned(), |_, _| Ok(()))
@@ -50,8 +134,52 @@ impl GraphQLSourceRuntime {
       None => None,
     };
 
+    let client_ip_forwarder = match config.forward_client_ip.as_ref() {
+      Some(c) => Some(ClientIpForwarder::new(c).map_err(|source| {
+        GraphQLSourceInitError::SourceInitFailed {
+          source: source.into(),
+        }
+      })?),
+      None => None,
+    };
+
+    let forwarded_headers = config
+      .forward_headers
+      .iter()
+      .flatten()
+      .map(|name| match HeaderName::from_bytes(name.as_bytes()) {
+        Ok(name) if RESERVED_HEADERS.contains(&name.as_str()) => {
+          Err(GraphQLSourceInitError::SourceInitFailed {
+            source: anyhow::anyhow!("header \"{}\" can't be forwarded", name),
+          })
+        }
+        Ok(name) => Ok(name),
+        Err(e) => Err(GraphQLSourceInitError::SourceInitFailed {
+          source: anyhow::anyhow!("invalid header name \"{}\" in forward_headers: {}", name, e),
+        }),
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if let Some(UpstreamErrorResponsePolicy::Map { status_codes }) = &config.upstream_error_response
+    {
+      for status in status_codes.keys().chain(status_codes.values()) {
+        StatusCode::from_u16(*status).map_err(|e| GraphQLSourceInitError::SourceInitFailed {
+          source: anyhow::anyhow!(
+            "invalid status code {} in upstream_error_response: {}",
+            status,
+            e
+          ),
+        })?;
+      }
+    }
+
     Ok(Self {
       schema_awareness,
+      client_ip_forwarder,
+      forwarded_headers,
+      shadow,
+      retry,
+      load_balancer,
       identifier,
       fetcher,
       config,
@@ -59,6 +187,200 @@ impl GraphQLSourceRuntime {
   }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn configure_client(
+  mut builder: reqwest::ClientBuilder,
+  config: &conductor_config::UpstreamClientConfig,
+) -> reqwest::ClientBuilder {
+  use std::time::Duration;
+
+  if let Some(max_idle) = config.pool_max_idle_per_host {
+    builder = builder.pool_max_idle_per_host(max_idle);
+  }
+
+  if let Some(idle_timeout) = config.pool_idle_timeout_secs {
+    builder = builder.pool_idle_timeout(Duration::from_secs(idle_timeout));
+  }
+
+  if let Some(keepalive) = config.tcp_keepalive_secs {
+    builder = builder.tcp_keepalive(Duration::from_secs(keepalive));
+  }
+
+  if config.http2_prior_knowledge {
+    builder = builder.http2_prior_knowledge();
+  }
+
+  builder
+}
+
+/// Loads the client certificate and the CA certificates, so invalid ones fail when the source is created rather than on the first request.
+#[cfg(not(target_arch = "wasm32"))]
+fn configure_tls(
+  mut builder: reqwest::ClientBuilder,
+  config: &conductor_config::UpstreamTlsConfig,
+) -> Result<reqwest::ClientBuilder, anyhow::Error> {
+  match (&config.client_cert, &config.client_key) {
+    (Some(cert), Some(key)) => {
+      let identity =
+        reqwest::Identity::from_pkcs8_pem(cert.contents().as_bytes(), key.contents().as_bytes())
+          .map_err(|e| anyhow::anyhow!("failed to load the client certificate: {}", e))?;
+
+      builder = builder.identity(identity);
+    }
+    (None, None) => {}
+    _ => {
+      return Err(anyhow::anyhow!(
+        "client_cert and client_key must be specified together"
+      ))
+    }
+  }
+
+  if let Some(ca) = &config.ca {
+    static CERTIFICATE_END: &str = "-----END CERTIFICATE-----";
+
+    let certificates = ca
+      .contents()
+      .split_inclusive(CERTIFICATE_END)
+      .filter(|block| block.contains(CERTIFICATE_END))
+      .map(|block| {
+        reqwest::Certificate::from_pem(block.as_bytes())
+          .map_err(|e| anyhow::anyhow!("failed to load a CA certificate: {}", e))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if certificates.is_empty() {
+      return Err(anyhow::anyhow!("no certificate found in the CA bundle"));
+    }
+
+    for certificate in certificates {
+      builder = builder.add_root_certificate(certificate);
+    }
+  }
+
+  Ok(builder)
+}
+
+impl GraphQLSourceRuntime {
+  fn error_response_status(&self, upstream_status: StatusCode) -> StatusCode {
+    match &self.config.upstream_error_response {
+      None | Some(UpstreamErrorResponsePolicy::Passthrough) => upstream_status,
+      Some(UpstreamErrorResponsePolicy::Normalize) => StatusCode::OK,
+      Some(UpstreamErrorResponsePolicy::Map { status_codes }) => status_codes
+        .get(&upstream_status.as_u16())
+        .and_then(|status| StatusCode::from_u16(*status).ok())
+        .unwrap_or(upstream_status),
+    }
+  }
+
+  /// Deeply nested responses are rejected before parsing, so they can't exhaust the stack.
+  fn check_response_depth(&self, body: &[u8]) -> Result<(), SourceError> {
+    match self.config.max_response_depth {
+      Some(max_depth) if exceeds_max_depth(body, max_depth) => {
+        Err(SourceError::ResponseTooDeep(max_depth))
+      }
+      _ => Ok(()),
+    }
+  }
+
+  /// Builds the HTTP request of the operation for the next upstream endpoint, and runs the upstream request hooks of the plugins.
+  async fn upstream_http_request<'a>(
+    &'a self,
+    plugin_manager: &Arc<Box<dyn PluginManager>>,
+    request_context: &mut RequestExecutionContext,
+  ) -> Result<(EndpointLease<'a>, ConductorHttpRequest), SourceError> {
+    let source_req = match request_context.downstream_graphql_request.as_mut() {
+      Some(req) => &mut req.request,
+      None => {
+        return Err(SourceError::UpstreamPlanningError(anyhow::anyhow!(
+          "source request isn't available at execution context!"
+        )))
+      }
+    };
+
+    plugin_manager.on_upstream_graphql_request(source_req).await;
+
+    // Only the header carries the rewritten name: the `operationName` of the body must keep matching an operation of the document.
+    let forwarded_operation_name = match (
+      &self.config.forwarded_operation_name,
+      &source_req.operation_name,
+    ) {
+      (Some(config), Some(operation_name)) => Some(config.apply(operation_name)),
+      _ => None,
+    };
+
+    let body = source_req.into();
+
+    // Retries pick another endpoint, see `execute`.
+    let endpoint = match self.load_balancer.pick() {
+      Some(endpoint) => endpoint,
+      None => return Err(SourceError::NoAvailableEndpoint),
+    };
+
+    // TODO: improve this by implementing https://github.com/the-guild-org/conductor-t2/issues/205
+    let mut conductor_http_request = ConductorHttpRequest {
+      body,
+      uri: endpoint.url().to_string(),
+      query_string: "".to_string(),
+      method: Method::POST,
+      headers: Default::default(),
+      peer_addr: None,
+    };
+
+    // Forwarded first, so the headers set by the source and the plugins take precedence.
+    for name in &self.forwarded_headers {
+      // The same header can be listed twice in the configuration.
+      if conductor_http_request.headers.contains_key(name) {
+        continue;
+      }
+
+      for value in request_context
+        .downstream_http_request
+        .headers
+        .get_all(name)
+      {
+        conductor_http_request
+          .headers
+          .append(name.clone(), value.clone());
+      }
+    }
+
+    conductor_http_request
+      .headers
+      .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+    if let Some(operation_name) = forwarded_operation_name {
+      match HeaderValue::from_str(&operation_name) {
+        Ok(value) => {
+          conductor_http_request
+            .headers
+            .insert(GRAPHQL_OPERATION_HEADER, value);
+        }
+        Err(e) => warn!(
+          "forwarded operation name \"{}\" is not a valid header value: {}",
+          operation_name, e
+        ),
+      }
+    }
+
+    if let Some(client_ip_forwarder) = &self.client_ip_forwarder {
+      client_ip_forwarder.forward(
+        &request_context.downstream_http_request,
+        &mut conductor_http_request.headers,
+      );
+    }
+
+    plugin_manager
+      .on_upstream_http_request(request_context, &mut conductor_http_request)
+      .await;
+
+    if request_context.is_short_circuit() {
+      return Err(SourceError::ShortCircuit);
+    }
+
+    Ok((endpoint, conductor_http_request))
+  }
+}
+
 impl SourceRuntime for GraphQLSourceRuntime {
   fn name(&self) -> &str {
     &self.identifier
@@ -87,81 +409,186 @@ impl SourceRuntime for GraphQLSourceRuntime {
   ) -> Pin<Box<(dyn Future<Output = Result<GraphQLResponse, SourceError>> + 'a)>> {
     Box::pin(wasm_polyfills::call_async(async move {
       let fetcher = &self.fetcher;
-      let endpoint = &self.config.endpoint;
-
-      let source_req = match request_context.downstream_graphql_request.as_mut() {
-        Some(req) => &mut req.request,
-        None => {
-          return Ok(GraphQLResponse::new_error(
-            "source request isn't available at execution context!",
-          ))
-        }
+      let is_query = request_context
+        .downstream_graphql_request
+        .as_ref()
+        .is_some_and(|req| req.is_running_query());
+
+      if request_context.downstream_graphql_request.is_none() {
+        return Ok(GraphQLResponse::new_error(
+          "source request isn't available at execution context!",
+        ));
+      }
+
+      let (mut endpoint, mut conductor_http_request) = self
+        .upstream_http_request(&plugin_manager, request_context)
+        .await?;
+
+      let shadow_comparison = match &self.shadow {
+        Some(shadow) if is_query => shadow.mirror(&conductor_http_request),
+        _ => None,
       };
 
-      plugin_manager.on_upstream_graphql_request(source_req).await;
+      debug!(
+        "dispatching upstream http request from the following input: {:?}",
+        conductor_http_request
+      );
+
+      // Only queries are retried, sending a mutation again could apply it twice.
+      let retry = self.retry.as_ref().filter(|_| is_query);
+      // The timeout is the budget of all the attempts together.
+      let deadline = Instant::now() + self.config.timeout();
+      let mut retries = 0;
+
+      let upstream_result = loop {
+        let upstream_req = fetcher
+          .request(
+            conductor_http_request.method.clone(),
+            &conductor_http_request.uri,
+          )
+          .headers(conductor_http_request.headers.clone())
+          .body(conductor_http_request.body.clone());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let upstream_req = upstream_req.timeout(deadline.saturating_duration_since(Instant::now()));
 
-      // TODO: improve this by implementing https://github.com/the-guild-org/conductor-t2/issues/205
-      let mut conductor_http_request = ConductorHttpRequest {
-        body: source_req.into(),
-        uri: endpoint.to_string(),
-        query_string: "".to_string(),
-        method: Method::POST,
-        headers: Default::default(),
+        let result = upstream_req.send().await;
+
+        endpoint.report(
+          result
+            .as_ref()
+            .is_ok_and(|res| !res.status().is_server_error()),
+        );
+
+        let backoff = retry
+          .filter(|retry| match &result {
+            Ok(res) => retry.is_retryable_status(res.status()),
+            Err(reqwest_middleware::Error::Reqwest(e)) => e.is_connect(),
+            Err(_) => false,
+          })
+          .and_then(|retry| retry.backoff(retries))
+          .filter(|backoff| *backoff < deadline.saturating_duration_since(Instant::now()));
+
+        match backoff {
+          Some(backoff) => {
+            match &result {
+              Ok(res) => debug!(
+                "upstream responded with retryable HTTP status {}, retrying in {:?}",
+                res.status(),
+                backoff
+              ),
+              Err(e) => debug!("upstream request failed: {}, retrying in {:?}", e, backoff),
+            }
+
+            // Retries are disabled on WASM runtime, see `new`.
+            #[cfg(not(target_arch = "wasm32"))]
+            wasm_polyfills::sleep(backoff).await;
+            retries += 1;
+
+            // Retries are sent to another endpoint when one is available, unless a plugin changed the URL of the request.
+            if let Some(other) = self.load_balancer.pick_other(&endpoint) {
+              if conductor_http_request.uri == endpoint.url() {
+                conductor_http_request.uri = other.url().to_string();
+              }
+
+              endpoint = other;
+            }
+          }
+          None => break result,
+        }
       };
 
-      conductor_http_request
-        .headers
-        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+      let upstream_response = match upstream_result {
+        Ok(res) => res,
+        Err(reqwest_middleware::Error::Reqwest(e)) if e.is_timeout() => {
+          return Err(SourceError::Timeout(self.config.timeout()))
+        }
+        Err(e) => return Err(SourceError::NetworkError(e)),
+      };
+      let status = upstream_response.status();
+      let headers = upstream_response.headers().clone();
+      let body = match upstream_response.bytes().await {
+        Ok(body) => body,
+        Err(e) if e.is_timeout() => return Err(SourceError::Timeout(self.config.timeout())),
+        Err(e) if status == StatusCode::OK => {
+          return Ok(GraphQLResponse::new_error(&e.to_string()))
+        }
+        Err(_) => return Err(SourceError::UnexpectedHTTPStatusError(status)),
+      };
+      let mut upstream_response = ConductorHttpResponse {
+        body,
+        status,
+        headers,
+      };
 
       plugin_manager
-        .on_upstream_http_request(request_context, &mut conductor_http_request)
+        .on_upstream_http_response(request_context, &mut upstream_response)
         .await;
 
       if request_context.is_short_circuit() {
         return Err(SourceError::ShortCircuit);
       }
 
-      debug!(
-        "dispatching upstream http request from the following input: {:?}",
-        conductor_http_request
-      );
-
-      let upstream_req = fetcher
-        .request(conductor_http_request.method, conductor_http_request.uri)
-        .headers(conductor_http_request.headers)
-        .body(conductor_http_request.body);
+      self.check_response_depth(&upstream_response.body)?;
 
-      let upstream_response = upstream_req.send().await;
+      match upstream_response.status {
+        StatusCode::OK => {
+          if let Some(shadow_comparison) = shadow_comparison {
+            shadow_comparison.compare(&upstream_response.body);
+          }
 
-      plugin_manager
-        .on_upstream_http_response(request_context, &upstream_response)
-        .await;
+          // DOTAN: Should we use the improved JSON parser here?
+          let response = match serde_json::from_slice::<GraphQLResponse>(&upstream_response.body) {
+            Ok(response) => response,
+            Err(e) => {
+              return Ok(GraphQLResponse::new_error(&format!(
+                "Failed to build json response {}",
+                e
+              )))
+            }
+          };
 
-      match upstream_response {
-        Ok(res) => match res.status() {
-          StatusCode::OK => {
-            let body = match res.bytes().await {
-              Ok(body) => body,
-              Err(e) => return Ok(GraphQLResponse::new_error(&e.to_string())),
-            };
-
-            // DOTAN: Should we use the improved JSON parser here?
-            let response = match serde_json::from_slice::<GraphQLResponse>(&body) {
-              Ok(response) => response,
-              Err(e) => {
-                return Ok(GraphQLResponse::new_error(&format!(
-                  "Failed to build json response {}",
-                  e
-                )))
-              }
-            };
+          Ok(response)
+        }
+        code => {
+          let response = serde_json::from_slice::<GraphQLResponse>(&upstream_response.body)
+            .ok()
+            .filter(|response| response.data.is_some() || response.errors.is_some());
 
-            Ok(response)
+          match response {
+            Some(response) => Ok(response.with_status_code(self.error_response_status(code))),
+            None => Err(SourceError::UnexpectedHTTPStatusError(code)),
           }
-          code => Err(SourceError::UnexpectedHTTPStatusError(code)),
-        },
-        Err(e) => Err(SourceError::NetworkError(e)),
+        }
       }
     }))
   }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  fn subscribe<'a>(
+    &'a self,
+    plugin_manager: Arc<Box<dyn PluginManager>>,
+    request_context: &'a mut RequestExecutionContext,
+  ) -> Pin<Box<(dyn Future<Output = Result<GraphQLResponseStream, SourceError>> + 'a)>> {
+    Box::pin(async move {
+      let (mut endpoint, conductor_http_request) = self
+        .upstream_http_request(&plugin_manager, request_context)
+        .await?;
+      // Plugins can rewrite the body of the upstream request, so the operation is read back from it.
+      let request = serde_json::from_slice::<GraphQLRequest>(&conductor_http_request.body)
+        .map_err(|e| SourceError::UpstreamPlanningError(e.into()))?;
+
+      let result = super::graphql_ws::subscribe(
+        endpoint.url(),
+        &conductor_http_request.headers,
+        request,
+        self.config.timeout(),
+      )
+      .await;
+
+      endpoint.report(result.is_ok());
+
+      result
+    })
+  }
 }
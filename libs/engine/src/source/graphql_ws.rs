@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use conductor_common::{
+  graphql::{GraphQLRequest, GraphQLResponse},
+  graphql_ws::{GraphQLWsMessage, GRAPHQL_TRANSPORT_WS_PROTOCOL},
+  http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderValue, HttpHeadersMap, CONTENT_TYPE},
+  source::{GraphQLResponseStream, SourceError},
+};
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+  connect_async,
+  tungstenite::{client::IntoClientRequest, Message},
+  MaybeTlsStream, WebSocketStream,
+};
+use tracing::debug;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Every subscription uses its own connection, so the id of the operation is constant.
+static SUBSCRIPTION_ID: &str = "1";
+
+/// The WebSocket URL of an HTTP endpoint, served on the same host and path.
+fn websocket_url(endpoint: &str) -> String {
+  match endpoint.strip_prefix("http") {
+    Some(rest) => format!("ws{}", rest),
+    None => endpoint.to_string(),
+  }
+}
+
+async fn send(socket: &mut Socket, message: &GraphQLWsMessage) -> Result<(), anyhow::Error> {
+  socket.send(Message::Text(message.to_text())).await?;
+
+  Ok(())
+}
+
+/// Returns the next message of the protocol, answering pings along the way.
+async fn receive(socket: &mut Socket) -> Result<GraphQLWsMessage, anyhow::Error> {
+  loop {
+    let message = match socket.next().await {
+      Some(Ok(Message::Text(text))) => serde_json::from_str::<GraphQLWsMessage>(&text)?,
+      Some(Ok(Message::Close(frame))) => {
+        return Err(anyhow::anyhow!(
+          "the upstream closed the connection: {}",
+          frame.map_or_else(|| "no reason".to_string(), |frame| frame.to_string())
+        ))
+      }
+      Some(Ok(_)) => continue,
+      Some(Err(e)) => return Err(e.into()),
+      None => return Err(anyhow::anyhow!("the upstream closed the connection")),
+    };
+
+    match message {
+      GraphQLWsMessage::Ping { .. } => {
+        send(socket, &GraphQLWsMessage::Pong { payload: None }).await?
+      }
+      message => return Ok(message),
+    }
+  }
+}
+
+async fn connect(
+  endpoint: &str,
+  headers: &HttpHeadersMap,
+  request: GraphQLRequest,
+) -> Result<Socket, anyhow::Error> {
+  let mut websocket_request = websocket_url(endpoint).into_client_request()?;
+  let websocket_headers = websocket_request.headers_mut();
+
+  for (name, value) in headers {
+    if name != CONTENT_TYPE {
+      websocket_headers.append(name, value.clone());
+    }
+  }
+
+  websocket_headers.insert(
+    SEC_WEBSOCKET_PROTOCOL,
+    HeaderValue::from_static(GRAPHQL_TRANSPORT_WS_PROTOCOL),
+  );
+
+  let (mut socket, _) = connect_async(websocket_request).await?;
+
+  send(
+    &mut socket,
+    &GraphQLWsMessage::ConnectionInit { payload: None },
+  )
+  .await?;
+
+  match receive(&mut socket).await? {
+    GraphQLWsMessage::ConnectionAck { .. } => {}
+    message => {
+      return Err(anyhow::anyhow!(
+        "expected the upstream to acknowledge the connection, got: {:?}",
+        message
+      ))
+    }
+  }
+
+  send(
+    &mut socket,
+    &GraphQLWsMessage::Subscribe {
+      id: SUBSCRIPTION_ID.to_string(),
+      payload: request,
+    },
+  )
+  .await?;
+
+  Ok(socket)
+}
+
+/// Subscribes to an upstream that implements the `graphql-transport-ws` protocol. The timeout applies to the connection and the acknowledgment of the upstream, not to the subscription itself.
+///
+/// Errors of the upstream after the subscription started are emitted as the last result of the stream.
+pub async fn subscribe(
+  endpoint: &str,
+  headers: &HttpHeadersMap,
+  request: GraphQLRequest,
+  timeout: Duration,
+) -> Result<GraphQLResponseStream, SourceError> {
+  let socket = match tokio::time::timeout(timeout, connect(endpoint, headers, request)).await {
+    Ok(Ok(socket)) => socket,
+    Ok(Err(e)) => return Err(SourceError::SubscriptionFailed(e)),
+    Err(_) => return Err(SourceError::Timeout(timeout)),
+  };
+
+  let results = futures::stream::unfold(Some(socket), |socket| async move {
+    let mut socket = socket?;
+
+    match receive(&mut socket).await {
+      Ok(GraphQLWsMessage::Next { id, payload }) if id == SUBSCRIPTION_ID => {
+        Some((payload, Some(socket)))
+      }
+      Ok(GraphQLWsMessage::Error { id, payload }) if id == SUBSCRIPTION_ID => {
+        Some((GraphQLResponse::new_errors(payload), None))
+      }
+      Ok(GraphQLWsMessage::Complete { id }) if id == SUBSCRIPTION_ID => {
+        let _ = socket.close(None).await;
+
+        None
+      }
+      Ok(message) => Some((
+        GraphQLResponse::new_error(&format!(
+          "unexpected message from the upstream: {:?}",
+          message
+        )),
+        None,
+      )),
+      Err(e) => {
+        debug!("upstream subscription failed: {}", e);
+
+        Some((
+          GraphQLResponse::new_error(&SourceError::SubscriptionFailed(e).to_string()),
+          None,
+        ))
+      }
+    }
+  });
+
+  Ok(Box::pin(results))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn websocket_urls() {
+    assert_eq!(
+      websocket_url("http://localhost:4000/graphql"),
+      "ws://localhost:4000/graphql"
+    );
+    assert_eq!(
+      websocket_url("https://api.example.com/graphql"),
+      "wss://api.example.com/graphql"
+    );
+    assert_eq!(
+      websocket_url("ws://localhost:4000/graphql"),
+      "ws://localhost:4000/graphql"
+    );
+  }
+}
@@ -0,0 +1,293 @@
+use std::sync::Mutex;
+
+use conductor_common::circuit::{Circuit, CircuitBreakerConfig, CircuitCheck, CircuitTransition};
+use conductor_config::GraphQLSourceConfig;
+use tracing::{info, warn};
+use web_time::Instant;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LoadBalancerError {
+  #[error("at least one endpoint must be specified")]
+  NoEndpoints,
+  #[error("the weight of endpoint \"{0}\" must be greater than 0")]
+  InvalidWeight(String),
+  #[error("failure_threshold, window and cooldown of the circuit_breaker must be greater than 0")]
+  InvalidCircuitBreaker,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+  url: String,
+  weight: i64,
+  /// The current weight of the smooth weighted round-robin.
+  current_weight: i64,
+  circuit: Circuit,
+}
+
+/// Picks the endpoint of every upstream request with a smooth weighted round-robin, skipping the endpoints whose circuit is open.
+#[derive(Debug)]
+pub struct LoadBalancer {
+  endpoints: Mutex<Vec<Endpoint>>,
+  circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+impl LoadBalancer {
+  pub fn new(config: &GraphQLSourceConfig) -> Result<Self, LoadBalancerError> {
+    let endpoints = config
+      .endpoint
+      .endpoints()
+      .into_iter()
+      .map(|endpoint| match endpoint.weight {
+        0 => Err(LoadBalancerError::InvalidWeight(endpoint.url)),
+        weight => Ok(Endpoint {
+          url: endpoint.url,
+          weight: weight as i64,
+          current_weight: 0,
+          circuit: Circuit::default(),
+        }),
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if endpoints.is_empty() {
+      return Err(LoadBalancerError::NoEndpoints);
+    }
+
+    if let Some(circuit_breaker) = &config.circuit_breaker {
+      if !circuit_breaker.is_valid() {
+        return Err(LoadBalancerError::InvalidCircuitBreaker);
+      }
+    }
+
+    Ok(Self {
+      endpoints: Mutex::new(endpoints),
+      circuit_breaker: config.circuit_breaker.clone(),
+    })
+  }
+
+  /// Returns the endpoint of the next upstream request, or `None` when the circuits of all the endpoints are open.
+  pub fn pick(&self) -> Option<EndpointLease<'_>> {
+    self.pick_excluding(None)
+  }
+
+  /// Returns an endpoint to retry a request that failed on the endpoint of `lease`, or `None` when no other endpoint is available.
+  pub fn pick_other(&self, lease: &EndpointLease<'_>) -> Option<EndpointLease<'_>> {
+    self.pick_excluding(Some(lease.index))
+  }
+
+  fn pick_excluding(&self, excluded: Option<usize>) -> Option<EndpointLease<'_>> {
+    let mut endpoints = self.endpoints.lock().ok()?;
+    let now = Instant::now();
+    let is_available = |index: usize, endpoint: &Endpoint| {
+      Some(index) != excluded
+        && match &self.circuit_breaker {
+          Some(circuit_breaker) => endpoint.circuit.is_available(now, circuit_breaker.cooldown),
+          None => true,
+        }
+    };
+
+    let total_weight: i64 = endpoints
+      .iter()
+      .enumerate()
+      .filter(|(index, endpoint)| is_available(*index, endpoint))
+      .map(|(_, endpoint)| endpoint.weight)
+      .sum();
+
+    // The index and the current weight of the picked endpoint, the first one wins on ties.
+    let mut picked: Option<(usize, i64)> = None;
+
+    for (index, endpoint) in endpoints.iter_mut().enumerate() {
+      if !is_available(index, &*endpoint) {
+        continue;
+      }
+
+      endpoint.current_weight += endpoint.weight;
+
+      if picked.map_or(true, |(_, weight)| endpoint.current_weight > weight) {
+        picked = Some((index, endpoint.current_weight));
+      }
+    }
+
+    let (index, _) = picked?;
+    let endpoint = &mut endpoints[index];
+    endpoint.current_weight -= total_weight;
+
+    let probe = match &self.circuit_breaker {
+      Some(circuit_breaker) => matches!(
+        endpoint.circuit.check(now, circuit_breaker.cooldown),
+        CircuitCheck::Probe
+      ),
+      None => false,
+    };
+
+    Some(EndpointLease {
+      balancer: self,
+      index,
+      url: endpoint.url.clone(),
+      probe,
+    })
+  }
+
+  fn record(&self, index: usize, success: bool, probe: bool) {
+    let circuit_breaker = match &self.circuit_breaker {
+      Some(circuit_breaker) => circuit_breaker,
+      None => return,
+    };
+
+    if let Ok(mut endpoints) = self.endpoints.lock() {
+      let endpoint = &mut endpoints[index];
+
+      match endpoint
+        .circuit
+        .record(Instant::now(), success, probe, circuit_breaker)
+      {
+        Some(CircuitTransition::Opened) => warn!(
+          "endpoint \"{}\" is removed from the rotation for {:?}",
+          endpoint.url, circuit_breaker.cooldown
+        ),
+        Some(CircuitTransition::Closed) => {
+          info!(
+            "endpoint \"{}\" is added back to the rotation",
+            endpoint.url
+          )
+        }
+        None => {}
+      }
+    }
+  }
+
+  fn release_probe(&self, index: usize) {
+    if let Ok(mut endpoints) = self.endpoints.lock() {
+      endpoints[index].circuit.release_probe();
+    }
+  }
+}
+
+/// An endpoint picked for an upstream request. When the request is the probe of a half-open circuit and its outcome is never reported, the probe is released once the lease is dropped.
+#[derive(Debug)]
+pub struct EndpointLease<'a> {
+  balancer: &'a LoadBalancer,
+  index: usize,
+  url: String,
+  probe: bool,
+}
+
+impl EndpointLease<'_> {
+  pub fn url(&self) -> &str {
+    &self.url
+  }
+
+  /// Records the outcome of an attempt. Only the first attempt is the probe of a half-open circuit.
+  pub fn report(&mut self, success: bool) {
+    self.balancer.record(self.index, success, self.probe);
+    self.probe = false;
+  }
+}
+
+impl Drop for EndpointLease<'_> {
+  fn drop(&mut self) {
+    if self.probe {
+      self.balancer.release_probe(self.index);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use conductor_config::WeightedEndpoint;
+
+  use super::*;
+
+  fn balancer(
+    weights: &[u32],
+    circuit_breaker: Option<CircuitBreakerConfig>,
+  ) -> Result<LoadBalancer, LoadBalancerError> {
+    let endpoints = weights
+      .iter()
+      .enumerate()
+      .map(|(index, weight)| WeightedEndpoint {
+        url: index.to_string(),
+        weight: *weight,
+      })
+      .collect();
+
+    LoadBalancer::new(&GraphQLSourceConfig {
+      endpoint: conductor_config::GraphQLSourceEndpoint::Multiple(endpoints),
+      circuit_breaker,
+      ..Default::default()
+    })
+  }
+
+  fn pick(balancer: &LoadBalancer, count: usize) -> Vec<String> {
+    (0..count)
+      .map(|_| balancer.pick().unwrap().url().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn weighted_round_robin() {
+    let balancer = balancer(&[5, 1, 1], None).unwrap();
+
+    assert_eq!(pick(&balancer, 7), vec!["0", "0", "1", "0", "2", "0", "0"]);
+  }
+
+  #[test]
+  fn removes_failing_endpoints() {
+    let balancer = balancer(
+      &[1, 1],
+      Some(CircuitBreakerConfig {
+        failure_threshold: 1,
+        window: Duration::from_secs(10),
+        cooldown: Duration::from_secs(30),
+      }),
+    )
+    .unwrap();
+
+    let mut lease = balancer.pick().unwrap();
+    assert_eq!(lease.url(), "0");
+    lease.report(false);
+    drop(lease);
+
+    assert_eq!(pick(&balancer, 3), vec!["1", "1", "1"]);
+
+    balancer.pick().unwrap().report(false);
+    assert!(balancer.pick().is_none());
+  }
+
+  #[test]
+  fn picks_another_endpoint_to_retry() {
+    let balancer = balancer(&[1, 1, 1], None).unwrap();
+
+    let lease = balancer.pick().unwrap();
+    assert_eq!(lease.url(), "0");
+    assert_eq!(balancer.pick_other(&lease).unwrap().url(), "1");
+    assert_eq!(balancer.pick_other(&lease).unwrap().url(), "2");
+
+    let balancer = self::balancer(&[1], None).unwrap();
+    let lease = balancer.pick().unwrap();
+    assert!(balancer.pick_other(&lease).is_none());
+  }
+
+  #[test]
+  fn rejects_invalid_config() {
+    assert!(matches!(
+      balancer(&[], None),
+      Err(LoadBalancerError::NoEndpoints)
+    ));
+    assert!(matches!(
+      balancer(&[1, 0], None),
+      Err(LoadBalancerError::InvalidWeight(_))
+    ));
+    assert!(matches!(
+      balancer(
+        &[1],
+        Some(CircuitBreakerConfig {
+          failure_threshold: 0,
+          ..Default::default()
+        })
+      ),
+      Err(LoadBalancerError::InvalidCircuitBreaker)
+    ));
+  }
+}
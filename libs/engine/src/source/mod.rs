@@ -1,3 +1,11 @@
+pub mod client_ip;
 pub mod federation_source;
 pub mod graphql_source;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod graphql_ws;
+pub mod load_balancer;
 pub mod mock_source;
+pub mod openapi;
+pub mod rest_source;
+pub mod retry;
+pub mod shadow;
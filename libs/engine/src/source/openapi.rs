@@ -0,0 +1,663 @@
+use std::collections::{BTreeMap, HashMap};
+
+use conductor_common::http::Method;
+use serde_json::Value;
+
+#[derive(thiserror::Error, Debug)]
+pub enum OpenApiError {
+  #[error("failed to parse the OpenAPI document: {0}")]
+  InvalidDocument(String),
+  #[error("unresolved reference \"{0}\"")]
+  UnresolvedReference(String),
+  #[error("invalid parameter in operation \"{0}\"")]
+  InvalidParameter(String),
+  #[error("field \"{0}\" is defined by multiple operations")]
+  DuplicateField(String),
+}
+
+static METHODS: &[(&str, Method)] = &[
+  ("get", Method::GET),
+  ("post", Method::POST),
+  ("put", Method::PUT),
+  ("patch", Method::PATCH),
+  ("delete", Method::DELETE),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterLocation {
+  Path,
+  Query,
+  Header,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Parameter {
+  pub name: String,
+  pub location: ParameterLocation,
+  pub required: bool,
+  /// The GraphQL type of the argument, from the schema of the parameter.
+  pub graphql_type: String,
+}
+
+/// An operation of the REST API, exposed as a root field.
+#[derive(Debug, PartialEq)]
+pub struct RestOperation {
+  pub method: Method,
+  /// The path template, with `{name}` placeholders for the path parameters.
+  pub path: String,
+  pub parameters: Vec<Parameter>,
+  /// Whether the operation accepts a request body, passed as the `input` argument.
+  pub has_body: bool,
+}
+
+/// The operations of an OpenAPI document, keyed by their field name.
+#[derive(Debug)]
+pub struct OpenApiSpec {
+  /// The URL of the first server of the document.
+  pub server_url: Option<String>,
+  pub queries: HashMap<String, RestOperation>,
+  pub mutations: HashMap<String, RestOperation>,
+  /// The GraphQL schema of the operations, generated from the schemas of their parameters and responses.
+  pub sdl: String,
+}
+
+/// The types that are always part of the generated schema.
+static BUILTIN_TYPES: &[&str] = &[
+  "Query", "Mutation", "JSON", "String", "Int", "Float", "Boolean", "ID",
+];
+
+fn is_graphql_name(name: &str) -> bool {
+  name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+    && !name.starts_with("__")
+    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Turns a schema name (or a path to an inline schema) into a valid GraphQL type name, in PascalCase.
+fn type_name(name: &str) -> String {
+  let type_name = field_name(name)
+    .split('_')
+    .map(|part| {
+      let mut chars = part.chars();
+
+      match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+      }
+    })
+    .collect::<String>();
+
+  match type_name.starts_with(|c: char| c.is_ascii_digit()) {
+    true => format!("_{}", type_name),
+    false => type_name,
+  }
+}
+
+fn scalar_type(schema_type: &str) -> &'static str {
+  match schema_type {
+    "string" => "String",
+    "integer" => "Int",
+    "number" => "Float",
+    "boolean" => "Boolean",
+    _ => "JSON",
+  }
+}
+
+/// The GraphQL type of an argument. Input objects are not generated, so objects are passed as `JSON`.
+fn input_type(document: &Value, schema: Option<&Value>) -> Result<String, OpenApiError> {
+  let schema = match schema {
+    Some(schema) => resolve(document, schema)?,
+    None => return Ok("JSON".to_string()),
+  };
+
+  Ok(match schema.get("type").and_then(Value::as_str) {
+    Some("array") => format!("[{}]", input_type(document, schema.get("items"))?),
+    Some(schema_type) => scalar_type(schema_type).to_string(),
+    None => "JSON".to_string(),
+  })
+}
+
+/// Generates the object types of the responses, from their JSON schemas.
+struct SchemaBuilder<'a> {
+  document: &'a Value,
+  /// The definitions of the object types, keyed by their name.
+  types: BTreeMap<String, String>,
+  /// The names of the object types generated for a `$ref`, so a referenced schema is only generated once.
+  references: HashMap<String, String>,
+}
+
+impl<'a> SchemaBuilder<'a> {
+  fn unique_type_name(&self, name: &str) -> String {
+    let name = type_name(name);
+    let mut unique_name = name.clone();
+    let mut suffix = 1;
+
+    while self.types.contains_key(&unique_name) || BUILTIN_TYPES.contains(&unique_name.as_str()) {
+      suffix += 1;
+      unique_name = format!("{}{}", name, suffix);
+    }
+
+    unique_name
+  }
+
+  /// The GraphQL type of a response schema. Schemas that can't be mapped to a GraphQL type (like `oneOf`, or objects without properties) are `JSON`.
+  fn output_type(&mut self, schema: Option<&Value>, name: &str) -> Result<String, OpenApiError> {
+    let schema = match schema {
+      Some(schema) => schema,
+      None => return Ok("JSON".to_string()),
+    };
+    let reference = schema.get("$ref").and_then(Value::as_str);
+
+    if let Some(type_name) = reference.and_then(|reference| self.references.get(reference)) {
+      return Ok(type_name.clone());
+    }
+
+    let name = reference
+      .and_then(|reference| reference.rsplit('/').next())
+      .unwrap_or(name);
+    let schema = resolve(self.document, schema)?;
+
+    match schema.get("type").and_then(Value::as_str) {
+      Some("array") => Ok(format!(
+        "[{}]",
+        self.output_type(schema.get("items"), name)?
+      )),
+      Some("object") | None => match schema.get("properties").and_then(Value::as_object) {
+        Some(properties) => self.object_type(properties, reference, name),
+        None => Ok("JSON".to_string()),
+      },
+      Some(schema_type) => Ok(scalar_type(schema_type).to_string()),
+    }
+  }
+
+  fn object_type(
+    &mut self,
+    properties: &serde_json::Map<String, Value>,
+    reference: Option<&str>,
+    name: &str,
+  ) -> Result<String, OpenApiError> {
+    let object_type = self.unique_type_name(name);
+
+    // Registered before the properties, so recursive schemas reference the type instead of generating it again.
+    self.types.insert(object_type.clone(), String::new());
+
+    if let Some(reference) = reference {
+      self
+        .references
+        .insert(reference.to_string(), object_type.clone());
+    }
+
+    let mut fields = vec![];
+    // Properties that are not valid GraphQL names can't be selected.
+    let mut properties = properties
+      .iter()
+      .filter(|(property, _)| is_graphql_name(property))
+      .collect::<Vec<_>>();
+    properties.sort_by_key(|(property, _)| *property);
+
+    for (property, schema) in properties {
+      let field_type = self.output_type(Some(schema), &format!("{}_{}", object_type, property))?;
+      fields.push(format!("  {}: {}", property, field_type));
+    }
+
+    if fields.is_empty() {
+      self.types.remove(&object_type);
+      self.references.retain(|_, name| *name != object_type);
+
+      return Ok("JSON".to_string());
+    }
+
+    self.types.insert(
+      object_type.clone(),
+      format!("type {} {{\n{}\n}}", object_type, fields.join("\n")),
+    );
+
+    Ok(object_type)
+  }
+
+  /// The type of the first successful JSON response of an operation.
+  fn response_type(&mut self, operation: &Value, name: &str) -> Result<String, OpenApiError> {
+    let response = operation
+      .get("responses")
+      .and_then(Value::as_object)
+      .into_iter()
+      .flatten()
+      .find(|(status, _)| status.starts_with('2'))
+      .map(|(_, response)| resolve(self.document, response))
+      .transpose()?;
+    let schema = response
+      .and_then(|response| response.get("content"))
+      .and_then(Value::as_object)
+      .into_iter()
+      .flatten()
+      .find(|(content_type, _)| content_type.contains("json"))
+      .and_then(|(_, content)| content.get("schema"));
+
+    self.output_type(schema, &format!("{}_response", name))
+  }
+}
+
+/// The definition of a root field, with the parameters of the operation as arguments.
+fn field_definition(name: &str, operation: &RestOperation, field_type: &str) -> String {
+  let mut arguments = operation
+    .parameters
+    .iter()
+    // Parameters that are not valid GraphQL names (like most headers) can't be passed as arguments.
+    .filter(|parameter| is_graphql_name(&parameter.name))
+    .map(|parameter| {
+      format!(
+        "{}: {}{}",
+        parameter.name,
+        parameter.graphql_type,
+        if parameter.required { "!" } else { "" }
+      )
+    })
+    .collect::<Vec<_>>();
+
+  if operation.has_body
+    && !operation
+      .parameters
+      .iter()
+      .any(|parameter| parameter.name == "input")
+  {
+    arguments.push("input: JSON".to_string());
+  }
+
+  match arguments.is_empty() {
+    true => format!("  {}: {}", name, field_type),
+    false => format!("  {}({}): {}", name, arguments.join(", "), field_type),
+  }
+}
+
+/// Turns an `operationId` (or a method and a path) into a valid GraphQL name.
+fn field_name(name: &str) -> String {
+  let mut field_name = String::with_capacity(name.len());
+
+  for c in name.chars() {
+    match c {
+      c if c.is_ascii_alphanumeric() => field_name.push(c),
+      _ if field_name.ends_with('_') || field_name.is_empty() => {}
+      _ => field_name.push('_'),
+    }
+  }
+
+  let field_name = field_name.trim_end_matches('_');
+
+  match field_name.starts_with(|c: char| c.is_ascii_digit()) {
+    true => format!("_{}", field_name),
+    false => field_name.to_string(),
+  }
+}
+
+/// Follows a local `$ref` (for example `#/components/parameters/id`), references to other documents are not supported.
+fn resolve<'a>(document: &'a Value, value: &'a Value) -> Result<&'a Value, OpenApiError> {
+  match value.get("$ref").and_then(Value::as_str) {
+    Some(reference) => reference
+      .strip_prefix('#')
+      .and_then(|pointer| document.pointer(pointer))
+      .ok_or_else(|| OpenApiError::UnresolvedReference(reference.to_string())),
+    None => Ok(value),
+  }
+}
+
+fn parameters(
+  document: &Value,
+  path_item: &Value,
+  operation: &Value,
+  name: &str,
+) -> Result<Vec<Parameter>, OpenApiError> {
+  let mut parameters: Vec<Parameter> = vec![];
+  let declared = [path_item, operation]
+    .into_iter()
+    .filter_map(|value| value.get("parameters").and_then(Value::as_array))
+    .flatten();
+
+  for parameter in declared {
+    let parameter = resolve(document, parameter)?;
+    let location = match parameter.get("in").and_then(Value::as_str) {
+      Some("path") => ParameterLocation::Path,
+      Some("query") => ParameterLocation::Query,
+      Some("header") => ParameterLocation::Header,
+      // Cookies are not supported.
+      Some(_) => continue,
+      None => return Err(OpenApiError::InvalidParameter(name.to_string())),
+    };
+    let parameter_name = match parameter.get("name").and_then(Value::as_str) {
+      Some(parameter_name) => parameter_name.to_string(),
+      None => return Err(OpenApiError::InvalidParameter(name.to_string())),
+    };
+    let parameter = Parameter {
+      required: location == ParameterLocation::Path
+        || parameter.get("required") == Some(&Value::Bool(true)),
+      graphql_type: input_type(document, parameter.get("schema"))?,
+      name: parameter_name,
+      location,
+    };
+
+    // The parameters of the operation override the ones of its path.
+    parameters.retain(|existing| {
+      existing.name != parameter.name || existing.location != parameter.location
+    });
+    parameters.push(parameter);
+  }
+
+  Ok(parameters)
+}
+
+impl OpenApiSpec {
+  /// Parses an OpenAPI document, in JSON or YAML format.
+  pub fn parse(document: &str) -> Result<Self, OpenApiError> {
+    // Parsed as YAML first, since the keys of YAML mappings (like the status codes of the responses) are not always strings.
+    let document = serde_yaml::from_str::<serde_yaml::Value>(document)
+      .map_err(|e| OpenApiError::InvalidDocument(e.to_string()))
+      .and_then(|document| {
+        serde_json::to_value(document).map_err(|e| OpenApiError::InvalidDocument(e.to_string()))
+      })?;
+    let mut spec = Self {
+      server_url: document
+        .pointer("/servers/0/url")
+        .and_then(Value::as_str)
+        .map(String::from),
+      queries: HashMap::new(),
+      mutations: HashMap::new(),
+      sdl: String::new(),
+    };
+    let mut schema_builder = SchemaBuilder {
+      document: &document,
+      types: BTreeMap::new(),
+      references: HashMap::new(),
+    };
+    let mut query_fields = BTreeMap::new();
+    let mut mutation_fields = BTreeMap::new();
+
+    let paths = document
+      .get("paths")
+      .and_then(Value::as_object)
+      .into_iter()
+      .flatten();
+
+    for (path, path_item) in paths {
+      let path_item = resolve(&document, path_item)?;
+
+      for (method_name, method) in METHODS {
+        let operation = match path_item.get(*method_name) {
+          Some(operation) => operation,
+          None => continue,
+        };
+        let name = field_name(
+          &operation
+            .get("operationId")
+            .and_then(Value::as_str)
+            .map(String::from)
+            .unwrap_or_else(|| format!("{}_{}", method_name, path)),
+        );
+        let rest_operation = RestOperation {
+          method: method.clone(),
+          path: path.clone(),
+          parameters: parameters(&document, path_item, operation, &name)?,
+          has_body: operation.get("requestBody").is_some(),
+        };
+        let field_type = schema_builder.response_type(operation, &name)?;
+        let (operations, fields) = match *method == Method::GET {
+          true => (&mut spec.queries, &mut query_fields),
+          false => (&mut spec.mutations, &mut mutation_fields),
+        };
+
+        fields.insert(
+          name.clone(),
+          field_definition(&name, &rest_operation, &field_type),
+        );
+
+        if operations.insert(name.clone(), rest_operation).is_some() {
+          return Err(OpenApiError::DuplicateField(name));
+        }
+      }
+    }
+
+    let mut definitions = vec!["scalar JSON".to_string()];
+
+    for (root_type, fields) in [("Query", query_fields), ("Mutation", mutation_fields)] {
+      if !fields.is_empty() {
+        let fields = fields.into_values().collect::<Vec<_>>();
+        definitions.push(format!("type {} {{\n{}\n}}", root_type, fields.join("\n")));
+      }
+    }
+
+    definitions.extend(schema_builder.types.into_values());
+    spec.sdl = definitions.join("\n\n");
+
+    Ok(spec)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  static PETSTORE: &str = r##"
+openapi: 3.0.0
+servers:
+  - url: https://petstore.example.com/v1
+paths:
+  /pets:
+    get:
+      operationId: listPets
+      parameters:
+        - name: limit
+          in: query
+      responses:
+        200:
+          description: A list of pets
+    post:
+      operationId: createPet
+      requestBody:
+        content:
+          application/json: {}
+  /pets/{petId}:
+    parameters:
+      - $ref: "#/components/parameters/petId"
+      - name: x-tenant
+        in: header
+    get:
+      parameters:
+        - name: x-tenant
+          in: header
+          required: true
+        - name: session
+          in: cookie
+components:
+  parameters:
+    petId:
+      name: petId
+      in: path
+"##;
+
+  #[test]
+  fn parses_operations() {
+    let spec = OpenApiSpec::parse(PETSTORE).unwrap();
+
+    assert_eq!(
+      spec.server_url.as_deref(),
+      Some("https://petstore.example.com/v1")
+    );
+    assert_eq!(
+      spec.queries.get("listPets"),
+      Some(&RestOperation {
+        method: Method::GET,
+        path: "/pets".to_string(),
+        parameters: vec![Parameter {
+          name: "limit".to_string(),
+          location: ParameterLocation::Query,
+          required: false,
+          graphql_type: "JSON".to_string(),
+        }],
+        has_body: false,
+      })
+    );
+    assert_eq!(
+      spec.queries.get("get_pets_petId"),
+      Some(&RestOperation {
+        method: Method::GET,
+        path: "/pets/{petId}".to_string(),
+        parameters: vec![
+          Parameter {
+            name: "petId".to_string(),
+            location: ParameterLocation::Path,
+            required: true,
+            graphql_type: "JSON".to_string(),
+          },
+          Parameter {
+            name: "x-tenant".to_string(),
+            location: ParameterLocation::Header,
+            required: true,
+            graphql_type: "JSON".to_string(),
+          },
+        ],
+        has_body: false,
+      })
+    );
+    assert!(spec.mutations.get("createPet").unwrap().has_body);
+    assert_eq!(spec.queries.len(), 2);
+    assert_eq!(spec.mutations.len(), 1);
+  }
+
+  #[test]
+  fn generates_sdl() {
+    let spec = OpenApiSpec::parse(
+      r##"
+paths:
+  /pets/{petId}:
+    get:
+      operationId: getPet
+      parameters:
+        - name: petId
+          in: path
+          schema:
+            type: integer
+        - name: tags
+          in: query
+          schema:
+            type: array
+            items:
+              type: string
+        - name: x-tenant
+          in: header
+      responses:
+        "200":
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+  /pets:
+    post:
+      operationId: createPet
+      requestBody:
+        content:
+          application/json: {}
+      responses:
+        "201":
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  id:
+                    type: integer
+components:
+  schemas:
+    Pet:
+      type: object
+      properties:
+        id:
+          type: integer
+        name:
+          type: string
+        parent:
+          $ref: "#/components/schemas/Pet"
+        owner:
+          type: object
+          properties:
+            email:
+              type: string
+        metadata:
+          type: object
+        x-internal:
+          type: string
+"##,
+    )
+    .unwrap();
+
+    assert_eq!(
+      spec.sdl,
+      r#"scalar JSON
+
+type Query {
+  getPet(petId: Int!, tags: [String]): Pet
+}
+
+type Mutation {
+  createPet(input: JSON): CreatePetResponse
+}
+
+type CreatePetResponse {
+  id: Int
+}
+
+type Pet {
+  id: Int
+  metadata: JSON
+  name: String
+  owner: PetOwner
+  parent: Pet
+}
+
+type PetOwner {
+  email: String
+}"#
+    );
+  }
+
+  #[test]
+  fn field_names() {
+    assert_eq!(field_name("listPets"), "listPets");
+    assert_eq!(field_name("get_/pets/{petId}"), "get_pets_petId");
+    assert_eq!(field_name("2fa-verify"), "_2fa_verify");
+    assert_eq!(type_name("get_pets_response"), "GetPetsResponse");
+    assert_eq!(type_name("2fa-verify"), "_2faVerify");
+  }
+
+  #[test]
+  fn rejects_invalid_documents() {
+    assert!(matches!(
+      OpenApiSpec::parse("paths: [").unwrap_err(),
+      OpenApiError::InvalidDocument(_)
+    ));
+    assert!(matches!(
+      OpenApiSpec::parse(
+        r##"
+paths:
+  /pets/{petId}:
+    get:
+      parameters:
+        - $ref: "#/components/parameters/missing"
+"##
+      )
+      .unwrap_err(),
+      OpenApiError::UnresolvedReference(_)
+    ));
+    assert!(matches!(
+      OpenApiSpec::parse(
+        r#"
+paths:
+  /a:
+    get:
+      operationId: pets
+  /b:
+    get:
+      operationId: pets
+"#
+      )
+      .unwrap_err(),
+      OpenApiError::DuplicateField(_)
+    ));
+  }
+}
@@ -0,0 +1,701 @@
+use std::{
+  collections::{HashMap, HashSet},
+  future::Future,
+  pin::Pin,
+  sync::Arc,
+};
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::{parse_graphql_schema, GraphQLError, GraphQLResponse, ParsedGraphQLSchema},
+  http::{
+    ConductorHttpRequest, ConductorHttpResponse, HeaderName, HeaderValue, HttpHeadersMap, Url,
+    CONTENT_TYPE,
+  },
+  plugin_manager::PluginManager,
+  source::{GraphQLSourceInitError, SourceError, SourceRuntime},
+  Field, FragmentDefinition, GraphQLValue, Selection, SelectionSet,
+};
+use conductor_config::{OpenApiSpecSource, RestSourceConfig};
+use futures::{stream, StreamExt};
+use minitrace_reqwest::{traced_reqwest, TracedHttpClient};
+use serde_json::{Map, Value};
+use tracing::debug;
+
+use crate::source::openapi::{OpenApiSpec, ParameterLocation, RestOperation};
+
+/// The argument holding the JSON request body of an operation.
+static INPUT_ARGUMENT: &str = "input";
+
+#[derive(Debug)]
+enum HeaderTemplatePart {
+  Literal(String),
+  /// A header of the downstream request.
+  Header(HeaderName),
+}
+
+/// The value of a header sent to the REST API, that can reference headers of the downstream request with `{header.NAME}`.
+#[derive(Debug)]
+struct HeaderTemplate {
+  name: HeaderName,
+  parts: Vec<HeaderTemplatePart>,
+}
+
+impl HeaderTemplate {
+  fn parse(name: &str, template: &str) -> Result<Self, anyhow::Error> {
+    static PLACEHOLDER_START: &str = "{header.";
+
+    let name = HeaderName::from_bytes(name.as_bytes())
+      .map_err(|e| anyhow::anyhow!("invalid header name \"{}\": {}", name, e))?;
+    let mut parts = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find(PLACEHOLDER_START) {
+      let end = rest[start..]
+        .find('}')
+        .map(|end| start + end)
+        .ok_or_else(|| anyhow::anyhow!("unclosed placeholder in header \"{}\"", name))?;
+      let header = &rest[start + PLACEHOLDER_START.len()..end];
+      let header = HeaderName::from_bytes(header.as_bytes())
+        .map_err(|e| anyhow::anyhow!("invalid header name \"{}\": {}", header, e))?;
+
+      if start > 0 {
+        parts.push(HeaderTemplatePart::Literal(rest[..start].to_string()));
+      }
+
+      parts.push(HeaderTemplatePart::Header(header));
+      rest = &rest[end + 1..];
+    }
+
+    if !rest.is_empty() {
+      parts.push(HeaderTemplatePart::Literal(rest.to_string()));
+    }
+
+    Ok(Self { name, parts })
+  }
+
+  /// Returns `None` when a referenced header is missing from the downstream request.
+  fn render(&self, downstream_headers: &HttpHeadersMap) -> Option<HeaderValue> {
+    let mut value = Vec::new();
+
+    for part in &self.parts {
+      match part {
+        HeaderTemplatePart::Literal(literal) => value.extend_from_slice(literal.as_bytes()),
+        HeaderTemplatePart::Header(header) => {
+          value.extend_from_slice(downstream_headers.get(header)?.as_bytes())
+        }
+      }
+    }
+
+    HeaderValue::from_bytes(&value).ok()
+  }
+}
+
+/// Percent-encodes a path parameter, keeping only the unreserved characters.
+///
+/// Returns `None` for empty and dot segments (`.` and `..`), since they would change the path of the request once the URL is normalized.
+fn encode_path_segment(value: &str) -> Option<String> {
+  if matches!(value, "" | "." | "..") {
+    return None;
+  }
+
+  let mut encoded = String::with_capacity(value.len());
+
+  for byte in value.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+        encoded.push(byte as char)
+      }
+      _ => encoded.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+
+  Some(encoded)
+}
+
+/// Parameters are sent as their JSON representation, without the quotes of strings.
+fn parameter_value(value: &Value) -> String {
+  match value {
+    Value::String(value) => value.clone(),
+    value => value.to_string(),
+  }
+}
+
+fn to_json(value: &GraphQLValue<'static, String>, variables: &Map<String, Value>) -> Value {
+  match value {
+    GraphQLValue::Variable(name) => variables.get(name).cloned().unwrap_or(Value::Null),
+    GraphQLValue::Int(number) => number.as_i64().map(Value::from).unwrap_or(Value::Null),
+    GraphQLValue::Float(number) => Value::from(*number),
+    GraphQLValue::String(value) => Value::from(value.as_str()),
+    GraphQLValue::Boolean(value) => Value::from(*value),
+    GraphQLValue::Null => Value::Null,
+    GraphQLValue::Enum(value) => Value::from(value.as_str()),
+    GraphQLValue::List(items) => items.iter().map(|item| to_json(item, variables)).collect(),
+    GraphQLValue::Object(fields) => Value::Object(
+      fields
+        .iter()
+        .map(|(name, value)| (name.clone(), to_json(value, variables)))
+        .collect(),
+    ),
+  }
+}
+
+type Fragments = HashMap<String, FragmentDefinition<'static, String>>;
+
+/// Flattens the fields of a selection set, including the ones selected through fragments. Since the responses are not typed, type conditions are ignored.
+fn collect_fields<'a>(
+  selection_set: &'a SelectionSet<'static, String>,
+  fragments: &'a Fragments,
+  visited_fragments: &mut HashSet<&'a str>,
+  fields: &mut Vec<&'a Field<'static, String>>,
+) {
+  for selection in &selection_set.items {
+    match selection {
+      Selection::Field(field) => fields.push(field),
+      Selection::InlineFragment(fragment) => collect_fields(
+        &fragment.selection_set,
+        fragments,
+        visited_fragments,
+        fields,
+      ),
+      Selection::FragmentSpread(spread) => {
+        if let Some(fragment) = fragments.get(&spread.fragment_name) {
+          if visited_fragments.insert(spread.fragment_name.as_str()) {
+            collect_fields(
+              &fragment.selection_set,
+              fragments,
+              visited_fragments,
+              fields,
+            );
+          }
+        }
+      }
+    }
+  }
+}
+
+fn fields<'a>(
+  selection_set: &'a SelectionSet<'static, String>,
+  fragments: &'a Fragments,
+) -> Vec<&'a Field<'static, String>> {
+  let mut fields = vec![];
+  collect_fields(selection_set, fragments, &mut HashSet::new(), &mut fields);
+
+  fields
+}
+
+fn response_key<'a>(field: &'a Field<'static, String>) -> &'a str {
+  field.alias.as_deref().unwrap_or(&field.name)
+}
+
+/// Maps a JSON response to the shape of the selection set.
+fn project(
+  value: &Value,
+  selection_set: &SelectionSet<'static, String>,
+  fragments: &Fragments,
+) -> Value {
+  if selection_set.items.is_empty() {
+    return value.clone();
+  }
+
+  match value {
+    Value::Array(items) => items
+      .iter()
+      .map(|item| project(item, selection_set, fragments))
+      .collect(),
+    Value::Object(object) => Value::Object(
+      fields(selection_set, fragments)
+        .into_iter()
+        .map(|field| {
+          let value = match object.get(&field.name) {
+            Some(value) => project(value, &field.selection_set, fragments),
+            None => Value::Null,
+          };
+
+          (response_key(field).to_string(), value)
+        })
+        .collect(),
+    ),
+    value => value.clone(),
+  }
+}
+
+/// A root field, resolved with a request to the REST API.
+struct Call<'a> {
+  field: &'a Field<'static, String>,
+  request: ConductorHttpRequest,
+}
+
+#[derive(Debug)]
+pub struct RestSourceRuntime {
+  pub fetcher: TracedHttpClient,
+  pub config: RestSourceConfig,
+  pub identifier: String,
+  spec: OpenApiSpec,
+  schema: Arc<ParsedGraphQLSchema>,
+  sdl: Arc<String>,
+  base_url: String,
+  headers: Vec<HeaderTemplate>,
+}
+
+impl RestSourceRuntime {
+  pub async fn new(
+    identifier: String,
+    config: RestSourceConfig,
+  ) -> Result<Self, GraphQLSourceInitError> {
+    tracing::info!(
+      "Initializing source '{}' of type 'rest' with config: {:?}",
+      identifier,
+      config
+    );
+
+    let client = wasm_polyfills::create_http_client()
+      .build()
+      .map_err(|source| GraphQLSourceInitError::FetcherError { source })?;
+    let document = match &config.spec {
+      OpenApiSpecSource::File { path } => path.contents.clone(),
+      OpenApiSpecSource::Inline { content } => content.clone(),
+      OpenApiSpecSource::Remote { url } => client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|source| GraphQLSourceInitError::FetcherError { source })?
+        .text()
+        .await
+        .map_err(|source| GraphQLSourceInitError::FetcherError { source })?,
+    };
+    let spec =
+      OpenApiSpec::parse(&document).map_err(|source| GraphQLSourceInitError::SourceInitFailed {
+        source: source.into(),
+      })?;
+    let schema = parse_graphql_schema(&spec.sdl).map_err(|source| {
+      GraphQLSourceInitError::SourceInitFailed {
+        source: anyhow::anyhow!("failed to generate the GraphQL schema: {}", source),
+      }
+    })?;
+    let base_url = config
+      .base_url
+      .clone()
+      .or_else(|| spec.server_url.clone())
+      .filter(|base_url| Url::parse(base_url).is_ok())
+      .ok_or_else(|| GraphQLSourceInitError::SourceInitFailed {
+        source: anyhow::anyhow!(
+          "the OpenAPI document doesn't declare an absolute server URL, base_url must be specified"
+        ),
+      })?
+      .trim_end_matches('/')
+      .to_string();
+    let headers = config
+      .headers
+      .iter()
+      .map(|(name, template)| HeaderTemplate::parse(name, template))
+      .collect::<Result<_, _>>()
+      .map_err(|source| GraphQLSourceInitError::SourceInitFailed { source })?;
+
+    Ok(Self {
+      fetcher: traced_reqwest(client),
+      config,
+      identifier,
+      schema: Arc::new(schema),
+      sdl: Arc::new(spec.sdl.clone()),
+      spec,
+      base_url,
+      headers,
+    })
+  }
+
+  /// Builds the request of an operation, from the arguments of its field.
+  fn build_request(
+    &self,
+    operation: &RestOperation,
+    field: &Field<'static, String>,
+    variables: &Map<String, Value>,
+    downstream_headers: &HttpHeadersMap,
+  ) -> Result<ConductorHttpRequest, String> {
+    let arguments = field
+      .arguments
+      .iter()
+      .map(|(name, value)| (name.as_str(), to_json(value, variables)))
+      .filter(|(_, value)| !value.is_null())
+      .collect::<HashMap<_, _>>();
+    let mut path = operation.path.clone();
+    let mut query = vec![];
+    let mut headers = HttpHeadersMap::new();
+
+    for template in &self.headers {
+      if let Some(value) = template.render(downstream_headers) {
+        headers.insert(template.name.clone(), value);
+      }
+    }
+
+    for parameter in &operation.parameters {
+      let value = match arguments.get(parameter.name.as_str()) {
+        Some(value) => value,
+        None if parameter.required => {
+          return Err(format!("missing required argument \"{}\"", parameter.name))
+        }
+        None => continue,
+      };
+
+      match parameter.location {
+        ParameterLocation::Path => {
+          let segment = encode_path_segment(&parameter_value(value)).ok_or_else(|| {
+            format!(
+              "invalid value for argument \"{}\": empty and dot segments are not allowed",
+              parameter.name
+            )
+          })?;
+
+          path = path.replace(&format!("{{{}}}", parameter.name), &segment)
+        }
+        // Lists are sent as repeated query parameters.
+        ParameterLocation::Query => match value {
+          Value::Array(items) => query.extend(
+            items
+              .iter()
+              .map(|item| (parameter.name.as_str(), parameter_value(item))),
+          ),
+          value => query.push((parameter.name.as_str(), parameter_value(value))),
+        },
+        ParameterLocation::Header => {
+          let name = HeaderName::from_bytes(parameter.name.as_bytes())
+            .map_err(|e| format!("invalid header \"{}\": {}", parameter.name, e))?;
+          let value = HeaderValue::from_str(&parameter_value(value))
+            .map_err(|e| format!("invalid header \"{}\": {}", parameter.name, e))?;
+
+          headers.insert(name, value);
+        }
+      }
+    }
+
+    let mut url = Url::parse(&format!("{}{}", self.base_url, path)).map_err(|e| e.to_string())?;
+
+    if !query.is_empty() {
+      url.query_pairs_mut().extend_pairs(query);
+    }
+
+    let body = match arguments.get(INPUT_ARGUMENT) {
+      Some(input) if operation.has_body => {
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        input.to_string().into()
+      }
+      _ => Default::default(),
+    };
+
+    Ok(ConductorHttpRequest {
+      method: operation.method.clone(),
+      uri: url.to_string(),
+      query_string: "".to_string(),
+      headers,
+      body,
+      peer_addr: None,
+    })
+  }
+
+  async fn send(&self, request: &ConductorHttpRequest) -> Result<ConductorHttpResponse, String> {
+    debug!("dispatching REST request: {:?}", request);
+
+    let upstream_request = self
+      .fetcher
+      .request(request.method.clone(), &request.uri)
+      .headers(request.headers.clone())
+      .body(request.body.clone());
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let upstream_request = upstream_request.timeout(self.config.timeout());
+
+    let response = upstream_request
+      .send()
+      .await
+      .map_err(|e| format!("failed to send the REST request: {}", e))?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response
+      .bytes()
+      .await
+      .map_err(|e| format!("failed to read the REST response: {}", e))?;
+
+    Ok(ConductorHttpResponse {
+      body,
+      status,
+      headers,
+    })
+  }
+}
+
+fn field_error(message: String, field: &Field<'static, String>) -> GraphQLError {
+  GraphQLError {
+    path: Some(vec![response_key(field).to_string()]),
+    ..GraphQLError::new(&message)
+  }
+}
+
+impl SourceRuntime for RestSourceRuntime {
+  fn name(&self) -> &str {
+    &self.identifier
+  }
+
+  fn schema(&self) -> Option<Arc<ParsedGraphQLSchema>> {
+    Some(self.schema.clone())
+  }
+
+  fn sdl(&self) -> Option<Arc<String>> {
+    Some(self.sdl.clone())
+  }
+
+  fn execute<'a>(
+    &'a self,
+    plugin_manager: Arc<Box<dyn PluginManager>>,
+    request_context: &'a mut RequestExecutionContext,
+  ) -> Pin<Box<(dyn Future<Output = Result<GraphQLResponse, SourceError>> + 'a)>> {
+    Box::pin(wasm_polyfills::call_async(async move {
+      let downstream_request = match request_context.downstream_graphql_request.as_ref() {
+        Some(request) => request,
+        None => {
+          return Ok(GraphQLResponse::new_error(
+            "source request isn't available at execution context!",
+          ))
+        }
+      };
+
+      let (root_type, operations) = match (
+        downstream_request.is_running_query(),
+        downstream_request.is_running_mutation(),
+      ) {
+        (true, _) => ("Query", &self.spec.queries),
+        (_, true) => ("Mutation", &self.spec.mutations),
+        _ => {
+          return Ok(GraphQLResponse::new_error(
+            "subscriptions are not supported by REST sources",
+          ))
+        }
+      };
+      let selection_set = match downstream_request.executable_selection_set() {
+        Some(selection_set) => selection_set.clone(),
+        None => return Ok(GraphQLResponse::new_error("operation not found")),
+      };
+      let fragments = downstream_request
+        .fragment_definitions()
+        .into_iter()
+        .map(|(name, fragment)| (name.to_string(), fragment.clone()))
+        .collect::<Fragments>();
+      let variables = downstream_request
+        .request
+        .variables
+        .clone()
+        .unwrap_or_default();
+      let is_mutation = root_type == "Mutation";
+
+      let mut data = Map::new();
+      let mut errors = vec![];
+      let mut calls = vec![];
+
+      for field in fields(&selection_set, &fragments) {
+        if field.name == "__typename" {
+          data.insert(response_key(field).to_string(), Value::from(root_type));
+          continue;
+        }
+
+        let request = operations
+          .get(&field.name)
+          .ok_or_else(|| {
+            format!(
+              "Cannot query field \"{}\" on type \"{}\".",
+              field.name, root_type
+            )
+          })
+          .and_then(|operation| {
+            self.build_request(
+              operation,
+              field,
+              &variables,
+              &request_context.downstream_http_request.headers,
+            )
+          });
+
+        match request {
+          Ok(request) => calls.push(Call { field, request }),
+          Err(message) => {
+            data.insert(response_key(field).to_string(), Value::Null);
+            errors.push(field_error(message, field));
+          }
+        }
+      }
+
+      let max_requests = self.config.max_requests();
+
+      if calls.len() > max_requests {
+        return Ok(GraphQLResponse::new_error(&format!(
+          "the operation sends {} requests to the REST API, the maximum is {}",
+          calls.len(),
+          max_requests
+        )));
+      }
+
+      for call in &mut calls {
+        plugin_manager
+          .on_upstream_http_request(request_context, &mut call.request)
+          .await;
+
+        if request_context.is_short_circuit() {
+          return Err(SourceError::ShortCircuit);
+        }
+      }
+
+      // The fields of a mutation are resolved one after the other, like in a GraphQL server.
+      let concurrency = match is_mutation {
+        true => 1,
+        false => self.config.max_concurrent_requests(),
+      };
+      let responses = stream::iter(calls.iter().map(|call| self.send(&call.request)))
+        .buffered(concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+      for (call, response) in calls.iter().zip(responses) {
+        let key = response_key(call.field).to_string();
+        let mut response = match response {
+          Ok(response) => response,
+          Err(message) => {
+            data.insert(key, Value::Null);
+            errors.push(field_error(message, call.field));
+            continue;
+          }
+        };
+
+        plugin_manager
+          .on_upstream_http_response(request_context, &mut response)
+          .await;
+
+        if request_context.is_short_circuit() {
+          return Err(SourceError::ShortCircuit);
+        }
+
+        if !response.status.is_success() {
+          data.insert(key, Value::Null);
+          errors.push(field_error(
+            format!("REST API responded with HTTP status {}", response.status),
+            call.field,
+          ));
+          continue;
+        }
+
+        let value = match response.body.is_empty() {
+          true => Ok(Value::Null),
+          false => serde_json::from_slice::<Value>(&response.body),
+        };
+
+        match value {
+          Ok(value) => {
+            data.insert(key, project(&value, &call.field.selection_set, &fragments));
+          }
+          Err(e) => {
+            data.insert(key, Value::Null);
+            errors.push(field_error(
+              format!("failed to parse the REST response: {}", e),
+              call.field,
+            ));
+          }
+        }
+      }
+
+      let mut response = GraphQLResponse::new_errors(errors);
+      response.data = Some(Value::Object(data));
+
+      if response.errors.as_ref().is_some_and(Vec::is_empty) {
+        response.errors = None;
+      }
+
+      Ok(response)
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::graphql::parse_graphql_operation;
+  use conductor_common::{Definition, OperationDefinition};
+  use serde_json::json;
+
+  use super::*;
+
+  fn selection_set(operation: &str) -> (SelectionSet<'static, String>, Fragments) {
+    let document = parse_graphql_operation(operation).unwrap();
+    let mut selection_set = None;
+    let mut fragments = Fragments::new();
+
+    for definition in document.definitions {
+      match definition {
+        Definition::Operation(OperationDefinition::SelectionSet(set)) => selection_set = Some(set),
+        Definition::Fragment(fragment) => {
+          fragments.insert(fragment.name.clone(), fragment);
+        }
+        _ => {}
+      }
+    }
+
+    (selection_set.unwrap(), fragments)
+  }
+
+  #[test]
+  fn projects_responses() {
+    let (selection_set, fragments) = selection_set(
+      "{ id name: title tags { label } ...Owner } fragment Owner on Pet { owner { id } }",
+    );
+    let value = json!([
+      {
+        "id": 1,
+        "title": "Rex",
+        "tags": [{ "label": "dog", "color": "brown" }],
+        "owner": { "id": 2, "email": "owner@example.com" },
+        "secret": true
+      },
+      { "id": 2 }
+    ]);
+
+    assert_eq!(
+      project(&value, &selection_set, &fragments),
+      json!([
+        {
+          "id": 1,
+          "name": "Rex",
+          "tags": [{ "label": "dog" }],
+          "owner": { "id": 2 }
+        },
+        { "id": 2, "name": null, "tags": null, "owner": null }
+      ])
+    );
+  }
+
+  #[test]
+  fn renders_header_templates() {
+    let mut downstream_headers = HttpHeadersMap::new();
+    downstream_headers.insert("x-api-token", HeaderValue::from_static("secret"));
+
+    let template = HeaderTemplate::parse("authorization", "Bearer {header.x-api-token}").unwrap();
+    assert_eq!(
+      template.render(&downstream_headers).unwrap(),
+      "Bearer secret"
+    );
+
+    let template = HeaderTemplate::parse("x-tenant", "{header.x-tenant}").unwrap();
+    assert!(template.render(&downstream_headers).is_none());
+
+    assert!(HeaderTemplate::parse("authorization", "Bearer {header.x-api-token").is_err());
+  }
+
+  #[test]
+  fn encodes_path_segments() {
+    assert_eq!(
+      encode_path_segment("abc-1.2_~").as_deref(),
+      Some("abc-1.2_~")
+    );
+    assert_eq!(encode_path_segment("a/b c").as_deref(), Some("a%2Fb%20c"));
+    assert_eq!(encode_path_segment("..."), Some("...".to_string()));
+    assert_eq!(encode_path_segment(".."), None);
+    assert_eq!(encode_path_segment("."), None);
+    assert_eq!(encode_path_segment(""), None);
+  }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use conductor_config::RetryConfig;
+use reqwest::StatusCode;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RetryPolicyError {
+  #[error("invalid retryable status code {0}")]
+  InvalidStatusCode(u16),
+}
+
+/// Decides whether a failed upstream request is sent again, and how long to wait before that.
+#[derive(Debug)]
+pub struct RetryPolicy {
+  max_retries: u32,
+  base_backoff: Duration,
+  jitter: bool,
+  retryable_status_codes: Vec<StatusCode>,
+}
+
+impl RetryPolicy {
+  pub fn new(config: &RetryConfig) -> Result<Self, RetryPolicyError> {
+    let retryable_status_codes = config
+      .retryable_status_codes
+      .iter()
+      .map(|status| {
+        StatusCode::from_u16(*status).map_err(|_| RetryPolicyError::InvalidStatusCode(*status))
+      })
+      .collect::<Result<_, _>>()?;
+
+    Ok(Self {
+      max_retries: config.max_retries,
+      base_backoff: Duration::from_millis(config.base_backoff_ms),
+      jitter: config.jitter,
+      retryable_status_codes,
+    })
+  }
+
+  pub fn is_retryable_status(&self, status: StatusCode) -> bool {
+    self.retryable_status_codes.contains(&status)
+  }
+
+  /// Returns the backoff before the given retry (the first retry is `0`), or `None` when there are no retries left.
+  pub fn backoff(&self, retry: u32) -> Option<Duration> {
+    if retry >= self.max_retries {
+      return None;
+    }
+
+    let backoff = self.base_backoff.saturating_mul(2u32.saturating_pow(retry));
+
+    match self.jitter {
+      true => Some(backoff.mul_f64(rand::random::<f64>())),
+      false => Some(backoff),
+    }
+  }
+}
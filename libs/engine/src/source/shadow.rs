@@ -0,0 +1,306 @@
+use std::{
+  sync::atomic::{AtomicU64, Ordering},
+  time::Duration,
+};
+
+use conductor_common::{
+  http::{Bytes, ConductorHttpRequest},
+  metrics::increment_counter,
+};
+use conductor_config::ShadowTrafficConfig;
+use futures::channel::oneshot;
+use minitrace_reqwest::TracedHttpClient;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+/// The number of differing paths printed when a shadow response differs from the primary response.
+const MAX_REPORTED_DIFFS: usize = 10;
+
+static COMPARISONS_COUNTER: &str = "conductor_shadow_comparisons_total";
+static COMPARISONS_COUNTER_HELP: &str =
+  "The number of shadow responses compared with the primary responses.";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ShadowTrafficError {
+  #[error("invalid shadow percentage {0}, expected a value between 0 and 100")]
+  InvalidPercentage(f64),
+}
+
+/// Mirrors upstream requests to a shadow upstream, without waiting for its response.
+#[derive(Debug)]
+pub struct ShadowTraffic {
+  endpoint: String,
+  rate: f64,
+  /// Set when the shadow responses are compared with the primary responses.
+  ignore_fields: Option<Vec<String>>,
+  fetcher: TracedHttpClient,
+  /// The timeout of the source, a shadow upstream that doesn't respond would otherwise keep its requests open forever.
+  #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+  timeout: Duration,
+  request_count: AtomicU64,
+}
+
+/// Hands the primary response over to a mirrored request, to be compared with the shadow response.
+///
+/// Dropping it without calling [`ShadowComparison::compare`] skips the comparison.
+pub struct ShadowComparison {
+  primary_body: oneshot::Sender<Bytes>,
+}
+
+impl ShadowComparison {
+  pub fn compare(self, primary_body: &Bytes) {
+    let _ = self.primary_body.send(primary_body.clone());
+  }
+}
+
+impl ShadowTraffic {
+  pub fn new(
+    config: &ShadowTrafficConfig,
+    fetcher: TracedHttpClient,
+    timeout: Duration,
+  ) -> Result<Self, ShadowTrafficError> {
+    if !(0.0..=100.0).contains(&config.percentage) {
+      return Err(ShadowTrafficError::InvalidPercentage(config.percentage));
+    }
+
+    Ok(Self {
+      endpoint: config.endpoint.clone(),
+      rate: config.percentage / 100.0,
+      ignore_fields: config
+        .compare
+        .as_ref()
+        .map(|compare| compare.ignore_fields.clone()),
+      fetcher,
+      timeout,
+      request_count: AtomicU64::new(0),
+    })
+  }
+
+  /// Deterministic sampling: a request is mirrored every time the accumulated rate crosses a whole number.
+  fn should_mirror(&self) -> bool {
+    let count = self.request_count.fetch_add(1, Ordering::Relaxed);
+
+    ((count + 1) as f64 * self.rate).floor() > (count as f64 * self.rate).floor()
+  }
+
+  /// Sends a copy of the upstream request to the shadow upstream in the background. Failures are only logged.
+  ///
+  /// When comparison is enabled and the request is mirrored, the returned handle should be given the primary response.
+  pub fn mirror(&self, upstream_request: &ConductorHttpRequest) -> Option<ShadowComparison> {
+    if !self.should_mirror() {
+      return None;
+    }
+
+    let endpoint = self.endpoint.clone();
+    let shadow_request = self
+      .fetcher
+      .request(upstream_request.method.clone(), &endpoint)
+      .headers(upstream_request.headers.clone())
+      .body(upstream_request.body.clone());
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let shadow_request = shadow_request.timeout(self.timeout);
+
+    let (comparison, comparison_receiver) = match &self.ignore_fields {
+      Some(ignore_fields) => {
+        let (sender, receiver) = oneshot::channel();
+
+        (
+          Some(ShadowComparison {
+            primary_body: sender,
+          }),
+          Some((receiver, ignore_fields.clone())),
+        )
+      }
+      None => (None, None),
+    };
+
+    let mirrored = async move {
+      let shadow_response = match shadow_request.send().await {
+        Ok(response) if response.status().is_success() => {
+          debug!("shadow upstream \"{}\" responded", endpoint);
+          response
+        }
+        Ok(response) => {
+          warn!(
+            "shadow upstream \"{}\" responded with unexpected HTTP status: {}",
+            endpoint,
+            response.status()
+          );
+          return;
+        }
+        Err(e) => {
+          warn!(
+            "failed to mirror request to shadow upstream \"{}\": {}",
+            endpoint, e
+          );
+          return;
+        }
+      };
+
+      let (primary_body, ignore_fields) = match comparison_receiver {
+        Some(comparison_receiver) => comparison_receiver,
+        None => return,
+      };
+
+      let shadow_body = match shadow_response.bytes().await {
+        Ok(body) => body,
+        Err(e) => {
+          warn!(
+            "failed to read the response of shadow upstream \"{}\": {}",
+            endpoint, e
+          );
+          return;
+        }
+      };
+
+      // The primary request failed, there is nothing to compare with.
+      let primary_body = match primary_body.await {
+        Ok(body) => body,
+        Err(_) => return,
+      };
+
+      let diffs = diff_responses(&primary_body, &shadow_body, &ignore_fields);
+      let result = match diffs.is_empty() {
+        true => "match",
+        false => "mismatch",
+      };
+
+      increment_counter(
+        COMPARISONS_COUNTER,
+        COMPARISONS_COUNTER_HELP,
+        &[("shadow", &endpoint), ("result", result)],
+      );
+
+      if diffs.is_empty() {
+        debug!(
+          "response of shadow upstream \"{}\" matches the primary response",
+          endpoint
+        );
+      } else {
+        let remaining = diffs.len().saturating_sub(MAX_REPORTED_DIFFS);
+        let mut summary = diffs
+          .into_iter()
+          .take(MAX_REPORTED_DIFFS)
+          .collect::<Vec<_>>()
+          .join(", ");
+
+        if remaining > 0 {
+          summary = format!("{} and {} more", summary, remaining);
+        }
+
+        warn!(
+          "response of shadow upstream \"{}\" differs from the primary response at: {}",
+          endpoint, summary
+        );
+      }
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    wasm_polyfills::spawn_local(mirrored);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::spawn(mirrored);
+
+    comparison
+  }
+}
+
+/// Returns the paths (for example: `data.users[0].name`) where the responses differ. Bodies that are not valid JSON are compared as a whole.
+pub fn diff_responses(primary: &[u8], shadow: &[u8], ignore_fields: &[String]) -> Vec<String> {
+  let mut diffs = Vec::new();
+
+  match (
+    serde_json::from_slice::<Value>(primary),
+    serde_json::from_slice::<Value>(shadow),
+  ) {
+    (Ok(primary), Ok(shadow)) => diff_values("", &primary, &shadow, ignore_fields, &mut diffs),
+    _ if primary != shadow => diffs.push("<body>".to_string()),
+    _ => {}
+  }
+
+  diffs
+}
+
+fn diff_values(
+  path: &str,
+  primary: &Value,
+  shadow: &Value,
+  ignore_fields: &[String],
+  diffs: &mut Vec<String>,
+) {
+  match (primary, shadow) {
+    (Value::Object(primary), Value::Object(shadow)) => {
+      let shadow_only_keys = shadow.keys().filter(|key| !primary.contains_key(*key));
+
+      for key in primary.keys().chain(shadow_only_keys) {
+        if ignore_fields.contains(key) {
+          continue;
+        }
+
+        let field_path = match path.is_empty() {
+          true => key.clone(),
+          false => format!("{}.{}", path, key),
+        };
+
+        match (primary.get(key), shadow.get(key)) {
+          (Some(primary), Some(shadow)) => {
+            diff_values(&field_path, primary, shadow, ignore_fields, diffs)
+          }
+          _ => diffs.push(field_path),
+        }
+      }
+    }
+    (Value::Array(primary), Value::Array(shadow)) if primary.len() == shadow.len() => {
+      for (index, (primary, shadow)) in primary.iter().zip(shadow).enumerate() {
+        diff_values(
+          &format!("{}[{}]", path, index),
+          primary,
+          shadow,
+          ignore_fields,
+          diffs,
+        );
+      }
+    }
+    (primary, shadow) if primary != shadow => diffs.push(path.to_string()),
+    _ => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn diff(primary: Value, shadow: Value) -> Vec<String> {
+    diff_responses(
+      primary.to_string().as_bytes(),
+      shadow.to_string().as_bytes(),
+      &["requestId".to_string()],
+    )
+  }
+
+  #[test]
+  fn ignored_field_difference() {
+    assert!(diff(
+      json!({ "data": { "users": [{ "name": "a", "requestId": "1" }] } }),
+      json!({ "data": { "users": [{ "name": "a", "requestId": "2" }] } }),
+    )
+    .is_empty());
+  }
+
+  #[test]
+  fn significant_field_difference() {
+    let mut diffs = diff(
+      json!({ "data": { "users": [{ "name": "a", "requestId": "1" }], "total": 1 } }),
+      json!({ "data": { "users": [{ "name": "b", "requestId": "2" }], "count": 1 } }),
+    );
+    diffs.sort();
+
+    assert_eq!(
+      diffs,
+      vec!["data.count", "data.total", "data.users[0].name"]
+    );
+  }
+}
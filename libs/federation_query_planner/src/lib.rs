@@ -1,7 +1,7 @@
-use std::{ops::Index, sync::Arc};
+use std::{collections::HashMap, ops::Index, sync::Arc};
 
 use anyhow::{anyhow, Error, Ok as anyhowOk};
-use conductor_common::http::ConductorHttpRequest;
+use conductor_common::http::{ConductorHttpRequest, ConductorHttpResponse};
 use conductor_common::{execute::RequestExecutionContext, plugin_manager::PluginManager};
 use constants::CONDUCTOR_INTERNAL_SERVICE_RESOLVER;
 use executor::{
@@ -18,6 +18,7 @@ use reqwest::Method;
 use serde_json::json;
 use serde_json::Value as SerdeValue;
 use supergraph::Supergraph;
+use tracing::error;
 
 use crate::{query_planner::plan_for_user_query, user_query::parse_user_query};
 
@@ -33,6 +34,8 @@ pub struct FederationExecutor<'a> {
   pub client: &'a minitrace_reqwest::TracedHttpClient,
   pub plugin_manager: Arc<Box<dyn PluginManager>>,
   pub supergraph: &'a Supergraph,
+  /// URLs of the subgraphs that take precedence over the ones declared in the supergraph, keyed by the uppercase subgraph name.
+  pub subgraph_urls: &'a HashMap<String, String>,
 }
 
 impl<'a> FederationExecutor<'a> {
@@ -196,10 +199,10 @@ impl<'a> FederationExecutor<'a> {
           ]
         });
       let url = self
-        .supergraph
-        .subgraphs
+        .subgraph_urls
         .get(&query_step.service_name)
-        .unwrap();
+        .or_else(|| self.supergraph.subgraphs.get(&query_step.service_name))
+        .ok_or_else(|| anyhow!("unknown subgraph \"{}\"", query_step.service_name))?;
 
       let variables_object = if let Some(arguments) = &entity_arguments {
         serde_json::json!({ "representations": arguments })
@@ -218,6 +221,7 @@ impl<'a> FederationExecutor<'a> {
         uri: url.to_string(),
         query_string: "".to_string(),
         headers: Default::default(),
+        peer_addr: None,
       };
 
       upstream_request
@@ -239,33 +243,49 @@ impl<'a> FederationExecutor<'a> {
         .headers(upstream_request.headers)
         .body(upstream_request.body);
 
-      let response = upstream_req.send().await;
-
-      self
-        .plugin_manager
-        .on_upstream_http_response(*request_context, &response)
-        .await;
-
-      let response = match response {
+      let response = match upstream_req.send().await {
         Ok(resp) => resp,
         Err(err) => {
-          eprintln!("Failed to send request: {}", err);
+          error!("Failed to send request: {}", err);
           return Err(anyhow::anyhow!("Failed to send request: {}", err));
         }
       };
+      let status = response.status();
+      let headers = response.headers().clone();
+      let body = match response.bytes().await {
+        Ok(body) => body,
+        Err(err) => {
+          error!("Failed to read response: {}", err);
+          return Err(anyhow::anyhow!("Failed to read response: {}", err));
+        }
+      };
+      let mut response = ConductorHttpResponse {
+        body,
+        status,
+        headers,
+      };
+
+      self
+        .plugin_manager
+        .on_upstream_http_response(*request_context, &mut response)
+        .await;
+
+      if request_context.is_short_circuit() {
+        return Err(anyhow::anyhow!("short circuit"));
+      }
 
-      if !response.status().is_success() {
-        eprintln!("Received error response: {:?}", response.status());
+      if !response.status.is_success() {
+        error!("Received error response: {:?}", response.status);
         return Err(anyhow::anyhow!(
           "Failed request with status: {}",
-          response.status()
+          response.status
         ));
       }
 
-      let response_data = match response.json::<QueryResponse>().await {
+      let response_data = match serde_json::from_slice::<QueryResponse>(&response.body) {
         Ok(data) => data,
         Err(err) => {
-          eprintln!("Failed to parse response: {}", err);
+          error!("Failed to parse response: {}", err);
           return Err(anyhow::anyhow!("Failed to parse response: {}", err));
         }
       };
@@ -273,7 +293,7 @@ impl<'a> FederationExecutor<'a> {
       // Check if there were any GraphQL errors
       if let Some(errors) = &response_data.errors {
         for error in errors {
-          eprintln!("Error: {:?}", error);
+          error!("Error: {:?}", error);
         }
       }
 
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -70,3 +72,77 @@ impl Default for LoggerConfigFormat {
     }
   }
 }
+
+/// An output for the logs, with its own format and filter.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub struct LoggerSinkConfig {
+  /// The destination of the logs written by this sink.
+  pub output: LoggerSinkOutput,
+  /// The format of the logs written by this sink.
+  #[serde(default)]
+  pub format: LoggerConfigFormat,
+  /// Environment filter configuration for this sink, using the same syntax as the top-level `filter`.
+  ///
+  /// If not specified, the top-level `filter` is used.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub filter: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+#[serde(tag = "type")]
+pub enum LoggerSinkOutput {
+  /// Writes the logs to the standard output.
+  ///
+  /// On WASM runtime, the logs are written to the JavaScript `console`.
+  #[serde(rename = "stdout")]
+  #[schemars(title = "stdout")]
+  Stdout,
+  /// Appends the logs to a local file. The file is created if it doesn't exist.
+  ///
+  /// Note: this output is not supported on WASM runtime.
+  #[serde(rename = "file")]
+  #[schemars(title = "file")]
+  File {
+    /// The path of the log file. Relative paths are resolved from the working directory of the process.
+    path: String,
+    /// Configures when the log file is rotated. By default, the file is never rotated.
+    #[serde(default)]
+    rotation: LoggerFileRotation,
+  },
+}
+
+/// When the file is rotated, the current file is renamed with a numeric suffix (`conductor.log` becomes `conductor.log.1`), and a new file is created.
+///
+/// If the rotation fails, the error is logged, the logs keep being written to the current file, and the rotation is retried after a minute.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, PartialEq)]
+pub struct LoggerFileRotation {
+  /// Rotates the file when writing to it would exceed this size, in bytes.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max_size_bytes: Option<u64>,
+  /// Rotates the file once it was written to for longer than this duration (for example: `1h` or `1d`).
+  /// The duration is measured from the time the file was opened.
+  #[serde(
+    deserialize_with = "humantime_serde::deserialize",
+    serialize_with = "humantime_serde::serialize",
+    default
+  )]
+  #[schemars(with = "Option<String>")]
+  pub interval: Option<Duration>,
+  /// The number of rotated files to keep, older files are deleted.
+  #[serde(default = "default_max_files")]
+  pub max_files: usize,
+}
+
+impl Default for LoggerFileRotation {
+  fn default() -> Self {
+    Self {
+      max_size_bytes: None,
+      interval: None,
+      max_files: default_max_files(),
+    }
+  }
+}
+
+fn default_max_files() -> usize {
+  5
+}
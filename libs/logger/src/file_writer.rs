@@ -0,0 +1,244 @@
+use std::{
+  fs::{self, File, OpenOptions},
+  io::{self, Write},
+  path::{Path, PathBuf},
+  time::{Duration, Instant, SystemTime},
+};
+
+use crate::config::LoggerFileRotation;
+
+/// How long to keep writing to the current file after a failed rotation, before trying to rotate it again.
+const ROTATION_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A log file that is rotated based on its size and age, see `LoggerFileRotation`.
+#[derive(Debug)]
+pub struct RotatingFile {
+  path: PathBuf,
+  rotation: LoggerFileRotation,
+  file: File,
+  size: u64,
+  opened_at: SystemTime,
+  rotation_failed_at: Option<Instant>,
+}
+
+fn open_append(path: &Path) -> io::Result<(File, u64)> {
+  let file = OpenOptions::new().create(true).append(true).open(path)?;
+  let size = file.metadata()?.len();
+
+  Ok((file, size))
+}
+
+impl RotatingFile {
+  pub fn open(path: impl Into<PathBuf>, rotation: LoggerFileRotation) -> io::Result<Self> {
+    let path = path.into();
+    let (file, size) = open_append(&path)?;
+
+    Ok(Self {
+      path,
+      rotation,
+      file,
+      size,
+      opened_at: SystemTime::now(),
+      rotation_failed_at: None,
+    })
+  }
+
+  fn rotated_path(&self, index: usize) -> PathBuf {
+    let mut path = self.path.clone().into_os_string();
+    path.push(format!(".{}", index));
+
+    path.into()
+  }
+
+  fn should_rotate(&self, incoming: usize) -> bool {
+    // An empty file is never rotated, even if a single write exceeds `max_size_bytes`.
+    if self.size == 0 {
+      return false;
+    }
+
+    if self
+      .rotation_failed_at
+      .is_some_and(|failed_at| failed_at.elapsed() < ROTATION_RETRY_INTERVAL)
+    {
+      return false;
+    }
+
+    let exceeds_size = self
+      .rotation
+      .max_size_bytes
+      .is_some_and(|max_size| self.size + incoming as u64 > max_size);
+    let exceeds_interval = self.rotation.interval.is_some_and(|interval| {
+      self
+        .opened_at
+        .elapsed()
+        .is_ok_and(|elapsed| elapsed >= interval)
+    });
+
+    exceeds_size || exceeds_interval
+  }
+
+  /// Shifts the rotated files by one (dropping the oldest), and starts a new file.
+  fn rotate(&mut self) -> io::Result<()> {
+    self.file.flush()?;
+
+    if self.rotation.max_files == 0 {
+      fs::remove_file(&self.path)?;
+    } else {
+      for index in (1..self.rotation.max_files).rev() {
+        match fs::rename(self.rotated_path(index), self.rotated_path(index + 1)) {
+          Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+          _ => {}
+        }
+      }
+
+      fs::rename(&self.path, self.rotated_path(1))?;
+    }
+
+    let (file, size) = open_append(&self.path)?;
+    self.file = file;
+    self.size = size;
+    self.opened_at = SystemTime::now();
+
+    Ok(())
+  }
+}
+
+impl Write for RotatingFile {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if self.should_rotate(buf.len()) {
+      // Failing to rotate should not lose the logs, so we keep writing to the current file, and only try again after `ROTATION_RETRY_INTERVAL`.
+      match self.rotate() {
+        Ok(()) => self.rotation_failed_at = None,
+        Err(e) => {
+          self.rotation_failed_at = Some(Instant::now());
+
+          // Events emitted while a subscriber is writing are discarded by `tracing`, so the failure is reported from another thread, by the global subscriber.
+          let path = self.path.clone();
+          std::thread::spawn(move || {
+            tracing::error!("failed to rotate log file {:?}: {}", path, e);
+          });
+        }
+      }
+    }
+
+    self.file.write_all(buf)?;
+    self.size += buf.len() as u64;
+
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.file.flush()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn log_path(name: &str) -> PathBuf {
+    let dir =
+      std::env::temp_dir().join(format!("conductor_logger_{}_{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir.join("conductor.log")
+  }
+
+  #[test]
+  fn rotates_by_size() {
+    let path = log_path("size");
+    let mut file = RotatingFile::open(
+      &path,
+      LoggerFileRotation {
+        max_size_bytes: Some(10),
+        interval: None,
+        max_files: 2,
+      },
+    )
+    .unwrap();
+
+    for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+      file.write_all(line.as_bytes()).unwrap();
+    }
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+    assert_eq!(
+      fs::read_to_string(path.with_extension("log.1")).unwrap(),
+      "third\n"
+    );
+    assert_eq!(
+      fs::read_to_string(path.with_extension("log.2")).unwrap(),
+      "second\n"
+    );
+    // Only `max_files` rotated files are kept.
+    assert!(!path.with_extension("log.3").exists());
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn rotates_by_interval() {
+    let path = log_path("interval");
+    let mut file = RotatingFile::open(
+      &path,
+      LoggerFileRotation {
+        max_size_bytes: None,
+        interval: Some(std::time::Duration::ZERO),
+        max_files: 1,
+      },
+    )
+    .unwrap();
+
+    file.write_all(b"first\n").unwrap();
+    file.write_all(b"second\n").unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+    assert_eq!(
+      fs::read_to_string(path.with_extension("log.1")).unwrap(),
+      "first\n"
+    );
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn backs_off_after_failed_rotation() {
+    let path = log_path("failed_rotation");
+    let mut file = RotatingFile::open(
+      &path,
+      LoggerFileRotation {
+        max_size_bytes: Some(10),
+        interval: None,
+        max_files: 1,
+      },
+    )
+    .unwrap();
+
+    // The current file can't be renamed over a non-empty directory.
+    let rotated_path = path.with_extension("log.1");
+    fs::create_dir_all(rotated_path.join("blocked")).unwrap();
+
+    file.write_all(b"first\n").unwrap();
+    file.write_all(b"second\n").unwrap();
+    assert!(file.rotation_failed_at.is_some());
+
+    // The rotation is not retried on every write.
+    fs::remove_dir_all(&rotated_path).unwrap();
+    file.write_all(b"third\n").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\nthird\n");
+    assert!(!rotated_path.exists());
+
+    // Once the interval elapsed, the rotation is tried again.
+    file.rotation_failed_at = Some(Instant::now() - ROTATION_RETRY_INTERVAL);
+    file.write_all(b"fourth\n").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+    assert_eq!(
+      fs::read_to_string(&rotated_path).unwrap(),
+      "first\nsecond\nthird\n"
+    );
+    assert!(file.rotation_failed_at.is_none());
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+}
@@ -1,2 +1,4 @@
 pub mod config;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file_writer;
 pub mod logger_layer;
@@ -1,4 +1,10 @@
-use crate::config::LoggerConfigFormat;
+use crate::config::{LoggerConfigFormat, LoggerSinkConfig, LoggerSinkOutput};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::file_writer::RotatingFile;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::Registry;
 use tracing_subscriber::{
@@ -6,6 +12,16 @@ use tracing_subscriber::{
   Layer,
 };
 
+#[derive(thiserror::Error, Debug)]
+pub enum LoggerError {
+  #[error("invalid logger filter: {0}")]
+  InvalidFilter(#[from] tracing_subscriber::filter::ParseError),
+  #[error("failed to open log file \"{0}\": {1}")]
+  FileOpenError(String, std::io::Error),
+  #[error("log file \"{0}\" can't be used, file outputs are not supported in WASM runtime")]
+  UnsupportedFileSink(String),
+}
+
 #[cfg(target_arch = "wasm32")]
 pub fn build_logger(
   format: &LoggerConfigFormat,
@@ -47,12 +63,61 @@ pub fn build_logger(
   })
 }
 
+#[cfg(target_arch = "wasm32")]
+pub fn build_loggers(
+  format: &LoggerConfigFormat,
+  filter: &str,
+  print_performance_info: bool,
+  sinks: &[LoggerSinkConfig],
+) -> Result<Box<dyn Layer<Registry> + Send + Sync>, LoggerError> {
+  if sinks.is_empty() {
+    return Ok(build_logger(format, filter, print_performance_info)?);
+  }
+
+  let mut layers = Vec::with_capacity(sinks.len());
+
+  for sink in sinks {
+    match &sink.output {
+      LoggerSinkOutput::Stdout => layers.push(build_logger(
+        &sink.format,
+        sink.filter.as_deref().unwrap_or(filter),
+        print_performance_info,
+      )?),
+      LoggerSinkOutput::File { path, .. } => {
+        return Err(LoggerError::UnsupportedFileSink(path.clone()))
+      }
+    }
+  }
+
+  Ok(layers.boxed())
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn build_logger(
   format: &LoggerConfigFormat,
   filter: &str,
   print_performance_info: bool,
 ) -> Result<Box<dyn Layer<Registry> + Send + Sync>, tracing_subscriber::filter::ParseError> {
+  build_fmt_layer(
+    format,
+    filter,
+    print_performance_info,
+    true,
+    std::io::stdout,
+  )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn build_fmt_layer<W>(
+  format: &LoggerConfigFormat,
+  filter: &str,
+  print_performance_info: bool,
+  ansi: bool,
+  writer: W,
+) -> Result<Box<dyn Layer<Registry> + Send + Sync>, tracing_subscriber::filter::ParseError>
+where
+  W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
   let timer = UtcTime::rfc_3339();
   let filter = EnvFilter::try_new(filter)?;
   let performance_spans = match print_performance_info {
@@ -63,21 +128,177 @@ pub fn build_logger(
   Ok(match format {
     LoggerConfigFormat::Json => fmt::Layer::<Registry>::default()
       .json()
+      .with_ansi(ansi)
       .with_timer(timer)
       .with_span_events(performance_spans)
+      .with_writer(writer)
       .with_filter(filter)
       .boxed(),
     LoggerConfigFormat::Pretty => fmt::Layer::<Registry>::default()
       .pretty()
+      .with_ansi(ansi)
       .with_timer(timer)
       .with_span_events(performance_spans)
+      .with_writer(writer)
       .with_filter(filter)
       .boxed(),
     LoggerConfigFormat::Compact => fmt::Layer::<Registry>::default()
       .compact()
+      .with_ansi(ansi)
       .with_timer(timer)
       .with_span_events(performance_spans)
+      .with_writer(writer)
       .with_filter(filter)
       .boxed(),
   })
 }
+
+/// Builds a layer for each of the sinks, all of them are attached to the same subscriber.
+///
+/// Without sinks, a single standard output layer is built, based on the top-level `format`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn build_loggers(
+  format: &LoggerConfigFormat,
+  filter: &str,
+  print_performance_info: bool,
+  sinks: &[LoggerSinkConfig],
+) -> Result<Box<dyn Layer<Registry> + Send + Sync>, LoggerError> {
+  if sinks.is_empty() {
+    return Ok(build_logger(format, filter, print_performance_info)?);
+  }
+
+  let mut layers = Vec::with_capacity(sinks.len());
+
+  for sink in sinks {
+    let sink_filter = sink.filter.as_deref().unwrap_or(filter);
+
+    layers.push(match &sink.output {
+      LoggerSinkOutput::Stdout => build_logger(&sink.format, sink_filter, print_performance_info)?,
+      LoggerSinkOutput::File { path, rotation } => {
+        let file = RotatingFile::open(path, rotation.clone())
+          .map_err(|e| LoggerError::FileOpenError(path.clone(), e))?;
+
+        // Escape codes are only meaningful for terminals.
+        build_fmt_layer(
+          &sink.format,
+          sink_filter,
+          print_performance_info,
+          false,
+          Mutex::new(file),
+        )?
+      }
+    });
+  }
+
+  Ok(layers.boxed())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+  };
+
+  use tracing::{dispatcher::with_default, Dispatch, Level};
+  use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt};
+
+  use super::*;
+  use crate::config::LoggerFileRotation;
+
+  /// Stands in for the standard output, so the written logs can be inspected.
+  #[derive(Clone, Default)]
+  struct BufferWriter(Arc<Mutex<Vec<u8>>>);
+
+  impl Write for BufferWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+      self.0.lock().unwrap().extend_from_slice(buf);
+      Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+      Ok(())
+    }
+  }
+
+  impl<'writer> MakeWriter<'writer> for BufferWriter {
+    type Writer = Self;
+
+    fn make_writer(&'writer self) -> Self::Writer {
+      self.clone()
+    }
+  }
+
+  #[test]
+  fn per_target_level() {
+    let logger = build_logger(
+      &LoggerConfigFormat::Compact,
+      "info,jwt_auth_plugin=debug",
+      false,
+    )
+    .unwrap();
+    let dispatch = Dispatch::new(tracing_subscriber::registry().with(logger));
+
+    with_default(&dispatch, || {
+      assert!(tracing::enabled!(target: "jwt_auth_plugin::jwt", Level::DEBUG));
+      assert!(!tracing::enabled!(target: "jwt_auth_plugin", Level::TRACE));
+      assert!(tracing::enabled!(target: "conductor_engine::gateway", Level::INFO));
+      assert!(!tracing::enabled!(target: "conductor_engine::gateway", Level::DEBUG));
+    });
+  }
+
+  #[test]
+  fn independent_sinks() {
+    let dir = std::env::temp_dir().join(format!("conductor_logger_{}_sinks", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("conductor.log");
+    let _ = std::fs::remove_file(&path);
+
+    let stdout = BufferWriter::default();
+    let stdout_layer = build_fmt_layer(
+      &LoggerConfigFormat::Compact,
+      "info",
+      false,
+      false,
+      stdout.clone(),
+    )
+    .unwrap();
+    let file_layer = build_loggers(
+      &LoggerConfigFormat::Compact,
+      "info",
+      false,
+      &[LoggerSinkConfig {
+        output: LoggerSinkOutput::File {
+          path: path.to_string_lossy().into_owned(),
+          rotation: LoggerFileRotation::default(),
+        },
+        format: LoggerConfigFormat::Json,
+        filter: Some("warn".to_string()),
+      }],
+    )
+    .unwrap();
+    let dispatch =
+      Dispatch::new(tracing_subscriber::registry().with(vec![stdout_layer, file_layer]));
+
+    with_default(&dispatch, || {
+      tracing::warn!(source = "test", "upstream is slow");
+      tracing::info!("request completed");
+    });
+
+    let stdout = String::from_utf8(stdout.0.lock().unwrap().clone()).unwrap();
+    assert!(stdout.contains("upstream is slow"));
+    assert!(stdout.contains("request completed"));
+    assert!(serde_json::from_str::<serde_json::Value>(stdout.lines().next().unwrap()).is_err());
+
+    // The file sink has its own format and filter.
+    let file = std::fs::read_to_string(&path).unwrap();
+    let lines = file.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1);
+    let event = serde_json::from_str::<serde_json::Value>(lines[0]).unwrap();
+    assert_eq!(event["level"], "WARN");
+    assert_eq!(event["fields"]["message"], "upstream is slow");
+    assert_eq!(event["fields"]["source"], "test");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -36,3 +36,4 @@ pub const GRAPHQL_ERROR_COUNT: &str = "graphql.error.count";
 // Conductor-specific
 pub const CONDUCTOR_ENDPOINT: &str = "conductor.endpoint";
 pub const CONDUCTOR_SOURCE: &str = "conductor.source";
+pub const CONDUCTOR_REMOTE_PARENT: &str = "conductor.remote_parent";
@@ -31,3 +31,8 @@ pub fn create_http_client() -> reqwest::ClientBuilder {
 
 #[cfg(target_arch = "wasm32")]
 pub use wasm_bindgen_futures::spawn_local;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: std::time::Duration) {
+  tokio::time::sleep(duration).await
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use conductor_common::{
+  circuit::CircuitBreakerConfig,
+  serde_utils::{JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `circuit_breaker` plugin stops sending requests to a source that keeps failing, to give it time to recover instead of adding load to it.
+///
+/// Every source has a circuit, which is closed at first. Upstream responses with a `5xx` status code, as well as upstream requests that failed or timed out, are counted as failures.
+/// Once `failure_threshold` failures happened within `window`, the circuit opens and requests to the source are rejected with a `503` status code and a `Retry-After` header for the `cooldown` duration.
+///
+/// After the cooldown, the circuit is half-open: a single request is sent to the source as a probe, while the other requests are still rejected. The circuit closes if the probe succeeds, and opens again for another cooldown otherwise.
+///
+/// Circuits are kept in the memory of the gateway instance, and are not shared between endpoints or between multiple instances.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "circuit_breaker_example")]
+pub struct CircuitBreakerPluginConfig {
+  /// The number of failures within `window` that opens the circuit.
+  #[serde(default = "default_failure_threshold")]
+  pub failure_threshold: u32,
+  /// The duration during which failures are counted.
+  #[serde(
+    deserialize_with = "humantime_serde::deserialize",
+    serialize_with = "humantime_serde::serialize",
+    default = "default_window"
+  )]
+  #[schemars(with = "String")]
+  pub window: Duration,
+  /// The duration during which requests are rejected once the circuit is open, before a probe request is sent to the source.
+  #[serde(
+    deserialize_with = "humantime_serde::deserialize",
+    serialize_with = "humantime_serde::serialize",
+    default = "default_cooldown"
+  )]
+  #[schemars(with = "String")]
+  pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerPluginConfig {
+  fn default() -> Self {
+    Self {
+      failure_threshold: default_failure_threshold(),
+      window: default_window(),
+      cooldown: default_cooldown(),
+    }
+  }
+}
+
+impl From<&CircuitBreakerPluginConfig> for CircuitBreakerConfig {
+  fn from(config: &CircuitBreakerPluginConfig) -> Self {
+    Self {
+      failure_threshold: config.failure_threshold,
+      window: config.window,
+      cooldown: config.cooldown,
+    }
+  }
+}
+
+fn default_failure_threshold() -> u32 {
+  5
+}
+
+fn default_window() -> Duration {
+  Duration::from_secs(10)
+}
+
+fn default_cooldown() -> Duration {
+  Duration::from_secs(30)
+}
+
+fn circuit_breaker_example() -> JsonSchemaExample<CircuitBreakerPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Open after 10 failures in a minute",
+      Some("This example rejects the requests to a source for 1 minute, once it failed 10 times within a minute."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "circuit_breaker".to_string(),
+    }),
+    example: CircuitBreakerPluginConfig {
+      failure_threshold: 10,
+      window: Duration::from_secs(60),
+      cooldown: Duration::from_secs(60),
+    },
+  }
+}
@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::CircuitBreakerPluginConfig as Config;
+pub use plugin::CircuitBreakerPlugin as Plugin;
@@ -0,0 +1,291 @@
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+};
+
+use conductor_common::{
+  circuit::{Circuit, CircuitBreakerConfig, CircuitCheck, CircuitTransition},
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{header::RETRY_AFTER, ConductorHttpRequest, ConductorHttpResponse, StatusCode},
+  plugin::{CreatablePlugin, Plugin, PluginError},
+  source::SourceRuntime,
+};
+use serde_json::Value;
+use tracing::{debug, info, warn};
+use web_time::Instant;
+
+use crate::config::CircuitBreakerPluginConfig;
+
+static SOURCE_CONTEXT_KEY: &str = "circuit_breaker:source";
+/// Set to `true` when the request is sent to the upstream, and to `false` once its response is recorded.
+static PENDING_CONTEXT_KEY: &str = "circuit_breaker:pending";
+/// Set to `true` when the request is the probe of a half-open circuit, and to `false` once its outcome is recorded.
+static PROBE_CONTEXT_KEY: &str = "circuit_breaker:probe";
+
+#[derive(Debug)]
+pub struct CircuitBreakerPlugin {
+  config: CircuitBreakerConfig,
+  /// Keyed by source name.
+  circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for CircuitBreakerPlugin {
+  type Config = CircuitBreakerPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    let config = CircuitBreakerConfig::from(&config);
+
+    if !config.is_valid() {
+      return Err(PluginError::InitError {
+        source: anyhow::anyhow!("failure_threshold, window and cooldown must be greater than 0"),
+      });
+    }
+
+    Ok(Box::new(Self {
+      config,
+      circuits: Mutex::new(HashMap::new()),
+    }))
+  }
+}
+
+impl CircuitBreakerPlugin {
+  fn record(&self, ctx: &mut RequestExecutionContext, success: bool) {
+    let source = match ctx.ctx_get(SOURCE_CONTEXT_KEY).and_then(Value::as_str) {
+      Some(source) => source.to_string(),
+      None => return,
+    };
+    let probe = ctx.ctx_get(PROBE_CONTEXT_KEY) == Some(&Value::Bool(true));
+
+    ctx.ctx_insert(PENDING_CONTEXT_KEY, false);
+    ctx.ctx_insert(PROBE_CONTEXT_KEY, false);
+
+    if let Ok(mut circuits) = self.circuits.lock() {
+      let transition = circuits.entry(source.clone()).or_default().record(
+        Instant::now(),
+        success,
+        probe,
+        &self.config,
+      );
+
+      match transition {
+        Some(CircuitTransition::Opened) => warn!(
+          "circuit of source \"{}\" is open, requests are rejected for {:?}",
+          source, self.config.cooldown
+        ),
+        Some(CircuitTransition::Closed) => info!("circuit of source \"{}\" is closed", source),
+        None => {}
+      }
+    }
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for CircuitBreakerPlugin {
+  async fn on_downstream_graphql_request(
+    &self,
+    source_runtime: Arc<Box<dyn SourceRuntime>>,
+    ctx: &mut RequestExecutionContext,
+  ) {
+    let source = source_runtime.name();
+
+    let check = match self.circuits.lock() {
+      Ok(mut circuits) => circuits
+        .entry(source.to_string())
+        .or_default()
+        .check(Instant::now(), self.config.cooldown),
+      // The circuit breaker should never be the reason a request fails.
+      Err(_) => CircuitCheck::Allowed,
+    };
+
+    ctx.ctx_insert(SOURCE_CONTEXT_KEY, source);
+
+    match check {
+      CircuitCheck::Allowed => {}
+      CircuitCheck::Probe => {
+        debug!("sending a probe request to source \"{}\"", source);
+
+        ctx.ctx_insert(PROBE_CONTEXT_KEY, true);
+      }
+      CircuitCheck::Rejected { retry_after } => {
+        debug!(
+          "circuit of source \"{}\" is open, retry after {:?}",
+          source, retry_after
+        );
+
+        let mut response = GraphQLResponse::new_error("upstream is unavailable")
+          .into_with_status_code(StatusCode::SERVICE_UNAVAILABLE);
+        // `Retry-After` is in whole seconds, rounded up so clients don't retry too early.
+        let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+        if let Ok(value) = retry_after_secs.to_string().parse() {
+          response.headers.insert(RETRY_AFTER, value);
+        }
+
+        ctx.short_circuit(response);
+      }
+    }
+  }
+
+  async fn on_upstream_http_request(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    _req: &mut ConductorHttpRequest,
+  ) {
+    ctx.ctx_insert(PENDING_CONTEXT_KEY, true);
+  }
+
+  async fn on_upstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    self.record(ctx, !response.status.is_server_error());
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    // The upstream request was sent, but no response was received: it failed or timed out.
+    if ctx.ctx_get(PENDING_CONTEXT_KEY) == Some(&Value::Bool(true))
+      && matches!(
+        response.status,
+        StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT
+      )
+    {
+      self.record(ctx, false);
+
+      return;
+    }
+
+    if ctx.ctx_get(PROBE_CONTEXT_KEY) == Some(&Value::Bool(true)) {
+      let source = ctx.ctx_get(SOURCE_CONTEXT_KEY).and_then(Value::as_str);
+
+      if let (Some(source), Ok(mut circuits)) = (source, self.circuits.lock()) {
+        if let Some(circuit) = circuits.get_mut(source) {
+          circuit.release_probe();
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use conductor_common::source::TestSource;
+
+  use super::*;
+
+  fn response(status: StatusCode) -> ConductorHttpResponse {
+    ConductorHttpResponse {
+      body: Default::default(),
+      status,
+      headers: Default::default(),
+    }
+  }
+
+  fn config() -> CircuitBreakerPluginConfig {
+    CircuitBreakerPluginConfig {
+      failure_threshold: 2,
+      window: Duration::from_secs(10),
+      cooldown: Duration::from_secs(30),
+    }
+  }
+
+  /// Runs a request through the plugin. The upstream responds with `upstream_status`, or fails when it's `None`.
+  /// Returns the response of the gateway.
+  async fn request(
+    plugin: &CircuitBreakerPlugin,
+    upstream_status: Option<StatusCode>,
+  ) -> ConductorHttpResponse {
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    plugin
+      .on_downstream_graphql_request(Arc::new(Box::new(TestSource::default())), &mut ctx)
+      .await;
+
+    if let Some(mut response) = ctx.short_circuit_response.take() {
+      plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+      return response;
+    }
+
+    plugin
+      .on_upstream_http_request(&mut ctx, &mut Default::default())
+      .await;
+
+    let mut response = match upstream_status {
+      Some(status) => {
+        let mut upstream_response = response(status);
+        plugin
+          .on_upstream_http_response(&mut ctx, &mut upstream_response)
+          .await;
+
+        upstream_response
+      }
+      None => response(StatusCode::BAD_GATEWAY),
+    };
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    response
+  }
+
+  #[tokio::test]
+  async fn opens_after_failures() {
+    let plugin = CircuitBreakerPlugin::create(config()).await.unwrap();
+
+    assert_eq!(
+      request(&plugin, Some(StatusCode::INTERNAL_SERVER_ERROR))
+        .await
+        .status,
+      StatusCode::INTERNAL_SERVER_ERROR
+    );
+    assert_eq!(
+      request(&plugin, Some(StatusCode::OK)).await.status,
+      StatusCode::OK
+    );
+    assert_eq!(request(&plugin, None).await.status, StatusCode::BAD_GATEWAY);
+
+    let response = request(&plugin, Some(StatusCode::OK)).await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers.get(RETRY_AFTER).unwrap(), "30");
+  }
+
+  #[tokio::test]
+  async fn ignores_client_errors() {
+    let plugin = CircuitBreakerPlugin::create(config()).await.unwrap();
+
+    for _ in 0..5 {
+      request(&plugin, Some(StatusCode::BAD_REQUEST)).await;
+    }
+
+    assert_eq!(
+      request(&plugin, Some(StatusCode::OK)).await.status,
+      StatusCode::OK
+    );
+  }
+
+  #[tokio::test]
+  async fn rejects_invalid_config() {
+    for config in [
+      CircuitBreakerPluginConfig {
+        failure_threshold: 0,
+        ..config()
+      },
+      CircuitBreakerPluginConfig {
+        window: Duration::ZERO,
+        ..config()
+      },
+      CircuitBreakerPluginConfig {
+        cooldown: Duration::ZERO,
+        ..config()
+      },
+    ] {
+      assert!(CircuitBreakerPlugin::create(config).await.is_err());
+    }
+  }
+}
@@ -0,0 +1,43 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `compression` plugin compresses the responses sent to the client, based on the `Accept-Encoding` header of the request.
+///
+/// The `br` (Brotli) and `gzip` encodings are supported. When the client accepts both with the same preference, `br` is used.
+///
+/// Responses that already have a `Content-Encoding`, or with a content type that is already compressed (like images, videos or archives), are not compressed.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "compression_example")]
+pub struct CompressionPluginConfig {
+  /// Responses with a smaller body (in bytes) are sent uncompressed, as compressing them is not worth the overhead.
+  #[serde(default = "default_min_size")]
+  pub min_size: usize,
+}
+
+impl Default for CompressionPluginConfig {
+  fn default() -> Self {
+    Self {
+      min_size: default_min_size(),
+    }
+  }
+}
+
+fn default_min_size() -> usize {
+  1024
+}
+
+fn compression_example() -> JsonSchemaExample<CompressionPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Minimum size",
+      Some("This example only compresses responses larger than 4KB."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "compression".to_string(),
+    }),
+    example: CompressionPluginConfig { min_size: 4096 },
+  }
+}
@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::CompressionPluginConfig as Config;
+pub use plugin::CompressionPlugin as Plugin;
@@ -0,0 +1,326 @@
+use std::io::Write;
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  http::{
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY},
+    ConductorHttpResponse, HeaderValue, HttpHeadersMap, CONTENT_TYPE,
+  },
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use tracing::{debug, error};
+
+use crate::config::CompressionPluginConfig;
+
+/// A trade-off between speed and size, the maximum (11) is too slow to compress responses on the fly.
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW_SIZE: u32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// Content types that are already compressed, compressing them again only wastes CPU.
+static COMPRESSED_CONTENT_TYPES: &[&str] = &[
+  "image/",
+  "video/",
+  "audio/",
+  "font/woff",
+  "application/zip",
+  "application/gzip",
+  "application/x-gzip",
+  "application/x-bzip2",
+  "application/x-7z-compressed",
+  "application/x-rar-compressed",
+  "application/zstd",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+  Brotli,
+  Gzip,
+}
+
+impl Encoding {
+  fn name(&self) -> &'static str {
+    match self {
+      Encoding::Brotli => "br",
+      Encoding::Gzip => "gzip",
+    }
+  }
+
+  fn compress(&self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+    match self {
+      Encoding::Brotli => {
+        let mut encoder = brotli::CompressorWriter::new(
+          Vec::new(),
+          BROTLI_BUFFER_SIZE,
+          BROTLI_QUALITY,
+          BROTLI_WINDOW_SIZE,
+        );
+        encoder.write_all(body)?;
+
+        // Consuming the writer finishes the stream.
+        Ok(encoder.into_inner())
+      }
+      Encoding::Gzip => {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(body)?;
+
+        encoder.finish()
+      }
+    }
+  }
+}
+
+/// Picks the supported encoding with the highest quality value in the `Accept-Encoding` headers, preferring `br` on ties.
+fn negotiate_encoding(headers: &HttpHeadersMap) -> Option<Encoding> {
+  let mut brotli: Option<f32> = None;
+  let mut gzip: Option<f32> = None;
+  let mut wildcard: Option<f32> = None;
+
+  let codings = headers
+    .get_all(ACCEPT_ENCODING)
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','));
+
+  for coding in codings {
+    let mut params = coding.split(';');
+    let name = params
+      .next()
+      .unwrap_or_default()
+      .trim()
+      .to_ascii_lowercase();
+    let quality = params
+      .filter_map(|param| param.trim().strip_prefix("q="))
+      .find_map(|quality| quality.trim().parse::<f32>().ok())
+      .unwrap_or(1.0);
+
+    match name.as_str() {
+      "br" => brotli = Some(quality),
+      "gzip" | "x-gzip" => gzip = Some(quality),
+      "*" => wildcard = Some(quality),
+      _ => {}
+    }
+  }
+
+  // Encodings that are not listed explicitly are covered by the wildcard, a quality of 0 means "not acceptable".
+  let brotli = brotli.or(wildcard).unwrap_or(0.0);
+  let gzip = gzip.or(wildcard).unwrap_or(0.0);
+
+  match (brotli, gzip) {
+    (brotli, gzip) if brotli > 0.0 && brotli >= gzip => Some(Encoding::Brotli),
+    (_, gzip) if gzip > 0.0 => Some(Encoding::Gzip),
+    _ => None,
+  }
+}
+
+fn is_compressible(response: &ConductorHttpResponse) -> bool {
+  if response.headers.contains_key(CONTENT_ENCODING) {
+    return false;
+  }
+
+  let content_type = response
+    .headers
+    .get(CONTENT_TYPE)
+    .and_then(|value| value.to_str().ok())
+    .map(|value| value.trim().to_ascii_lowercase())
+    .unwrap_or_default();
+
+  !COMPRESSED_CONTENT_TYPES
+    .iter()
+    .any(|compressed| content_type.starts_with(compressed))
+}
+
+#[derive(Debug)]
+pub struct CompressionPlugin(CompressionPluginConfig);
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for CompressionPlugin {
+  type Config = CompressionPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    Ok(Box::new(Self(config)))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for CompressionPlugin {
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    if response.body.len() < self.0.min_size || !is_compressible(response) {
+      return;
+    }
+
+    let encoding = match negotiate_encoding(&ctx.downstream_http_request.headers) {
+      Some(encoding) => encoding,
+      None => return,
+    };
+
+    match encoding.compress(&response.body) {
+      Ok(compressed) => {
+        debug!(
+          "compressed response with {} from {} to {} bytes",
+          encoding.name(),
+          response.body.len(),
+          compressed.len()
+        );
+
+        response.body = compressed.into();
+        response.headers.remove(CONTENT_LENGTH);
+        response
+          .headers
+          .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+
+        let varies_by_encoding = response
+          .headers
+          .get_all(VARY)
+          .iter()
+          .filter_map(|value| value.to_str().ok())
+          .flat_map(|value| value.split(','))
+          .any(|name| name.trim().eq_ignore_ascii_case(ACCEPT_ENCODING.as_str()));
+
+        if !varies_by_encoding {
+          response
+            .headers
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+        }
+      }
+      Err(e) => error!(
+        "failed to compress response with {}: {}",
+        encoding.name(),
+        e
+      ),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Read;
+
+  use conductor_common::http::{ConductorHttpRequest, StatusCode};
+
+  use super::*;
+
+  fn headers(values: &[(&'static str, &'static str)]) -> HttpHeadersMap {
+    let mut headers = HttpHeadersMap::new();
+
+    for (name, value) in values {
+      headers.append(*name, HeaderValue::from_static(value));
+    }
+
+    headers
+  }
+
+  /// Sends a response through the plugin, for a request with the given `Accept-Encoding` header.
+  async fn respond(
+    accept_encoding: &'static str,
+    response_headers: &[(&'static str, &'static str)],
+    body: Vec<u8>,
+  ) -> ConductorHttpResponse {
+    let plugin = CompressionPlugin::create(CompressionPluginConfig { min_size: 100 })
+      .await
+      .unwrap();
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      headers: headers(&[("accept-encoding", accept_encoding)]),
+      ..Default::default()
+    });
+    let mut response = ConductorHttpResponse {
+      body: body.into(),
+      status: StatusCode::OK,
+      headers: headers(response_headers),
+    };
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    response
+  }
+
+  fn json_body(size: usize) -> Vec<u8> {
+    format!(r#"{{"data":{{"text":"{}"}}}}"#, "a".repeat(size)).into_bytes()
+  }
+
+  #[test]
+  fn negotiates_encoding() {
+    let negotiate =
+      |value: &'static str| negotiate_encoding(&headers(&[("accept-encoding", value)]));
+
+    assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+    assert_eq!(negotiate("gzip"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("br;q=0.5, gzip;q=0.8"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("br;q=0, *"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("*;q=0.1"), Some(Encoding::Brotli));
+    assert_eq!(negotiate("deflate, identity"), None);
+    assert_eq!(negotiate("gzip;q=0"), None);
+    assert_eq!(negotiate_encoding(&HttpHeadersMap::new()), None);
+  }
+
+  #[tokio::test]
+  async fn compresses_with_gzip() {
+    let body = json_body(1000);
+    let response = respond(
+      "gzip",
+      &[("content-type", "application/json")],
+      body.clone(),
+    )
+    .await;
+
+    assert_eq!(response.headers.get(CONTENT_ENCODING).unwrap(), "gzip");
+    assert_eq!(response.headers.get(VARY).unwrap(), "accept-encoding");
+
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(&response.body[..])
+      .read_to_end(&mut decompressed)
+      .unwrap();
+    assert_eq!(decompressed, body);
+  }
+
+  #[tokio::test]
+  async fn compresses_with_brotli() {
+    let body = json_body(1000);
+    let response = respond(
+      "gzip, br",
+      &[
+        ("content-type", "application/json"),
+        ("vary", "Accept-Encoding"),
+      ],
+      body.clone(),
+    )
+    .await;
+
+    assert_eq!(response.headers.get(CONTENT_ENCODING).unwrap(), "br");
+    // The existing `Vary` header already covers the encoding.
+    assert_eq!(response.headers.get_all(VARY).iter().count(), 1);
+
+    let mut decompressed = Vec::new();
+    brotli::Decompressor::new(&response.body[..], BROTLI_BUFFER_SIZE)
+      .read_to_end(&mut decompressed)
+      .unwrap();
+    assert_eq!(decompressed, body);
+  }
+
+  #[tokio::test]
+  async fn skips_small_responses() {
+    let body = json_body(10);
+    let response = respond("gzip", &[], body.clone()).await;
+
+    assert!(response.headers.get(CONTENT_ENCODING).is_none());
+    assert_eq!(response.body, body);
+  }
+
+  #[tokio::test]
+  async fn skips_compressed_responses() {
+    let body = vec![0; 1000];
+
+    for response_headers in [
+      [("content-type", "image/png")],
+      [("content-type", "application/zip")],
+      [("content-encoding", "deflate")],
+    ] {
+      let response = respond("gzip, br", &response_headers, body.clone()).await;
+
+      assert_eq!(response.body, body);
+    }
+  }
+}
@@ -30,6 +30,7 @@ pub struct CorsPluginConfig {
   /// `Access-Control-Allow-Origin`: Determines which origins are allowed to access the resource. It can be a specific origin or a wildcard for allowing any origin.
   /// You can also specify a special value "*" to allow any origin to access the resource.
   /// You can also specify a special value "reflect" to allow the origin of the incoming request to access the resource.
+  /// You can also specify a comma-separated list of origins (for example: "https://a.example.com, https://b.example.com"): the origin of the incoming request is used if it's in the list, and the header is omitted otherwise.
   #[serde(default = "default_wildcard", skip_serializing_if = "Option::is_none")]
   pub allowed_origin: Option<String>,
 
@@ -33,16 +33,19 @@ impl CorsPlugin {
     response_headers: &mut HttpHeadersMap,
   ) {
     if let Some(origin) = &self.0.allowed_origin {
+      let request_origin = request_headers.get(ORIGIN).and_then(|v| v.to_str().ok());
       let value = match origin.as_str() {
-        "*" => WILDCARD,
-        "reflect" => request_headers
-          .get(ORIGIN)
-          .and_then(|v| v.to_str().ok())
-          .unwrap_or(WILDCARD),
-        _ => origin,
+        "*" => Some(WILDCARD),
+        "reflect" => Some(request_origin.unwrap_or(WILDCARD)),
+        list if list.contains(',') => request_origin.filter(|request_origin| {
+          list
+            .split(',')
+            .any(|allowed| allowed.trim() == *request_origin)
+        }),
+        _ => Some(origin.as_str()),
       };
 
-      if let Ok(parsed_value) = value.parse() {
+      if let Some(Ok(parsed_value)) = value.map(HeaderValue::from_str) {
         response_headers.append(ACCESS_CONTROL_ALLOW_ORIGIN, parsed_value);
       }
       if let Ok(vary_value) = "Origin".parse() {
@@ -134,6 +137,7 @@ impl Plugin for CorsPlugin {
       self.configure_exposed_headers(&mut response_headers);
       self.configure_max_age(&mut response_headers);
       self.configure_allowed_headers(request_headers, &mut response_headers);
+      self.configred_allow_private_netowkr(&mut response_headers);
 
       if let Ok(content_length_value) = "0".parse() {
         response_headers.insert(CONTENT_LENGTH, content_length_value);
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `cost_limit` plugin computes a weighted cost for every GraphQL operation, and rejects operations that are too expensive before they are sent to the upstream.
+///
+/// Every field costs `default_field_cost`, unless a different cost is set in `field_costs`. Fields with one of the `list_size_arguments` (for example, `users(first: 50)`) return a list, so the cost of their selections is multiplied by the value of the argument. Fields selected through fragments and inline fragments are counted as if they were selected directly.
+///
+/// Operations with a cost higher than `max_cost` are rejected with a `400` status code.
+///
+/// Operations resolved by the `trusted_documents` plugin are checked after they are resolved.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "cost_limit_example")]
+pub struct CostLimitPluginConfig {
+  /// The maximum cost of an operation.
+  pub max_cost: u64,
+  /// The cost of a field without an entry in `field_costs`.
+  #[serde(default = "default_field_cost")]
+  pub default_field_cost: u64,
+  /// The cost of specific fields, by field name, for example: `{ "search": 10 }`. The cost applies to every field with this name, regardless of its parent type.
+  #[serde(default)]
+  pub field_costs: HashMap<String, u64>,
+  /// The names of the arguments used to set the size of a list. When a field has one of these arguments, the cost of its selections is multiplied by the value of the argument.
+  #[serde(default = "default_list_size_arguments")]
+  pub list_size_arguments: Vec<String>,
+  /// Adds the computed cost of the operation to the `extensions` of the response, as `{ "cost": { "requested": 12, "maximum": 100 } }`, so clients can tune their operations.
+  #[serde(default)]
+  pub expose_cost: bool,
+}
+
+fn default_field_cost() -> u64 {
+  1
+}
+
+fn default_list_size_arguments() -> Vec<String> {
+  vec!["first".to_string(), "last".to_string(), "limit".to_string()]
+}
+
+fn cost_limit_example() -> JsonSchemaExample<CostLimitPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Limit cost",
+      Some("This example rejects operations with a cost higher than 1000, where the `search` field costs 10 and every other field costs 1. The computed cost is added to every response."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "cost_limit".to_string(),
+    }),
+    example: CostLimitPluginConfig {
+      max_cost: 1000,
+      default_field_cost: default_field_cost(),
+      field_costs: HashMap::from([("search".to_string(), 10)]),
+      list_size_arguments: default_list_size_arguments(),
+      expose_cost: true,
+    },
+  }
+}
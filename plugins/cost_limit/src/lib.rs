@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::CostLimitPluginConfig as Config;
+pub use plugin::CostLimitPlugin as Plugin;
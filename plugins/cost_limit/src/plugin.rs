@@ -0,0 +1,338 @@
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::{GraphQLResponse, ParsedGraphQLRequest},
+  http::{ConductorHttpResponse, StatusCode},
+  plugin::{CreatablePlugin, Plugin, PluginError},
+  source::SourceRuntime,
+  Field, FragmentDefinition, GraphQLValue, Selection, SelectionSet,
+};
+use serde_json::{json, Map, Value};
+use tracing::debug;
+
+use crate::config::CostLimitPluginConfig;
+
+static COST_CONTEXT_KEY: &str = "cost_limit:cost";
+
+struct CostWalker<'a> {
+  config: &'a CostLimitPluginConfig,
+  variables: Option<&'a Map<String, Value>>,
+  variable_defaults: HashMap<&'a str, &'a GraphQLValue<'static, String>>,
+  fragments: HashMap<&'a str, &'a FragmentDefinition<'static, String>>,
+  /// Fragments can be spread many times, but each one is only walked once.
+  fragment_costs: HashMap<&'a str, u64>,
+  /// The fragments being walked, used to stop at fragment cycles.
+  visiting: HashSet<&'a str>,
+}
+
+impl<'a> CostWalker<'a> {
+  fn new(config: &'a CostLimitPluginConfig, gql_request: &'a ParsedGraphQLRequest) -> Self {
+    Self {
+      config,
+      variables: gql_request.request.variables.as_ref(),
+      variable_defaults: gql_request
+        .executable_variable_definitions()
+        .iter()
+        .filter_map(|definition| {
+          definition
+            .default_value
+            .as_ref()
+            .map(|value| (definition.name.as_str(), value))
+        })
+        .collect(),
+      fragments: gql_request.fragment_definitions(),
+      fragment_costs: HashMap::new(),
+      visiting: HashSet::new(),
+    }
+  }
+
+  fn selection_set_cost(&mut self, selection_set: &'a SelectionSet<'static, String>) -> u64 {
+    selection_set
+      .items
+      .iter()
+      .map(|selection| match selection {
+        Selection::Field(field) => self.field_cost(field),
+        Selection::InlineFragment(fragment) => self.selection_set_cost(&fragment.selection_set),
+        Selection::FragmentSpread(spread) => self.fragment_cost(&spread.fragment_name),
+      })
+      .fold(0, u64::saturating_add)
+  }
+
+  fn field_cost(&mut self, field: &'a Field<'static, String>) -> u64 {
+    let cost = self
+      .config
+      .field_costs
+      .get(&field.name)
+      .copied()
+      .unwrap_or(self.config.default_field_cost);
+    let list_size = field
+      .arguments
+      .iter()
+      .filter(|(name, _)| self.config.list_size_arguments.contains(name))
+      .filter_map(|(_, value)| self.list_size(value))
+      .max()
+      .unwrap_or(1);
+
+    cost.saturating_add(
+      self
+        .selection_set_cost(&field.selection_set)
+        .saturating_mul(list_size),
+    )
+  }
+
+  fn fragment_cost(&mut self, name: &'a str) -> u64 {
+    if let Some(cost) = self.fragment_costs.get(name) {
+      return *cost;
+    }
+
+    if !self.visiting.insert(name) {
+      return 0;
+    }
+
+    let cost = match self.fragments.get(name).copied() {
+      Some(fragment) => self.selection_set_cost(&fragment.selection_set),
+      None => 0,
+    };
+
+    self.visiting.remove(name);
+    self.fragment_costs.insert(name, cost);
+
+    cost
+  }
+
+  fn list_size(&self, value: &GraphQLValue<'static, String>) -> Option<u64> {
+    match value {
+      GraphQLValue::Int(size) => size.as_i64().map(|size| size.max(0) as u64),
+      GraphQLValue::Variable(name) => {
+        match self.variables.and_then(|variables| variables.get(name)) {
+          // Negative sizes are invalid, and don't select any item.
+          Some(size) => size.as_u64().or_else(|| size.as_i64().map(|_| 0)),
+          None => self
+            .variable_defaults
+            .get(name.as_str())
+            .and_then(|value| self.list_size(value)),
+        }
+      }
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct CostLimitPlugin(CostLimitPluginConfig);
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for CostLimitPlugin {
+  type Config = CostLimitPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    Ok(Box::new(Self(config)))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for CostLimitPlugin {
+  async fn on_downstream_graphql_request(
+    &self,
+    _source_runtime: Arc<Box<dyn SourceRuntime>>,
+    ctx: &mut RequestExecutionContext,
+  ) {
+    let cost = match &ctx.downstream_graphql_request {
+      Some(gql_request) => match gql_request.executable_selection_set() {
+        Some(selection_set) => {
+          CostWalker::new(&self.0, gql_request).selection_set_cost(selection_set)
+        }
+        None => return,
+      },
+      None => return,
+    };
+
+    if self.0.expose_cost {
+      ctx.ctx_insert(COST_CONTEXT_KEY, cost);
+    }
+
+    if cost > self.0.max_cost {
+      debug!(
+        "operation cost of {} exceeds the maximum allowed cost of {}",
+        cost, self.0.max_cost
+      );
+
+      ctx.short_circuit(
+        GraphQLResponse::new_error(&format!(
+          "operation cost of {} exceeds the maximum allowed cost of {}",
+          cost, self.0.max_cost
+        ))
+        .into_with_status_code(StatusCode::BAD_REQUEST),
+      );
+    }
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    let cost = match ctx.ctx_get(COST_CONTEXT_KEY).and_then(Value::as_u64) {
+      Some(cost) => cost,
+      None => return,
+    };
+
+    let mut body = match serde_json::from_slice::<Value>(&response.body) {
+      Ok(Value::Object(body)) => body,
+      _ => return,
+    };
+
+    let extensions = body
+      .entry("extensions")
+      .or_insert_with(|| Value::Object(Map::new()));
+
+    if let Value::Object(extensions) = extensions {
+      extensions.insert(
+        "cost".to_string(),
+        json!({ "requested": cost, "maximum": self.0.max_cost }),
+      );
+
+      if let Ok(body) = serde_json::to_vec(&body) {
+        response.body = body.into();
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{graphql::GraphQLRequest, source::TestSource};
+
+  use super::*;
+
+  fn config(max_cost: u64) -> CostLimitPluginConfig {
+    CostLimitPluginConfig {
+      max_cost,
+      default_field_cost: 1,
+      field_costs: HashMap::new(),
+      list_size_arguments: vec!["first".to_string(), "last".to_string()],
+      expose_cost: true,
+    }
+  }
+
+  /// Runs an operation through the plugin, and returns the response body.
+  /// Operations that are not rejected get a `{ "data": null }` response.
+  async fn execute(
+    config: CostLimitPluginConfig,
+    operation: &str,
+    variables: Option<Value>,
+  ) -> Value {
+    let plugin = CostLimitPlugin::create(config).await.unwrap();
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    ctx.downstream_graphql_request = Some(
+      ParsedGraphQLRequest::create_and_parse(GraphQLRequest {
+        operation: operation.to_string(),
+        operation_name: None,
+        variables: variables.and_then(|variables| variables.as_object().cloned()),
+        extensions: None,
+      })
+      .unwrap(),
+    );
+    plugin
+      .on_downstream_graphql_request(Arc::new(Box::new(TestSource::default())), &mut ctx)
+      .await;
+
+    let mut response = ctx
+      .short_circuit_response
+      .take()
+      .unwrap_or_else(|| ConductorHttpResponse {
+        body: r#"{"data":null}"#.into(),
+        status: StatusCode::OK,
+        headers: Default::default(),
+      });
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    serde_json::from_slice(&response.body).unwrap()
+  }
+
+  async fn cost(config: CostLimitPluginConfig, operation: &str, variables: Option<Value>) -> Value {
+    execute(config, operation, variables).await["extensions"]["cost"]["requested"].clone()
+  }
+
+  #[tokio::test]
+  async fn counts_fields() {
+    assert_eq!(
+      cost(config(100), "{ user { id name friends { id } } }", None).await,
+      5
+    );
+  }
+
+  #[tokio::test]
+  async fn field_costs() {
+    let config = CostLimitPluginConfig {
+      default_field_cost: 2,
+      field_costs: HashMap::from([("search".to_string(), 10), ("id".to_string(), 0)]),
+      ..config(100)
+    };
+
+    assert_eq!(cost(config, "{ search { id name } }", None).await, 12);
+  }
+
+  #[tokio::test]
+  async fn multiplies_lists() {
+    let operation = "{ users(first: 10) { id posts(last: 5) { id } } }";
+
+    // users + 10 * (id + posts + 5 * id)
+    assert_eq!(cost(config(1000), operation, None).await, 71);
+  }
+
+  #[tokio::test]
+  async fn multiplies_lists_with_variables() {
+    let operation = "query ($first: Int = 3) { users(first: $first) { id } }";
+
+    assert_eq!(
+      cost(config(1000), operation, Some(json!({ "first": 10 }))).await,
+      11
+    );
+    assert_eq!(cost(config(1000), operation, None).await, 4);
+  }
+
+  #[tokio::test]
+  async fn counts_fragments() {
+    let operation = r#"
+      query {
+        users(first: 2) { ...UserFields }
+        node { ... on User { ...UserFields } }
+      }
+      fragment UserFields on User { id name }
+    "#;
+
+    // users + 2 * 2 + node + 2
+    assert_eq!(cost(config(1000), operation, None).await, 8);
+  }
+
+  #[tokio::test]
+  async fn rejects_expensive_operation() {
+    let body = execute(config(10), "{ users(first: 100) { id } }", None).await;
+
+    assert_eq!(
+      body,
+      json!({
+        "errors": [{ "message": "operation cost of 101 exceeds the maximum allowed cost of 10" }],
+        "extensions": { "cost": { "requested": 101, "maximum": 10 } }
+      })
+    );
+  }
+
+  #[tokio::test]
+  async fn hides_cost_by_default() {
+    let config = CostLimitPluginConfig {
+      expose_cost: false,
+      ..config(100)
+    };
+
+    assert_eq!(
+      execute(config, "{ user { id } }", None).await,
+      json!({ "data": null })
+    );
+  }
+}
@@ -0,0 +1,30 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `depth_limit` plugin rejects GraphQL operations with deeply nested selections, before they are sent to the upstream.
+///
+/// Every field adds one level of depth, and the fields selected through fragments and inline fragments are counted as if they were selected directly. Operations deeper than `max_depth` are rejected with a `400` status code, and an error pointing to the path of the deepest field.
+///
+/// Operations resolved by the `trusted_documents` plugin are checked after they are resolved.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "depth_limit_example")]
+pub struct DepthLimitPluginConfig {
+  /// The maximum depth of an operation. A query selecting only root fields has a depth of `1`.
+  pub max_depth: usize,
+}
+
+fn depth_limit_example() -> JsonSchemaExample<DepthLimitPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Limit depth",
+      Some("This example rejects operations with more than 10 levels of nested fields."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "depth_limit".to_string(),
+    }),
+    example: DepthLimitPluginConfig { max_depth: 10 },
+  }
+}
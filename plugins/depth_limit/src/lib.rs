@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::DepthLimitPluginConfig as Config;
+pub use plugin::DepthLimitPlugin as Plugin;
@@ -0,0 +1,321 @@
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::{GraphQLError, GraphQLErrorLocation, GraphQLResponse},
+  http::StatusCode,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+  source::SourceRuntime,
+  Field, FragmentDefinition, Selection, SelectionSet,
+};
+use tracing::debug;
+
+use crate::config::DepthLimitPluginConfig;
+
+#[derive(Debug, Clone)]
+struct DeepestField {
+  depth: usize,
+  /// The response keys of the fields leading to the deepest field, including it.
+  path: Vec<String>,
+  location: GraphQLErrorLocation,
+}
+
+struct DepthWalker<'a> {
+  fragments: HashMap<&'a str, &'a FragmentDefinition<'static, String>>,
+  /// Fragments can be spread many times, but each one is only walked once.
+  fragment_deepest_fields: HashMap<&'a str, Option<DeepestField>>,
+  /// The fragments being walked, used to stop at fragment cycles.
+  visiting: HashSet<&'a str>,
+}
+
+impl<'a> DepthWalker<'a> {
+  fn new(fragments: HashMap<&'a str, &'a FragmentDefinition<'static, String>>) -> Self {
+    Self {
+      fragments,
+      fragment_deepest_fields: HashMap::new(),
+      visiting: HashSet::new(),
+    }
+  }
+
+  fn deepest_field(
+    &mut self,
+    selection_set: &'a SelectionSet<'static, String>,
+  ) -> Option<DeepestField> {
+    selection_set
+      .items
+      .iter()
+      .filter_map(|selection| match selection {
+        Selection::Field(field) => Some(self.field_deepest_field(field)),
+        // Fragments don't add a level of depth, their fields are counted as if they were selected directly.
+        Selection::InlineFragment(fragment) => self.deepest_field(&fragment.selection_set),
+        Selection::FragmentSpread(spread) => self.fragment_deepest_field(&spread.fragment_name),
+      })
+      .reduce(|deepest, other| match other.depth > deepest.depth {
+        true => other,
+        false => deepest,
+      })
+  }
+
+  fn field_deepest_field(&mut self, field: &'a Field<'static, String>) -> DeepestField {
+    let response_key = field.alias.as_ref().unwrap_or(&field.name).clone();
+
+    match self.deepest_field(&field.selection_set) {
+      Some(mut deepest) => {
+        deepest.depth += 1;
+        deepest.path.insert(0, response_key);
+
+        deepest
+      }
+      None => DeepestField {
+        depth: 1,
+        path: vec![response_key],
+        location: field.position.into(),
+      },
+    }
+  }
+
+  fn fragment_deepest_field(&mut self, name: &'a str) -> Option<DeepestField> {
+    if let Some(deepest) = self.fragment_deepest_fields.get(name) {
+      return deepest.clone();
+    }
+
+    if !self.visiting.insert(name) {
+      return None;
+    }
+
+    let deepest = match self.fragments.get(name).copied() {
+      Some(fragment) => self.deepest_field(&fragment.selection_set),
+      None => None,
+    };
+
+    self.visiting.remove(name);
+    self.fragment_deepest_fields.insert(name, deepest.clone());
+
+    deepest
+  }
+}
+
+#[derive(Debug)]
+pub struct DepthLimitPlugin(DepthLimitPluginConfig);
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for DepthLimitPlugin {
+  type Config = DepthLimitPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    if config.max_depth == 0 {
+      return Err(PluginError::InitError {
+        source: anyhow::anyhow!("max_depth must be greater than 0"),
+      });
+    }
+
+    Ok(Box::new(Self(config)))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for DepthLimitPlugin {
+  async fn on_downstream_graphql_request(
+    &self,
+    _source_runtime: Arc<Box<dyn SourceRuntime>>,
+    ctx: &mut RequestExecutionContext,
+  ) {
+    let deepest = match &ctx.downstream_graphql_request {
+      Some(gql_request) => match gql_request.executable_selection_set() {
+        Some(selection_set) => {
+          DepthWalker::new(gql_request.fragment_definitions()).deepest_field(selection_set)
+        }
+        None => return,
+      },
+      None => return,
+    };
+
+    if let Some(deepest) = deepest.filter(|deepest| deepest.depth > self.0.max_depth) {
+      let path = deepest.path.join(".");
+
+      debug!(
+        "operation depth of {} exceeds the maximum allowed depth of {}, at path {}",
+        deepest.depth, self.0.max_depth, path
+      );
+
+      ctx.short_circuit(
+        GraphQLResponse::new_errors(vec![GraphQLError {
+          message: format!(
+            "operation depth of {} exceeds the maximum allowed depth of {}, at path \"{}\"",
+            deepest.depth, self.0.max_depth, path
+          ),
+          locations: Some(vec![deepest.location]),
+          path: Some(deepest.path),
+          extensions: None,
+        }])
+        .into_with_status_code(StatusCode::BAD_REQUEST),
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    graphql::{GraphQLRequest, ParsedGraphQLRequest},
+    source::TestSource,
+  };
+
+  use super::*;
+
+  /// Returns the error of a rejected operation, or `None` if the operation is allowed.
+  async fn execute(max_depth: usize, operation: &str) -> Option<GraphQLError> {
+    let plugin = DepthLimitPlugin::create(DepthLimitPluginConfig { max_depth })
+      .await
+      .unwrap();
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    ctx.downstream_graphql_request = Some(
+      ParsedGraphQLRequest::create_and_parse(GraphQLRequest {
+        operation: operation.to_string(),
+        operation_name: None,
+        variables: None,
+        extensions: None,
+      })
+      .unwrap(),
+    );
+    plugin
+      .on_downstream_graphql_request(Arc::new(Box::new(TestSource::default())), &mut ctx)
+      .await;
+
+    let response = ctx.short_circuit_response?;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    let body: GraphQLResponse = serde_json::from_slice(&response.body).unwrap();
+
+    body.errors.unwrap().into_iter().next()
+  }
+
+  fn path(error: Option<GraphQLError>) -> Option<Vec<String>> {
+    error.and_then(|error| error.path)
+  }
+
+  static NESTED: &str = "query { user { friends { friends { name } } } }";
+
+  #[tokio::test]
+  async fn allows_operation_within_limit() {
+    assert!(execute(4, NESTED).await.is_none());
+    assert!(execute(1, "{ __typename }").await.is_none());
+  }
+
+  #[tokio::test]
+  async fn rejects_deep_operation() {
+    let error = execute(3, NESTED).await.unwrap();
+
+    assert_eq!(
+      error.message,
+      "operation depth of 4 exceeds the maximum allowed depth of 3, at path \"user.friends.friends.name\""
+    );
+    assert_eq!(error.locations.unwrap()[0].column, 36);
+  }
+
+  #[tokio::test]
+  async fn reports_deepest_path() {
+    let operation = r#"
+      query {
+        shallow { id }
+        user { posts { author { name } } }
+        other { id }
+      }
+    "#;
+
+    assert_eq!(
+      path(execute(2, operation).await),
+      Some(vec![
+        "user".to_string(),
+        "posts".to_string(),
+        "author".to_string(),
+        "name".to_string(),
+      ])
+    );
+  }
+
+  #[tokio::test]
+  async fn uses_aliases_in_path() {
+    assert_eq!(
+      path(execute(1, "{ me: user { id } }").await),
+      Some(vec!["me".to_string(), "id".to_string()])
+    );
+  }
+
+  #[tokio::test]
+  async fn counts_fragment_spreads() {
+    let operation = r#"
+      query { user { ...UserFields } }
+      fragment UserFields on User { friends { ...FriendFields } }
+      fragment FriendFields on User { name }
+    "#;
+
+    assert!(execute(3, operation).await.is_none());
+    assert_eq!(
+      path(execute(2, operation).await),
+      Some(vec![
+        "user".to_string(),
+        "friends".to_string(),
+        "name".to_string(),
+      ])
+    );
+  }
+
+  #[tokio::test]
+  async fn counts_inline_fragments() {
+    let operation = r#"
+      query {
+        node {
+          ... on User { friends { ... on User { name } } }
+          ... { id }
+        }
+      }
+    "#;
+
+    assert!(execute(3, operation).await.is_none());
+    assert_eq!(
+      path(execute(2, operation).await),
+      Some(vec![
+        "node".to_string(),
+        "friends".to_string(),
+        "name".to_string(),
+      ])
+    );
+  }
+
+  #[tokio::test]
+  async fn counts_fragments_spread_at_different_depths() {
+    let operation = r#"
+      query {
+        a: user { ...Name }
+        b: user { friends { ...Name } }
+      }
+      fragment Name on User { name }
+    "#;
+
+    assert!(execute(3, operation).await.is_none());
+    assert_eq!(
+      path(execute(2, operation).await),
+      Some(vec![
+        "b".to_string(),
+        "friends".to_string(),
+        "name".to_string()
+      ])
+    );
+  }
+
+  #[tokio::test]
+  async fn stops_at_fragment_cycles() {
+    let operation = r#"
+      query { user { ...A } }
+      fragment A on User { friends { ...B } }
+      fragment B on User { friends { ...A } }
+    "#;
+
+    assert!(execute(3, operation).await.is_none());
+    assert!(execute(2, operation).await.is_some());
+  }
+}
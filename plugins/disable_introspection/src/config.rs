@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Default, Deserialize, Serialize, Debug, Clone, JsonSchema)]
 #[schemars(example = "disable_introspection_example1")]
 #[schemars(example = "disable_introspection_example2")]
+#[schemars(example = "disable_introspection_example3")]
 /// The `disable_introspection` plugin allows you to disable introspection for your GraphQL API.
 ///
 /// A [GraphQL introspection query](https://graphql.org/learn/introspection/) is a special GraphQL query that returns information about the GraphQL schema of your API.
@@ -16,6 +17,10 @@ use serde::{Deserialize, Serialize};
 ///
 /// It can either disable introspection for all requests, or only for requests that match a specific condition (using VRL scripting language).
 ///
+/// Internal tooling can keep using introspection, by sending requests from one of the `allowed_ips`, or with the configured `bypass_header`.
+///
+/// Only the `__schema` and `__type` root fields are considered introspection: operations selecting `__typename` are never blocked.
+///
 pub struct DisableIntrospectionPluginConfig {
   #[serde(default, skip_serializing_if = "Option::is_none")]
   /// A VRL condition that determines whether to disable introspection for the request. This condition is evaluated only if the incoming GraphQL request is detected as an introspection query.
@@ -26,6 +31,20 @@ pub struct DisableIntrospectionPluginConfig {
   ///
   /// In case of a runtime error, or an unexpected return value, the script will be ignored and introspection will be disabled for the incoming request.
   pub condition: Option<VrlConfigReference>,
+  /// A list of IP addresses or CIDR ranges (for example: `10.0.0.0/8`) allowed to run introspection queries. The address of the directly connected peer is used.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub allowed_ips: Vec<String>,
+  /// A request header that allows introspection queries when it's set to the configured value.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub bypass_header: Option<IntrospectionBypassHeader>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct IntrospectionBypassHeader {
+  /// The name of the HTTP header.
+  pub name: String,
+  /// The value of the header that allows introspection. Treat it as a secret, since anyone sending it can introspect the schema.
+  pub value: String,
 }
 
 fn disable_introspection_example1() -> JsonSchemaExample<DisableIntrospectionPluginConfig> {
@@ -56,6 +75,27 @@ fn disable_introspection_example2() -> JsonSchemaExample<DisableIntrospectionPlu
         }),
         example: DisableIntrospectionPluginConfig {
             condition: Some(VrlConfigReference::Inline { content: "%downstream_http_req.headers.\"bypass-introspection\" != \"1\"".to_string() }),
+            ..Default::default()
         },
     }
 }
+
+fn disable_introspection_example3() -> JsonSchemaExample<DisableIntrospectionPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Internal tooling",
+      Some("This example disables introspection, except for requests from the internal network, or with the `x-introspection-token` header set to the configured secret."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "disable_introspection".to_string(),
+    }),
+    example: DisableIntrospectionPluginConfig {
+      allowed_ips: vec!["10.0.0.0/8".to_string()],
+      bypass_header: Some(IntrospectionBypassHeader {
+        name: "x-introspection-token".to_string(),
+        value: "my-secret-token".to_string(),
+      }),
+      ..Default::default()
+    },
+  }
+}
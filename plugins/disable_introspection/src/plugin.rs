@@ -1,14 +1,15 @@
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
 use crate::config::DisableIntrospectionPluginConfig;
 use conductor_common::{
   graphql::GraphQLResponse,
-  http::StatusCode,
+  http::{HeaderName, StatusCode},
   plugin::{CreatablePlugin, Plugin, PluginError},
   source::SourceRuntime,
   vrl_utils::{conductor_request_to_value, VrlProgramProxy},
 };
-use tracing::error;
+use ipnet::IpNet;
+use tracing::{debug, error};
 use vrl::value;
 
 use conductor_common::execute::RequestExecutionContext;
@@ -16,6 +17,8 @@ use conductor_common::execute::RequestExecutionContext;
 #[derive(Debug)]
 pub struct DisableIntrospectionPlugin {
   condition: Option<VrlProgramProxy>,
+  allowed_ips: Vec<IpNet>,
+  bypass_header: Option<(HeaderName, String)>,
 }
 
 #[async_trait::async_trait(?Send)]
@@ -35,7 +38,55 @@ impl CreatablePlugin for DisableIntrospectionPlugin {
       None => None,
     };
 
-    Ok(Box::new(Self { condition }))
+    let allowed_ips = config
+      .allowed_ips
+      .iter()
+      .map(|ip| {
+        ip.parse::<IpNet>()
+          .or_else(|_| ip.parse::<IpAddr>().map(IpNet::from))
+          .map_err(|_| PluginError::InitError {
+            source: anyhow::anyhow!(
+              "invalid allowed ip \"{}\", expected an IP address or a CIDR range",
+              ip
+            ),
+          })
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let bypass_header = match config.bypass_header {
+      Some(header) => Some((
+        HeaderName::from_bytes(header.name.as_bytes()).map_err(|e| PluginError::InitError {
+          source: anyhow::anyhow!("invalid header name \"{}\": {}", header.name, e),
+        })?,
+        header.value,
+      )),
+      None => None,
+    };
+
+    Ok(Box::new(Self {
+      condition,
+      allowed_ips,
+      bypass_header,
+    }))
+  }
+}
+
+impl DisableIntrospectionPlugin {
+  /// Returns `true` when the request comes from an allowed IP, or carries the bypass header.
+  fn is_bypassed(&self, ctx: &RequestExecutionContext) -> bool {
+    let request = &ctx.downstream_http_request;
+    let allowed_ip = request
+      .peer_addr
+      .is_some_and(|peer_addr| self.allowed_ips.iter().any(|ip| ip.contains(&peer_addr)));
+    let bypass_header = self.bypass_header.as_ref().is_some_and(|(name, value)| {
+      request
+        .headers
+        .get_all(name)
+        .iter()
+        .any(|header| header.as_bytes() == value.as_bytes())
+    });
+
+    allowed_ip || bypass_header
   }
 }
 
@@ -48,6 +99,12 @@ impl Plugin for DisableIntrospectionPlugin {
   ) {
     if let Some(op) = &ctx.downstream_graphql_request {
       if op.is_introspection_query() {
+        if self.is_bypassed(ctx) {
+          debug!("introspection is allowed for this request, skipping");
+
+          return;
+        }
+
         let should_disable = match &self.condition {
           Some(program) => {
             let downstream_http_req = conductor_request_to_value(&ctx.downstream_http_request);
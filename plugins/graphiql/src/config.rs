@@ -3,9 +3,11 @@ use conductor_common::serde_utils::{
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default, JsonSchema)]
 #[schemars(example = "graphiql_example")]
+#[schemars(example = "graphiql_customized_example")]
 /// This plugin adds a GraphiQL interface to your Endpoint.
 ///
 /// This plugin is rendering the GraphiQL interface for HTTP `GET` requests, that are not intercepted by other plugins.
@@ -16,6 +18,22 @@ pub struct GraphiQLPluginConfig {
   )]
   /// Enable/disable the HTTP headers editor in the GraphiQL interface.
   pub headers_editor_enabled: Option<bool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  /// The `Cache-Control` header value to use for the GraphiQL interface response.
+  ///
+  /// The response also carries an `ETag`, so browsers can revalidate it with `If-None-Match` and receive a `304 Not Modified` when it's unchanged.
+  ///
+  /// If not specified, `no-cache` is used.
+  pub cache_control: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  /// The title of the GraphiQL page. If not specified, `Conductor` is used.
+  pub title: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  /// The GraphQL operation shown in the editor when GraphiQL is opened.
+  pub default_query: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  /// HTTP headers shown in the headers editor when GraphiQL is opened, for example: `{ "Authorization": "Bearer <token>" }`.
+  pub default_headers: Option<BTreeMap<String, String>>,
 }
 
 fn graphiql_example() -> JsonSchemaExample<GraphiQLPluginConfig> {
@@ -26,6 +44,32 @@ fn graphiql_example() -> JsonSchemaExample<GraphiQLPluginConfig> {
     }),
     example: GraphiQLPluginConfig {
       headers_editor_enabled: Default::default(),
+      cache_control: Default::default(),
+      title: Default::default(),
+      default_query: Default::default(),
+      default_headers: Default::default(),
+    },
+  }
+}
+
+fn graphiql_customized_example() -> JsonSchemaExample<GraphiQLPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Customized GraphiQL",
+      Some("This example sets the page title, and the operation and headers shown when GraphiQL is opened."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "graphiql".to_string(),
+    }),
+    example: GraphiQLPluginConfig {
+      headers_editor_enabled: Some(true),
+      cache_control: Default::default(),
+      title: Some("Admin API".to_string()),
+      default_query: Some("query { __typename }".to_string()),
+      default_headers: Some(BTreeMap::from([(
+        "Authorization".to_string(),
+        "Bearer <token>".to_string(),
+      )])),
     },
   }
 }
@@ -42,4 +86,9 @@ pub struct GraphiQLSource {
   pub query: String,
   #[serde(rename = "isHeadersEditorEnabled")]
   pub headers_editor_enabled: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub title: Option<String>,
+  /// The headers, as a JSON string.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub headers: Option<String>,
 }
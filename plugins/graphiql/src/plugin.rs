@@ -1,9 +1,15 @@
+use std::{
+  collections::hash_map::DefaultHasher,
+  hash::{Hash, Hasher},
+};
+
 use crate::config::{GraphiQLPluginConfig, GraphiQLSource};
 use conductor_common::{
   graphql::{ExtractGraphQLOperationError, APPLICATION_GRAPHQL_JSON_MIME},
   http::{
-    extract_accept, extract_content_type, HeaderValue, Method, Mime, APPLICATION_JSON,
-    APPLICATION_WWW_FORM_URLENCODED,
+    extract_accept, extract_content_type,
+    header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH},
+    HeaderValue, Method, Mime, APPLICATION_JSON, APPLICATION_WWW_FORM_URLENCODED,
   },
   plugin::{CreatablePlugin, PluginError},
 };
@@ -38,10 +44,13 @@ impl Plugin for GraphiQLPlugin {
         if accept != Some(APPLICATION_JSON)
           && accept != Some(APPLICATION_GRAPHQL_JSON_MIME.to_owned())
         {
-          ctx.short_circuit(render_graphiql(
-            &self.config,
-            ctx.downstream_http_request.uri.clone(),
-          ));
+          let response = render_graphiql(&self.config, ctx.downstream_http_request.uri.clone());
+          let response = match headers.get(IF_NONE_MATCH) {
+            Some(if_none_match) => not_modified_if_matches(if_none_match, response),
+            None => response,
+          };
+
+          ctx.short_circuit(response);
         }
       }
     }
@@ -51,16 +60,35 @@ impl Plugin for GraphiQLPlugin {
 use conductor_common::http::{ConductorHttpResponse, HttpHeadersMap, StatusCode, CONTENT_TYPE};
 
 const YOGA_GRAPHIQL_VERSION: &str = "4.2.1";
+const DEFAULT_CACHE_CONTROL: &str = "no-cache";
+const DEFAULT_TITLE: &str = "Conductor";
 
 pub fn render_graphiql(config: &GraphiQLPluginConfig, endpoint: String) -> ConductorHttpResponse {
+  let cache_control = config
+    .cache_control
+    .as_deref()
+    .unwrap_or(DEFAULT_CACHE_CONTROL);
+  let title = config.title.as_deref().unwrap_or(DEFAULT_TITLE);
+  let headers = match config
+    .default_headers
+    .as_ref()
+    .map(serde_json::to_string_pretty)
+  {
+    Some(Ok(headers)) => Some(headers),
+    Some(Err(e)) => return ExtractGraphQLOperationError::SerializationError(e).into_response(None),
+    None => None,
+  };
   let config = GraphiQLSource {
     endpoint,
-    query: String::from(""),
+    query: config.default_query.clone().unwrap_or_default(),
     headers_editor_enabled: config.headers_editor_enabled.unwrap_or_default(),
+    title: Some(title.to_string()),
+    headers,
   };
 
+  // `<` is escaped so configured values can't close the script tag.
   let config_json = match serde_json::to_string(&config) {
-    Ok(json) => json,
+    Ok(json) => json.replace('<', "\\u003c"),
     Err(e) => return ExtractGraphQLOperationError::SerializationError(e).into_response(None),
   };
 
@@ -69,7 +97,7 @@ pub fn render_graphiql(config: &GraphiQLPluginConfig, endpoint: String) -> Condu
   <html lang="en">
     <head>
       <meta charset="utf-8" />
-      <title>Conductor</title>
+      <title>{2}</title>
       <link
         rel="stylesheet"
         href="https://unpkg.com/@graphql-yoga/graphiql@{0}/dist/style.css"
@@ -86,11 +114,26 @@ pub fn render_graphiql(config: &GraphiQLPluginConfig, endpoint: String) -> Condu
       </script>
     </body>
   </html>"#,
-    YOGA_GRAPHIQL_VERSION, config_json
+    YOGA_GRAPHIQL_VERSION,
+    config_json,
+    escape_html(title)
   );
 
   let mut header_map = HttpHeadersMap::new();
   header_map.append(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+  header_map.append(ETAG, etag(&body));
+
+  match HeaderValue::from_str(cache_control) {
+    Ok(value) => {
+      header_map.append(CACHE_CONTROL, value);
+    }
+    Err(_) => {
+      header_map.append(
+        CACHE_CONTROL,
+        HeaderValue::from_static(DEFAULT_CACHE_CONTROL),
+      );
+    }
+  }
 
   ConductorHttpResponse {
     body: body.into(),
@@ -98,3 +141,54 @@ pub fn render_graphiql(config: &GraphiQLPluginConfig, endpoint: String) -> Condu
     headers: header_map,
   }
 }
+
+fn escape_html(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&#39;")
+}
+
+/// The rendered page only depends on the plugin configuration and the endpoint, so hashing the body gives a stable validator.
+fn etag(body: &str) -> HeaderValue {
+  let mut hasher = DefaultHasher::new();
+  body.hash(&mut hasher);
+
+  // A hex-encoded hash is always a valid header value.
+  HeaderValue::from_str(&format!("\"{:016x}\"", hasher.finish())).unwrap()
+}
+
+/// Replaces the response with an empty `304 Not Modified` if one of the tags in `If-None-Match` matches its `ETag`.
+fn not_modified_if_matches(
+  if_none_match: &HeaderValue,
+  response: ConductorHttpResponse,
+) -> ConductorHttpResponse {
+  let (Ok(if_none_match), Some(etag)) = (if_none_match.to_str(), response.headers.get(ETAG)) else {
+    return response;
+  };
+
+  // Weak comparison is used, as defined in https://www.rfc-editor.org/rfc/rfc9110#section-13.1.2
+  let matches = if_none_match
+    .split(',')
+    .map(str::trim)
+    .any(|tag| tag == "*" || tag.trim_start_matches("W/").as_bytes() == etag.as_bytes());
+
+  if !matches {
+    return response;
+  }
+
+  let mut headers = HttpHeadersMap::new();
+  for name in [ETAG, CACHE_CONTROL] {
+    if let Some(value) = response.headers.get(&name) {
+      headers.insert(name, value.clone());
+    }
+  }
+
+  ConductorHttpResponse {
+    body: Default::default(),
+    status: StatusCode::NOT_MODIFIED,
+    headers,
+  }
+}
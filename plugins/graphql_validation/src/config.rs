@@ -3,4 +3,12 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default, JsonSchema)]
 // #[schemars(example = "graphql_validation_example_1")]
-pub struct GraphQLValidationPluginConfig {}
+pub struct GraphQLValidationPluginConfig {
+  /// The maximum number of variables (keys of the `variables` object) allowed in a single operation.
+  ///
+  /// Operations exceeding this limit are rejected with `400 Bad Request`, before being validated or executed.
+  ///
+  /// This check does not require Schema Awareness. By default, the number of variables is not limited.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max_variables: Option<usize>,
+}
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use conductor_common::{
   execute::RequestExecutionContext,
   graphql::{validate_graphql_operation, GraphQLResponse},
+  http::StatusCode,
   plugin::{CreatablePlugin, Plugin, PluginError},
   source::SourceRuntime,
 };
@@ -29,6 +30,26 @@ impl Plugin for GraphQLValidationPlugin {
     request_context: &mut RequestExecutionContext,
   ) {
     if let Some(operation) = &request_context.downstream_graphql_request {
+      if let Some(max_variables) = self.0.max_variables {
+        let variables_count = operation
+          .request
+          .variables
+          .as_ref()
+          .map_or(0, |variables| variables.len());
+
+        if variables_count > max_variables {
+          request_context.short_circuit(
+            GraphQLResponse::new_error(&format!(
+              "operation has {} variables, exceeding the maximum of {}",
+              variables_count, max_variables
+            ))
+            .into_with_status_code(StatusCode::BAD_REQUEST),
+          );
+
+          return;
+        }
+      }
+
       if let Some(schema) = source_runtime.schema() {
         let errors = validate_graphql_operation(schema.as_ref(), &operation.parsed_operation);
 
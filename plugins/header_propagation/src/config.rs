@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `header_propagation` plugin copies headers of the incoming request to the requests sent to the upstream.
+///
+/// Headers are not forwarded unless they match an `allow` pattern, and don't match any `deny` pattern. Patterns are case-insensitive, and support the `*` (any sequence of characters) and `?` (any single character) wildcards, for example: `x-tenant-*`.
+///
+/// Headers that describe the connection or the body of the request (like `Host`, `Content-Length`, `Content-Type`, `Connection` or `Transfer-Encoding`) are never forwarded, as they are set for the upstream request by Conductor.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, JsonSchema)]
+#[schemars(example = "header_propagation_example")]
+pub struct HeaderPropagationPluginConfig {
+  /// Patterns of the header names to forward.
+  #[serde(default)]
+  pub allow: Vec<String>,
+  /// Patterns of the header names to never forward, even if they match an `allow` pattern.
+  #[serde(default)]
+  pub deny: Vec<String>,
+  /// Forwards headers under a different name, keyed by the name of the incoming header.
+  ///
+  /// Renamed headers must still match an `allow` pattern to be forwarded.
+  #[serde(default)]
+  pub rename: HashMap<String, String>,
+}
+
+fn header_propagation_example() -> JsonSchemaExample<HeaderPropagationPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Tenant headers",
+      Some("This example forwards the tenant headers (except `x-tenant-secret`) and the `x-request-id` header, which is sent to the upstream as `x-correlation-id`."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "header_propagation".to_string(),
+    }),
+    example: HeaderPropagationPluginConfig {
+      allow: vec!["x-tenant-*".to_string(), "x-request-id".to_string()],
+      deny: vec!["x-tenant-secret".to_string()],
+      rename: HashMap::from([("x-request-id".to_string(), "x-correlation-id".to_string())]),
+    },
+  }
+}
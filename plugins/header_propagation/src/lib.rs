@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::HeaderPropagationPluginConfig as Config;
+pub use plugin::HeaderPropagationPlugin as Plugin;
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  http::{ConductorHttpRequest, HeaderName},
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use tracing::debug;
+
+use crate::config::HeaderPropagationPluginConfig;
+
+/// Headers that describe the downstream connection or body, and are never forwarded.
+static RESERVED_HEADERS: &[&str] = &[
+  "host",
+  "content-length",
+  "content-type",
+  "content-encoding",
+  "accept-encoding",
+  "connection",
+  "keep-alive",
+  "transfer-encoding",
+  "te",
+  "trailer",
+  "upgrade",
+  "proxy-authorization",
+  "proxy-connection",
+];
+
+/// Matches a lowercase header name against a lowercase pattern, where `*` matches any sequence of characters and `?` matches a single character.
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+  let (mut p, mut n) = (0, 0);
+  // The position of the last `*` in the pattern, and the position in the name it was matched at.
+  let mut backtrack: Option<(usize, usize)> = None;
+
+  while n < name.len() {
+    match pattern.get(p) {
+      Some(b'*') => {
+        backtrack = Some((p, n));
+        p += 1;
+      }
+      Some(c) if *c == b'?' || *c == name[n] => {
+        p += 1;
+        n += 1;
+      }
+      _ => match backtrack {
+        // Let the last `*` match one more character, and retry.
+        Some((star, matched)) => {
+          backtrack = Some((star, matched + 1));
+          p = star + 1;
+          n = matched + 1;
+        }
+        None => return false,
+      },
+    }
+  }
+
+  pattern[p..].iter().all(|c| *c == b'*')
+}
+
+#[derive(Debug)]
+pub struct HeaderPropagationPlugin {
+  allow: Vec<String>,
+  deny: Vec<String>,
+  rename: HashMap<String, HeaderName>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for HeaderPropagationPlugin {
+  type Config = HeaderPropagationPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    let rename = config
+      .rename
+      .into_iter()
+      .map(|(from, to)| match HeaderName::from_bytes(to.as_bytes()) {
+        Ok(to) => Ok((from.to_ascii_lowercase(), to)),
+        Err(e) => Err(PluginError::InitError {
+          source: anyhow::anyhow!("invalid header name \"{}\": {}", to, e),
+        }),
+      })
+      .collect::<Result<_, _>>()?;
+    let lowercase = |patterns: Vec<String>| -> Vec<String> {
+      patterns
+        .into_iter()
+        .map(|pattern| pattern.to_ascii_lowercase())
+        .collect()
+    };
+
+    Ok(Box::new(Self {
+      allow: lowercase(config.allow),
+      deny: lowercase(config.deny),
+      rename,
+    }))
+  }
+}
+
+impl HeaderPropagationPlugin {
+  fn is_forwarded(&self, name: &str) -> bool {
+    let matches = |pattern: &String| glob_match(pattern.as_bytes(), name.as_bytes());
+
+    !RESERVED_HEADERS.contains(&name)
+      && self.allow.iter().any(matches)
+      && !self.deny.iter().any(matches)
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for HeaderPropagationPlugin {
+  async fn on_upstream_http_request(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    req: &mut ConductorHttpRequest,
+  ) {
+    let downstream_headers = &ctx.downstream_http_request.headers;
+
+    // Header names are always lowercase.
+    for name in downstream_headers.keys() {
+      if !self.is_forwarded(name.as_str()) {
+        continue;
+      }
+
+      let upstream_name = self.rename.get(name.as_str()).unwrap_or(name).clone();
+
+      debug!("forwarding header \"{}\" as \"{}\"", name, upstream_name);
+
+      req.headers.remove(&upstream_name);
+
+      for value in downstream_headers.get_all(name) {
+        req.headers.append(upstream_name.clone(), value.clone());
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::http::{HeaderValue, HttpHeadersMap};
+
+  use super::*;
+
+  async fn forward(
+    config: HeaderPropagationPluginConfig,
+    headers: &[(&'static str, &'static str)],
+  ) -> HttpHeadersMap {
+    let plugin = HeaderPropagationPlugin::create(config).await.unwrap();
+    let mut downstream_headers = HttpHeadersMap::new();
+
+    for (name, value) in headers {
+      downstream_headers.append(*name, HeaderValue::from_static(value));
+    }
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      headers: downstream_headers,
+      ..Default::default()
+    });
+    let mut req = ConductorHttpRequest::default();
+    plugin.on_upstream_http_request(&mut ctx, &mut req).await;
+
+    req.headers
+  }
+
+  fn patterns(patterns: &[&str]) -> Vec<String> {
+    patterns.iter().map(|pattern| pattern.to_string()).collect()
+  }
+
+  #[test]
+  fn glob() {
+    assert!(glob_match(b"x-tenant-*", b"x-tenant-id"));
+    assert!(glob_match(b"x-tenant-*", b"x-tenant-"));
+    assert!(glob_match(b"*-id", b"x-tenant-id"));
+    assert!(glob_match(b"x-*-*-id", b"x-a-b-c-id"));
+    assert!(glob_match(b"x-?", b"x-a"));
+    assert!(glob_match(b"*", b"cookie"));
+    assert!(!glob_match(b"x-tenant-*", b"x-tenant"));
+    assert!(!glob_match(b"x-?", b"x-ab"));
+    assert!(!glob_match(b"*-id", b"x-tenant-ids"));
+  }
+
+  #[tokio::test]
+  async fn forwards_nothing_by_default() {
+    let headers = forward(
+      Default::default(),
+      &[("authorization", "Bearer token"), ("x-tenant-id", "acme")],
+    )
+    .await;
+
+    assert!(headers.is_empty());
+  }
+
+  #[tokio::test]
+  async fn allow_and_deny() {
+    let config = HeaderPropagationPluginConfig {
+      allow: patterns(&["X-Tenant-*", "*"]),
+      deny: patterns(&["authorization", "cookie", "x-tenant-secret"]),
+      ..Default::default()
+    };
+    let headers = forward(
+      config,
+      &[
+        ("x-tenant-id", "acme"),
+        ("x-tenant-secret", "secret"),
+        ("authorization", "Bearer token"),
+        ("cookie", "session=1"),
+        ("accept", "application/json"),
+        ("content-type", "application/json"),
+        ("host", "localhost"),
+      ],
+    )
+    .await;
+
+    assert_eq!(headers.len(), 2);
+    assert_eq!(headers.get("x-tenant-id").unwrap(), "acme");
+    assert_eq!(headers.get("accept").unwrap(), "application/json");
+  }
+
+  #[tokio::test]
+  async fn renames_headers() {
+    let config = HeaderPropagationPluginConfig {
+      allow: patterns(&["x-request-id", "x-tenant"]),
+      rename: HashMap::from([
+        ("X-Request-Id".to_string(), "x-correlation-id".to_string()),
+        ("x-not-allowed".to_string(), "x-allowed".to_string()),
+      ]),
+      ..Default::default()
+    };
+    let headers = forward(
+      config,
+      &[
+        ("x-request-id", "abc"),
+        ("x-tenant", "a"),
+        ("x-tenant", "b"),
+        ("x-not-allowed", "value"),
+      ],
+    )
+    .await;
+
+    assert_eq!(headers.len(), 3);
+    assert_eq!(headers.get("x-correlation-id").unwrap(), "abc");
+    assert_eq!(
+      headers
+        .get_all("x-tenant")
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect::<Vec<_>>(),
+      vec!["a", "b"]
+    );
+  }
+
+  #[tokio::test]
+  async fn rejects_invalid_rename() {
+    let config = HeaderPropagationPluginConfig {
+      rename: HashMap::from([("x-request-id".to_string(), "invalid name".to_string())]),
+      ..Default::default()
+    };
+
+    assert!(HeaderPropagationPlugin::create(config).await.is_err());
+  }
+}
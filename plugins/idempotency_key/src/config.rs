@@ -0,0 +1,49 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `idempotency_key` plugin requires clients to attach an idempotency key to every GraphQL mutation, so retried mutations can be safely deduplicated by the upstream.
+///
+/// Mutations sent without the header (or with an empty value) are rejected with a `400` status code. Queries and subscriptions are not affected.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "idempotency_key_example")]
+pub struct IdempotencyKeyPluginConfig {
+  /// Rejects mutations that do not carry the idempotency key header. When disabled, all requests are passed through.
+  #[serde(default = "default_require_on_mutations")]
+  pub require_on_mutations: bool,
+  /// The name of the HTTP header carrying the idempotency key.
+  #[serde(default = "default_header")]
+  pub header: String,
+}
+
+impl Default for IdempotencyKeyPluginConfig {
+  fn default() -> Self {
+    Self {
+      require_on_mutations: default_require_on_mutations(),
+      header: default_header(),
+    }
+  }
+}
+
+fn default_require_on_mutations() -> bool {
+  true
+}
+
+fn default_header() -> String {
+  "Idempotency-Key".to_string()
+}
+
+fn idempotency_key_example() -> JsonSchemaExample<IdempotencyKeyPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Require idempotency key",
+      Some("This example rejects mutations that do not carry an `Idempotency-Key` header."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "idempotency_key".to_string(),
+    }),
+    example: IdempotencyKeyPluginConfig::default(),
+  }
+}
@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::IdempotencyKeyPluginConfig as Config;
+pub use plugin::IdempotencyKeyPlugin as Plugin;
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{HeaderName, StatusCode},
+  plugin::{CreatablePlugin, Plugin, PluginError},
+  source::SourceRuntime,
+};
+
+use crate::config::IdempotencyKeyPluginConfig;
+
+#[derive(Debug)]
+pub struct IdempotencyKeyPlugin {
+  require_on_mutations: bool,
+  header: HeaderName,
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for IdempotencyKeyPlugin {
+  type Config = IdempotencyKeyPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    let header =
+      HeaderName::from_bytes(config.header.as_bytes()).map_err(|e| PluginError::InitError {
+        source: anyhow::anyhow!("invalid header name \"{}\": {}", config.header, e),
+      })?;
+
+    Ok(Box::new(Self {
+      require_on_mutations: config.require_on_mutations,
+      header,
+    }))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for IdempotencyKeyPlugin {
+  async fn on_downstream_graphql_request(
+    &self,
+    _source_runtime: Arc<Box<dyn SourceRuntime>>,
+    ctx: &mut RequestExecutionContext,
+  ) {
+    if !self.require_on_mutations {
+      return;
+    }
+
+    let is_mutation = ctx
+      .downstream_graphql_request
+      .as_ref()
+      .is_some_and(|gql_request| gql_request.is_running_mutation());
+
+    if !is_mutation {
+      return;
+    }
+
+    let has_key = ctx
+      .downstream_http_request
+      .headers
+      .get(&self.header)
+      .is_some_and(|value| !value.is_empty());
+
+    if !has_key {
+      ctx.short_circuit(
+        GraphQLResponse::new_error(&format!("mutations require a \"{}\" header", self.header))
+          .into_with_status_code(StatusCode::BAD_REQUEST),
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    graphql::{GraphQLRequest, ParsedGraphQLRequest},
+    http::{ConductorHttpRequest, HttpHeadersMap},
+    source::TestSource,
+  };
+
+  use super::*;
+
+  async fn execute(
+    config: IdempotencyKeyPluginConfig,
+    operation: &str,
+    idempotency_key: Option<&str>,
+  ) -> RequestExecutionContext {
+    let plugin = IdempotencyKeyPlugin::create(config).await.unwrap();
+    let mut headers = HttpHeadersMap::new();
+
+    if let Some(idempotency_key) = idempotency_key {
+      headers.append("idempotency-key", idempotency_key.parse().unwrap());
+    }
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      headers,
+      ..Default::default()
+    });
+    ctx.downstream_graphql_request = Some(
+      ParsedGraphQLRequest::create_and_parse(GraphQLRequest {
+        operation: operation.to_string(),
+        operation_name: None,
+        variables: None,
+        extensions: None,
+      })
+      .unwrap(),
+    );
+    plugin
+      .on_downstream_graphql_request(Arc::new(Box::new(TestSource::default())), &mut ctx)
+      .await;
+
+    ctx
+  }
+
+  #[tokio::test]
+  async fn mutation_without_key_is_rejected() {
+    for idempotency_key in [None, Some("")] {
+      let ctx = execute(
+        Default::default(),
+        "mutation { createUser { id } }",
+        idempotency_key,
+      )
+      .await;
+
+      let response = ctx.short_circuit_response.expect("expected a response");
+      assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    }
+  }
+
+  #[tokio::test]
+  async fn mutation_with_key_is_allowed() {
+    let ctx = execute(
+      Default::default(),
+      "mutation { createUser { id } }",
+      Some("8e03978e-40d5-43e8-bc93-6894a57f9324"),
+    )
+    .await;
+    assert!(!ctx.is_short_circuit());
+  }
+
+  #[tokio::test]
+  async fn query_without_key_is_allowed() {
+    let ctx = execute(Default::default(), "query { user { id } }", None).await;
+    assert!(!ctx.is_short_circuit());
+  }
+
+  #[tokio::test]
+  async fn disabled_enforcement() {
+    let ctx = execute(
+      IdempotencyKeyPluginConfig {
+        require_on_mutations: false,
+        ..Default::default()
+      },
+      "mutation { createUser { id } }",
+      None,
+    )
+    .await;
+    assert!(!ctx.is_short_circuit());
+  }
+}
@@ -15,12 +15,16 @@ use std::time::Duration;
 /// By default, the plugin will look for the JWT token in the `Authorization` header, with the `Bearer` prefix.
 ///
 /// You can also configure the plugin to reject requests that don't have a valid JWT token.
+///
+/// The claims of an authenticated request are stored in the request context under `jwt_auth:upstream:claims` (see `context_key_prefix`), so other plugins can use them (for example, to identify the caller). Changes made to them by VRL and Rhai scripts are seen by the other plugins, and forwarded to the upstream.
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, Default)]
 #[schemars(example = "jwt_auth_example_1")]
 #[schemars(example = "jwt_auth_example_2")]
 #[schemars(example = "jwt_auth_example_3")]
 #[schemars(example = "jwt_auth_example_4")]
 #[schemars(example = "jwt_auth_example_5")]
+#[schemars(example = "jwt_auth_example_6")]
+#[schemars(example = "jwt_auth_example_7")]
 pub struct JwtAuthPluginConfig {
   /// A list of JWKS providers to use for verifying the JWT signature.
   /// Can be either a path to a local JSON of the file-system, or a URL to a remote JWKS provider.
@@ -30,11 +34,15 @@ pub struct JwtAuthPluginConfig {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub issuers: Option<Vec<String>>,
   /// The list of [JWT audiences](https://tools.ietf.org/html/rfc7519#section-4.1.3) are allowed to access.
-  /// If this field is set, the token's `aud` field must be one of the values in this list, otherwise the token's `aud` field is not checked.
+  /// If this field is set, the token's `aud` field (either a string or an array of strings) must match the values in this list according to `audience_match`, otherwise the token's `aud` field is not checked.
   #[serde(skip_serializing_if = "Option::is_none")]
   pub audiences: Option<Vec<String>>,
+  /// Specifies how a token with multiple audiences is matched against `audiences`.
+  /// If not specified, the token is accepted if any of its audiences is allowed.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub audience_match: Option<JwtAuthAudienceMatch>,
   /// A list of locations to look up for the JWT token in the incoming HTTP request.
-  /// The first one that is found will be used.
+  /// The first one that is found will be used. Locations that are found but can't be used (for example, a header with a different prefix) are skipped.
   #[serde(
     default = "default_lookup_location",
     skip_serializing_if = "Vec::is_empty"
@@ -43,6 +51,10 @@ pub struct JwtAuthPluginConfig {
   /// If set to `true`, the entire request will be rejected if the JWT token is not present in the request.
   #[serde(skip_serializing_if = "Option::is_none")]
   pub reject_unauthenticated_requests: Option<bool>,
+  /// If set to `true`, requests without a JWT token are allowed to proceed even when `reject_unauthenticated_requests` is enabled, so public and private fields can be served by the same endpoint.
+  /// Such requests are marked with `jwt_auth:anonymous` (see `context_key_prefix`) in the request context, so other plugins can tell them apart. Requests with an invalid token are still rejected.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub allow_anonymous: Option<bool>,
   /// List of allowed algorithms for verifying the JWT signature.
   /// If not specified, the default list of all supported algorithms in [`jsonwebtoken` crate](https://crates.io/crates/jsonwebtoken) are used.
   #[serde(
@@ -57,12 +69,58 @@ pub struct JwtAuthPluginConfig {
   #[serde(skip_serializing_if = "Option::is_none")]
   /// Forward the JWT claims to the upstream service in the specified header.
   pub forward_claims_to_upstream_header: Option<String>,
+  /// Forward individual JWT claims to the upstream service, each in its own header (for example, `sub` in `X-User-Id`).
+  /// Claims that are not strings are forwarded as JSON. The headers replace any header with the same name, and they are removed when the claim is missing from the token (or when the request is not authenticated), so the upstream can trust them.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub claim_header_mappings: Vec<JwtAuthClaimHeaderMapping>,
+  /// Specifies how to handle a `Cookie` header that fails to parse, when looking up the token in cookies.
+  /// If not specified, malformed cookies are ignored and the lookup continues with the remaining cookies.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub cookie_parse_failure: Option<JwtAuthCookieParseFailurePolicy>,
+  /// Specifies which value to use when a `header` lookup location is sent multiple times in the same request (for example, two `Authorization` headers).
+  /// If not specified, the first value is used.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub multiple_headers: Option<JwtAuthMultipleHeadersPolicy>,
+  /// A leeway, in seconds, applied when validating the time-based claims (`exp`, and `nbf` if `validate_nbf` is enabled), to account for clock skew between the token issuer and the gateway.
+  /// If not specified, a leeway of 60 seconds is used.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub leeway_secs: Option<u64>,
+  /// If set to `false`, tokens without an [`exp` (expiration time)](https://tools.ietf.org/html/rfc7519#section-4.1.4) claim are accepted, and never expire. The `exp` claim is still validated when it's present.
+  /// If not specified, tokens without an `exp` claim are rejected.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub require_expiration: Option<bool>,
+  /// If set to `true`, tokens with a [`nbf` (not before)](https://tools.ietf.org/html/rfc7519#section-4.1.5) claim in the future are rejected.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub validate_nbf: Option<bool>,
+  /// A list of scopes that must all be granted to the token, otherwise the request is rejected with a `403 Forbidden` response.
+  /// The scopes are read from `scope_claim`, either as a space-delimited string (as in [RFC 8693](https://datatracker.ietf.org/doc/html/rfc8693#section-4.2)) or as an array of strings.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub required_scopes: Option<Vec<String>>,
+  /// The name of the claim that holds the scopes granted to the token.
+  /// If not specified, the `scope` claim is used.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub scope_claim: Option<String>,
+  /// The maximum number of decoded tokens kept in memory, so repeated requests with the same token skip the signature verification.
+  /// Tokens are cached until their `exp`, and the least recently used token is evicted when the cache is full. If not specified, decoded tokens are not cached.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub token_cache_size: Option<usize>,
+  /// A list of revoked tokens, identified by their [`jti` (JWT ID)](https://tools.ietf.org/html/rfc7519#section-4.1.7) claim.
+  /// Revoked tokens are rejected even if they are valid and not expired yet, tokens without a `jti` claim are never considered revoked.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub revoked_tokens: Option<JwtAuthRevocationListConfig>,
+  /// The prefix of the request context keys the plugin stores its values in (for example, `<prefix>:upstream:claims` and `<prefix>:anonymous`).
+  /// Set a distinct prefix for each instance of the plugin, when multiple instances run for the same request. If not specified, `jwt_auth` is used.
+  ///
+  /// Regardless of the prefix, the claims are available to other plugins (for example, the `jwt_claim` key of the `rate_limit` plugin) as the authenticated claims of the request. When multiple instances authenticate the same request, the claims of the first one are used.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub context_key_prefix: Option<String>,
 }
 
 pub fn default_lookup_location() -> Vec<JwtAuthPluginLookupLocation> {
   vec![JwtAuthPluginLookupLocation::Header {
     name: "Authorization".to_string(),
     prefix: Some("Bearer".to_string()),
+    prefix_exact: None,
   }]
 }
 
@@ -91,6 +149,10 @@ pub enum JwtAuthPluginLookupLocation {
   Header {
     name: String,
     prefix: Option<String>,
+    /// If set to `true`, the header value must start with `prefix` exactly.
+    /// Otherwise, a `Bearer` prefix is matched case-insensitively and regardless of the whitespace around it, as clients are not consistent about it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prefix_exact: Option<bool>,
   },
   #[serde(rename = "query_param")]
   #[schemars(title = "query_param")]
@@ -100,10 +162,83 @@ pub enum JwtAuthPluginLookupLocation {
   Cookie { name: String },
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct JwtAuthClaimHeaderMapping {
+  /// The name of the claim in the JWT payload.
+  pub claim: String,
+  /// The name of the header to forward the claim in.
+  pub header: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[serde(tag = "source")]
+pub enum JwtAuthRevocationListConfig {
+  /// A list of revoked `jti` values, specified in the configuration.
+  #[serde(rename = "inline")]
+  #[schemars(title = "inline")]
+  Inline { ids: Vec<String> },
+  /// A local file on the file-system, with a revoked `jti` value on each line. Empty lines and lines starting with `#` are ignored.
+  /// This file is read on startup, and reloaded when its modification time changes.
+  #[serde(rename = "local")]
+  #[schemars(title = "local")]
+  Local {
+    #[serde(rename = "path")]
+    /// A path to a local file on the file-system. Relative to the location of the root configuration file.
+    file: LocalFileReference,
+  },
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema, Default)]
+pub enum JwtAuthAudienceMatch {
+  /// The token is accepted if at least one of its audiences is in `audiences`.
+  #[serde(rename = "any")]
+  #[schemars(title = "any")]
+  #[default]
+  Any,
+  /// The token is accepted only if all of its audiences are in `audiences`.
+  #[serde(rename = "all")]
+  #[schemars(title = "all")]
+  All,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema, Default)]
+pub enum JwtAuthCookieParseFailurePolicy {
+  /// Skip the malformed cookie, and keep looking for the token in the remaining cookies.
+  #[serde(rename = "ignore")]
+  #[schemars(title = "ignore")]
+  #[default]
+  Ignore,
+  /// Reject the request with a `400 Bad Request` response.
+  #[serde(rename = "reject")]
+  #[schemars(title = "reject")]
+  Reject,
+  /// Stop looking in the `Cookie` header, and move on to the next configured lookup location.
+  #[serde(rename = "try_next")]
+  #[schemars(title = "try_next")]
+  TryNext,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema, Default)]
+pub enum JwtAuthMultipleHeadersPolicy {
+  /// Use the first value of the header.
+  #[serde(rename = "use_first")]
+  #[schemars(title = "use_first")]
+  #[default]
+  UseFirst,
+  /// Use the last value of the header.
+  #[serde(rename = "use_last")]
+  #[schemars(title = "use_last")]
+  UseLast,
+  /// Reject the request with a `400 Bad Request` response.
+  #[serde(rename = "reject")]
+  #[schemars(title = "reject")]
+  Reject,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
 #[serde(tag = "source")]
 pub enum JwksProviderSourceConfig {
-  /// A local file on the file-system. This file will be read once on startup and cached.
+  /// A local file on the file-system. This file is read on startup and cached, and reloaded when its modification time changes.
   #[serde(rename = "local")]
   #[schemars(title = "local")]
   Local {
@@ -125,11 +260,90 @@ pub enum JwksProviderSourceConfig {
     #[schemars(with = "String")]
     /// Duration after which the cached JWKS should be expired. If not specified, the default value will be used.
     cache_duration: Option<Duration>,
+    #[serde(
+      deserialize_with = "humantime_serde::deserialize",
+      serialize_with = "humantime_serde::serialize",
+      default
+    )]
+    #[schemars(with = "String")]
+    /// Duration after the expiration of the cached JWKS, during which the expired JWKS is still used while it's refreshed in the background.
+    /// If not specified, requests wait for the JWKS to be refreshed once it's expired.
+    stale_duration: Option<Duration>,
     /// If set to `true`, the JWKS will be fetched on startup and cached. In case of invalid JWKS, the error will be ignored and the plugin will try to fetch again when server receives the first request.
     /// If set to `false`, the JWKS will be fetched on-demand, when the first request comes in.
     prefetch: Option<bool>,
+    #[serde(
+      deserialize_with = "humantime_serde::deserialize",
+      serialize_with = "humantime_serde::serialize",
+      default = "default_min_refresh_interval"
+    )]
+    #[schemars(with = "String")]
+    /// When a token refers to a key that is missing from the cached JWKS (for example, after a key rotation), the JWKS is refreshed before the cache expires.
+    /// This is the minimum duration between two such refreshes, concurrent refreshes are always coalesced into a single fetch.
+    min_refresh_interval: Option<Duration>,
+    #[serde(
+      deserialize_with = "humantime_serde::deserialize",
+      serialize_with = "humantime_serde::serialize",
+      default
+    )]
+    #[schemars(with = "String")]
+    /// When the remote JWKS responds with a `4xx` status code (except `429`), the URL is not expected to serve a key set, so it's not fetched again.
+    /// Set this duration to fetch it again once it elapsed (for example, `1h`), in case the URL starts serving a key set later. If not specified, the URL is not fetched again until the gateway restarts.
+    client_error_retry_interval: Option<Duration>,
+  },
+  /// A secret shared with the token issuer, used to verify tokens signed with an HMAC algorithm (`HS256`, `HS384` or `HS512`).
+  #[serde(rename = "secret")]
+  #[schemars(title = "secret")]
+  Secret {
+    /// The shared secret. Consider using an environment variable (for example: `${JWT_SECRET}`) instead of storing the secret in the config file.
+    secret: String,
+    /// Specifies how the `secret` is encoded. If not specified, the `secret` is used as-is.
+    #[serde(default)]
+    encoding: JwtAuthSecretEncoding,
+    /// The HMAC algorithms of the tokens verified with this secret. If not specified, only `HS256` is allowed.
+    #[serde(default = "default_secret_algorithms")]
+    #[schemars(with = "Vec<String>")]
+    algorithms: Vec<Algorithm>,
+  },
+  /// An [OAuth 2.0 token introspection](https://datatracker.ietf.org/doc/html/rfc7662) endpoint, used to verify opaque tokens that are not JWTs.
+  /// Tokens reported as active are accepted, and the fields of the introspection response are used as the token claims: like the claims of a JWT, they are validated against `issuers`, `audiences` and `required_scopes`.
+  /// A call to the introspection endpoint that doesn't complete within 10 seconds fails.
+  #[serde(rename = "introspection")]
+  #[schemars(title = "introspection")]
+  Introspection {
+    /// The URL of the introspection endpoint.
+    introspection_endpoint: String,
+    /// The client ID used to authenticate with the introspection endpoint, using HTTP Basic authentication.
+    client_id: String,
+    /// The client secret used to authenticate with the introspection endpoint. Consider using an environment variable (for example: `${INTROSPECTION_CLIENT_SECRET}`) instead of storing the secret in the config file.
+    client_secret: String,
+    #[serde(
+      deserialize_with = "humantime_serde::deserialize",
+      serialize_with = "humantime_serde::serialize",
+      default = "default_introspection_cache_duration"
+    )]
+    #[schemars(with = "String")]
+    /// Duration for which the introspection result of a token is cached, so the endpoint is not called on every request. Results are never cached beyond the token's `exp`.
+    cache_duration: Option<Duration>,
   },
 }
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema, Default)]
+pub enum JwtAuthSecretEncoding {
+  /// The secret is used as-is.
+  #[serde(rename = "raw")]
+  #[schemars(title = "raw")]
+  #[default]
+  Raw,
+  /// The secret is base64-encoded, and decoded before use.
+  #[serde(rename = "base64")]
+  #[schemars(title = "base64")]
+  Base64,
+}
+
+fn default_secret_algorithms() -> Vec<Algorithm> {
+  vec![Algorithm::HS256]
+}
 fn default_polling_interval() -> Option<Duration> {
   // Some providers like MS Azure have rate limit configured. So let's use 10 minutes, like Envoy does.
   // and allow users to adjust it if needed.
@@ -137,6 +351,17 @@ fn default_polling_interval() -> Option<Duration> {
   Some(Duration::from_secs(10 * 60))
 }
 
+/// Also used when `min_refresh_interval` is explicitly set to `null`, or the config is built in-memory without it.
+pub(crate) const DEFAULT_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+fn default_min_refresh_interval() -> Option<Duration> {
+  Some(DEFAULT_MIN_REFRESH_INTERVAL)
+}
+
+fn default_introspection_cache_duration() -> Option<Duration> {
+  Some(Duration::from_secs(30))
+}
+
 fn jwt_auth_example_1() -> JsonSchemaExample<JwtAuthPluginConfig> {
   JsonSchemaExample {
     metadata: JsonSchemaExampleMetadata::new(
@@ -156,6 +381,7 @@ fn jwt_auth_example_1() -> JsonSchemaExample<JwtAuthPluginConfig> {
       lookup_locations: vec![JwtAuthPluginLookupLocation::Header {
         name: "Authorization".to_string(),
         prefix: Some("Bearer".to_string()),
+        prefix_exact: None,
       }],
       ..Default::default()
     },
@@ -177,11 +403,15 @@ fn jwt_auth_example_2() -> JsonSchemaExample<JwtAuthPluginConfig> {
       jwks_providers: vec![JwksProviderSourceConfig::Remote {
         url: "https://example.com/jwks.json".to_string(),
         cache_duration: Some(Duration::from_secs(10 * 60)),
+        stale_duration: None,
         prefetch: Some(true),
+        min_refresh_interval: None,
+        client_error_retry_interval: None,
       }],
       lookup_locations: vec![JwtAuthPluginLookupLocation::Header {
         name: "Authorization".to_string(),
         prefix: Some("Bearer".to_string()),
+        prefix_exact: None,
       }],
       ..Default::default()
     },
@@ -203,7 +433,10 @@ fn jwt_auth_example_3() -> JsonSchemaExample<JwtAuthPluginConfig> {
       jwks_providers: vec![JwksProviderSourceConfig::Remote {
         url: "https://example.com/jwks.json".to_string(),
         cache_duration: Some(Duration::from_secs(10 * 60)),
+        stale_duration: None,
         prefetch: Some(true),
+        min_refresh_interval: None,
+        client_error_retry_interval: None,
       }],
       lookup_locations: vec![JwtAuthPluginLookupLocation::Cookie {
         name: "auth".to_string(),
@@ -229,7 +462,10 @@ fn jwt_auth_example_4() -> JsonSchemaExample<JwtAuthPluginConfig> {
       jwks_providers: vec![JwksProviderSourceConfig::Remote {
         url: "https://example.com/jwks.json".to_string(),
         cache_duration: Some(Duration::from_secs(10 * 60)),
+        stale_duration: None,
         prefetch: Some(true),
+        min_refresh_interval: None,
+        client_error_retry_interval: None,
       }],
       lookup_locations: vec![JwtAuthPluginLookupLocation::Cookie {
         name: "jwt".to_string(),
@@ -257,7 +493,10 @@ fn jwt_auth_example_5() -> JsonSchemaExample<JwtAuthPluginConfig> {
       jwks_providers: vec![JwksProviderSourceConfig::Remote {
         url: "https://example.com/jwks.json".to_string(),
         cache_duration: Some(Duration::from_secs(10 * 60)),
+        stale_duration: None,
         prefetch: None,
+        min_refresh_interval: None,
+        client_error_retry_interval: None,
       }],
       lookup_locations: vec![JwtAuthPluginLookupLocation::Cookie {
         name: "jwt".to_string(),
@@ -268,3 +507,58 @@ fn jwt_auth_example_5() -> JsonSchemaExample<JwtAuthPluginConfig> {
     },
   }
 }
+
+fn jwt_auth_example_6() -> JsonSchemaExample<JwtAuthPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Shared Secret",
+      Some(
+        "This example verifies `HS256` tokens signed with a shared secret, that is loaded from the `JWT_SECRET` environment variable.",
+      ),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "jwt_auth".to_string(),
+    }),
+    example: JwtAuthPluginConfig {
+      jwks_providers: vec![JwksProviderSourceConfig::Secret {
+        secret: "${JWT_SECRET}".to_string(),
+        encoding: JwtAuthSecretEncoding::Raw,
+        algorithms: vec![Algorithm::HS256],
+      }],
+      lookup_locations: vec![JwtAuthPluginLookupLocation::Header {
+        name: "Authorization".to_string(),
+        prefix: Some("Bearer".to_string()),
+        prefix_exact: None,
+      }],
+      ..Default::default()
+    },
+  }
+}
+
+fn jwt_auth_example_7() -> JsonSchemaExample<JwtAuthPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Token Introspection",
+      Some(
+        "This example verifies opaque tokens with an OAuth 2.0 token introspection endpoint. The client secret is loaded from the `INTROSPECTION_CLIENT_SECRET` environment variable.",
+      ),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "jwt_auth".to_string(),
+    }),
+    example: JwtAuthPluginConfig {
+      jwks_providers: vec![JwksProviderSourceConfig::Introspection {
+        introspection_endpoint: "https://example.com/oauth2/introspect".to_string(),
+        client_id: "conductor".to_string(),
+        client_secret: "${INTROSPECTION_CLIENT_SECRET}".to_string(),
+        cache_duration: Some(Duration::from_secs(30)),
+      }],
+      lookup_locations: vec![JwtAuthPluginLookupLocation::Header {
+        name: "Authorization".to_string(),
+        prefix: Some("Bearer".to_string()),
+        prefix_exact: None,
+      }],
+      ..Default::default()
+    },
+  }
+}
@@ -0,0 +1,147 @@
+use std::{collections::HashMap, fmt::Debug, sync::RwLock, time::Duration};
+
+use reqwest::StatusCode;
+use serde_json::{Map, Value};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Used when the introspection source configuration doesn't specify `cache_duration`.
+const DEFAULT_CACHE_DURATION: Duration = Duration::from_secs(30);
+/// Bounds the memory used by the cache, as every distinct token sent to the gateway ends up in it.
+const MAX_CACHED_RESULTS: usize = 10_000;
+/// The maximum time to wait for the introspection endpoint, so requests don't hang when it doesn't respond.
+#[cfg(not(target_arch = "wasm32"))]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, Debug)]
+pub enum IntrospectionError {
+  #[error("failed to call the introspection endpoint: {0}")]
+  NetworkError(reqwest::Error),
+  #[error("introspection endpoint responded with unexpected status code: {0}")]
+  UnexpectedStatusCode(StatusCode),
+  #[error("failed to parse the introspection response: {0}")]
+  InvalidResponse(serde_json::Error),
+  #[error("token is not active")]
+  InactiveToken,
+}
+
+#[derive(Debug)]
+struct CachedResult {
+  /// The introspection response of an active token, `None` if the token is not active.
+  claims: Option<Value>,
+  expires_at: SystemTime,
+}
+
+/// Verifies opaque tokens with an [RFC 7662](https://datatracker.ietf.org/doc/html/rfc7662) token introspection endpoint.
+pub struct TokenIntrospection {
+  endpoint: String,
+  client_id: String,
+  client_secret: String,
+  cache_duration: Duration,
+  client: reqwest::Client,
+  cache: RwLock<HashMap<String, CachedResult>>,
+}
+
+// The client secret is never printed.
+impl Debug for TokenIntrospection {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("TokenIntrospection")
+      .field("endpoint", &self.endpoint)
+      .field("client_id", &self.client_id)
+      .field("cache_duration", &self.cache_duration)
+      .finish_non_exhaustive()
+  }
+}
+
+impl TokenIntrospection {
+  pub fn new(
+    endpoint: &str,
+    client_id: &str,
+    client_secret: &str,
+    cache_duration: Option<Duration>,
+  ) -> Result<Self, reqwest::Error> {
+    let client = wasm_polyfills::create_http_client();
+    #[cfg(not(target_arch = "wasm32"))]
+    let client = client.timeout(REQUEST_TIMEOUT);
+
+    Ok(Self {
+      endpoint: endpoint.to_string(),
+      client_id: client_id.to_string(),
+      client_secret: client_secret.to_string(),
+      cache_duration: cache_duration.unwrap_or(DEFAULT_CACHE_DURATION),
+      client: client.build()?,
+      cache: RwLock::new(HashMap::new()),
+    })
+  }
+
+  /// Returns the introspection response of an active token, which is used as the token claims.
+  pub async fn introspect(&self, token: &str) -> Result<Value, IntrospectionError> {
+    if let Some(claims) = self.cached(token) {
+      return claims.ok_or(IntrospectionError::InactiveToken);
+    }
+
+    let response = self
+      .client
+      .post(&self.endpoint)
+      .basic_auth(&self.client_id, Some(&self.client_secret))
+      .form(&[("token", token), ("token_type_hint", "access_token")])
+      .send()
+      .await
+      .map_err(IntrospectionError::NetworkError)?;
+
+    if !response.status().is_success() {
+      return Err(IntrospectionError::UnexpectedStatusCode(response.status()));
+    }
+
+    let response_text = response
+      .text()
+      .await
+      .map_err(IntrospectionError::NetworkError)?;
+    let response = serde_json::from_str::<Map<String, Value>>(&response_text)
+      .map_err(IntrospectionError::InvalidResponse)?;
+
+    let claims = match response.get("active").and_then(Value::as_bool) {
+      Some(true) => Some(Value::Object(response)),
+      _ => None,
+    };
+    self.store(token, claims.clone());
+
+    claims.ok_or(IntrospectionError::InactiveToken)
+  }
+
+  fn cached(&self, token: &str) -> Option<Option<Value>> {
+    let cache = self.cache.read().ok()?;
+    let cached = cache.get(token)?;
+
+    match cached.expires_at > SystemTime::now() {
+      true => Some(cached.claims.clone()),
+      false => None,
+    }
+  }
+
+  fn store(&self, token: &str, claims: Option<Value>) {
+    let now = SystemTime::now();
+    let mut expires_at = now + self.cache_duration;
+
+    // An active token must not outlive its expiration because of the cache.
+    if let Some(exp) = claims
+      .as_ref()
+      .and_then(|claims| claims.get("exp"))
+      .and_then(Value::as_u64)
+    {
+      expires_at = expires_at.min(UNIX_EPOCH + Duration::from_secs(exp));
+    }
+
+    let mut cache = match self.cache.write() {
+      Ok(cache) => cache,
+      Err(_) => return,
+    };
+
+    if cache.len() >= MAX_CACHED_RESULTS {
+      cache.retain(|_, cached| cached.expires_at > now);
+    }
+
+    if cache.len() < MAX_CACHED_RESULTS {
+      cache.insert(token.to_string(), CachedResult { claims, expires_at });
+    }
+  }
+}
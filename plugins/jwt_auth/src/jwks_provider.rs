@@ -1,18 +1,68 @@
 use std::{
-  sync::{Arc, RwLock},
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, RwLock,
+  },
   time::Duration,
 };
 
-use web_time::SystemTime;
+use futures::lock::Mutex;
+use web_time::{Instant, SystemTime};
 
 use jsonwebtoken::jwk::JwkSet;
+use reqwest::{header::RETRY_AFTER, StatusCode};
 
-use crate::config::JwksProviderSourceConfig;
+use crate::config::{JwksProviderSourceConfig, DEFAULT_MIN_REFRESH_INTERVAL};
+use conductor_common::serde_utils::LocalFileReference;
+
+/// Initial delay used when retrying after a network error, doubled on every consecutive failure.
+const NETWORK_ERROR_BASE_BACKOFF: Duration = Duration::from_secs(1);
+/// Upper bound for the delay between two fetch attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
 
 #[derive(Debug)]
 pub struct JwksProvider {
   config: JwksProviderSourceConfig,
   jwk: RwLock<Option<Arc<TimedJwtSet>>>,
+  backoff: RwLock<Option<FetchBackoff>>,
+  /// Held while fetching, so concurrent callers wait for the in-flight fetch instead of starting their own.
+  fetch_lock: Mutex<()>,
+  /// The time the last fetch attempt completed, successfully or not.
+  last_fetch: RwLock<Option<Instant>>,
+  /// The modification time of a local source file, at the time it was loaded. `None` if the file was never read from the file-system.
+  local_modified: RwLock<Option<std::time::SystemTime>>,
+  /// Set while a background refresh of a stale set is in flight.
+  refreshing: AtomicBool,
+  cache_hits: AtomicU64,
+  cache_refreshes: AtomicU64,
+}
+
+/// Counts how the requests for the key set were served.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct JwksCacheStats {
+  /// Requests served with the cached set, including stale sets that were refreshed in the background.
+  pub hits: u64,
+  /// Fetches of the set from its source, successful or not.
+  pub refreshes: u64,
+}
+
+#[derive(Debug, PartialEq)]
+enum CacheState {
+  Fresh,
+  /// Expired, but still within `stale_duration`.
+  Stale,
+  Expired,
+}
+
+/// Tracks the state of a remote JWKS source that failed to respond with a valid set.
+#[derive(Debug, Clone)]
+struct FetchBackoff {
+  /// The next point in time we are allowed to hit the remote source again, `None` if it's not fetched again.
+  retry_at: Option<SystemTime>,
+  /// The amount of consecutive failed attempts.
+  attempts: u32,
+  /// The status code returned by the last attempt, if the server responded at all.
+  status: Option<StatusCode>,
 }
 
 #[derive(Debug)]
@@ -25,6 +75,29 @@ impl TimedJwtSet {
   pub fn get_jwk(&self) -> &JwkSet {
     &self.set
   }
+
+  pub fn key_summaries(&self) -> Vec<JwkSummary> {
+    self
+      .set
+      .keys
+      .iter()
+      .map(|jwk| JwkSummary {
+        kid: jwk.common.key_id.clone(),
+        algorithm: jwk
+          .common
+          .key_algorithm
+          .as_ref()
+          .map(|algorithm| format!("{:?}", algorithm)),
+      })
+      .collect()
+  }
+}
+
+/// Identifies a loaded key without exposing its key material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwkSummary {
+  pub kid: Option<String>,
+  pub algorithm: Option<String>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -35,6 +108,65 @@ pub enum JwksProviderError {
   JwksContentInvalidStructure(serde_json::Error),
   #[error("failed to acquire access to jwk handle")]
   FailedToAcquireJwk,
+  #[error("remote jwks responded with unexpected status code: {0}")]
+  UnexpectedStatusCode(StatusCode),
+  #[error("remote jwks is not available, next attempt is allowed in {0:?}")]
+  FetchBackoff(Duration),
+  #[error("remote jwks responded with status code {0}, it's not fetched again")]
+  ClientError(StatusCode),
+  #[error("remote jwks responded with status code {0}, next attempt is allowed in {1:?}")]
+  ClientErrorBackoff(StatusCode, Duration),
+  #[error("failed to read local jwks file \"{0}\": {1}")]
+  LocalFileReadError(String, std::io::Error),
+  #[error("failed to parse local jwks file \"{0}\": {1}")]
+  LocalFileInvalidStructure(String, serde_json::Error),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn local_file_modified(path: &str) -> std::io::Result<std::time::SystemTime> {
+  std::fs::metadata(path).and_then(|metadata| metadata.modified())
+}
+
+/// Reads the current contents of a local source file, along with its modification time.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_local_file(
+  file: &LocalFileReference,
+) -> Result<(String, Option<std::time::SystemTime>), JwksProviderError> {
+  let modified = local_file_modified(&file.path)
+    .map_err(|e| JwksProviderError::LocalFileReadError(file.path.clone(), e))?;
+  let contents = std::fs::read_to_string(&file.path)
+    .map_err(|e| JwksProviderError::LocalFileReadError(file.path.clone(), e))?;
+
+  Ok((contents, Some(modified)))
+}
+
+/// There is no file-system on wasm32, so the contents loaded with the configuration are used.
+#[cfg(target_arch = "wasm32")]
+fn read_local_file(
+  file: &LocalFileReference,
+) -> Result<(String, Option<std::time::SystemTime>), JwksProviderError> {
+  Ok((file.contents.clone(), None))
+}
+
+/// Parses the `Retry-After` header, only the delay-seconds form is supported.
+fn parse_retry_after(response: &reqwest::Response) -> Option<Duration> {
+  response
+    .headers()
+    .get(RETRY_AFTER)
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.trim().parse::<u64>().ok())
+    .map(|secs| Duration::from_secs(secs).min(MAX_BACKOFF))
+}
+
+/// A `4xx` status code other than `429`, the remote source is not expected to serve a set when fetched again.
+fn is_client_error(status: StatusCode) -> bool {
+  status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS
+}
+
+fn network_error_backoff(attempts: u32) -> Duration {
+  NETWORK_ERROR_BASE_BACKOFF
+    .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+    .min(MAX_BACKOFF)
 }
 
 impl JwksProvider {
@@ -43,45 +175,233 @@ impl JwksProvider {
       JwksProviderSourceConfig::Remote {
         url,
         cache_duration,
-        prefetch: _,
+        ..
       } => {
         // @expected: if initiating an http client fails, then we have to exit.
         let client = wasm_polyfills::create_http_client().build().unwrap();
         tracing::debug!("loading jwks for a remote source: {}", url);
 
-        let response_text = client
-          .get(url)
-          .send()
-          .await
-          .map_err(JwksProviderError::RemoteJwksNetworkError)?
-          .text()
-          .await
-          .map_err(JwksProviderError::RemoteJwksNetworkError)?;
+        let response = match client.get(url).send().await {
+          Ok(response) => response,
+          Err(e) => {
+            let attempts = self.record_failure(None, None);
+            tracing::warn!(
+              "failed to fetch remote jwks from {} (attempt {}): {}",
+              url,
+              attempts,
+              e
+            );
+
+            return Err(JwksProviderError::RemoteJwksNetworkError(e));
+          }
+        };
+
+        let status = response.status();
+
+        if !status.is_success() {
+          let retry_after = match status {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+              parse_retry_after(&response)
+            }
+            _ => None,
+          };
+          let attempts = self.record_failure(Some(status), retry_after);
+          tracing::warn!(
+            "remote jwks at {} responded with status code {} (attempt {})",
+            url,
+            status,
+            attempts
+          );
+
+          return Err(JwksProviderError::UnexpectedStatusCode(status));
+        }
+
+        let response_text = match response.text().await {
+          Ok(response_text) => response_text,
+          Err(e) => {
+            let attempts = self.record_failure(None, None);
+            tracing::warn!(
+              "failed to read remote jwks from {} (attempt {}): {}",
+              url,
+              attempts,
+              e
+            );
+
+            return Err(JwksProviderError::RemoteJwksNetworkError(e));
+          }
+        };
         let expiration =
           SystemTime::now().checked_add(cache_duration.unwrap_or(Duration::from_secs(10 * 60)));
-        let set = serde_json::from_str::<JwkSet>(&response_text)
-          .map_err(JwksProviderError::JwksContentInvalidStructure)?;
+        let set = match serde_json::from_str::<JwkSet>(&response_text) {
+          Ok(set) => set,
+          Err(e) => {
+            let attempts = self.record_failure(None, None);
+            tracing::warn!(
+              "remote jwks at {} responded with an invalid set (attempt {}): {}",
+              url,
+              attempts,
+              e
+            );
+
+            return Err(JwksProviderError::JwksContentInvalidStructure(e));
+          }
+        };
 
         TimedJwtSet { expiration, set }
       }
-      JwksProviderSourceConfig::Local { file } => TimedJwtSet {
-        expiration: None,
-        set: serde_json::from_str::<JwkSet>(&file.contents)
-          .map_err(JwksProviderError::JwksContentInvalidStructure)?,
-      },
+      JwksProviderSourceConfig::Local { file } => {
+        let loaded_from_file = self
+          .local_modified
+          .read()
+          .is_ok_and(|local_modified| local_modified.is_some());
+        let (contents, modified) = match read_local_file(file) {
+          Ok(result) => result,
+          // The configuration might not originate from the file-system (for example, when it's built in-memory),
+          // so the contents loaded with it are used.
+          Err(e) if !loaded_from_file => {
+            tracing::debug!("using the configured contents of {}: {}", file.path, e);
+
+            (file.contents.clone(), None)
+          }
+          Err(e) => return Err(e),
+        };
+        let set = serde_json::from_str::<JwkSet>(&contents)
+          .map_err(|e| JwksProviderError::LocalFileInvalidStructure(file.path.clone(), e))?;
+
+        if let Ok(mut w_local_modified) = self.local_modified.write() {
+          *w_local_modified = modified;
+        }
+
+        TimedJwtSet {
+          expiration: None,
+          set,
+        }
+      }
+      // Shared secrets and opaque tokens are verified by the plugin directly, they don't have a key set.
+      JwksProviderSourceConfig::Secret { .. } | JwksProviderSourceConfig::Introspection { .. } => {
+        TimedJwtSet {
+          expiration: None,
+          set: JwkSet { keys: vec![] },
+        }
+      }
     }));
 
+    if let Some(new_jwk) = &new_jwk {
+      tracing::debug!(
+        "loaded jwks from {}: {:?}",
+        self.source_name(),
+        new_jwk.key_summaries()
+      );
+    }
+
     if let Ok(mut w_jwk) = self.jwk.write() {
       *w_jwk = new_jwk;
     }
 
+    if let Ok(mut w_backoff) = self.backoff.write() {
+      *w_backoff = None;
+    }
+
     Ok(self)
   }
 
+  /// Loads the set, and records the completion time of the attempt.
+  async fn fetch(&self) -> Result<&Self, JwksProviderError> {
+    self.cache_refreshes.fetch_add(1, Ordering::Relaxed);
+    let result = self.load_jwks().await;
+
+    if let Ok(mut w_last_fetch) = self.last_fetch.write() {
+      *w_last_fetch = Some(Instant::now());
+    }
+
+    tracing::debug!(
+      "jwks cache of {}: {:?}",
+      self.source_name(),
+      self.cache_stats()
+    );
+
+    result
+  }
+
+  /// Records a failed fetch attempt, and computes when the next attempt is allowed:
+  /// - `429`/`503` responses are retried after `Retry-After` (or with backoff, if it's missing).
+  /// - Other `4xx` responses are not retried, unless `client_error_retry_interval` is configured.
+  /// - Network errors, invalid sets and other statuses are retried with an exponential backoff.
+  fn record_failure(&self, status: Option<StatusCode>, retry_after: Option<Duration>) -> u32 {
+    let mut w_backoff = match self.backoff.write() {
+      Ok(w_backoff) => w_backoff,
+      Err(_) => return 0,
+    };
+
+    let attempts = w_backoff.as_ref().map_or(0, |b| b.attempts) + 1;
+    let retry_in = match status {
+      Some(StatusCode::TOO_MANY_REQUESTS) | Some(StatusCode::SERVICE_UNAVAILABLE) => {
+        Some(retry_after.unwrap_or(network_error_backoff(attempts)))
+      }
+      Some(status) if is_client_error(status) => self.client_error_retry_interval(),
+      _ => Some(network_error_backoff(attempts)),
+    };
+    let retry_at = retry_in.and_then(|retry_in| SystemTime::now().checked_add(retry_in));
+
+    *w_backoff = Some(FetchBackoff {
+      retry_at,
+      attempts,
+      status,
+    });
+
+    attempts
+  }
+
+  /// Checks if the remote source is currently in a backoff period, returns the matching error if so.
+  fn check_backoff(&self) -> Result<(), JwksProviderError> {
+    if let Ok(backoff) = self.backoff.try_read() {
+      if let Some(backoff) = backoff.as_ref() {
+        let remaining = backoff.retry_at.map(|retry_at| {
+          retry_at
+            .duration_since(SystemTime::now())
+            .unwrap_or_default()
+        });
+        let client_error = backoff.status.filter(|status| is_client_error(*status));
+
+        return match (remaining, client_error) {
+          (None, Some(status)) => Err(JwksProviderError::ClientError(status)),
+          (Some(remaining), Some(status)) if !remaining.is_zero() => {
+            Err(JwksProviderError::ClientErrorBackoff(status, remaining))
+          }
+          (Some(remaining), None) if !remaining.is_zero() => {
+            Err(JwksProviderError::FetchBackoff(remaining))
+          }
+          _ => Ok(()),
+        };
+      }
+    }
+
+    Ok(())
+  }
+
+  fn client_error_retry_interval(&self) -> Option<Duration> {
+    match &self.config {
+      JwksProviderSourceConfig::Remote {
+        client_error_retry_interval,
+        ..
+      } => *client_error_retry_interval,
+      JwksProviderSourceConfig::Local { .. }
+      | JwksProviderSourceConfig::Secret { .. }
+      | JwksProviderSourceConfig::Introspection { .. } => None,
+    }
+  }
+
   pub fn new(config: JwksProviderSourceConfig) -> Self {
     Self {
       config,
       jwk: RwLock::new(None),
+      backoff: RwLock::new(None),
+      fetch_lock: Mutex::new(()),
+      last_fetch: RwLock::new(None),
+      local_modified: RwLock::new(None),
+      refreshing: AtomicBool::new(false),
+      cache_hits: AtomicU64::new(0),
+      cache_refreshes: AtomicU64::new(0),
     }
   }
 
@@ -98,7 +418,9 @@ impl JwksProvider {
         }
         None => false,
       },
-      JwksProviderSourceConfig::Local { .. } => false,
+      JwksProviderSourceConfig::Local { .. }
+      | JwksProviderSourceConfig::Secret { .. }
+      | JwksProviderSourceConfig::Introspection { .. } => false,
     }
   }
 
@@ -109,29 +431,213 @@ impl JwksProvider {
         Some(prefetch) => *prefetch,
         None => false,
       },
-      JwksProviderSourceConfig::Local { .. } => false,
+      JwksProviderSourceConfig::Local { .. }
+      | JwksProviderSourceConfig::Secret { .. }
+      | JwksProviderSourceConfig::Introspection { .. } => false,
     }
   }
 
-  fn needs_refetch(&self) -> bool {
-    if let Ok(jwk) = self.jwk.try_read() {
-      return match jwk.as_ref() {
-        Some(jwk) => match jwk.expiration {
-          Some(expiration) => SystemTime::now() > expiration,
-          None => false,
-        },
-        None => true,
-      };
+  fn cache_state(&self) -> CacheState {
+    if self.local_file_changed() {
+      return CacheState::Expired;
+    }
+
+    let expiration = match self.jwk.try_read() {
+      Ok(jwk) => match jwk.as_ref() {
+        Some(jwk) => jwk.expiration,
+        None => return CacheState::Expired,
+      },
+      Err(_) => return CacheState::Expired,
+    };
+    let now = SystemTime::now();
+
+    match expiration {
+      Some(expiration) if now > expiration => {
+        let stale_until = self
+          .stale_duration()
+          .and_then(|stale_duration| expiration.checked_add(stale_duration));
+
+        match stale_until {
+          Some(stale_until) if now <= stale_until => CacheState::Stale,
+          _ => CacheState::Expired,
+        }
+      }
+      _ => CacheState::Fresh,
+    }
+  }
+
+  fn stale_duration(&self) -> Option<Duration> {
+    match &self.config {
+      JwksProviderSourceConfig::Remote { stale_duration, .. } => *stale_duration,
+      JwksProviderSourceConfig::Local { .. }
+      | JwksProviderSourceConfig::Secret { .. }
+      | JwksProviderSourceConfig::Introspection { .. } => None,
+    }
+  }
+
+  pub fn cache_stats(&self) -> JwksCacheStats {
+    JwksCacheStats {
+      hits: self.cache_hits.load(Ordering::Relaxed),
+      refreshes: self.cache_refreshes.load(Ordering::Relaxed),
+    }
+  }
+
+  /// Checks if a local source file was modified since it was read. A file that can't be accessed anymore is considered
+  /// modified, so the error is reported by the next load.
+  #[cfg(not(target_arch = "wasm32"))]
+  fn local_file_changed(&self) -> bool {
+    match &self.config {
+      JwksProviderSourceConfig::Local { file } => match self.local_modified.try_read() {
+        Ok(local_modified) => local_modified.is_some_and(|local_modified| {
+          local_file_modified(&file.path).ok() != Some(local_modified)
+        }),
+        Err(_) => false,
+      },
+      JwksProviderSourceConfig::Remote { .. }
+      | JwksProviderSourceConfig::Secret { .. }
+      | JwksProviderSourceConfig::Introspection { .. } => false,
+    }
+  }
+
+  #[cfg(target_arch = "wasm32")]
+  fn local_file_changed(&self) -> bool {
+    false
+  }
+
+  fn source_name(&self) -> &str {
+    match &self.config {
+      JwksProviderSourceConfig::Remote { url, .. } => url,
+      JwksProviderSourceConfig::Local { file } => &file.path,
+      JwksProviderSourceConfig::Secret { .. } => "shared secret",
+      JwksProviderSourceConfig::Introspection {
+        introspection_endpoint,
+        ..
+      } => introspection_endpoint,
+    }
+  }
+
+  /// The key ids and algorithms of the currently loaded set, empty if no set was loaded yet.
+  pub fn loaded_keys(&self) -> Vec<JwkSummary> {
+    self
+      .current_jwk_set()
+      .map(|jwk| jwk.key_summaries())
+      .unwrap_or_default()
+  }
+
+  fn has_jwk_set(&self) -> bool {
+    self.jwk.try_read().is_ok_and(|jwk| jwk.is_some())
+  }
+
+  pub async fn retrieve_jwk_set(self: &Arc<Self>) -> Result<Arc<TimedJwtSet>, JwksProviderError> {
+    match self.cache_state() {
+      CacheState::Fresh => {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+      }
+      CacheState::Stale => {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        self.refresh_in_background();
+      }
+      CacheState::Expired => self.fetch_expired().await?,
+    }
+
+    self.current_jwk_set()
+  }
+
+  async fn fetch_expired(&self) -> Result<(), JwksProviderError> {
+    let _fetch_guard = self.fetch_lock.lock().await;
+
+    // Another caller might have fetched the set while we were waiting for the lock.
+    if self.cache_state() == CacheState::Expired {
+      match self.check_backoff() {
+        Ok(_) => {
+          self.fetch().await?;
+        }
+        // While the remote source is backing off, we can keep using the previous set, if we have one.
+        Err(e) if !self.has_jwk_set() => return Err(e),
+        Err(e) => tracing::debug!("using previously fetched jwks: {}", e),
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Refreshes a stale set without blocking the caller, that keeps using the stale set in the meantime.
+  fn refresh_in_background(self: &Arc<Self>) {
+    if self.refreshing.swap(true, Ordering::AcqRel) {
+      return;
     }
 
-    true
+    let provider = self.clone();
+    let refresh = async move {
+      {
+        let _fetch_guard = provider.fetch_lock.lock().await;
+
+        if provider.cache_state() != CacheState::Fresh && provider.check_backoff().is_ok() {
+          if let Err(e) = provider.fetch().await {
+            tracing::warn!(
+              "failed to refresh stale jwks from {}: {}",
+              provider.source_name(),
+              e
+            );
+          }
+        }
+      }
+
+      provider.refreshing.store(false, Ordering::Release);
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    wasm_polyfills::spawn_local(refresh);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::spawn(refresh);
   }
 
-  pub async fn retrieve_jwk_set(&self) -> Result<Arc<TimedJwtSet>, JwksProviderError> {
-    if self.needs_refetch() {
-      self.load_jwks().await?;
+  /// Forces a refresh of a remote set, even if the cached one is not expired yet. This is used when a token refers to a key
+  /// that is missing from the cached set, for example right after the keys were rotated.
+  ///
+  /// Concurrent calls share a single fetch, and forced refreshes are not performed more often than `min_refresh_interval`.
+  pub async fn refresh_jwk_set(self: &Arc<Self>) -> Result<Arc<TimedJwtSet>, JwksProviderError> {
+    let min_refresh_interval = match &self.config {
+      JwksProviderSourceConfig::Remote {
+        min_refresh_interval,
+        ..
+      } => min_refresh_interval.unwrap_or(DEFAULT_MIN_REFRESH_INTERVAL),
+      JwksProviderSourceConfig::Local { .. }
+      | JwksProviderSourceConfig::Secret { .. }
+      | JwksProviderSourceConfig::Introspection { .. } => return self.retrieve_jwk_set().await,
+    };
+
+    let requested_at = Instant::now();
+    let _fetch_guard = self.fetch_lock.lock().await;
+    let last_fetch = self
+      .last_fetch
+      .read()
+      .ok()
+      .and_then(|last_fetch| *last_fetch);
+
+    match last_fetch {
+      // A fetch that completed while we were waiting for the lock already picked up the latest set.
+      Some(last_fetch) if last_fetch >= requested_at => {}
+      Some(last_fetch) if last_fetch.elapsed() < min_refresh_interval => {
+        tracing::debug!(
+          "skipping jwks refresh, last fetch was {:?} ago",
+          last_fetch.elapsed()
+        );
+      }
+      _ => match self.check_backoff() {
+        Ok(_) => {
+          self.fetch().await?;
+        }
+        Err(e) if !self.has_jwk_set() => return Err(e),
+        Err(e) => tracing::debug!("using previously fetched jwks: {}", e),
+      },
     }
 
+    self.current_jwk_set()
+  }
+
+  fn current_jwk_set(&self) -> Result<Arc<TimedJwtSet>, JwksProviderError> {
     if let Ok(jwk) = self.jwk.try_read() {
       if let Some(jwk) = jwk.as_ref() {
         return Ok(jwk.clone());
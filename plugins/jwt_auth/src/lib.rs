@@ -1,13 +1,23 @@
 mod config;
+mod introspection;
 mod jwks_provider;
 mod plugin;
+mod revocation;
+mod shared_secret;
+mod token_cache;
 
 #[cfg(test)]
 mod test;
 
 pub use crate::config::JwksProviderSourceConfig as JwksProvider;
+pub use crate::config::JwtAuthAudienceMatch as AudienceMatch;
+pub use crate::config::JwtAuthClaimHeaderMapping as ClaimHeaderMapping;
+pub use crate::config::JwtAuthCookieParseFailurePolicy as CookieParseFailurePolicy;
+pub use crate::config::JwtAuthMultipleHeadersPolicy as MultipleHeadersPolicy;
 pub use crate::config::JwtAuthPluginConfig as Config;
 pub use crate::config::JwtAuthPluginLookupLocation as LookupLocation;
+pub use crate::config::JwtAuthRevocationListConfig as RevocationList;
+pub use crate::config::JwtAuthSecretEncoding as SecretEncoding;
 pub use crate::plugin::JwtAuthPlugin as Plugin;
 pub use jsonwebtoken::{decode, encode, Algorithm, EncodingKey, Header as JwtHeader};
 pub use serde_json::Value as ClaimsJsonObject;
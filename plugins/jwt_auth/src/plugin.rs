@@ -1,25 +1,34 @@
-use std::str::FromStr;
+use std::{collections::HashMap, sync::Arc};
 
 use conductor_common::{
-  execute::RequestExecutionContext,
+  execute::{AuthenticatedClaimsKey, RequestExecutionContext},
   graphql::GraphQLResponse,
-  http::{parse_query_string, ConductorHttpRequest, StatusCode},
+  http::{
+    header::WWW_AUTHENTICATE, parse_query_string, ConductorHttpRequest, HttpHeadersMap, StatusCode,
+  },
   plugin::{CreatablePlugin, Plugin, PluginError},
 };
 use cookie::Cookie;
 use futures::future::join_all;
 use jsonwebtoken::{
   decode, decode_header,
-  jwk::{Jwk, JwkSet},
+  jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm},
   Algorithm, DecodingKey, Header, TokenData, Validation,
 };
-use reqwest::header::{HeaderName, HeaderValue, ToStrError, COOKIE};
+use reqwest::header::{HeaderName, HeaderValue, InvalidHeaderName, ToStrError, COOKIE};
 use serde_json::Value;
 use tracing::{error, warn};
 
 use crate::{
-  config::{JwtAuthPluginConfig, JwtAuthPluginLookupLocation},
-  jwks_provider::JwksProvider,
+  config::{
+    JwksProviderSourceConfig, JwtAuthAudienceMatch, JwtAuthCookieParseFailurePolicy,
+    JwtAuthMultipleHeadersPolicy, JwtAuthPluginConfig, JwtAuthPluginLookupLocation,
+  },
+  introspection::TokenIntrospection,
+  jwks_provider::{JwksProvider, TimedJwtSet},
+  revocation::RevocationList,
+  shared_secret::{SharedSecret, SharedSecretError},
+  token_cache::TokenCache,
 };
 
 type TokenPayload = TokenData<Value>;
@@ -27,30 +36,53 @@ type TokenPayload = TokenData<Value>;
 #[derive(Debug)]
 pub struct JwtAuthPlugin {
   config: JwtAuthPluginConfig,
-  providers: Vec<JwksProvider>,
+  providers: Vec<Arc<JwksProvider>>,
+  secrets: Vec<SharedSecret>,
+  introspections: Vec<TokenIntrospection>,
+  token_cache: Option<TokenCache>,
+  revocation_list: Option<RevocationList>,
+  /// The `claim_header_mappings`, with their header names parsed.
+  claim_headers: Vec<(String, HeaderName)>,
 }
 
-static CLAIMS_CONTEXT_KEY: &str = "jwt_auth:upstream:claims";
-static TOKEN_CONTEXT_KEY: &str = "jwt_auth:upstream:token";
+static DEFAULT_CONTEXT_KEY_PREFIX: &str = "jwt_auth";
+static CLAIMS_CONTEXT_KEY: &str = "upstream:claims";
+static TOKEN_CONTEXT_KEY: &str = "upstream:token";
+static ANONYMOUS_CONTEXT_KEY: &str = "anonymous";
+static DEFAULT_SCOPE_CLAIM: &str = "scope";
+static BEARER_PREFIX: &str = "Bearer";
+
+/// The token that authenticated the request, by the `context_key_prefix` of the instance of the plugin that authenticated it.
+///
+/// Its claims are only stored under the string key, so the changes made by VRL and Rhai scripts are forwarded to the upstream.
+#[derive(Debug)]
+struct AuthenticatedTokens(HashMap<String, String>);
 
 #[derive(Debug, thiserror::Error)]
 pub enum LookupError {
-  #[error("failed to locate the value in the incoming request")]
-  LookupFailed,
+  /// Holds the errors of the lookup locations that were found but couldn't be used. It's empty when the value is not present at all.
+  #[error("failed to locate the value in the incoming request: {0:?}")]
+  LookupFailed(Vec<LookupError>),
   #[error("prefix does not match the found value")]
   MismatchedPrefix,
   #[error("failed to convert header to string")]
   FailedToStringifyHeader(ToStrError),
+  #[error("failed to parse cookie: {0}")]
+  MalformedCookie(String),
+  #[error("multiple values found for header {0}")]
+  MultipleHeaderValues(String),
 }
 
 impl PartialEq for LookupError {
   fn eq(&self, other: &Self) -> bool {
     match (self, other) {
-      (Self::LookupFailed, Self::LookupFailed) => true,
+      (Self::LookupFailed(e1), Self::LookupFailed(e2)) => e1 == e2,
       (Self::MismatchedPrefix, Self::MismatchedPrefix) => true,
       (Self::FailedToStringifyHeader(s1), Self::FailedToStringifyHeader(s2)) => {
         s1.to_string() == s2.to_string()
       }
+      (Self::MalformedCookie(s1), Self::MalformedCookie(s2)) => s1 == s2,
+      (Self::MultipleHeaderValues(s1), Self::MultipleHeaderValues(s2)) => s1 == s2,
       _ => false,
     }
   }
@@ -76,6 +108,25 @@ pub enum JwtError {
   AllProvidersFailedToDecode(Vec<JwtError>),
   #[error("http request parsing error: {0:?}")]
   HTTPRequestParsingError(String),
+  #[error("token is missing required scopes: {0:?}")]
+  InsufficientScope(Vec<String>),
+  #[error("token introspection failed: {0}")]
+  IntrospectionFailed(String),
+  #[error("token has been revoked")]
+  TokenRevoked,
+}
+
+impl JwtError {
+  /// The [RFC 6750](https://datatracker.ietf.org/doc/html/rfc6750#section-3) challenge sent with the rejections of invalid tokens.
+  pub(crate) fn www_authenticate_challenge(&self) -> Option<&'static str> {
+    match self {
+      JwtError::InsufficientScope(_) => Some("Bearer error=\"insufficient_scope\""),
+      JwtError::AllProvidersFailedToDecode(_)
+      | JwtError::FailedToDecodeToken(_)
+      | JwtError::TokenRevoked => Some("Bearer error=\"invalid_token\""),
+      _ => None,
+    }
+  }
 }
 
 impl From<JwtError> for StatusCode {
@@ -88,9 +139,11 @@ impl From<JwtError> for StatusCode {
       JwtError::JwkMissingAlgorithm
       | JwtError::FailedToLocateProvider
       | JwtError::InvalidDecodingKey(_) => StatusCode::INTERNAL_SERVER_ERROR,
-      JwtError::AllProvidersFailedToDecode(_) | JwtError::FailedToDecodeToken(_) => {
-        StatusCode::UNAUTHORIZED
-      }
+      JwtError::AllProvidersFailedToDecode(_)
+      | JwtError::FailedToDecodeToken(_)
+      | JwtError::IntrospectionFailed(_)
+      | JwtError::TokenRevoked => StatusCode::UNAUTHORIZED,
+      JwtError::InsufficientScope(_) => StatusCode::FORBIDDEN,
     }
   }
 }
@@ -103,8 +156,16 @@ impl CreatablePlugin for JwtAuthPlugin {
     let providers = config
       .jwks_providers
       .iter()
-      .map(|provider_config| JwksProvider::new(provider_config.clone()))
-      .collect::<Vec<JwksProvider>>();
+      .filter(|provider_config| {
+        !matches!(
+          provider_config,
+          JwksProviderSourceConfig::Secret { .. } | JwksProviderSourceConfig::Introspection { .. }
+        )
+      })
+      .map(|provider_config| Arc::new(JwksProvider::new(provider_config.clone())))
+      .collect::<Vec<_>>();
+    let secrets =
+      build_shared_secrets(&config).map_err(|e| PluginError::InitError { source: e.into() })?;
 
     for provider in providers.iter().filter(|provider| provider.can_prefetch()) {
       if provider.retrieve_jwk_set().await.is_err() {
@@ -112,7 +173,144 @@ impl CreatablePlugin for JwtAuthPlugin {
       }
     }
 
-    Ok(Box::new(Self { config, providers }))
+    let introspections =
+      build_introspections(&config).map_err(|e| PluginError::InitError { source: e.into() })?;
+    let claim_headers =
+      build_claim_headers(&config).map_err(|e| PluginError::InitError { source: e.into() })?;
+
+    Ok(Box::new(Self {
+      introspections,
+      claim_headers,
+      token_cache: config.token_cache_size.map(TokenCache::new),
+      revocation_list: config.revoked_tokens.as_ref().map(RevocationList::new),
+      config,
+      providers,
+      secrets,
+    }))
+  }
+}
+
+fn build_introspections(
+  config: &JwtAuthPluginConfig,
+) -> Result<Vec<TokenIntrospection>, reqwest::Error> {
+  config
+    .jwks_providers
+    .iter()
+    .filter_map(|provider_config| match provider_config {
+      JwksProviderSourceConfig::Introspection {
+        introspection_endpoint,
+        client_id,
+        client_secret,
+        cache_duration,
+      } => Some(TokenIntrospection::new(
+        introspection_endpoint,
+        client_id,
+        client_secret,
+        *cache_duration,
+      )),
+      _ => None,
+    })
+    .collect()
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("invalid header name \"{header}\" for claim \"{claim}\": {source}")]
+pub struct InvalidClaimHeaderError {
+  claim: String,
+  header: String,
+  source: InvalidHeaderName,
+}
+
+fn build_claim_headers(
+  config: &JwtAuthPluginConfig,
+) -> Result<Vec<(String, HeaderName)>, InvalidClaimHeaderError> {
+  config
+    .claim_header_mappings
+    .iter()
+    .map(|mapping| {
+      let header_name = HeaderName::from_bytes(mapping.header.as_bytes()).map_err(|source| {
+        InvalidClaimHeaderError {
+          claim: mapping.claim.clone(),
+          header: mapping.header.clone(),
+          source,
+        }
+      })?;
+
+      Ok((mapping.claim.clone(), header_name))
+    })
+    .collect()
+}
+
+fn build_shared_secrets(
+  config: &JwtAuthPluginConfig,
+) -> Result<Vec<SharedSecret>, SharedSecretError> {
+  config
+    .jwks_providers
+    .iter()
+    .filter_map(|provider_config| match provider_config {
+      JwksProviderSourceConfig::Secret {
+        secret,
+        encoding,
+        algorithms,
+      } => Some(SharedSecret::new(secret, *encoding, algorithms)),
+      _ => None,
+    })
+    .collect()
+}
+
+/// Resolves the signature algorithm of a JWK.
+/// The `alg` parameter is optional in a JWK, so it's inferred from the curve for EC and OKP keys, as each curve can only be used with a single algorithm.
+pub(crate) fn jwk_algorithm(jwk: &Jwk) -> Result<Algorithm, JwtError> {
+  let unsupported = || {
+    JwtError::JwkAlgorithmNotSupported(jsonwebtoken::errors::ErrorKind::InvalidAlgorithmName.into())
+  };
+
+  match jwk.common.key_algorithm {
+    Some(key_alg) => match key_alg {
+      KeyAlgorithm::HS256 => Ok(Algorithm::HS256),
+      KeyAlgorithm::HS384 => Ok(Algorithm::HS384),
+      KeyAlgorithm::HS512 => Ok(Algorithm::HS512),
+      KeyAlgorithm::ES256 => Ok(Algorithm::ES256),
+      KeyAlgorithm::ES384 => Ok(Algorithm::ES384),
+      KeyAlgorithm::RS256 => Ok(Algorithm::RS256),
+      KeyAlgorithm::RS384 => Ok(Algorithm::RS384),
+      KeyAlgorithm::RS512 => Ok(Algorithm::RS512),
+      KeyAlgorithm::PS256 => Ok(Algorithm::PS256),
+      KeyAlgorithm::PS384 => Ok(Algorithm::PS384),
+      KeyAlgorithm::PS512 => Ok(Algorithm::PS512),
+      KeyAlgorithm::EdDSA => Ok(Algorithm::EdDSA),
+      _ => Err(unsupported()),
+    },
+    None => match &jwk.algorithm {
+      AlgorithmParameters::EllipticCurve(params) => match params.curve {
+        EllipticCurve::P256 => Ok(Algorithm::ES256),
+        EllipticCurve::P384 => Ok(Algorithm::ES384),
+        _ => Err(unsupported()),
+      },
+      AlgorithmParameters::OctetKeyPair(params) => match params.curve {
+        EllipticCurve::Ed25519 => Ok(Algorithm::EdDSA),
+        _ => Err(unsupported()),
+      },
+      _ => Err(JwtError::JwkMissingAlgorithm),
+    },
+  }
+}
+
+fn strip_header_prefix<'a>(value: &'a str, prefix: &str, exact: bool) -> Option<&'a str> {
+  if exact || !prefix.trim().eq_ignore_ascii_case(BEARER_PREFIX) {
+    return value.strip_prefix(prefix);
+  }
+
+  // The `Bearer` scheme is matched case-insensitively, and has to be followed by whitespace (or nothing).
+  let value = value.trim_start();
+  let scheme = value.get(..BEARER_PREFIX.len())?;
+  let rest = &value[BEARER_PREFIX.len()..];
+
+  match scheme.eq_ignore_ascii_case(BEARER_PREFIX)
+    && (rest.is_empty() || rest.starts_with(char::is_whitespace))
+  {
+    true => Some(rest),
+    false => None,
   }
 }
 
@@ -120,6 +318,11 @@ impl JwtAuthPlugin {
   #[cfg(test)]
   pub(crate) fn new_from_config(config: JwtAuthPluginConfig) -> Self {
     Self {
+      secrets: build_shared_secrets(&config).unwrap(),
+      introspections: build_introspections(&config).unwrap(),
+      claim_headers: build_claim_headers(&config).unwrap(),
+      token_cache: config.token_cache_size.map(TokenCache::new),
+      revocation_list: config.revoked_tokens.as_ref().map(RevocationList::new),
       config,
       providers: vec![],
     }
@@ -142,15 +345,14 @@ impl JwtAuthPlugin {
     }
 
     // If we don't have `kid` on the token, we should try to match the `alg` field.
+    // Keys that can't verify signatures (for example, encryption keys) are skipped.
     for jwk in jwks {
-      for key in &jwk.keys {
-        if let Some(key_alg) = key.common.key_algorithm {
-          let key_alg_cmp = Algorithm::from_str(&key_alg.to_string())
-            .map_err(JwtError::JwkAlgorithmNotSupported)?;
-          if key_alg_cmp == jwt_header.alg {
-            return Ok(jwk);
-          }
-        }
+      if jwk
+        .keys
+        .iter()
+        .any(|key| jwk_algorithm(key).is_ok_and(|key_alg| key_alg == jwt_header.alg))
+      {
+        return Ok(jwk);
       }
     }
 
@@ -158,23 +360,50 @@ impl JwtAuthPlugin {
   }
 
   pub(crate) fn lookup(&self, req: &ConductorHttpRequest) -> Result<String, LookupError> {
-    for lookup_config in &self.config.lookup_locations {
+    let cookie_parse_failure = self.config.cookie_parse_failure.unwrap_or_default();
+    let multiple_headers = self.config.multiple_headers.unwrap_or_default();
+    let mut misses = vec![];
+
+    'locations: for lookup_config in &self.config.lookup_locations {
       match lookup_config {
-        JwtAuthPluginLookupLocation::Header { name, prefix } => {
-          if let Some(header_value) = req.headers.get(name) {
-            let header_value = header_value
-              .to_str()
-              .map_err(LookupError::FailedToStringifyHeader)?;
+        JwtAuthPluginLookupLocation::Header {
+          name,
+          prefix,
+          prefix_exact,
+        } => {
+          let mut header_values = req.headers.get_all(name).iter();
+          let header_value = match multiple_headers {
+            JwtAuthMultipleHeadersPolicy::UseFirst => header_values.next(),
+            JwtAuthMultipleHeadersPolicy::UseLast => header_values.next_back(),
+            JwtAuthMultipleHeadersPolicy::Reject => {
+              match (header_values.next(), header_values.next()) {
+                (_, Some(_)) => return Err(LookupError::MultipleHeaderValues(name.clone())),
+                (value, None) => value,
+              }
+            }
+          };
+
+          if let Some(header_value) = header_value {
+            // A header that can't be used is recorded, and the lookup continues with the next location.
+            let header_value = match header_value.to_str() {
+              Ok(header_value) => header_value,
+              Err(e) => {
+                misses.push(LookupError::FailedToStringifyHeader(e));
+                continue;
+              }
+            };
 
             match prefix {
-              Some(prefix) => match header_value.strip_prefix(prefix) {
-                Some(stripped_value) => {
-                  return Ok(stripped_value.trim().to_string());
-                }
-                None => {
-                  return Err(LookupError::MismatchedPrefix);
+              Some(prefix) => {
+                match strip_header_prefix(header_value, prefix, prefix_exact.is_some_and(|v| v)) {
+                  Some(stripped_value) => {
+                    return Ok(stripped_value.trim().to_string());
+                  }
+                  None => {
+                    misses.push(LookupError::MismatchedPrefix);
+                  }
                 }
-              },
+              }
               None => {
                 return Ok(header_value.to_string());
               }
@@ -197,6 +426,11 @@ impl JwtAuthPlugin {
             };
 
             for item in raw_cookies {
+              // Empty items are just leftover separators (e.g. a trailing `;`), not malformed cookies.
+              if item.trim().is_empty() {
+                continue;
+              }
+
               match Cookie::parse_encoded(item) {
                 Ok(v) => {
                   let (cookie_name, cookie_value) = v.name_value_trimmed();
@@ -205,14 +439,25 @@ impl JwtAuthPlugin {
                     return Ok(cookie_value.to_string());
                   }
                 }
-                Err(e) => {
-                  // Should we reject the entire request in case of invalid cookies?
-                  // I think it's better to consider this as a user error? maybe return 400?
-                  warn!(
-                    "jwt plugin failed to parse cookie value, ignoring cookie. error: {}",
-                    e
-                  );
-                }
+                Err(e) => match cookie_parse_failure {
+                  JwtAuthCookieParseFailurePolicy::Ignore => {
+                    warn!(
+                      "jwt plugin failed to parse cookie value, ignoring cookie. error: {}",
+                      e
+                    );
+                  }
+                  JwtAuthCookieParseFailurePolicy::TryNext => {
+                    warn!(
+                      "jwt plugin failed to parse cookie value, trying next lookup location. error: {}",
+                      e
+                    );
+
+                    continue 'locations;
+                  }
+                  JwtAuthCookieParseFailurePolicy::Reject => {
+                    return Err(LookupError::MalformedCookie(e.to_string()));
+                  }
+                },
               }
             }
           }
@@ -220,20 +465,55 @@ impl JwtAuthPlugin {
       }
     }
 
-    Err(LookupError::LookupFailed)
+    Err(LookupError::LookupFailed(misses))
   }
 
   fn try_decode_from_jwk(&self, token: &str, jwk: &Jwk) -> Result<TokenPayload, JwtError> {
     let decoding_key = DecodingKey::from_jwk(jwk).map_err(JwtError::InvalidDecodingKey)?;
-    let key_alg = jwk
-      .common
-      .key_algorithm
-      .ok_or(JwtError::JwkMissingAlgorithm)?;
+    let alg = jwk_algorithm(jwk)?;
 
-    let alg =
-      Algorithm::from_str(&key_alg.to_string()).map_err(JwtError::JwkAlgorithmNotSupported)?;
+    self.decode_with_key(token, &decoding_key, alg)
+  }
+
+  /// Tokens signed with a shared secret can be verified by any of the secrets allowing the token's algorithm.
+  fn try_decode_from_secrets(
+    &self,
+    token: &str,
+    header: &Header,
+  ) -> Result<TokenPayload, Vec<JwtError>> {
+    let mut errors = vec![];
 
+    for secret in self
+      .secrets
+      .iter()
+      .filter(|secret| secret.allows(header.alg))
+    {
+      match self.decode_with_key(token, secret.key(), header.alg) {
+        Ok(token_data) => return Ok(token_data),
+        Err(e) => errors.push(e),
+      }
+    }
+
+    Err(errors)
+  }
+
+  fn decode_with_key(
+    &self,
+    token: &str,
+    decoding_key: &DecodingKey,
+    alg: Algorithm,
+  ) -> Result<TokenPayload, JwtError> {
     let mut validation = Validation::new(alg);
+    validation.validate_nbf = self.config.validate_nbf == Some(true);
+
+    // `exp` is required by default, non-expiring tokens have to be allowed explicitly.
+    if self.config.require_expiration == Some(false) {
+      validation.required_spec_claims.remove("exp");
+    }
+
+    if let Some(leeway_secs) = self.config.leeway_secs {
+      validation.leeway = leeway_secs;
+    }
 
     // This only validates the existence of the claim, it does not validate the values, we'll do it after decoding.
     if let Some(iss) = &self.config.issuers {
@@ -245,12 +525,19 @@ impl JwtAuthPlugin {
       validation.set_audience(aud);
     }
 
-    let token_data = match decode::<Value>(token, &decoding_key, &validation) {
+    let token_data = match decode::<Value>(token, decoding_key, &validation) {
       Ok(data) => data,
       Err(e) => return Err(JwtError::FailedToDecodeToken(e)),
     };
 
-    match (&self.config.issuers, token_data.claims.get("iss")) {
+    self.validate_issuer_and_audience(&token_data.claims)?;
+
+    Ok(token_data)
+  }
+
+  /// Checks the `iss` and `aud` claims against the configured `issuers` and `audiences`, for signed and introspected tokens alike.
+  fn validate_issuer_and_audience(&self, claims: &Value) -> Result<(), JwtError> {
+    match (&self.config.issuers, claims.get("iss")) {
       (Some(issuers), Some(Value::String(token_iss))) => {
         if !issuers.contains(token_iss) {
           return Err(JwtError::FailedToDecodeToken(
@@ -266,28 +553,34 @@ impl JwtAuthPlugin {
       _ => {}
     };
 
-    match (&self.config.audiences, token_data.claims.get("aud")) {
-      (Some(audiences), Some(Value::Array(token_aud))) => {
-        let all_valid = token_aud.iter().all(|v| match v {
-          Value::String(token_aud) => audiences.contains(token_aud),
-          _ => false,
-        });
-
-        if !all_valid {
-          return Err(JwtError::FailedToDecodeToken(
-            jsonwebtoken::errors::ErrorKind::InvalidAudience.into(),
-          ));
+    if let Some(audiences) = &self.config.audiences {
+      // `aud` is either a single string, or an array of strings.
+      let token_audiences = match claims.get("aud") {
+        Some(Value::Array(token_aud)) => token_aud.iter().collect(),
+        Some(token_aud) => vec![token_aud],
+        None => vec![],
+      };
+      let is_allowed = |token_aud: &&Value| {
+        token_aud
+          .as_str()
+          .is_some_and(|token_aud| audiences.iter().any(|aud| aud == token_aud))
+      };
+
+      let valid = match self.config.audience_match.unwrap_or_default() {
+        JwtAuthAudienceMatch::Any => token_audiences.iter().any(is_allowed),
+        JwtAuthAudienceMatch::All => {
+          !token_audiences.is_empty() && token_audiences.iter().all(is_allowed)
         }
-      }
-      (Some(_), None) => {
+      };
+
+      if !valid {
         return Err(JwtError::FailedToDecodeToken(
           jsonwebtoken::errors::ErrorKind::InvalidAudience.into(),
         ));
       }
-      _ => {}
-    };
+    }
 
-    Ok(token_data)
+    Ok(())
   }
 
   fn decode_and_validate_token(&self, token: &str, jwks: &[Jwk]) -> Result<TokenPayload, JwtError> {
@@ -305,6 +598,115 @@ impl JwtAuthPlugin {
     ))
   }
 
+  fn decode_token(&self, token: &str, jwks: &Vec<&JwkSet>) -> Result<TokenPayload, JwtError> {
+    // First, we need to decode the header to determine which provider to use.
+    let header = decode_header(token).map_err(JwtError::InvalidJwtHeader)?;
+    let secret_errors = match self.try_decode_from_secrets(token, &header) {
+      Ok(token_data) => return Ok(token_data),
+      Err(errors) => errors,
+    };
+
+    match self.find_matching_jwks(&header, jwks) {
+      Ok(jwk) => self.decode_and_validate_token(token, &jwk.keys),
+      // The token could only be verified by the shared secrets.
+      Err(JwtError::FailedToLocateProvider) if !secret_errors.is_empty() => {
+        Err(JwtError::AllProvidersFailedToDecode(secret_errors))
+      }
+      Err(e) => Err(e),
+    }
+  }
+
+  /// Scopes are granted either as a space-delimited string, or as an array of strings.
+  fn validate_scopes(&self, claims: &Value) -> Result<(), JwtError> {
+    let required_scopes = match &self.config.required_scopes {
+      Some(required_scopes) => required_scopes,
+      None => return Ok(()),
+    };
+    let scope_claim = self
+      .config
+      .scope_claim
+      .as_deref()
+      .unwrap_or(DEFAULT_SCOPE_CLAIM);
+
+    let granted_scopes = match claims.get(scope_claim) {
+      Some(Value::String(scopes)) => scopes.split_whitespace().collect::<Vec<_>>(),
+      Some(Value::Array(scopes)) => scopes.iter().filter_map(Value::as_str).collect(),
+      _ => vec![],
+    };
+    let missing_scopes = required_scopes
+      .iter()
+      .filter(|scope| !granted_scopes.contains(&scope.as_str()))
+      .cloned()
+      .collect::<Vec<_>>();
+
+    match missing_scopes.is_empty() {
+      true => Ok(()),
+      false => Err(JwtError::InsufficientScope(missing_scopes)),
+    }
+  }
+
+  fn context_key_prefix(&self) -> &str {
+    self
+      .config
+      .context_key_prefix
+      .as_deref()
+      .unwrap_or(DEFAULT_CONTEXT_KEY_PREFIX)
+  }
+
+  /// Keys are prefixed with `context_key_prefix`, so multiple instances of the plugin don't overwrite each other's values.
+  fn context_key(&self, name: &str) -> String {
+    format!("{}:{}", self.context_key_prefix(), name)
+  }
+
+  fn store_token(&self, ctx: &mut RequestExecutionContext, claims: Value, token: String) {
+    // Stored under string keys, so VRL and Rhai scripts can use them.
+    let claims_key = self.context_key(CLAIMS_CONTEXT_KEY);
+    ctx.ctx_insert(claims_key.clone(), claims);
+    if self.config.forward_token_to_upstream_header.is_some() {
+      ctx.ctx_insert(self.context_key(TOKEN_CONTEXT_KEY), token.clone());
+    }
+
+    // When multiple instances authenticate the same request, the claims of the first one are kept.
+    if ctx.get::<AuthenticatedClaimsKey>().is_none() {
+      ctx.insert(AuthenticatedClaimsKey(claims_key));
+    }
+
+    let prefix = self.context_key_prefix().to_string();
+
+    match ctx.get_mut::<AuthenticatedTokens>() {
+      Some(tokens) => {
+        tokens.0.insert(prefix, token);
+      }
+      None => {
+        ctx.insert(AuthenticatedTokens(HashMap::from([(prefix, token)])));
+      }
+    }
+  }
+
+  fn authenticated_token<'a>(&self, ctx: &'a RequestExecutionContext) -> Option<&'a String> {
+    ctx
+      .get::<AuthenticatedTokens>()
+      .and_then(|tokens| tokens.0.get(self.context_key_prefix()))
+  }
+
+  /// The claims of the token that this instance authenticated the request with, including the changes made by VRL and Rhai scripts.
+  fn authenticated_claims<'a>(&self, ctx: &'a RequestExecutionContext) -> Option<&'a Value> {
+    self.authenticated_token(ctx)?;
+
+    ctx.ctx_get(self.context_key(CLAIMS_CONTEXT_KEY))
+  }
+
+  fn validate_not_revoked(&self, claims: &Value) -> Result<(), JwtError> {
+    let jti = claims.get("jti").and_then(Value::as_str);
+
+    match (&self.revocation_list, jti) {
+      (Some(revocation_list), Some(jti)) if revocation_list.is_revoked(jti) => {
+        Err(JwtError::TokenRevoked)
+      }
+      _ => Ok(()),
+    }
+  }
+
   pub(crate) fn authenticate(
     &self,
     jwks: &Vec<&JwkSet>,
@@ -312,13 +714,27 @@ impl JwtAuthPlugin {
   ) -> Result<(TokenData<Value>, String), JwtError> {
     match self.lookup(req) {
       Ok(token) => {
-        // First, we need to decode the header to determine which provider to use.
-        let header = decode_header(&token).map_err(JwtError::InvalidJwtHeader)?;
-        let jwk = self.find_matching_jwks(&header, jwks)?;
+        let cached = self
+          .token_cache
+          .as_ref()
+          .and_then(|token_cache| token_cache.get(&token));
+        let token_data = match cached {
+          Some(token_data) => token_data,
+          None => {
+            let token_data = self.decode_token(&token, jwks)?;
+
+            if let Some(token_cache) = &self.token_cache {
+              token_cache.insert(&token, &token_data);
+            }
+
+            token_data
+          }
+        };
+        // Revocation is checked on every request, as a cached token might have been revoked since it was decoded.
+        self.validate_not_revoked(&token_data.claims)?;
+        self.validate_scopes(&token_data.claims)?;
 
-        self
-          .decode_and_validate_token(&token, &jwk.keys)
-          .map(|token_data| (token_data, token))
+        Ok((token_data, token))
       }
       Err(e) => {
         warn!("jwt plugin failed to lookup token. error: {}", e);
@@ -327,46 +743,143 @@ impl JwtAuthPlugin {
       }
     }
   }
+
+  /// Retrieves the key sets of all providers. When `refresh` is set, remote sets are refreshed even if they are not expired yet.
+  async fn retrieve_jwk_sets(&self, refresh: bool) -> Vec<Arc<TimedJwtSet>> {
+    join_all(self.providers.iter().map(|provider| async move {
+      match refresh {
+        true => provider.refresh_jwk_set().await,
+        false => provider.retrieve_jwk_set().await,
+      }
+    }))
+    .await
+    .into_iter()
+    .filter_map(|result| match result {
+      Ok(set) => Some(set),
+      Err(e) => {
+        error!("failed to retrieve jwks: {}", e);
+        None
+      }
+    })
+    .collect()
+  }
+
+  /// Checks if the token in the request refers to a `kid` that is missing from all of the given key sets.
+  fn has_unknown_kid(&self, jwks: &[&JwkSet], req: &ConductorHttpRequest) -> bool {
+    let kid = self
+      .lookup(req)
+      .ok()
+      .and_then(|token| decode_header(&token).ok())
+      .and_then(|header| header.kid);
+
+    match kid {
+      Some(kid) => jwks.iter().all(|jwk| jwk.find(&kid).is_none()),
+      None => false,
+    }
+  }
+
+  async fn introspect_token(
+    &self,
+    req: &ConductorHttpRequest,
+  ) -> Result<(TokenData<Value>, String), JwtError> {
+    let token = self.lookup(req).map_err(JwtError::LookupFailed)?;
+    let mut errors = vec![];
+
+    for introspection in &self.introspections {
+      match introspection.introspect(&token).await {
+        Ok(claims) => {
+          self.validate_issuer_and_audience(&claims)?;
+          self.validate_not_revoked(&claims)?;
+          self.validate_scopes(&claims)?;
+
+          let token_data = TokenData {
+            header: Header::default(),
+            claims,
+          };
+
+          return Ok((token_data, token));
+        }
+        Err(e) => errors.push(JwtError::IntrospectionFailed(e.to_string())),
+      }
+    }
+
+    Err(JwtError::AllProvidersFailedToDecode(errors))
+  }
+
+  pub(crate) async fn authenticate_with_providers(
+    &self,
+    req: &ConductorHttpRequest,
+  ) -> Result<(TokenData<Value>, String), JwtError> {
+    let jwks = self.retrieve_jwk_sets(false).await;
+    let valid_jwks = jwks.iter().map(|set| set.get_jwk()).collect::<Vec<_>>();
+
+    match self.authenticate(&valid_jwks, req) {
+      Err(JwtError::LookupFailed(e)) => Err(JwtError::LookupFailed(e)),
+      // Opaque tokens are not JWTs, so they can only be verified by an introspection endpoint.
+      Err(JwtError::InvalidJwtHeader(_)) if !self.introspections.is_empty() => {
+        self.introspect_token(req).await
+      }
+      Err(e) if self.has_unknown_kid(&valid_jwks, req) => {
+        // The token might be signed with a key that was added after the sets were cached, so we try again with fresh sets.
+        warn!(
+          "jwt token refers to an unknown kid, refreshing jwks. error: {}",
+          e
+        );
+
+        let jwks = self.retrieve_jwk_sets(true).await;
+        let valid_jwks = jwks.iter().map(|set| set.get_jwk()).collect::<Vec<_>>();
+
+        self.authenticate(&valid_jwks, req)
+      }
+      result => result,
+    }
+  }
 }
 
 #[async_trait::async_trait(?Send)]
 impl Plugin for JwtAuthPlugin {
   async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
-    let jwks = join_all(
-      self
-        .providers
-        .iter()
-        .map(|provider| provider.retrieve_jwk_set()),
-    )
-    .await;
-
-    let valid_jwks = jwks
-      .iter()
-      .filter_map(|r| match r {
-        Ok(result) => Some(result.get_jwk()),
-        Err(_) => None,
-      })
-      .collect::<Vec<_>>();
-
-    match self.authenticate(&valid_jwks, &ctx.downstream_http_request) {
+    match self
+      .authenticate_with_providers(&ctx.downstream_http_request)
+      .await
+    {
       Ok((token_data, token)) => {
-        if self.config.forward_claims_to_upstream_header.is_some() {
-          ctx.ctx_insert(CLAIMS_CONTEXT_KEY, token_data.claims);
-        }
-        if self.config.forward_token_to_upstream_header.is_some() {
-          ctx.ctx_insert(TOKEN_CONTEXT_KEY, token);
-        }
+        ctx.mark_authenticated();
+
+        // The claims are always stored, so other plugins can use them.
+        self.store_token(ctx, token_data.claims, token);
+      }
+      Err(JwtError::LookupFailed(LookupError::LookupFailed(misses)))
+        if misses.is_empty() && self.config.allow_anonymous.is_some_and(|v| v) =>
+      {
+        ctx.ctx_insert(self.context_key(ANONYMOUS_CONTEXT_KEY), true);
       }
       Err(e) => {
         warn!("jwt token error: {}", e);
 
-        if self
-          .config
-          .reject_unauthenticated_requests
-          .is_some_and(|v| v)
+        // Malformed requests are only reported when configured to reject them, and tokens are only checked for scopes when some are required, so these are always rejected.
+        let always_rejected = matches!(
+          e,
+          JwtError::LookupFailed(LookupError::MalformedCookie(_))
+            | JwtError::LookupFailed(LookupError::MultipleHeaderValues(_))
+            | JwtError::InsufficientScope(_)
+        );
+
+        if always_rejected
+          || self
+            .config
+            .reject_unauthenticated_requests
+            .is_some_and(|v| v)
         {
+          let mut headers = HttpHeadersMap::new();
+
+          if let Some(challenge) = e.www_authenticate_challenge() {
+            headers.insert(WWW_AUTHENTICATE, HeaderValue::from_static(challenge));
+          }
+
           ctx.short_circuit(
-            GraphQLResponse::new_error("unauthenticated request").into_with_status_code(e.into()),
+            GraphQLResponse::new_error("unauthenticated request")
+              .into_with_status_code_and_headers(e.into(), headers),
           );
         }
       }
@@ -379,7 +892,7 @@ impl Plugin for JwtAuthPlugin {
     upstream_req: &mut ConductorHttpRequest,
   ) {
     if let Some(header_name) = &self.config.forward_claims_to_upstream_header {
-      if let Some(claims) = ctx.ctx_get(CLAIMS_CONTEXT_KEY) {
+      if let Some(claims) = self.authenticated_claims(ctx) {
         match claims.to_string().parse::<HeaderValue>() {
           Ok(header_value) => {
             if let Ok(header_name) = header_name.parse::<HeaderName>() {
@@ -403,9 +916,41 @@ impl Plugin for JwtAuthPlugin {
       }
     }
 
+    if !self.claim_headers.is_empty() {
+      let claims = self.authenticated_claims(ctx);
+
+      for (claim_name, header_name) in &self.claim_headers {
+        // The header is always overwritten, so a value that didn't come from the token is never forwarded.
+        let header_value = match claims.and_then(|claims| claims.get(claim_name)) {
+          Some(Value::String(claim)) => claim.parse::<HeaderValue>(),
+          Some(claim) => claim.to_string().parse::<HeaderValue>(),
+          None => {
+            upstream_req.headers.remove(header_name);
+            continue;
+          }
+        };
+
+        match header_value {
+          Ok(header_value) => {
+            upstream_req.headers.insert(header_name, header_value);
+          }
+          Err(_) => {
+            ctx.short_circuit(
+              GraphQLResponse::new_error(&format!(
+                "Failed to parse claim \"{}\" as header value",
+                claim_name
+              ))
+              .into_with_status_code(StatusCode::BAD_REQUEST),
+            );
+            return;
+          }
+        }
+      }
+    }
+
     if let Some(header_name) = &self.config.forward_token_to_upstream_header {
-      if let Some(token) = ctx.ctx_get(TOKEN_CONTEXT_KEY) {
-        match token.as_str().and_then(|t| t.parse::<HeaderValue>().ok()) {
+      if let Some(token) = self.authenticated_token(ctx) {
+        match token.parse::<HeaderValue>().ok() {
           Some(header_value) => {
             if let Ok(header_name) = header_name.parse::<HeaderName>() {
               upstream_req.headers.append(header_name, header_value);
@@ -0,0 +1,111 @@
+use std::{collections::HashSet, sync::RwLock};
+
+use conductor_common::serde_utils::LocalFileReference;
+
+use crate::config::JwtAuthRevocationListConfig;
+
+#[derive(Debug, Default)]
+struct RevokedTokens {
+  ids: HashSet<String>,
+  /// The modification time of the local file, at the time it was loaded. `None` if the list was not read from the file-system.
+  modified: Option<std::time::SystemTime>,
+}
+
+/// Holds the `jti` values of revoked tokens.
+/// A local file is reloaded when its modification time changes, so tokens can be revoked without restarting the gateway.
+#[derive(Debug)]
+pub struct RevocationList {
+  file: Option<LocalFileReference>,
+  revoked: RwLock<RevokedTokens>,
+}
+
+/// Every non-empty line is a revoked `jti`, lines starting with `#` are comments.
+fn parse_revoked_ids(contents: &str) -> HashSet<String> {
+  contents
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    .map(String::from)
+    .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn local_file_modified(path: &str) -> Option<std::time::SystemTime> {
+  std::fs::metadata(path)
+    .and_then(|metadata| metadata.modified())
+    .ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_file_modified(_path: &str) -> Option<std::time::SystemTime> {
+  None
+}
+
+impl RevocationList {
+  pub fn new(config: &JwtAuthRevocationListConfig) -> Self {
+    match config {
+      JwtAuthRevocationListConfig::Inline { ids } => Self {
+        file: None,
+        revoked: RwLock::new(RevokedTokens {
+          ids: ids.iter().cloned().collect(),
+          modified: None,
+        }),
+      },
+      JwtAuthRevocationListConfig::Local { file } => Self {
+        file: Some(file.clone()),
+        revoked: RwLock::new(RevokedTokens {
+          ids: parse_revoked_ids(&file.contents),
+          modified: local_file_modified(&file.path),
+        }),
+      },
+    }
+  }
+
+  pub fn is_revoked(&self, jti: &str) -> bool {
+    self.reload_if_modified();
+
+    self
+      .revoked
+      .read()
+      .is_ok_and(|revoked| revoked.ids.contains(jti))
+  }
+
+  /// The previous list is kept if the file can't be read, so a broken deployment of the file doesn't un-revoke tokens.
+  fn reload_if_modified(&self) {
+    let file = match &self.file {
+      Some(file) => file,
+      None => return,
+    };
+    let modified = match local_file_modified(&file.path) {
+      Some(modified) => modified,
+      None => return,
+    };
+
+    if self
+      .revoked
+      .read()
+      .is_ok_and(|revoked| revoked.modified == Some(modified))
+    {
+      return;
+    }
+
+    match std::fs::read_to_string(&file.path) {
+      Ok(contents) => {
+        let ids = parse_revoked_ids(&contents);
+        tracing::debug!("loaded {} revoked tokens from {}", ids.len(), file.path);
+
+        if let Ok(mut w_revoked) = self.revoked.write() {
+          *w_revoked = RevokedTokens {
+            ids,
+            modified: Some(modified),
+          };
+        }
+      }
+      Err(e) => tracing::warn!(
+        "failed to reload revoked tokens from {}, using the previous list: {}",
+        file.path,
+        e
+      ),
+    }
+  }
+}
@@ -0,0 +1,73 @@
+use std::fmt::Debug;
+
+use jsonwebtoken::{Algorithm, DecodingKey};
+
+use crate::config::JwtAuthSecretEncoding;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SharedSecretError {
+  #[error("shared secret is empty")]
+  EmptySecret,
+  #[error("failed to decode base64 shared secret: {0}")]
+  InvalidBase64Secret(jsonwebtoken::errors::Error),
+  #[error(
+    "algorithm {0:?} can't be used with a shared secret, expected one of: HS256, HS384, HS512"
+  )]
+  UnsupportedAlgorithm(Algorithm),
+}
+
+/// Verifies tokens signed with a shared secret, using one of the HMAC algorithms.
+pub struct SharedSecret {
+  key: DecodingKey,
+  algorithms: Vec<Algorithm>,
+}
+
+// The key material is never printed.
+impl Debug for SharedSecret {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("SharedSecret")
+      .field("algorithms", &self.algorithms)
+      .finish_non_exhaustive()
+  }
+}
+
+impl SharedSecret {
+  pub fn new(
+    secret: &str,
+    encoding: JwtAuthSecretEncoding,
+    algorithms: &[Algorithm],
+  ) -> Result<Self, SharedSecretError> {
+    if secret.is_empty() {
+      return Err(SharedSecretError::EmptySecret);
+    }
+
+    if let Some(algorithm) = algorithms.iter().find(|algorithm| {
+      !matches!(
+        algorithm,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+      )
+    }) {
+      return Err(SharedSecretError::UnsupportedAlgorithm(*algorithm));
+    }
+
+    let key = match encoding {
+      JwtAuthSecretEncoding::Raw => DecodingKey::from_secret(secret.as_bytes()),
+      JwtAuthSecretEncoding::Base64 => {
+        DecodingKey::from_base64_secret(secret).map_err(SharedSecretError::InvalidBase64Secret)?
+      }
+    };
+
+    Ok(Self {
+      key,
+      algorithms: algorithms.to_vec(),
+    })
+  }
+
+  pub fn key(&self) -> &DecodingKey {
+    &self.key
+  }
+
+  pub fn allows(&self, algorithm: Algorithm) -> bool {
+    self.algorithms.contains(&algorithm)
+  }
+}
@@ -0,0 +1,67 @@
+use std::{sync::Mutex, time::Duration};
+
+use conductor_common::lru::LruMap;
+use jsonwebtoken::{Header, TokenData};
+use serde_json::Value;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+struct CachedToken {
+  header: Header,
+  claims: Value,
+  expires_at: SystemTime,
+}
+
+/// Keeps recently decoded tokens, so the signature of a token is not verified again on every request.
+/// Tokens are only cached until their `exp`, and the least recently used token is evicted when the cache is full.
+#[derive(Debug)]
+pub struct TokenCache {
+  cached: Mutex<LruMap<CachedToken>>,
+}
+
+impl TokenCache {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      cached: Mutex::new(LruMap::new(capacity)),
+    }
+  }
+
+  pub fn get(&self, token: &str) -> Option<TokenData<Value>> {
+    let mut cached = self.cached.lock().ok()?;
+
+    match cached.get_mut(token) {
+      Some(cached_token) if cached_token.expires_at > SystemTime::now() => Some(TokenData {
+        header: cached_token.header.clone(),
+        claims: cached_token.claims.clone(),
+      }),
+      Some(_) => {
+        cached.remove(token);
+        None
+      }
+      None => None,
+    }
+  }
+
+  /// Tokens without an `exp` claim are never cached, as there is no way to tell how long they remain valid.
+  pub fn insert(&self, token: &str, token_data: &TokenData<Value>) {
+    let expires_at = match token_data.claims.get("exp").and_then(Value::as_u64) {
+      Some(exp) => UNIX_EPOCH + Duration::from_secs(exp),
+      None => return,
+    };
+
+    if expires_at <= SystemTime::now() {
+      return;
+    }
+
+    if let Ok(mut cached) = self.cached.lock() {
+      cached.insert(
+        token.to_string(),
+        CachedToken {
+          header: token_data.header.clone(),
+          claims: token_data.claims.clone(),
+          expires_at,
+        },
+      );
+    }
+  }
+}
@@ -0,0 +1,63 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `last_errors` plugin records the most recent error returned for every GraphQL operation name, and exposes them as JSON.
+///
+/// The recorded errors are kept in memory, and can be retrieved with a `GET` request to the configured `path` of the endpoint (for example: `GET /graphql/_last_errors`). The request must send the configured `secret` as a bearer token (`Authorization: Bearer <secret>`), or be authenticated by an authentication plugin (like `jwt_auth`) that runs before this plugin. Each entry contains the error message, the error code (taken from the `extensions.code` of the GraphQL error, if present), the HTTP status code and the time it was recorded.
+///
+/// Operations without an `operationName` are recorded as `anonymous`.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "last_errors_example")]
+pub struct LastErrorsPluginConfig {
+  /// The path that exposes the recorded errors, relative to the endpoint path.
+  #[serde(default = "default_path")]
+  pub path: String,
+  /// The maximum number of distinct operation names to keep track of.
+  ///
+  /// When the limit is reached, the operation with the oldest recorded error is evicted to make room for the new one.
+  #[serde(default = "default_max_operations")]
+  pub max_operations: usize,
+  /// A secret that must be sent as a bearer token (`Authorization: Bearer <secret>`) to retrieve the recorded errors.
+  ///
+  /// When not set, the request must be authenticated by an authentication plugin that runs before this plugin.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub secret: Option<String>,
+}
+
+impl Default for LastErrorsPluginConfig {
+  fn default() -> Self {
+    Self {
+      path: default_path(),
+      max_operations: default_max_operations(),
+      secret: None,
+    }
+  }
+}
+
+fn default_path() -> String {
+  "/_last_errors".to_string()
+}
+
+fn default_max_operations() -> usize {
+  100
+}
+
+fn last_errors_example() -> JsonSchemaExample<LastErrorsPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Track last errors",
+      Some("This example keeps track of the last error of up to 500 operations, and exposes them on `GET /graphql/_errors` (assuming the endpoint is `/graphql`) to requests that send the secret as a bearer token."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "last_errors".to_string(),
+    }),
+    example: LastErrorsPluginConfig {
+      path: "/_errors".to_string(),
+      max_operations: 500,
+      secret: Some("my-secret".to_string()),
+    },
+  }
+}
@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::LastErrorsPluginConfig as Config;
+pub use plugin::LastErrorsPlugin as Plugin;
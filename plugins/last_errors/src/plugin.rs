@@ -0,0 +1,310 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use conductor_common::{
+  admin_endpoint::AdminEndpoint,
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{ConductorHttpResponse, HeaderValue, HttpHeadersMap, StatusCode, CONTENT_TYPE},
+  lru::LruMap,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use serde::Serialize;
+use serde_json::Value;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::LastErrorsPluginConfig;
+
+static ANONYMOUS_OPERATION: &str = "anonymous";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LastError {
+  pub message: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub code: Option<String>,
+  pub status: u16,
+  /// Milliseconds since the Unix epoch.
+  pub timestamp: u64,
+}
+
+#[derive(Debug)]
+pub struct LastErrorsPlugin {
+  endpoint: AdminEndpoint,
+  /// Keyed by operation name. Recording an error marks the operation as used, so the operation with the oldest recorded error is evicted first.
+  errors: Mutex<LruMap<LastError>>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for LastErrorsPlugin {
+  type Config = LastErrorsPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    Ok(Box::new(Self {
+      endpoint: AdminEndpoint::new(&config.path, config.secret),
+      errors: Mutex::new(LruMap::new(config.max_operations)),
+    }))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for LastErrorsPlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    self.endpoint.handle(ctx, || self.render());
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    // Only GraphQL operations are tracked, this also skips the responses rendered by this plugin.
+    let operation_name = match &ctx.downstream_graphql_request {
+      Some(gql_request) => gql_request
+        .request
+        .operation_name
+        .clone()
+        .unwrap_or_else(|| ANONYMOUS_OPERATION.to_string()),
+      None => return,
+    };
+
+    if let Some(error) = extract_error(response) {
+      if let Ok(mut errors) = self.errors.lock() {
+        errors.insert(operation_name, error);
+      }
+    }
+  }
+}
+
+impl LastErrorsPlugin {
+  fn render(&self) -> ConductorHttpResponse {
+    let body = match self.errors.lock() {
+      Ok(errors) => serde_json::to_vec(&errors.iter().collect::<BTreeMap<_, _>>()),
+      Err(_) => {
+        return GraphQLResponse::new_error("failed to access recorded errors")
+          .into_with_status_code(StatusCode::INTERNAL_SERVER_ERROR)
+      }
+    };
+
+    match body {
+      Ok(body) => {
+        let mut headers = HttpHeadersMap::new();
+        headers.append(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        ConductorHttpResponse {
+          body: body.into(),
+          status: StatusCode::OK,
+          headers,
+        }
+      }
+      Err(e) => GraphQLResponse::new_error(&format!("failed to serialize recorded errors: {}", e))
+        .into_with_status_code(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+  }
+}
+
+/// Takes the first GraphQL error of the response. Responses with a non-success status code and no GraphQL errors are recorded as well.
+fn extract_error(response: &ConductorHttpResponse) -> Option<LastError> {
+  let first_error = serde_json::from_slice::<GraphQLResponse>(&response.body)
+    .ok()
+    .and_then(|response| response.errors)
+    .and_then(|errors| errors.into_iter().next());
+
+  let (message, code) = match first_error {
+    Some(error) => {
+      let code = error
+        .extensions
+        .as_ref()
+        .and_then(|extensions| extensions.get("code"))
+        .and_then(Value::as_str)
+        .map(String::from);
+
+      (error.message, code)
+    }
+    None if !response.status.is_success() => (response.status.to_string(), None),
+    None => return None,
+  };
+
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_millis() as u64)
+    .unwrap_or_default();
+
+  Some(LastError {
+    message,
+    code,
+    status: response.status.as_u16(),
+    timestamp,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    execute::ENDPOINT_CONTEXT_KEY,
+    graphql::{GraphQLError, GraphQLRequest, ParsedGraphQLRequest},
+    http::{ConductorHttpRequest, Method},
+  };
+  use serde_json::json;
+
+  use super::*;
+
+  async fn plugin(max_operations: usize) -> Box<LastErrorsPlugin> {
+    LastErrorsPlugin::create(LastErrorsPluginConfig {
+      max_operations,
+      secret: Some("secret".to_string()),
+      ..Default::default()
+    })
+    .await
+    .unwrap()
+  }
+
+  fn respond(plugin: &LastErrorsPlugin, operation_name: &str, mut response: ConductorHttpResponse) {
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    ctx.downstream_graphql_request = Some(
+      ParsedGraphQLRequest::create_and_parse(GraphQLRequest {
+        operation: format!("query {} {{ __typename }}", operation_name),
+        operation_name: Some(operation_name.to_string()),
+        variables: None,
+        extensions: None,
+      })
+      .unwrap(),
+    );
+
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+  }
+
+  async fn request(
+    plugin: &LastErrorsPlugin,
+    uri: &str,
+    secret: Option<&str>,
+  ) -> RequestExecutionContext {
+    let mut headers = HttpHeadersMap::new();
+
+    if let Some(secret) = secret {
+      headers.insert(
+        "authorization",
+        format!("Bearer {}", secret).parse().unwrap(),
+      );
+    }
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::GET,
+      uri: uri.to_string(),
+      headers,
+      ..Default::default()
+    });
+    ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, "/graphql");
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    ctx
+  }
+
+  async fn retrieve(plugin: &LastErrorsPlugin) -> Value {
+    let ctx = request(plugin, "/graphql/_last_errors", Some("secret")).await;
+
+    let response = ctx.short_circuit_response.expect("expected a response");
+    assert_eq!(response.status, StatusCode::OK);
+
+    serde_json::from_slice(&response.body).unwrap()
+  }
+
+  #[tokio::test]
+  async fn records_errored_operation() {
+    let plugin = plugin(10).await;
+    let mut error = GraphQLError::new("user not found");
+    error.extensions = Some(json!({ "code": "NOT_FOUND" }).as_object().unwrap().clone());
+
+    respond(
+      &plugin,
+      "GetUser",
+      GraphQLResponse::new_errors(vec![error]).into(),
+    );
+    respond(
+      &plugin,
+      "GetPosts",
+      GraphQLResponse::new_error("upstream failed").into_with_status_code(StatusCode::BAD_GATEWAY),
+    );
+    // Successful operations are not recorded.
+    respond(
+      &plugin,
+      "GetComments",
+      ConductorHttpResponse {
+        body: json!({ "data": { "__typename": "Query" } })
+          .to_string()
+          .into(),
+        status: StatusCode::OK,
+        headers: Default::default(),
+      },
+    );
+
+    let errors = retrieve(&plugin).await;
+    let errors = errors.as_object().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors["GetUser"]["message"], "user not found");
+    assert_eq!(errors["GetUser"]["code"], "NOT_FOUND");
+    assert_eq!(errors["GetUser"]["status"], 200);
+    assert!(errors["GetUser"]["timestamp"].as_u64().unwrap() > 0);
+    assert_eq!(errors["GetPosts"]["message"], "upstream failed");
+    assert_eq!(errors["GetPosts"]["status"], 502);
+  }
+
+  #[tokio::test]
+  async fn keeps_last_error_only() {
+    let plugin = plugin(10).await;
+
+    respond(
+      &plugin,
+      "GetUser",
+      GraphQLResponse::new_error("first").into(),
+    );
+    respond(
+      &plugin,
+      "GetUser",
+      GraphQLResponse::new_error("second").into(),
+    );
+
+    let errors = retrieve(&plugin).await;
+    assert_eq!(errors.as_object().unwrap().len(), 1);
+    assert_eq!(errors["GetUser"]["message"], "second");
+  }
+
+  #[tokio::test]
+  async fn bounded_operations() {
+    let plugin = plugin(3).await;
+
+    for i in 0..10 {
+      respond(
+        &plugin,
+        &format!("Operation{}", i),
+        GraphQLResponse::new_error("failed").into(),
+      );
+    }
+
+    let errors = retrieve(&plugin).await;
+    let mut names = errors.as_object().unwrap().keys().collect::<Vec<_>>();
+    names.sort();
+    // The oldest operations are evicted first.
+    assert_eq!(names, vec!["Operation7", "Operation8", "Operation9"]);
+  }
+
+  #[tokio::test]
+  async fn requires_secret() {
+    let plugin = plugin(10).await;
+
+    respond(
+      &plugin,
+      "GetUser",
+      GraphQLResponse::new_error("failed").into(),
+    );
+
+    for secret in [None, Some("wrong")] {
+      let ctx = request(&plugin, "/graphql/_last_errors", secret).await;
+      let response = ctx.short_circuit_response.expect("expected a response");
+      assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+      assert!(!String::from_utf8_lossy(&response.body).contains("GetUser"));
+    }
+
+    // Only the exact path is served.
+    let ctx = request(&plugin, "/graphql/other/_last_errors", Some("secret")).await;
+    assert!(ctx.short_circuit_response.is_none());
+  }
+}
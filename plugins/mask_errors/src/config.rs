@@ -0,0 +1,64 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `mask_errors` plugin replaces the messages of the GraphQL errors returned by the upstream with a generic message, so internal details (like stack traces or table names) are not leaked to the client.
+///
+/// The `path` and `locations` of masked errors, as well as their `extensions.code`, are kept as-is, so clients can still handle the errors. The other `extensions` (for example, `extensions.exception.stacktrace`) are removed, unless they are listed in `allowed_extensions`.
+///
+/// Errors with a code or a message in the allowlists are passed to the client verbatim.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "mask_errors_example")]
+pub struct MaskErrorsPluginConfig {
+  /// The message that replaces the message of masked errors.
+  #[serde(default = "default_message")]
+  pub message: String,
+  /// Errors with an `extensions.code` in this list are not masked.
+  #[serde(default)]
+  pub allowed_codes: Vec<String>,
+  /// Errors with a message in this list are not masked. The message must match exactly.
+  #[serde(default)]
+  pub allowed_messages: Vec<String>,
+  /// The `extensions` of masked errors that are kept, in addition to `extensions.code`.
+  #[serde(default)]
+  pub allowed_extensions: Vec<String>,
+  /// When enabled, the original message of masked errors is added to the error as `extensions.originalError`.
+  ///
+  /// > ❗️ This exposes the original messages to the client, only enable it in development environments.
+  #[serde(default)]
+  pub debug: bool,
+}
+
+impl Default for MaskErrorsPluginConfig {
+  fn default() -> Self {
+    Self {
+      message: default_message(),
+      allowed_codes: vec![],
+      allowed_messages: vec![],
+      allowed_extensions: vec![],
+      debug: false,
+    }
+  }
+}
+
+fn default_message() -> String {
+  "Unexpected error.".to_string()
+}
+
+fn mask_errors_example() -> JsonSchemaExample<MaskErrorsPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Allowed codes",
+      Some("This example masks all upstream errors, except the ones with `UNAUTHENTICATED` or `BAD_USER_INPUT` code."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "mask_errors".to_string(),
+    }),
+    example: MaskErrorsPluginConfig {
+      allowed_codes: vec!["UNAUTHENTICATED".to_string(), "BAD_USER_INPUT".to_string()],
+      ..Default::default()
+    },
+  }
+}
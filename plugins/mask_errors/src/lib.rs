@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::MaskErrorsPluginConfig as Config;
+pub use plugin::MaskErrorsPlugin as Plugin;
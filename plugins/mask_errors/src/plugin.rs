@@ -0,0 +1,281 @@
+use conductor_common::{
+  execute::RequestExecutionContext,
+  http::ConductorHttpResponse,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use serde_json::{Map, Value};
+use tracing::debug;
+
+use crate::config::MaskErrorsPluginConfig;
+
+#[derive(Debug)]
+pub struct MaskErrorsPlugin(MaskErrorsPluginConfig);
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for MaskErrorsPlugin {
+  type Config = MaskErrorsPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    Ok(Box::new(Self(config)))
+  }
+}
+
+impl MaskErrorsPlugin {
+  fn is_allowed(&self, error: &Map<String, Value>) -> bool {
+    let code = error
+      .get("extensions")
+      .and_then(|extensions| extensions.get("code"))
+      .and_then(Value::as_str);
+    let message = error.get("message").and_then(Value::as_str);
+
+    code.is_some_and(|code| self.0.allowed_codes.iter().any(|allowed| allowed == code))
+      || message.is_some_and(|message| {
+        self
+          .0
+          .allowed_messages
+          .iter()
+          .any(|allowed| allowed == message)
+      })
+  }
+
+  /// Returns `true` if the error was masked.
+  fn mask(&self, error: &mut Map<String, Value>) -> bool {
+    if self.is_allowed(error) {
+      return false;
+    }
+
+    let original = error.insert("message".to_string(), Value::from(self.0.message.as_str()));
+
+    // Extensions can carry internal details too, like the stack trace of Apollo Server's `extensions.exception`.
+    if let Some(Value::Object(extensions)) = error.get_mut("extensions") {
+      extensions.retain(|name, _| {
+        name == "code"
+          || self
+            .0
+            .allowed_extensions
+            .iter()
+            .any(|allowed| allowed == name)
+      });
+
+      if extensions.is_empty() {
+        error.remove("extensions");
+      }
+    }
+
+    if self.0.debug {
+      if let Some(original) = original {
+        let extensions = error
+          .entry("extensions")
+          .or_insert_with(|| Value::Object(Map::new()));
+
+        if let Value::Object(extensions) = extensions {
+          extensions.insert("originalError".to_string(), original);
+        }
+      }
+    }
+
+    true
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for MaskErrorsPlugin {
+  async fn on_upstream_http_response(
+    &self,
+    _ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    let mut body = match serde_json::from_slice::<Value>(&response.body) {
+      Ok(Value::Object(body)) => body,
+      _ => return,
+    };
+
+    let masked = match body.get_mut("errors") {
+      Some(Value::Array(errors)) => errors
+        .iter_mut()
+        .filter_map(Value::as_object_mut)
+        .map(|error| self.mask(error))
+        .filter(|masked| *masked)
+        .count(),
+      _ => return,
+    };
+
+    if masked == 0 {
+      return;
+    }
+
+    debug!("masked {} upstream errors", masked);
+
+    if let Ok(body) = serde_json::to_vec(&body) {
+      response.body = body.into();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::http::StatusCode;
+  use serde_json::json;
+
+  use super::*;
+
+  async fn execute(config: MaskErrorsPluginConfig, body: Value) -> Value {
+    let plugin = MaskErrorsPlugin::create(config).await.unwrap();
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    let mut response = ConductorHttpResponse {
+      body: body.to_string().into(),
+      status: StatusCode::OK,
+      headers: Default::default(),
+    };
+    plugin
+      .on_upstream_http_response(&mut ctx, &mut response)
+      .await;
+
+    serde_json::from_slice(&response.body).unwrap()
+  }
+
+  #[tokio::test]
+  async fn masks_messages() {
+    let body = execute(
+      Default::default(),
+      json!({
+        "data": { "user": null },
+        "errors": [
+          {
+            "message": "relation \"users\" does not exist",
+            "path": ["user"],
+            "extensions": { "code": "INTERNAL_SERVER_ERROR" }
+          },
+          { "message": "at Object.resolve (/app/resolvers.js:10:5)" }
+        ]
+      }),
+    )
+    .await;
+
+    assert_eq!(
+      body,
+      json!({
+        "data": { "user": null },
+        "errors": [
+          {
+            "message": "Unexpected error.",
+            "path": ["user"],
+            "extensions": { "code": "INTERNAL_SERVER_ERROR" }
+          },
+          { "message": "Unexpected error." }
+        ]
+      })
+    );
+  }
+
+  #[tokio::test]
+  async fn allowlists() {
+    let config = MaskErrorsPluginConfig {
+      message: "Something went wrong".to_string(),
+      allowed_codes: vec!["BAD_USER_INPUT".to_string()],
+      allowed_messages: vec!["user not found".to_string()],
+      ..Default::default()
+    };
+    let body = execute(
+      config,
+      json!({
+        "errors": [
+          { "message": "invalid email", "extensions": { "code": "BAD_USER_INPUT" } },
+          { "message": "user not found" },
+          { "message": "user not found: id 1" }
+        ]
+      }),
+    )
+    .await;
+
+    assert_eq!(
+      body["errors"],
+      json!([
+        { "message": "invalid email", "extensions": { "code": "BAD_USER_INPUT" } },
+        { "message": "user not found" },
+        { "message": "Something went wrong" }
+      ])
+    );
+  }
+
+  #[tokio::test]
+  async fn debug_keeps_original_message() {
+    let config = MaskErrorsPluginConfig {
+      debug: true,
+      ..Default::default()
+    };
+    let body = execute(
+      config,
+      json!({
+        "errors": [
+          { "message": "connection refused", "extensions": { "code": "DOWNSTREAM_SERVICE_ERROR" } },
+          { "message": "timeout" }
+        ]
+      }),
+    )
+    .await;
+
+    assert_eq!(
+      body["errors"],
+      json!([
+        {
+          "message": "Unexpected error.",
+          "extensions": { "code": "DOWNSTREAM_SERVICE_ERROR", "originalError": "connection refused" }
+        },
+        { "message": "Unexpected error.", "extensions": { "originalError": "timeout" } }
+      ])
+    );
+  }
+
+  #[tokio::test]
+  async fn removes_extensions() {
+    let config = MaskErrorsPluginConfig {
+      allowed_codes: vec!["BAD_USER_INPUT".to_string()],
+      allowed_extensions: vec!["requestId".to_string()],
+      ..Default::default()
+    };
+    let body = execute(
+      config,
+      json!({
+        "errors": [
+          {
+            "message": "relation \"users\" does not exist",
+            "extensions": {
+              "code": "INTERNAL_SERVER_ERROR",
+              "requestId": "1",
+              "exception": { "stacktrace": ["Error: relation \"users\" does not exist"] }
+            }
+          },
+          { "message": "timeout", "extensions": { "exception": { "stacktrace": [] } } },
+          {
+            "message": "invalid email",
+            "extensions": { "code": "BAD_USER_INPUT", "exception": { "stacktrace": [] } }
+          }
+        ]
+      }),
+    )
+    .await;
+
+    assert_eq!(
+      body["errors"],
+      json!([
+        {
+          "message": "Unexpected error.",
+          "extensions": { "code": "INTERNAL_SERVER_ERROR", "requestId": "1" }
+        },
+        { "message": "Unexpected error." },
+        {
+          "message": "invalid email",
+          "extensions": { "code": "BAD_USER_INPUT", "exception": { "stacktrace": [] } }
+        }
+      ])
+    );
+  }
+
+  #[tokio::test]
+  async fn ignores_responses_without_errors() {
+    let body = json!({ "data": { "__typename": "Query" } });
+
+    assert_eq!(execute(Default::default(), body.clone()).await, body);
+  }
+}
@@ -1,3 +1,4 @@
 mod plugin;
 
 pub use crate::plugin::MatchContentTypePlugin as Plugin;
+pub use crate::plugin::DEFAULT_RESPONSE_CONTENT_TYPE;
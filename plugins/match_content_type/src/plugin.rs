@@ -1,14 +1,61 @@
 use conductor_common::{
   graphql::APPLICATION_GRAPHQL_JSON,
-  http::{extract_accept, ConductorHttpResponse, HeaderValue, APPLICATION_JSON, CONTENT_TYPE},
+  http::{ConductorHttpResponse, HeaderValue, ACCEPT, CONTENT_TYPE},
 };
 
 use conductor_common::execute::RequestExecutionContext;
 
 use conductor_common::plugin::Plugin;
 
+pub static DEFAULT_RESPONSE_CONTENT_TYPE: &str = "application/json";
+
+static GRAPHQL_RESPONSE_CONTENT_TYPES: [&str; 2] =
+  [APPLICATION_GRAPHQL_JSON, DEFAULT_RESPONSE_CONTENT_TYPE];
+
+/// Sets the `Content-Type` of responses that don't have one yet: the first GraphQL response media type listed in the `Accept` header, or the endpoint's default content type when there is none (no `Accept` header, or `*/*`).
 #[derive(Debug)]
-pub struct MatchContentTypePlugin;
+pub struct MatchContentTypePlugin {
+  default_content_type: HeaderValue,
+}
+
+impl MatchContentTypePlugin {
+  pub fn new(default_content_type: HeaderValue) -> Self {
+    Self {
+      default_content_type,
+    }
+  }
+
+  fn negotiate(&self, ctx: &RequestExecutionContext) -> HeaderValue {
+    let accept = match ctx
+      .downstream_http_request
+      .headers
+      .get(ACCEPT)
+      .and_then(|value| value.to_str().ok())
+    {
+      Some(accept) => accept,
+      None => return self.default_content_type.clone(),
+    };
+
+    accept
+      .split(',')
+      .filter_map(|media_range| media_range.split(';').next())
+      .map(str::trim)
+      .find_map(|media_type| {
+        GRAPHQL_RESPONSE_CONTENT_TYPES
+          .iter()
+          .find(|content_type| content_type.eq_ignore_ascii_case(media_type))
+          .copied()
+      })
+      .map(HeaderValue::from_static)
+      .unwrap_or_else(|| self.default_content_type.clone())
+  }
+}
+
+impl Default for MatchContentTypePlugin {
+  fn default() -> Self {
+    Self::new(HeaderValue::from_static(DEFAULT_RESPONSE_CONTENT_TYPE))
+  }
+}
 
 #[async_trait::async_trait(?Send)]
 impl Plugin for MatchContentTypePlugin {
@@ -17,20 +64,8 @@ impl Plugin for MatchContentTypePlugin {
     ctx: &mut RequestExecutionContext,
     response: &mut ConductorHttpResponse,
   ) {
-    let headers = &mut response.headers;
-
-    if headers.get(CONTENT_TYPE).is_none() {
-      let accept_header =
-        extract_accept(&ctx.downstream_http_request.headers).unwrap_or(APPLICATION_JSON);
-
-      if accept_header == APPLICATION_JSON || accept_header == "*/*" {
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-      } else if accept_header == APPLICATION_GRAPHQL_JSON {
-        headers.insert(
-          CONTENT_TYPE,
-          HeaderValue::from_static(APPLICATION_GRAPHQL_JSON),
-        );
-      }
+    if !response.headers.contains_key(CONTENT_TYPE) {
+      response.headers.insert(CONTENT_TYPE, self.negotiate(ctx));
     }
   }
 }
@@ -0,0 +1,47 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `max_body_size` plugin rejects requests with a body larger than the configured limit, with a `413` status code.
+///
+/// The `Content-Length` header is checked first, so oversized requests are rejected without looking at their body. Requests without a `Content-Length` header are checked using the length of their body.
+///
+/// This check is cheap, so it's recommended to declare this plugin before other plugins, to reject oversized requests before they are processed.
+///
+/// The server stops reading request bodies that exceed the `max_body_bytes` of their endpoint, and rejects them with a `413` status code before buffering them. Endpoints without this plugin accept bodies up to 256KB.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "max_body_size_example")]
+pub struct MaxBodySizePluginConfig {
+  /// The maximum size of a request body, in bytes.
+  #[serde(default = "default_max_body_bytes")]
+  pub max_body_bytes: usize,
+}
+
+impl Default for MaxBodySizePluginConfig {
+  fn default() -> Self {
+    Self {
+      max_body_bytes: default_max_body_bytes(),
+    }
+  }
+}
+
+fn default_max_body_bytes() -> usize {
+  1024 * 1024
+}
+
+fn max_body_size_example() -> JsonSchemaExample<MaxBodySizePluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Limit body size",
+      Some("This example rejects requests with a body larger than 100KB."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "max_body_size".to_string(),
+    }),
+    example: MaxBodySizePluginConfig {
+      max_body_bytes: 100 * 1024,
+    },
+  }
+}
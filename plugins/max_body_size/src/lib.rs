@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::MaxBodySizePluginConfig as Config;
+pub use plugin::MaxBodySizePlugin as Plugin;
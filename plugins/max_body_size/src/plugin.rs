@@ -0,0 +1,105 @@
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{header::CONTENT_LENGTH, StatusCode},
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use tracing::debug;
+
+use crate::config::MaxBodySizePluginConfig;
+
+#[derive(Debug)]
+pub struct MaxBodySizePlugin(MaxBodySizePluginConfig);
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for MaxBodySizePlugin {
+  type Config = MaxBodySizePluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    Ok(Box::new(Self(config)))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for MaxBodySizePlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    let request = &ctx.downstream_http_request;
+    // An invalid `Content-Length` is ignored, the actual body is still checked.
+    let content_length = request
+      .headers
+      .get(CONTENT_LENGTH)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.trim().parse::<u64>().ok());
+    let body_size = match content_length {
+      Some(content_length) if content_length > self.0.max_body_bytes as u64 => content_length,
+      _ => request.body.len() as u64,
+    };
+
+    if body_size > self.0.max_body_bytes as u64 {
+      debug!(
+        "request body of {} bytes exceeds the maximum of {} bytes",
+        body_size, self.0.max_body_bytes
+      );
+
+      ctx.short_circuit(
+        GraphQLResponse::new_error(&format!(
+          "request body exceeds the maximum size of {} bytes",
+          self.0.max_body_bytes
+        ))
+        .into_with_status_code(StatusCode::PAYLOAD_TOO_LARGE),
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::http::{Bytes, ConductorHttpRequest, HttpHeadersMap};
+
+  use super::*;
+
+  async fn send(content_length: Option<&str>, body: &'static str) -> Option<StatusCode> {
+    let plugin = MaxBodySizePlugin::create(MaxBodySizePluginConfig { max_body_bytes: 10 })
+      .await
+      .unwrap();
+    let mut headers = HttpHeadersMap::new();
+
+    if let Some(content_length) = content_length {
+      headers.insert(CONTENT_LENGTH, content_length.parse().unwrap());
+    }
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      headers,
+      body: Bytes::from_static(body.as_bytes()),
+      ..Default::default()
+    });
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    ctx.short_circuit_response.map(|response| response.status)
+  }
+
+  #[tokio::test]
+  async fn allows_small_body() {
+    assert_eq!(send(Some("10"), "0123456789").await, None);
+    assert_eq!(send(None, "0123456789").await, None);
+  }
+
+  #[tokio::test]
+  async fn rejects_large_content_length() {
+    assert_eq!(
+      send(Some("11"), "").await,
+      Some(StatusCode::PAYLOAD_TOO_LARGE)
+    );
+  }
+
+  #[tokio::test]
+  async fn rejects_large_body() {
+    // The body is checked when the header is missing, invalid or doesn't match the body.
+    for content_length in [None, Some("invalid"), Some("1")] {
+      assert_eq!(
+        send(content_length, "01234567890").await,
+        Some(StatusCode::PAYLOAD_TOO_LARGE)
+      );
+    }
+  }
+}
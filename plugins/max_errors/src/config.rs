@@ -0,0 +1,43 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `max_errors` plugin limits the number of GraphQL errors returned to the client in a single response.
+///
+/// When a response contains more errors than the configured limit, only the first `max_errors` errors are returned, followed by a single error with the message `N more errors omitted`.
+///
+/// The complete list of errors of a truncated response is always written to the gateway logs.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "max_errors_example")]
+pub struct MaxErrorsPluginConfig {
+  /// The maximum number of errors returned to the client, not including the error added to note the omitted errors.
+  #[serde(default = "default_max_errors")]
+  pub max_errors: usize,
+}
+
+impl Default for MaxErrorsPluginConfig {
+  fn default() -> Self {
+    Self {
+      max_errors: default_max_errors(),
+    }
+  }
+}
+
+fn default_max_errors() -> usize {
+  100
+}
+
+fn max_errors_example() -> JsonSchemaExample<MaxErrorsPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Limit errors",
+      Some("This example returns at most 10 GraphQL errors in a response."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "max_errors".to_string(),
+    }),
+    example: MaxErrorsPluginConfig { max_errors: 10 },
+  }
+}
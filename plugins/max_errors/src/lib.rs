@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::MaxErrorsPluginConfig as Config;
+pub use plugin::MaxErrorsPlugin as Plugin;
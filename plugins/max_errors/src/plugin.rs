@@ -0,0 +1,127 @@
+use conductor_common::{
+  execute::RequestExecutionContext,
+  http::ConductorHttpResponse,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::config::MaxErrorsPluginConfig;
+
+#[derive(Debug)]
+pub struct MaxErrorsPlugin(MaxErrorsPluginConfig);
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for MaxErrorsPlugin {
+  type Config = MaxErrorsPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    Ok(Box::new(Self(config)))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for MaxErrorsPlugin {
+  fn on_downstream_http_response(
+    &self,
+    _ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    let mut body = match serde_json::from_slice::<Value>(&response.body) {
+      Ok(body) => body,
+      Err(_) => return,
+    };
+
+    let errors = match body.get_mut("errors").and_then(Value::as_array_mut) {
+      Some(errors) if errors.len() > self.0.max_errors => errors,
+      _ => return,
+    };
+
+    warn!(
+      "response contains {} errors, truncating to {}. full list of errors: {}",
+      errors.len(),
+      self.0.max_errors,
+      Value::Array(errors.clone())
+    );
+
+    let omitted = errors.len() - self.0.max_errors;
+    errors.truncate(self.0.max_errors);
+    errors.push(json!({
+      "message": format!("{} more errors omitted", omitted)
+    }));
+
+    if let Ok(body) = serde_json::to_vec(&body) {
+      response.body = body.into();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    graphql::{GraphQLError, GraphQLResponse},
+    http::StatusCode,
+  };
+
+  use super::*;
+
+  async fn respond(max_errors: usize, errors_count: usize) -> Value {
+    let plugin = MaxErrorsPlugin::create(MaxErrorsPluginConfig { max_errors })
+      .await
+      .unwrap();
+    let errors = (0..errors_count)
+      .map(|i| GraphQLError::new(&format!("error {}", i)))
+      .collect();
+    let mut response = GraphQLResponse::new_errors(errors).into_with_status_code(StatusCode::OK);
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    serde_json::from_slice(&response.body).unwrap()
+  }
+
+  fn messages(body: &Value) -> Vec<&str> {
+    body["errors"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|error| error["message"].as_str().unwrap())
+      .collect()
+  }
+
+  #[tokio::test]
+  async fn under_the_limit() {
+    let body = respond(3, 3).await;
+
+    assert_eq!(messages(&body), vec!["error 0", "error 1", "error 2"]);
+  }
+
+  #[tokio::test]
+  async fn over_the_limit() {
+    let body = respond(2, 5).await;
+
+    assert_eq!(
+      messages(&body),
+      vec!["error 0", "error 1", "3 more errors omitted"]
+    );
+  }
+
+  #[tokio::test]
+  async fn keeps_data() {
+    let plugin = MaxErrorsPlugin::create(MaxErrorsPluginConfig { max_errors: 1 })
+      .await
+      .unwrap();
+    let mut response = ConductorHttpResponse {
+      body: json!({ "data": { "user": null }, "errors": [{ "message": "a" }, { "message": "b" }] })
+        .to_string()
+        .into(),
+      status: StatusCode::OK,
+      headers: Default::default(),
+    };
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    let body: Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(body["data"], json!({ "user": null }));
+    assert_eq!(messages(&body), vec!["a", "1 more errors omitted"]);
+  }
+}
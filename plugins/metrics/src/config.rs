@@ -0,0 +1,73 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `metrics` plugin records metrics about the requests handled by the gateway, and exposes them in the Prometheus text format.
+///
+/// The metrics are kept in memory, and can be scraped with a `GET` request to the configured `path` of the endpoint (for example: `GET /graphql/metrics`). The request must send the configured `secret` as a bearer token (`Authorization: Bearer <secret>`, see the `authorization` option of the Prometheus scrape configuration), or be authenticated by an authentication plugin (like `jwt_auth`) that runs before this plugin. The following metrics are recorded:
+///
+/// - `conductor_requests_total` (counter): the number of requests, labeled by `endpoint`, `source` and response `status`.
+///
+/// - `conductor_upstream_request_duration_seconds` (histogram): the latency of the requests sent to the upstream, labeled by `endpoint` and `source`.
+///
+/// - `conductor_shadow_comparisons_total` (counter): the number of shadow responses compared with the primary responses, labeled by `shadow` upstream and `result` (`match` or `mismatch`). See the `shadow` option of the GraphQL source.
+///
+/// - `conductor_trusted_documents_lookups_total` (counter): the number of trusted document lookups, labeled by `protocol` and `result` (`hit`, `miss` or `store_error`). See the `trusted_documents` plugin.
+///
+/// - `conductor_trusted_documents_remote_cache_total` (counter): the number of lookups of a `remote` trusted documents store, labeled by `store` URL and `result` (`hit` if served from the cache, or `miss`).
+///
+/// The `source` label is empty for requests that were rejected before a GraphQL operation was extracted.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "metrics_example")]
+pub struct MetricsPluginConfig {
+  /// The path that exposes the metrics, relative to the endpoint path.
+  #[serde(default = "default_path")]
+  pub path: String,
+  /// The upper bounds (in seconds) of the buckets of the upstream latency histogram, in increasing order.
+  #[serde(default = "default_latency_buckets")]
+  pub latency_buckets: Vec<f64>,
+  /// A secret that must be sent as a bearer token (`Authorization: Bearer <secret>`) to scrape the metrics.
+  ///
+  /// When not set, the request must be authenticated by an authentication plugin that runs before this plugin.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub secret: Option<String>,
+}
+
+impl Default for MetricsPluginConfig {
+  fn default() -> Self {
+    Self {
+      path: default_path(),
+      latency_buckets: default_latency_buckets(),
+      secret: None,
+    }
+  }
+}
+
+fn default_path() -> String {
+  "/metrics".to_string()
+}
+
+fn default_latency_buckets() -> Vec<f64> {
+  vec![
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+  ]
+}
+
+fn metrics_example() -> JsonSchemaExample<MetricsPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Custom path",
+      Some("This example exposes the metrics on `GET /graphql/_metrics` (assuming the endpoint is `/graphql`) to scrapers that send the secret as a bearer token."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "metrics".to_string(),
+    }),
+    example: MetricsPluginConfig {
+      path: "/_metrics".to_string(),
+      secret: Some("my-secret".to_string()),
+      ..Default::default()
+    },
+  }
+}
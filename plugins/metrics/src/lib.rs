@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::MetricsPluginConfig as Config;
+pub use plugin::MetricsPlugin as Plugin;
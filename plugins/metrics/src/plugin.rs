@@ -0,0 +1,477 @@
+use std::{
+  collections::BTreeMap,
+  fmt::Write,
+  sync::{Arc, Mutex},
+};
+
+use conductor_common::{
+  admin_endpoint::AdminEndpoint,
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{
+    ConductorHttpRequest, ConductorHttpResponse, HeaderValue, HttpHeadersMap, StatusCode,
+    CONTENT_TYPE,
+  },
+  metrics::counters,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+  source::SourceRuntime,
+};
+use serde_json::Value;
+use web_time::Instant;
+
+use crate::config::MetricsPluginConfig;
+
+static SCRAPE_CONTEXT_KEY: &str = "metrics:scrape";
+static SOURCE_CONTEXT_KEY: &str = "metrics:source";
+static UPSTREAM_STARTED_CONTEXT_KEY: &str = "metrics:upstream_started";
+
+#[derive(Debug)]
+struct Histogram {
+  /// The number of observations of every bucket, not cumulative. The last one counts the observations above the highest bound.
+  counts: Vec<u64>,
+  sum: f64,
+  count: u64,
+}
+
+impl Histogram {
+  fn new(bounds: &[f64]) -> Self {
+    Self {
+      counts: vec![0; bounds.len() + 1],
+      sum: 0.0,
+      count: 0,
+    }
+  }
+
+  fn observe(&mut self, bounds: &[f64], value: f64) {
+    let index = bounds
+      .iter()
+      .position(|bound| value <= *bound)
+      .unwrap_or(bounds.len());
+
+    self.counts[index] += 1;
+    self.sum += value;
+    self.count += 1;
+  }
+}
+
+#[derive(Debug, Default)]
+struct MetricsStore {
+  /// Keyed by endpoint, source and status code.
+  requests: BTreeMap<(String, String, u16), u64>,
+  /// Keyed by endpoint and source.
+  upstream_latencies: BTreeMap<(String, String), Histogram>,
+}
+
+#[derive(Debug)]
+pub struct MetricsPlugin {
+  config: MetricsPluginConfig,
+  endpoint: AdminEndpoint,
+  /// Request timings are stored in the request context as seconds since this instant.
+  started: Instant,
+  store: Mutex<MetricsStore>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for MetricsPlugin {
+  type Config = MetricsPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    let valid_buckets = config.latency_buckets.iter().all(|bound| bound.is_finite())
+      && config
+        .latency_buckets
+        .windows(2)
+        .all(|bounds| bounds[0] < bounds[1]);
+
+    if !valid_buckets {
+      return Err(PluginError::InitError {
+        source: anyhow::anyhow!("latency_buckets must be finite and in increasing order"),
+      });
+    }
+
+    Ok(Box::new(Self {
+      endpoint: AdminEndpoint::new(&config.path, config.secret.clone()),
+      config,
+      started: Instant::now(),
+      store: Mutex::new(MetricsStore::default()),
+    }))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for MetricsPlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    if self.endpoint.handle(ctx, || self.render()) {
+      ctx.ctx_insert(SCRAPE_CONTEXT_KEY, true);
+    }
+  }
+
+  async fn on_downstream_graphql_request(
+    &self,
+    source_runtime: Arc<Box<dyn SourceRuntime>>,
+    ctx: &mut RequestExecutionContext,
+  ) {
+    ctx.ctx_insert(SOURCE_CONTEXT_KEY, source_runtime.name());
+  }
+
+  async fn on_upstream_http_request(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    _req: &mut ConductorHttpRequest,
+  ) {
+    ctx.ctx_insert(
+      UPSTREAM_STARTED_CONTEXT_KEY,
+      self.started.elapsed().as_secs_f64(),
+    );
+  }
+
+  async fn on_upstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    _response: &mut ConductorHttpResponse,
+  ) {
+    let started = match ctx
+      .ctx_get(UPSTREAM_STARTED_CONTEXT_KEY)
+      .and_then(Value::as_f64)
+    {
+      Some(started) => started,
+      None => return,
+    };
+    let latency = (self.started.elapsed().as_secs_f64() - started).max(0.0);
+    let bounds = &self.config.latency_buckets;
+
+    if let Ok(mut store) = self.store.lock() {
+      store
+        .upstream_latencies
+        .entry(labels(ctx))
+        .or_insert_with(|| Histogram::new(bounds))
+        .observe(bounds, latency);
+    }
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    // Scrapes are not counted as requests.
+    if ctx.ctx_get(SCRAPE_CONTEXT_KEY).is_some() {
+      return;
+    }
+
+    let (endpoint, source) = labels(ctx);
+
+    if let Ok(mut store) = self.store.lock() {
+      *store
+        .requests
+        .entry((endpoint, source, response.status.as_u16()))
+        .or_default() += 1;
+    }
+  }
+}
+
+impl MetricsPlugin {
+  fn render(&self) -> ConductorHttpResponse {
+    let store = match self.store.lock() {
+      Ok(store) => store,
+      Err(_) => {
+        return GraphQLResponse::new_error("failed to access recorded metrics")
+          .into_with_status_code(StatusCode::INTERNAL_SERVER_ERROR)
+      }
+    };
+    let mut body = String::new();
+
+    // Writing to a `String` can't fail.
+    let _ = writeln!(
+      body,
+      "# HELP conductor_requests_total The number of requests handled by the gateway."
+    );
+    let _ = writeln!(body, "# TYPE conductor_requests_total counter");
+
+    for ((endpoint, source, status), count) in store.requests.iter() {
+      let _ = writeln!(
+        body,
+        "conductor_requests_total{{endpoint=\"{}\",source=\"{}\",status=\"{}\"}} {}",
+        escape_label(endpoint),
+        escape_label(source),
+        status,
+        count
+      );
+    }
+
+    let _ = writeln!(
+      body,
+      "# HELP conductor_upstream_request_duration_seconds The latency of the requests sent to the upstream."
+    );
+    let _ = writeln!(
+      body,
+      "# TYPE conductor_upstream_request_duration_seconds histogram"
+    );
+
+    for ((endpoint, source), histogram) in store.upstream_latencies.iter() {
+      let labels = format!(
+        "endpoint=\"{}\",source=\"{}\"",
+        escape_label(endpoint),
+        escape_label(source)
+      );
+      let mut cumulative = 0;
+
+      for (bound, count) in self.config.latency_buckets.iter().zip(&histogram.counts) {
+        cumulative += count;
+        let _ = writeln!(
+          body,
+          "conductor_upstream_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+          labels, bound, cumulative
+        );
+      }
+
+      let _ = writeln!(
+        body,
+        "conductor_upstream_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+        labels, histogram.count
+      );
+      let _ = writeln!(
+        body,
+        "conductor_upstream_request_duration_seconds_sum{{{}}} {}",
+        labels, histogram.sum
+      );
+      let _ = writeln!(
+        body,
+        "conductor_upstream_request_duration_seconds_count{{{}}} {}",
+        labels, histogram.count
+      );
+    }
+
+    for (name, counter) in counters() {
+      let _ = writeln!(body, "# HELP {} {}", name, counter.help);
+      let _ = writeln!(body, "# TYPE {} counter", name);
+
+      for (labels, count) in counter.values.iter() {
+        let labels = labels
+          .iter()
+          .map(|(label, value)| format!("{}=\"{}\"", label, escape_label(value)))
+          .collect::<Vec<_>>()
+          .join(",");
+
+        let _ = writeln!(body, "{}{{{}}} {}", name, labels, count);
+      }
+    }
+
+    let mut headers = HttpHeadersMap::new();
+    headers.append(
+      CONTENT_TYPE,
+      HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8"),
+    );
+
+    ConductorHttpResponse {
+      body: body.into(),
+      status: StatusCode::OK,
+      headers,
+    }
+  }
+}
+
+/// Returns the endpoint and source labels of the request.
+fn labels(ctx: &RequestExecutionContext) -> (String, String) {
+  let source = ctx
+    .ctx_get(SOURCE_CONTEXT_KEY)
+    .and_then(Value::as_str)
+    .unwrap_or_default();
+
+  (
+    ctx.endpoint().unwrap_or_default().to_string(),
+    source.to_string(),
+  )
+}
+
+fn escape_label(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    execute::ENDPOINT_CONTEXT_KEY, http::Method, metrics::increment_counter, source::TestSource,
+  };
+
+  use super::*;
+
+  fn response(status: StatusCode) -> ConductorHttpResponse {
+    ConductorHttpResponse {
+      body: Default::default(),
+      status,
+      headers: Default::default(),
+    }
+  }
+
+  /// Runs a request through the plugin, with an upstream request when `status` is OK.
+  async fn request(plugin: &MetricsPlugin, status: StatusCode) {
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::POST,
+      uri: "/graphql".to_string(),
+      ..Default::default()
+    });
+    ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, "/graphql");
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    if status == StatusCode::OK {
+      plugin
+        .on_downstream_graphql_request(Arc::new(Box::new(TestSource::default())), &mut ctx)
+        .await;
+      plugin
+        .on_upstream_http_request(&mut ctx, &mut Default::default())
+        .await;
+      plugin
+        .on_upstream_http_response(&mut ctx, &mut response(StatusCode::OK))
+        .await;
+    }
+
+    plugin.on_downstream_http_response(&mut ctx, &mut response(status));
+  }
+
+  async fn scrape(plugin: &MetricsPlugin, uri: &str) -> Option<ConductorHttpResponse> {
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::GET,
+      uri: uri.to_string(),
+      ..Default::default()
+    });
+    ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, "/graphql");
+    ctx.mark_authenticated();
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    let mut response = ctx.short_circuit_response.take()?;
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    Some(response)
+  }
+
+  fn body(response: &ConductorHttpResponse) -> &str {
+    std::str::from_utf8(&response.body).unwrap()
+  }
+
+  #[tokio::test]
+  async fn records_requests() {
+    let plugin = MetricsPlugin::create(MetricsPluginConfig {
+      latency_buckets: vec![60.0],
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    request(&plugin, StatusCode::OK).await;
+    request(&plugin, StatusCode::OK).await;
+    request(&plugin, StatusCode::UNAUTHORIZED).await;
+
+    let response = scrape(&plugin, "/graphql/metrics").await.unwrap();
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+      response.headers.get(CONTENT_TYPE).unwrap(),
+      "text/plain; version=0.0.4; charset=utf-8"
+    );
+
+    let body = body(&response);
+    assert!(body.contains(
+      "conductor_requests_total{endpoint=\"/graphql\",source=\"test\",status=\"200\"} 2\n"
+    ));
+    assert!(body
+      .contains("conductor_requests_total{endpoint=\"/graphql\",source=\"\",status=\"401\"} 1\n"));
+    assert!(body.contains(
+      "conductor_upstream_request_duration_seconds_bucket{endpoint=\"/graphql\",source=\"test\",le=\"60\"} 2\n"
+    ));
+    assert!(body.contains(
+      "conductor_upstream_request_duration_seconds_bucket{endpoint=\"/graphql\",source=\"test\",le=\"+Inf\"} 2\n"
+    ));
+    assert!(body.contains(
+      "conductor_upstream_request_duration_seconds_count{endpoint=\"/graphql\",source=\"test\"} 2\n"
+    ));
+  }
+
+  #[tokio::test]
+  async fn exposes_shared_counters() {
+    let plugin = MetricsPlugin::create(Default::default()).await.unwrap();
+
+    increment_counter(
+      "conductor_test_total",
+      "A counter recorded outside of the plugin.",
+      &[("result", "a\"b")],
+    );
+
+    let response = scrape(&plugin, "/graphql/metrics").await.unwrap();
+    let body = body(&response);
+    assert!(
+      body.contains("# HELP conductor_test_total A counter recorded outside of the plugin.\n")
+    );
+    assert!(body.contains("# TYPE conductor_test_total counter\n"));
+    assert!(body.contains("conductor_test_total{result=\"a\\\"b\"} 1\n"));
+  }
+
+  #[tokio::test]
+  async fn does_not_count_scrapes() {
+    let plugin = MetricsPlugin::create(Default::default()).await.unwrap();
+
+    scrape(&plugin, "/graphql/metrics").await.unwrap();
+    let response = scrape(&plugin, "/graphql/metrics?format=text")
+      .await
+      .unwrap();
+
+    assert!(!body(&response).contains("conductor_requests_total{"));
+  }
+
+  #[tokio::test]
+  async fn ignores_other_paths() {
+    let plugin = MetricsPlugin::create(Default::default()).await.unwrap();
+
+    assert!(scrape(&plugin, "/graphql").await.is_none());
+    assert!(scrape(&plugin, "/graphql/metrics/other").await.is_none());
+    assert!(scrape(&plugin, "/graphql/other/metrics").await.is_none());
+  }
+
+  #[tokio::test]
+  async fn requires_credentials() {
+    let plugin = MetricsPlugin::create(MetricsPluginConfig {
+      secret: Some("secret".to_string()),
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    // Authenticated requests still need the secret, when it's configured.
+    let response = scrape(&plugin, "/graphql/metrics").await.unwrap();
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers.get("www-authenticate").unwrap(), "Bearer");
+
+    let mut headers = HttpHeadersMap::new();
+    headers.insert("authorization", "Bearer secret".parse().unwrap());
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::GET,
+      uri: "/graphql/metrics".to_string(),
+      headers,
+      ..Default::default()
+    });
+    ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, "/graphql");
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    let response = ctx.short_circuit_response.unwrap();
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(body(&response).contains("conductor_requests_total"));
+  }
+
+  #[tokio::test]
+  async fn rejects_invalid_buckets() {
+    for latency_buckets in [vec![1.0, 0.5], vec![1.0, 1.0], vec![f64::NAN]] {
+      let config = MetricsPluginConfig {
+        latency_buckets,
+        ..Default::default()
+      };
+
+      assert!(MetricsPlugin::create(config).await.is_err());
+    }
+  }
+
+  #[test]
+  fn escapes_labels() {
+    assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+  }
+}
@@ -0,0 +1,45 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `omit_nulls` plugin reduces the size of GraphQL responses by removing fields with a `null` value from the `data` of the response.
+///
+/// Fields are removed at any depth of the response, including objects nested in lists. Items of lists are never removed (even if they are `null`), so the ordering and length of lists are preserved. The `errors` and `extensions` of the response are not modified.
+///
+/// > Note: clients must treat missing fields as `null` when this plugin is used.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "omit_nulls_example")]
+pub struct OmitNullsPluginConfig {
+  /// Removes fields with a `null` value from the response `data`. When disabled, responses are returned unchanged.
+  #[serde(default = "default_omit_null_fields")]
+  pub omit_null_fields: bool,
+}
+
+impl Default for OmitNullsPluginConfig {
+  fn default() -> Self {
+    Self {
+      omit_null_fields: default_omit_null_fields(),
+    }
+  }
+}
+
+fn default_omit_null_fields() -> bool {
+  true
+}
+
+fn omit_nulls_example() -> JsonSchemaExample<OmitNullsPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Omit nulls",
+      Some("This example removes all `null` fields from the response `data`."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "omit_nulls".to_string(),
+    }),
+    example: OmitNullsPluginConfig {
+      omit_null_fields: true,
+    },
+  }
+}
@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::OmitNullsPluginConfig as Config;
+pub use plugin::OmitNullsPlugin as Plugin;
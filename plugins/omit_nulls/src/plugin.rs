@@ -0,0 +1,139 @@
+use conductor_common::{
+  execute::RequestExecutionContext,
+  http::ConductorHttpResponse,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use serde_json::Value;
+
+use crate::config::OmitNullsPluginConfig;
+
+#[derive(Debug)]
+pub struct OmitNullsPlugin(OmitNullsPluginConfig);
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for OmitNullsPlugin {
+  type Config = OmitNullsPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    Ok(Box::new(Self(config)))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for OmitNullsPlugin {
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    if !self.0.omit_null_fields || ctx.downstream_graphql_request.is_none() {
+      return;
+    }
+
+    let mut body = match serde_json::from_slice::<Value>(&response.body) {
+      Ok(body) => body,
+      Err(_) => return,
+    };
+
+    if let Some(data) = body.get_mut("data") {
+      omit_null_fields(data);
+
+      if let Ok(body) = serde_json::to_vec(&body) {
+        response.body = body.into();
+      }
+    }
+  }
+}
+
+/// Removes object fields with a `null` value. List items are kept in place, to preserve the ordering of lists.
+fn omit_null_fields(value: &mut Value) {
+  match value {
+    Value::Object(map) => {
+      map.retain(|_, value| !value.is_null());
+      map.values_mut().for_each(omit_null_fields);
+    }
+    Value::Array(values) => values.iter_mut().for_each(omit_null_fields),
+    _ => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    graphql::{GraphQLRequest, ParsedGraphQLRequest},
+    http::StatusCode,
+  };
+  use serde_json::json;
+
+  use super::*;
+
+  async fn respond(omit_null_fields: bool, body: Value) -> Value {
+    let plugin = OmitNullsPlugin::create(OmitNullsPluginConfig { omit_null_fields })
+      .await
+      .unwrap();
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    ctx.downstream_graphql_request = Some(
+      ParsedGraphQLRequest::create_and_parse(GraphQLRequest {
+        operation: "query { __typename }".to_string(),
+        operation_name: None,
+        variables: None,
+        extensions: None,
+      })
+      .unwrap(),
+    );
+    let mut response = ConductorHttpResponse {
+      body: body.to_string().into(),
+      status: StatusCode::OK,
+      headers: Default::default(),
+    };
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    serde_json::from_slice(&response.body).unwrap()
+  }
+
+  #[tokio::test]
+  async fn nested_objects() {
+    let body = respond(
+      true,
+      json!({
+        "data": { "user": { "name": "Dotan", "email": null, "address": { "city": null, "country": "IL" } }, "viewer": null },
+        "errors": [{ "message": "failed", "path": null }]
+      }),
+    )
+    .await;
+
+    assert_eq!(
+      body,
+      json!({
+        "data": { "user": { "name": "Dotan", "address": { "country": "IL" } } },
+        "errors": [{ "message": "failed", "path": null }]
+      })
+    );
+  }
+
+  #[tokio::test]
+  async fn lists() {
+    let body = respond(
+      true,
+      json!({
+        "data": { "users": [{ "id": 1, "name": null }, null, { "id": 3, "tags": [null, "a"] }] }
+      }),
+    )
+    .await;
+
+    assert_eq!(
+      body,
+      json!({
+        "data": { "users": [{ "id": 1 }, null, { "id": 3, "tags": [null, "a"] }] }
+      })
+    );
+  }
+
+  #[tokio::test]
+  async fn disabled() {
+    let original = json!({ "data": { "user": { "name": null } } });
+    let body = respond(false, original.clone()).await;
+
+    assert_eq!(body, original);
+  }
+}
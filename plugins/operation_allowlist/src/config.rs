@@ -0,0 +1,34 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `operation_allowlist` plugin restricts the endpoint to a set of pre-approved GraphQL operations.
+///
+/// Unlike trusted documents, clients keep sending the full GraphQL operation. The gateway normalizes the incoming operation (whitespace, commas and comments are ignored), hashes it with SHA-256, and rejects operations with a hash that is not part of the allowlist with a `403` status code.
+///
+/// To compute the hash of an operation, send it once through the gateway with the `debug` log level: the hash of every rejected operation is logged.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "operation_allowlist_example")]
+pub struct OperationAllowlistPluginConfig {
+  /// The hex-encoded SHA-256 hashes of the allowed normalized operations.
+  pub hashes: Vec<String>,
+}
+
+fn operation_allowlist_example() -> JsonSchemaExample<OperationAllowlistPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Allowlist",
+      Some(
+        "This example allows a single operation, identified by the hash of its normalized form.",
+      ),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "operation_allowlist".to_string(),
+    }),
+    example: OperationAllowlistPluginConfig {
+      hashes: vec!["2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae".to_string()],
+    },
+  }
+}
@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::OperationAllowlistPluginConfig as Config;
+pub use plugin::OperationAllowlistPlugin as Plugin;
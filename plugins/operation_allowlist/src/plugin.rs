@@ -0,0 +1,122 @@
+use std::{collections::HashSet, sync::Arc};
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::StatusCode,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+  source::SourceRuntime,
+};
+use tracing::debug;
+
+use crate::config::OperationAllowlistPluginConfig;
+
+#[derive(Debug)]
+pub struct OperationAllowlistPlugin {
+  hashes: HashSet<String>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for OperationAllowlistPlugin {
+  type Config = OperationAllowlistPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    Ok(Box::new(Self {
+      hashes: config
+        .hashes
+        .into_iter()
+        .map(|hash| hash.to_lowercase())
+        .collect(),
+    }))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for OperationAllowlistPlugin {
+  async fn on_downstream_graphql_request(
+    &self,
+    _source_runtime: Arc<Box<dyn SourceRuntime>>,
+    ctx: &mut RequestExecutionContext,
+  ) {
+    let hash = match &ctx.downstream_graphql_request {
+      Some(gql_request) => gql_request.normalized_operation_hash(),
+      None => return,
+    };
+
+    if !self.hashes.contains(&hash) {
+      debug!("operation with hash {} is not allowed", hash);
+
+      ctx.short_circuit(
+        GraphQLResponse::new_error("operation is not allowed")
+          .into_with_status_code(StatusCode::FORBIDDEN),
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    graphql::{GraphQLRequest, ParsedGraphQLRequest},
+    source::TestSource,
+  };
+
+  use super::*;
+
+  fn parse(operation: &str) -> ParsedGraphQLRequest {
+    ParsedGraphQLRequest::create_and_parse(GraphQLRequest {
+      operation: operation.to_string(),
+      operation_name: None,
+      variables: None,
+      extensions: None,
+    })
+    .unwrap()
+  }
+
+  async fn execute(plugin: &OperationAllowlistPlugin, operation: &str) -> RequestExecutionContext {
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    ctx.downstream_graphql_request = Some(parse(operation));
+    plugin
+      .on_downstream_graphql_request(Arc::new(Box::new(TestSource::default())), &mut ctx)
+      .await;
+
+    ctx
+  }
+
+  async fn plugin() -> Box<OperationAllowlistPlugin> {
+    OperationAllowlistPlugin::create(OperationAllowlistPluginConfig {
+      hashes: vec![parse("query GetUser { user(id: 1) { id name } }").normalized_operation_hash()],
+    })
+    .await
+    .unwrap()
+  }
+
+  #[tokio::test]
+  async fn allowed_operation() {
+    let plugin = plugin().await;
+
+    let ctx = execute(
+      &plugin,
+      r#"
+        # fetches the user
+        query GetUser {
+          user(id: 1) {
+            id,
+            name
+          }
+        }
+      "#,
+    )
+    .await;
+    assert!(!ctx.is_short_circuit());
+  }
+
+  #[tokio::test]
+  async fn disallowed_operation() {
+    let plugin = plugin().await;
+
+    let ctx = execute(&plugin, "query GetUser { user(id: 2) { id name } }").await;
+    let response = ctx.short_circuit_response.expect("expected a response");
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+  }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `rate_limit` plugin limits the amount of requests each client can send, using a [token bucket](https://en.wikipedia.org/wiki/Token_bucket) per client.
+///
+/// Every client has a bucket of `capacity` tokens, and every request takes one token from the bucket. A token is added back every `refill_interval`, so a client can send a burst of `capacity` requests, and then one request per `refill_interval`.
+/// When the bucket is empty, the request is rejected with a `429` status code and a `Retry-After` header.
+/// Allowed and rejected responses have the `X-RateLimit-Limit` (the `capacity`), `X-RateLimit-Remaining` (the tokens left in the bucket) and `X-RateLimit-Reset` (the number of seconds until the bucket is full again) headers, so clients can slow down before they are limited.
+///
+/// Buckets are kept in the memory of the gateway instance, so the limits are not shared between multiple instances.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "rate_limit_example_1")]
+#[schemars(example = "rate_limit_example_2")]
+pub struct RateLimitPluginConfig {
+  /// Identifies the client of a request. Requests without a client identifier share a single bucket.
+  #[serde(default)]
+  pub key: RateLimitKey,
+  /// The maximum number of tokens in a bucket, which is the size of the largest burst of requests a client can send.
+  #[serde(default = "default_capacity")]
+  pub capacity: u32,
+  /// The time it takes to add a single token back to a bucket.
+  #[serde(
+    deserialize_with = "humantime_serde::deserialize",
+    serialize_with = "humantime_serde::serialize",
+    default = "default_refill_interval"
+  )]
+  #[schemars(with = "String")]
+  pub refill_interval: Duration,
+  /// The maximum number of buckets kept at the same time. When the limit is reached, the bucket of the least recently seen client is forgotten.
+  #[serde(default = "default_max_keys")]
+  pub max_keys: usize,
+}
+
+impl Default for RateLimitPluginConfig {
+  fn default() -> Self {
+    Self {
+      key: RateLimitKey::default(),
+      capacity: default_capacity(),
+      refill_interval: default_refill_interval(),
+      max_keys: default_max_keys(),
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema, Default)]
+#[serde(tag = "source")]
+pub enum RateLimitKey {
+  /// The IP address of the directly connected peer.
+  /// If the gateway is behind a proxy, consider using a header set by the proxy (for example, `X-Real-IP`) instead.
+  #[serde(rename = "client_ip")]
+  #[schemars(title = "client_ip")]
+  #[default]
+  ClientIp,
+  /// The value of a request header, for example, an API key.
+  #[serde(rename = "header")]
+  #[schemars(title = "header")]
+  Header {
+    /// The name of the HTTP header.
+    name: String,
+  },
+  /// A claim of the token that authenticated the request, as set by the `jwt_auth` plugin.
+  /// The `jwt_auth` plugin must be declared before this plugin.
+  #[serde(rename = "jwt_claim")]
+  #[schemars(title = "jwt_claim")]
+  JwtClaim {
+    /// The name of the claim.
+    #[serde(default = "default_claim")]
+    claim: String,
+  },
+}
+
+fn default_capacity() -> u32 {
+  100
+}
+
+fn default_refill_interval() -> Duration {
+  Duration::from_millis(100)
+}
+
+fn default_max_keys() -> usize {
+  10_000
+}
+
+fn default_claim() -> String {
+  "sub".to_string()
+}
+
+fn rate_limit_example_1() -> JsonSchemaExample<RateLimitPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Limit by client IP",
+      Some("This example allows every client IP to send bursts of up to 20 requests, and then one request per second."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "rate_limit".to_string(),
+    }),
+    example: RateLimitPluginConfig {
+      capacity: 20,
+      refill_interval: Duration::from_secs(1),
+      ..Default::default()
+    },
+  }
+}
+
+fn rate_limit_example_2() -> JsonSchemaExample<RateLimitPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Limit by authenticated user",
+      Some("This example limits every user, identified by the `sub` claim of their JWT, to 100 requests per minute. The `jwt_auth` plugin must be declared before this plugin."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "rate_limit".to_string(),
+    }),
+    example: RateLimitPluginConfig {
+      key: RateLimitKey::JwtClaim {
+        claim: default_claim(),
+      },
+      capacity: 100,
+      refill_interval: Duration::from_millis(600),
+      ..Default::default()
+    },
+  }
+}
@@ -0,0 +1,6 @@
+mod config;
+mod plugin;
+
+pub use config::RateLimitKey as Key;
+pub use config::RateLimitPluginConfig as Config;
+pub use plugin::RateLimitPlugin as Plugin;
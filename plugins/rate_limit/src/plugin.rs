@@ -0,0 +1,424 @@
+use std::{sync::Mutex, time::Duration};
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{
+    header::RETRY_AFTER, ConductorHttpResponse, HeaderName, HeaderValue, HttpHeadersMap, StatusCode,
+  },
+  lru::LruMap,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use serde_json::Value;
+use tracing::debug;
+use web_time::Instant;
+
+use crate::config::{RateLimitKey, RateLimitPluginConfig};
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+  tokens: f64,
+  updated_at: Instant,
+}
+
+/// The state of the bucket of a request, once the request took its token. Stored in the request context, so the rate limit headers are also added to the allowed responses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BucketCheck {
+  /// `false` if the bucket had no token left for the request.
+  allowed: bool,
+  /// The tokens left in the bucket.
+  tokens: f64,
+}
+
+#[derive(Debug)]
+struct BucketStore {
+  buckets: LruMap<Bucket>,
+}
+
+impl BucketStore {
+  fn take(
+    &mut self,
+    key: &str,
+    now: Instant,
+    capacity: f64,
+    refill_interval: Duration,
+  ) -> BucketCheck {
+    let refilled = |bucket: &Bucket| {
+      let refill =
+        now.duration_since(bucket.updated_at).as_secs_f64() / refill_interval.as_secs_f64();
+
+      (bucket.tokens + refill).min(capacity)
+    };
+
+    // When the map is full, a new key evicts the least recently seen one, without scanning the other buckets.
+    let tokens = self.buckets.get(key).map_or(capacity, refilled);
+
+    let allowed = tokens >= 1.0;
+    let tokens = match allowed {
+      true => tokens - 1.0,
+      false => tokens,
+    };
+
+    self.buckets.insert(
+      key.to_string(),
+      Bucket {
+        tokens,
+        updated_at: now,
+      },
+    );
+
+    BucketCheck { allowed, tokens }
+  }
+}
+
+#[derive(Debug)]
+enum KeyExtractor {
+  ClientIp,
+  Header(HeaderName),
+  JwtClaim(String),
+}
+
+impl KeyExtractor {
+  fn extract(&self, ctx: &RequestExecutionContext) -> Option<String> {
+    match self {
+      KeyExtractor::ClientIp => ctx
+        .downstream_http_request
+        .peer_addr
+        .map(|peer_addr| peer_addr.to_string()),
+      KeyExtractor::Header(name) => ctx
+        .downstream_http_request
+        .headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(String::from),
+      KeyExtractor::JwtClaim(claim) => {
+        match ctx
+          .authenticated_claims()
+          .and_then(|claims| claims.get(claim))
+        {
+          Some(Value::String(value)) => Some(value.clone()),
+          Some(Value::Null) | None => None,
+          Some(value) => Some(value.to_string()),
+        }
+      }
+    }
+  }
+}
+
+/// Headers are in whole seconds, rounded up so clients don't retry too early.
+fn ceil_secs(duration: Duration) -> u64 {
+  duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+#[derive(Debug)]
+pub struct RateLimitPlugin {
+  key: KeyExtractor,
+  capacity: f64,
+  refill_interval: Duration,
+  store: Mutex<BucketStore>,
+}
+
+impl RateLimitPlugin {
+  /// The `X-RateLimit-*` headers, so clients can slow down before they are limited. The reset is the number of seconds until the bucket is full again.
+  fn headers(&self, check: &BucketCheck) -> HttpHeadersMap {
+    let reset = self
+      .refill_interval
+      .mul_f64((self.capacity - check.tokens).max(0.0));
+    let mut headers = HttpHeadersMap::new();
+    headers.insert("x-ratelimit-limit", HeaderValue::from(self.capacity as u64));
+    headers.insert(
+      "x-ratelimit-remaining",
+      HeaderValue::from(check.tokens.floor() as u64),
+    );
+    headers.insert("x-ratelimit-reset", HeaderValue::from(ceil_secs(reset)));
+
+    headers
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for RateLimitPlugin {
+  type Config = RateLimitPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    if config.capacity == 0 || config.refill_interval.is_zero() || config.max_keys == 0 {
+      return Err(PluginError::InitError {
+        source: anyhow::anyhow!("capacity, refill_interval and max_keys must be greater than 0"),
+      });
+    }
+
+    let key = match config.key {
+      RateLimitKey::ClientIp => KeyExtractor::ClientIp,
+      RateLimitKey::Header { name } => {
+        KeyExtractor::Header(HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+          PluginError::InitError {
+            source: anyhow::anyhow!("invalid header name \"{}\": {}", name, e),
+          }
+        })?)
+      }
+      RateLimitKey::JwtClaim { claim } => KeyExtractor::JwtClaim(claim),
+    };
+
+    Ok(Box::new(Self {
+      key,
+      capacity: config.capacity as f64,
+      refill_interval: config.refill_interval,
+      store: Mutex::new(BucketStore {
+        buckets: LruMap::new(config.max_keys),
+      }),
+    }))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for RateLimitPlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    // Requests without a key share the bucket of the empty key.
+    let key = self.key.extract(ctx).unwrap_or_default();
+
+    let check = match self.store.lock() {
+      Ok(mut store) => store.take(&key, Instant::now(), self.capacity, self.refill_interval),
+      Err(_) => {
+        ctx.short_circuit(
+          GraphQLResponse::new_error("failed to check rate limit")
+            .into_with_status_code(StatusCode::INTERNAL_SERVER_ERROR),
+        );
+
+        return;
+      }
+    };
+
+    ctx.insert(check);
+
+    if !check.allowed {
+      let retry_after = self.refill_interval.mul_f64(1.0 - check.tokens);
+      debug!(
+        "rate limit exceeded for key {:?}, retry after {:?}",
+        key, retry_after
+      );
+
+      let mut response = GraphQLResponse::new_error("rate limit exceeded")
+        .into_with_status_code(StatusCode::TOO_MANY_REQUESTS);
+      response.headers.extend(self.headers(&check));
+      response
+        .headers
+        .insert(RETRY_AFTER, HeaderValue::from(ceil_secs(retry_after)));
+
+      ctx.short_circuit(response);
+    }
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    if let Some(check) = ctx.get::<BucketCheck>() {
+      response.headers.extend(self.headers(check));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::{IpAddr, Ipv4Addr};
+
+  use conductor_common::{execute::AUTHENTICATED_CLAIMS_CONTEXT_KEY, http::ConductorHttpRequest};
+  use serde_json::json;
+
+  use super::*;
+
+  async fn plugin(config: RateLimitPluginConfig) -> Box<RateLimitPlugin> {
+    RateLimitPlugin::create(config).await.unwrap()
+  }
+
+  async fn send(
+    plugin: &RateLimitPlugin,
+    request: ConductorHttpRequest,
+  ) -> Option<ConductorHttpResponse> {
+    let mut ctx = RequestExecutionContext::new(request);
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    ctx.short_circuit_response
+  }
+
+  /// Sends the request, and returns the response once it went through the response hook.
+  async fn respond(
+    plugin: &RateLimitPlugin,
+    request: ConductorHttpRequest,
+  ) -> ConductorHttpResponse {
+    let mut ctx = RequestExecutionContext::new(request);
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    let mut response = ctx
+      .short_circuit_response
+      .take()
+      .unwrap_or_else(|| ConductorHttpResponse {
+        body: Default::default(),
+        status: StatusCode::OK,
+        headers: HttpHeadersMap::new(),
+      });
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    response
+  }
+
+  fn from_ip(ip: [u8; 4]) -> ConductorHttpRequest {
+    ConductorHttpRequest {
+      peer_addr: Some(IpAddr::V4(Ipv4Addr::from(ip))),
+      ..Default::default()
+    }
+  }
+
+  #[tokio::test]
+  async fn limits_bursts() {
+    let plugin = plugin(RateLimitPluginConfig {
+      capacity: 2,
+      refill_interval: Duration::from_secs(10),
+      ..Default::default()
+    })
+    .await;
+
+    assert!(send(&plugin, from_ip([10, 0, 0, 1])).await.is_none());
+    assert!(send(&plugin, from_ip([10, 0, 0, 1])).await.is_none());
+
+    let response = send(&plugin, from_ip([10, 0, 0, 1])).await.unwrap();
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+      response.headers.get(RETRY_AFTER),
+      Some(&"10".parse().unwrap())
+    );
+
+    // Every client has its own bucket.
+    assert!(send(&plugin, from_ip([10, 0, 0, 2])).await.is_none());
+  }
+
+  #[tokio::test]
+  async fn rate_limit_headers() {
+    let plugin = plugin(RateLimitPluginConfig {
+      capacity: 3,
+      refill_interval: Duration::from_secs(10),
+      ..Default::default()
+    })
+    .await;
+    let header = |response: &ConductorHttpResponse, name: &str| {
+      response
+        .headers
+        .get(name)
+        .map(|value| value.to_str().unwrap().to_string())
+    };
+
+    for (remaining, reset) in [("2", "10"), ("1", "20"), ("0", "30")] {
+      let response = respond(&plugin, from_ip([10, 0, 0, 1])).await;
+      assert_eq!(response.status, StatusCode::OK);
+      assert_eq!(header(&response, "x-ratelimit-limit").unwrap(), "3");
+      assert_eq!(
+        header(&response, "x-ratelimit-remaining").unwrap(),
+        remaining
+      );
+      assert_eq!(header(&response, "x-ratelimit-reset").unwrap(), reset);
+      assert_eq!(header(&response, "retry-after"), None);
+    }
+
+    let response = respond(&plugin, from_ip([10, 0, 0, 1])).await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&response, "x-ratelimit-limit").unwrap(), "3");
+    assert_eq!(header(&response, "x-ratelimit-remaining").unwrap(), "0");
+    assert_eq!(header(&response, "x-ratelimit-reset").unwrap(), "30");
+    assert_eq!(header(&response, "retry-after").unwrap(), "10");
+  }
+
+  #[tokio::test]
+  async fn refills_over_time() {
+    let plugin = plugin(RateLimitPluginConfig {
+      capacity: 1,
+      refill_interval: Duration::from_millis(50),
+      ..Default::default()
+    })
+    .await;
+
+    assert!(send(&plugin, from_ip([10, 0, 0, 1])).await.is_none());
+    assert!(send(&plugin, from_ip([10, 0, 0, 1])).await.is_some());
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(send(&plugin, from_ip([10, 0, 0, 1])).await.is_none());
+  }
+
+  #[tokio::test]
+  async fn header_key() {
+    let plugin = plugin(RateLimitPluginConfig {
+      key: RateLimitKey::Header {
+        name: "x-api-key".to_string(),
+      },
+      capacity: 1,
+      refill_interval: Duration::from_secs(10),
+      ..Default::default()
+    })
+    .await;
+    let with_key = |key: &str| {
+      let mut headers = HttpHeadersMap::new();
+      headers.append("x-api-key", key.parse().unwrap());
+
+      ConductorHttpRequest {
+        headers,
+        ..Default::default()
+      }
+    };
+
+    assert!(send(&plugin, with_key("a")).await.is_none());
+    assert!(send(&plugin, with_key("a")).await.is_some());
+    assert!(send(&plugin, with_key("b")).await.is_none());
+
+    // Requests without the header share a bucket.
+    assert!(send(&plugin, Default::default()).await.is_none());
+    assert!(send(&plugin, Default::default()).await.is_some());
+  }
+
+  #[tokio::test]
+  async fn jwt_claim_key() {
+    let plugin = plugin(RateLimitPluginConfig {
+      key: RateLimitKey::JwtClaim {
+        claim: "sub".to_string(),
+      },
+      capacity: 1,
+      refill_interval: Duration::from_secs(10),
+      ..Default::default()
+    })
+    .await;
+    let send_as = |sub: &'static str| {
+      let plugin = &plugin;
+
+      async move {
+        let mut ctx = RequestExecutionContext::new(Default::default());
+        ctx.ctx_insert(AUTHENTICATED_CLAIMS_CONTEXT_KEY, json!({ "sub": sub }));
+        plugin.on_downstream_http_request(&mut ctx).await;
+
+        ctx.short_circuit_response
+      }
+    };
+
+    assert!(send_as("user1").await.is_none());
+    assert!(send_as("user1").await.is_some());
+    assert!(send_as("user2").await.is_none());
+  }
+
+  #[tokio::test]
+  async fn bounded_keys() {
+    let plugin = plugin(RateLimitPluginConfig {
+      capacity: 1,
+      refill_interval: Duration::from_secs(10),
+      max_keys: 2,
+      ..Default::default()
+    })
+    .await;
+
+    assert!(send(&plugin, from_ip([10, 0, 0, 1])).await.is_none());
+    assert!(send(&plugin, from_ip([10, 0, 0, 2])).await.is_none());
+    assert!(send(&plugin, from_ip([10, 0, 0, 3])).await.is_none());
+
+    // The least recently seen client was forgotten, so its bucket is full again.
+    assert!(send(&plugin, from_ip([10, 0, 0, 1])).await.is_none());
+    assert!(send(&plugin, from_ip([10, 0, 0, 3])).await.is_some());
+  }
+}
@@ -0,0 +1,47 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `request_id` plugin assigns a correlation id to every request, so a request can be followed across the gateway and the upstream.
+///
+/// The id is read from the configured request header, or generated as a UUID v4 when the header is missing or invalid (empty, or longer than 128 characters).
+///
+/// The id is forwarded to the upstream and returned to the client using the same header, and added to the tracing span of the request as the `request.id` property.
+///
+/// Other plugins can read the id from the request context. Declare this plugin first, so the id is available to every other plugin.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "request_id_example")]
+pub struct RequestIdPluginConfig {
+  /// The name of the HTTP header carrying the request id.
+  #[serde(default = "default_header")]
+  pub header: String,
+}
+
+impl Default for RequestIdPluginConfig {
+  fn default() -> Self {
+    Self {
+      header: default_header(),
+    }
+  }
+}
+
+fn default_header() -> String {
+  "x-request-id".to_string()
+}
+
+fn request_id_example() -> JsonSchemaExample<RequestIdPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Custom header",
+      Some("This example reads and propagates the request id using the `X-Correlation-Id` header."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "request_id".to_string(),
+    }),
+    example: RequestIdPluginConfig {
+      header: "x-correlation-id".to_string(),
+    },
+  }
+}
@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::RequestIdPluginConfig as Config;
+pub use plugin::RequestIdPlugin as Plugin;
@@ -0,0 +1,176 @@
+use conductor_common::{
+  execute::{RequestExecutionContext, REQUEST_ID_CONTEXT_KEY},
+  http::{ConductorHttpRequest, ConductorHttpResponse, HeaderName, HeaderValue},
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use minitrace::local::LocalSpan;
+use tracing::debug;
+
+use crate::config::RequestIdPluginConfig;
+
+/// Longer incoming ids are replaced, so clients can't flood logs and traces with large values.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+#[derive(Debug)]
+pub struct RequestIdPlugin {
+  header: HeaderName,
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for RequestIdPlugin {
+  type Config = RequestIdPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    let header =
+      HeaderName::from_bytes(config.header.as_bytes()).map_err(|e| PluginError::InitError {
+        source: anyhow::anyhow!("invalid header name \"{}\": {}", config.header, e),
+      })?;
+
+    Ok(Box::new(Self { header }))
+  }
+}
+
+impl RequestIdPlugin {
+  fn header_value(&self, ctx: &RequestExecutionContext) -> Option<HeaderValue> {
+    ctx
+      .request_id()
+      .and_then(|request_id| HeaderValue::from_str(request_id).ok())
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for RequestIdPlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    let incoming = ctx
+      .downstream_http_request
+      .headers
+      .get(&self.header)
+      .and_then(|value| value.to_str().ok())
+      .map(str::trim)
+      .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LENGTH)
+      .map(String::from);
+    let request_id = match incoming {
+      Some(request_id) => request_id,
+      None => uuid::Uuid::new_v4().to_string(),
+    };
+
+    debug!("handling request with id {}", request_id);
+
+    let property = request_id.clone();
+    LocalSpan::add_property(|| ("request.id", property));
+    ctx.ctx_insert(REQUEST_ID_CONTEXT_KEY, request_id);
+  }
+
+  async fn on_upstream_http_request(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    req: &mut ConductorHttpRequest,
+  ) {
+    if let Some(value) = self.header_value(ctx) {
+      req.headers.insert(self.header.clone(), value);
+    }
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    if let Some(value) = self.header_value(ctx) {
+      response.headers.insert(self.header.clone(), value);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::http::{HttpHeadersMap, StatusCode};
+
+  use super::*;
+
+  /// Runs a request through the plugin, and returns the ids sent to the upstream and returned to the client.
+  async fn execute(
+    config: RequestIdPluginConfig,
+    incoming: Option<&str>,
+  ) -> (Option<HeaderValue>, Option<HeaderValue>) {
+    let plugin = RequestIdPlugin::create(config.clone()).await.unwrap();
+    let mut headers = HttpHeadersMap::new();
+
+    if let Some(incoming) = incoming {
+      headers.insert(
+        HeaderName::from_bytes(config.header.as_bytes()).unwrap(),
+        incoming.parse().unwrap(),
+      );
+    }
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      headers,
+      ..Default::default()
+    });
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    let mut upstream_request = ConductorHttpRequest::default();
+    plugin
+      .on_upstream_http_request(&mut ctx, &mut upstream_request)
+      .await;
+
+    let mut response = ConductorHttpResponse {
+      body: Default::default(),
+      status: StatusCode::OK,
+      headers: HttpHeadersMap::new(),
+    };
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    assert_eq!(
+      ctx.request_id(),
+      response
+        .headers
+        .get(&config.header)
+        .and_then(|value| value.to_str().ok())
+    );
+
+    (
+      upstream_request.headers.get(&config.header).cloned(),
+      response.headers.get(&config.header).cloned(),
+    )
+  }
+
+  #[tokio::test]
+  async fn propagates_incoming_id() {
+    let (upstream, downstream) = execute(Default::default(), Some("abc-123")).await;
+
+    assert_eq!(upstream, Some(HeaderValue::from_static("abc-123")));
+    assert_eq!(downstream, Some(HeaderValue::from_static("abc-123")));
+  }
+
+  #[tokio::test]
+  async fn generates_missing_id() {
+    let (upstream, downstream) = execute(Default::default(), None).await;
+    let upstream = upstream.unwrap();
+
+    assert!(uuid::Uuid::parse_str(upstream.to_str().unwrap()).is_ok());
+    assert_eq!(downstream, Some(upstream));
+  }
+
+  #[tokio::test]
+  async fn replaces_invalid_id() {
+    let too_long = "a".repeat(MAX_REQUEST_ID_LENGTH + 1);
+
+    for incoming in ["", "  ", too_long.as_str()] {
+      let (upstream, _) = execute(Default::default(), Some(incoming)).await;
+
+      assert!(uuid::Uuid::parse_str(upstream.unwrap().to_str().unwrap()).is_ok());
+    }
+  }
+
+  #[tokio::test]
+  async fn custom_header() {
+    let config = RequestIdPluginConfig {
+      header: "x-correlation-id".to_string(),
+    };
+    let (upstream, downstream) = execute(config, Some("abc-123")).await;
+
+    assert_eq!(upstream, Some(HeaderValue::from_static("abc-123")));
+    assert_eq!(downstream, Some(HeaderValue::from_static("abc-123")));
+  }
+}
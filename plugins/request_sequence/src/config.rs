@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `request_sequence` plugin protects server-to-server integrations from replayed requests, without relying on timestamps.
+///
+/// Every client sends its identifier and a monotonically increasing sequence number with each request. Requests with a sequence number that is not greater than the last one seen from the same client are rejected with a `409` status code, and requests without the headers are rejected with a `400` status code.
+///
+/// The last sequence number of a client is kept for the configured `window`: the first request of an unknown client (or of a client that was idle for longer than the window) is always accepted, and sets the starting point of the sequence.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "request_sequence_example")]
+pub struct RequestSequencePluginConfig {
+  /// The name of the HTTP header carrying the identifier of the client.
+  #[serde(default = "default_client_id_header")]
+  pub client_id_header: String,
+  /// The name of the HTTP header carrying the sequence number of the request.
+  #[serde(default = "default_sequence_header")]
+  pub sequence_header: String,
+  /// How long the last sequence number of a client is remembered after its last request.
+  #[serde(
+    deserialize_with = "humantime_serde::deserialize",
+    serialize_with = "humantime_serde::serialize",
+    default = "default_window"
+  )]
+  #[schemars(with = "String")]
+  pub window: Duration,
+  /// The maximum number of clients tracked at the same time. When the limit is reached, the least recently seen client is forgotten.
+  #[serde(default = "default_max_clients")]
+  pub max_clients: usize,
+}
+
+impl Default for RequestSequencePluginConfig {
+  fn default() -> Self {
+    Self {
+      client_id_header: default_client_id_header(),
+      sequence_header: default_sequence_header(),
+      window: default_window(),
+      max_clients: default_max_clients(),
+    }
+  }
+}
+
+fn default_client_id_header() -> String {
+  "x-client-id".to_string()
+}
+
+fn default_sequence_header() -> String {
+  "x-request-sequence".to_string()
+}
+
+fn default_window() -> Duration {
+  Duration::from_secs(60 * 60)
+}
+
+fn default_max_clients() -> usize {
+  10_000
+}
+
+fn request_sequence_example() -> JsonSchemaExample<RequestSequencePluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Reject replayed requests",
+      Some("This example remembers the last sequence number of each client for 10 minutes."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "request_sequence".to_string(),
+    }),
+    example: RequestSequencePluginConfig {
+      window: Duration::from_secs(10 * 60),
+      ..Default::default()
+    },
+  }
+}
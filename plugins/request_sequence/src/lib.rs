@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::RequestSequencePluginConfig as Config;
+pub use plugin::RequestSequencePlugin as Plugin;
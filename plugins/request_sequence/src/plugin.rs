@@ -0,0 +1,267 @@
+use std::{sync::Mutex, time::Duration};
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{HeaderName, StatusCode},
+  lru::LruMap,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use tracing::debug;
+use web_time::Instant;
+
+use crate::config::RequestSequencePluginConfig;
+
+#[derive(Debug, Clone, Copy)]
+struct ClientSequence {
+  last: u64,
+  seen_at: Instant,
+}
+
+#[derive(Debug, PartialEq)]
+enum SequenceCheck {
+  Accepted,
+  Replayed { last: u64 },
+}
+
+#[derive(Debug)]
+struct SequenceStore {
+  clients: LruMap<ClientSequence>,
+}
+
+impl SequenceStore {
+  fn check(
+    &mut self,
+    client_id: &str,
+    sequence: u64,
+    now: Instant,
+    window: Duration,
+  ) -> SequenceCheck {
+    if let Some(client) = self.clients.get(client_id) {
+      // A client that was idle for longer than the window starts a new sequence.
+      if now.duration_since(client.seen_at) <= window && sequence <= client.last {
+        return SequenceCheck::Replayed { last: client.last };
+      }
+    } else if self.clients.is_full() {
+      // Clients that were idle for longer than the window would start a new sequence anyway, so they are dropped first.
+      self
+        .clients
+        .retain(|_, client| now.duration_since(client.seen_at) <= window);
+    }
+
+    self.clients.insert(
+      client_id.to_string(),
+      ClientSequence {
+        last: sequence,
+        seen_at: now,
+      },
+    );
+
+    SequenceCheck::Accepted
+  }
+}
+
+#[derive(Debug)]
+pub struct RequestSequencePlugin {
+  client_id_header: HeaderName,
+  sequence_header: HeaderName,
+  window: Duration,
+  store: Mutex<SequenceStore>,
+}
+
+fn header_name(name: &str) -> Result<HeaderName, PluginError> {
+  HeaderName::from_bytes(name.as_bytes()).map_err(|e| PluginError::InitError {
+    source: anyhow::anyhow!("invalid header name \"{}\": {}", name, e),
+  })
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for RequestSequencePlugin {
+  type Config = RequestSequencePluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    if config.max_clients == 0 {
+      return Err(PluginError::InitError {
+        source: anyhow::anyhow!("max_clients must be greater than 0"),
+      });
+    }
+
+    Ok(Box::new(Self {
+      client_id_header: header_name(&config.client_id_header)?,
+      sequence_header: header_name(&config.sequence_header)?,
+      window: config.window,
+      store: Mutex::new(SequenceStore {
+        clients: LruMap::new(config.max_clients),
+      }),
+    }))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for RequestSequencePlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    let headers = &ctx.downstream_http_request.headers;
+    let client_id = headers
+      .get(&self.client_id_header)
+      .and_then(|value| value.to_str().ok())
+      .filter(|value| !value.is_empty());
+    let sequence = headers
+      .get(&self.sequence_header)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.trim().parse::<u64>().ok());
+
+    let (client_id, sequence) = match (client_id, sequence) {
+      (Some(client_id), Some(sequence)) => (client_id.to_string(), sequence),
+      _ => {
+        ctx.short_circuit(
+          GraphQLResponse::new_error(&format!(
+            "missing or invalid \"{}\" and \"{}\" headers",
+            self.client_id_header, self.sequence_header
+          ))
+          .into_with_status_code(StatusCode::BAD_REQUEST),
+        );
+
+        return;
+      }
+    };
+
+    let result = match self.store.lock() {
+      Ok(mut store) => store.check(&client_id, sequence, Instant::now(), self.window),
+      Err(_) => {
+        ctx.short_circuit(
+          GraphQLResponse::new_error("failed to verify request sequence")
+            .into_with_status_code(StatusCode::INTERNAL_SERVER_ERROR),
+        );
+
+        return;
+      }
+    };
+
+    if let SequenceCheck::Replayed { last } = result {
+      debug!(
+        "rejecting request of client {} with sequence {}, last seen sequence is {}",
+        client_id, sequence, last
+      );
+
+      ctx.short_circuit(
+        GraphQLResponse::new_error("request sequence was already used")
+          .into_with_status_code(StatusCode::CONFLICT),
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::http::{ConductorHttpRequest, HttpHeadersMap};
+
+  use super::*;
+
+  async fn plugin(config: RequestSequencePluginConfig) -> Box<RequestSequencePlugin> {
+    RequestSequencePlugin::create(config).await.unwrap()
+  }
+
+  async fn send(
+    plugin: &RequestSequencePlugin,
+    client_id: Option<&str>,
+    sequence: Option<&str>,
+  ) -> Option<StatusCode> {
+    let mut headers = HttpHeadersMap::new();
+
+    if let Some(client_id) = client_id {
+      headers.append("x-client-id", client_id.parse().unwrap());
+    }
+
+    if let Some(sequence) = sequence {
+      headers.append("x-request-sequence", sequence.parse().unwrap());
+    }
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      headers,
+      ..Default::default()
+    });
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    ctx.short_circuit_response.map(|response| response.status)
+  }
+
+  #[tokio::test]
+  async fn increasing_sequence_is_accepted() {
+    let plugin = plugin(Default::default()).await;
+
+    // The first request of a client is accepted with any sequence number.
+    assert_eq!(send(&plugin, Some("a"), Some("41")).await, None);
+    assert_eq!(send(&plugin, Some("a"), Some("42")).await, None);
+    assert_eq!(send(&plugin, Some("a"), Some("100")).await, None);
+    // Sequences are tracked per client.
+    assert_eq!(send(&plugin, Some("b"), Some("1")).await, None);
+  }
+
+  #[tokio::test]
+  async fn replayed_sequence_is_rejected() {
+    let plugin = plugin(Default::default()).await;
+
+    assert_eq!(send(&plugin, Some("a"), Some("10")).await, None);
+    assert_eq!(
+      send(&plugin, Some("a"), Some("10")).await,
+      Some(StatusCode::CONFLICT)
+    );
+    assert_eq!(
+      send(&plugin, Some("a"), Some("9")).await,
+      Some(StatusCode::CONFLICT)
+    );
+    // Rejected requests don't move the sequence forward.
+    assert_eq!(send(&plugin, Some("a"), Some("11")).await, None);
+  }
+
+  #[tokio::test]
+  async fn missing_headers_are_rejected() {
+    let plugin = plugin(Default::default()).await;
+
+    assert_eq!(
+      send(&plugin, None, Some("1")).await,
+      Some(StatusCode::BAD_REQUEST)
+    );
+    assert_eq!(
+      send(&plugin, Some("a"), None).await,
+      Some(StatusCode::BAD_REQUEST)
+    );
+    assert_eq!(
+      send(&plugin, Some("a"), Some("-1")).await,
+      Some(StatusCode::BAD_REQUEST)
+    );
+  }
+
+  #[tokio::test]
+  async fn sequence_restarts_after_window() {
+    let plugin = plugin(RequestSequencePluginConfig {
+      window: Duration::from_millis(50),
+      ..Default::default()
+    })
+    .await;
+
+    assert_eq!(send(&plugin, Some("a"), Some("10")).await, None);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(send(&plugin, Some("a"), Some("1")).await, None);
+  }
+
+  #[tokio::test]
+  async fn bounded_clients() {
+    let plugin = plugin(RequestSequencePluginConfig {
+      max_clients: 2,
+      ..Default::default()
+    })
+    .await;
+
+    assert_eq!(send(&plugin, Some("a"), Some("10")).await, None);
+    assert_eq!(send(&plugin, Some("b"), Some("10")).await, None);
+    assert_eq!(send(&plugin, Some("c"), Some("10")).await, None);
+
+    // The least recently seen client was forgotten, so its sequence starts over.
+    assert_eq!(send(&plugin, Some("a"), Some("1")).await, None);
+    assert_eq!(
+      send(&plugin, Some("c"), Some("10")).await,
+      Some(StatusCode::CONFLICT)
+    );
+  }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `response_cache` plugin caches the responses of GraphQL queries in memory, and serves repeated queries without calling the upstream.
+///
+/// Responses are cached by the GraphQL operation, its `operationName` and `variables`, and the values of the configured `vary_headers`. Only queries are cached: mutations and subscriptions are always executed. Responses with GraphQL errors or a non-`200` status code are not cached.
+///
+/// Clients can bypass the cache with a `Cache-Control: no-cache` request header (the response is still cached for the following requests), or `Cache-Control: no-store` (the response is not cached either).
+///
+/// If responses depend on the caller (for example, authenticated requests), make sure to list the headers identifying the caller (for example, `Authorization`) in `vary_headers`, so responses are not shared between callers.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "response_cache_example")]
+pub struct ResponseCachePluginConfig {
+  /// How long a response is served from the cache.
+  #[serde(
+    deserialize_with = "humantime_serde::deserialize",
+    serialize_with = "humantime_serde::serialize",
+    default = "default_ttl"
+  )]
+  #[schemars(with = "String")]
+  pub ttl: Duration,
+  /// The names of the request headers that are part of the cache key. Requests with different values for these headers never share a cached response.
+  #[serde(default)]
+  pub vary_headers: Vec<String>,
+  /// The maximum number of responses kept in memory. When the limit is reached, the oldest response is evicted.
+  #[serde(default = "default_max_entries")]
+  pub max_entries: usize,
+}
+
+impl Default for ResponseCachePluginConfig {
+  fn default() -> Self {
+    Self {
+      ttl: default_ttl(),
+      vary_headers: vec![],
+      max_entries: default_max_entries(),
+    }
+  }
+}
+
+fn default_ttl() -> Duration {
+  Duration::from_secs(30)
+}
+
+fn default_max_entries() -> usize {
+  1000
+}
+
+fn response_cache_example() -> JsonSchemaExample<ResponseCachePluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Cache per user",
+      Some("This example caches query responses for 1 minute, separately for every `Authorization` header."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "response_cache".to_string(),
+    }),
+    example: ResponseCachePluginConfig {
+      ttl: Duration::from_secs(60),
+      vary_headers: vec!["Authorization".to_string()],
+      ..Default::default()
+    },
+  }
+}
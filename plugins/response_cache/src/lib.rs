@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::ResponseCachePluginConfig as Config;
+pub use plugin::ResponseCachePlugin as Plugin;
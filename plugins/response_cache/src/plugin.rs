@@ -0,0 +1,374 @@
+use std::{sync::Arc, sync::Mutex, time::Duration};
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::ParsedGraphQLRequest,
+  http::{
+    header::{CACHE_CONTROL, CONTENT_TYPE},
+    Bytes, ConductorHttpResponse, HeaderName, HeaderValue, HttpHeadersMap, StatusCode,
+  },
+  lru::LruMap,
+  plugin::{CreatablePlugin, Plugin, PluginError},
+  source::SourceRuntime,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+use web_time::Instant;
+
+use crate::config::ResponseCachePluginConfig;
+
+/// Holds the cache key of a cacheable request, until its response is stored.
+static CACHE_KEY_CONTEXT_KEY: &str = "response_cache:key";
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+  body: Bytes,
+  content_type: Option<HeaderValue>,
+  expires_at: Instant,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct CacheControl {
+  no_cache: bool,
+  no_store: bool,
+}
+
+impl CacheControl {
+  fn from_headers(headers: &HttpHeadersMap) -> Self {
+    headers
+      .get_all(CACHE_CONTROL)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .flat_map(|value| value.split(','))
+      .fold(Self::default(), |mut cache_control, directive| {
+        let directive = directive.trim();
+
+        if directive.eq_ignore_ascii_case("no-cache") {
+          cache_control.no_cache = true;
+        } else if directive.eq_ignore_ascii_case("no-store") {
+          cache_control.no_store = true;
+        }
+
+        cache_control
+      })
+  }
+}
+
+#[derive(Debug)]
+pub struct ResponseCachePlugin {
+  ttl: Duration,
+  vary_headers: Vec<HeaderName>,
+  /// Cached responses are not marked as used when they are served, so the oldest response is evicted first.
+  responses: Mutex<LruMap<CachedResponse>>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for ResponseCachePlugin {
+  type Config = ResponseCachePluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    if config.max_entries == 0 {
+      return Err(PluginError::InitError {
+        source: anyhow::anyhow!("max_entries must be greater than 0"),
+      });
+    }
+
+    let vary_headers = config
+      .vary_headers
+      .iter()
+      .map(|name| {
+        HeaderName::from_bytes(name.as_bytes()).map_err(|e| PluginError::InitError {
+          source: anyhow::anyhow!("invalid header name \"{}\": {}", name, e),
+        })
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Box::new(Self {
+      ttl: config.ttl,
+      vary_headers,
+      responses: Mutex::new(LruMap::new(config.max_entries)),
+    }))
+  }
+}
+
+impl ResponseCachePlugin {
+  fn cache_key(&self, ctx: &RequestExecutionContext, gql_request: &ParsedGraphQLRequest) -> String {
+    let headers = &ctx.downstream_http_request.headers;
+    let vary_values = self
+      .vary_headers
+      .iter()
+      .map(|name| {
+        headers
+          .get_all(name)
+          .iter()
+          .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+    let key = json!([
+      gql_request.normalized_operation(),
+      gql_request.request.operation_name,
+      gql_request.request.variables,
+      vary_values,
+    ]);
+
+    format!("{:x}", Sha256::digest(key.to_string()))
+  }
+
+  fn cached_response(&self, key: &str) -> Option<CachedResponse> {
+    let mut responses = self.responses.lock().ok()?;
+
+    match responses.get(key) {
+      Some(cached) if cached.expires_at > Instant::now() => Some(cached.clone()),
+      Some(_) => {
+        responses.remove(key);
+        None
+      }
+      None => None,
+    }
+  }
+
+  fn insert(&self, key: &str, response: &ConductorHttpResponse) {
+    let mut responses = match self.responses.lock() {
+      Ok(responses) => responses,
+      Err(_) => return,
+    };
+    let now = Instant::now();
+
+    // Expired responses are dropped first, so they don't take the place of fresh ones.
+    if !responses.contains_key(key) && responses.is_full() {
+      responses.retain(|_, cached| cached.expires_at > now);
+    }
+
+    responses.insert(
+      key.to_string(),
+      CachedResponse {
+        body: response.body.clone(),
+        content_type: response.headers.get(CONTENT_TYPE).cloned(),
+        expires_at: now + self.ttl,
+      },
+    );
+  }
+}
+
+/// Responses with GraphQL errors might be caused by a transient failure, so they are not cached.
+fn is_cacheable_response(response: &ConductorHttpResponse) -> bool {
+  response.status == StatusCode::OK
+    && serde_json::from_slice::<Value>(&response.body).is_ok_and(|body| {
+      body.get("errors").map_or(true, |errors| {
+        errors.is_null() || errors.as_array().is_some_and(Vec::is_empty)
+      })
+    })
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for ResponseCachePlugin {
+  async fn on_downstream_graphql_request(
+    &self,
+    _source_runtime: Arc<Box<dyn SourceRuntime>>,
+    ctx: &mut RequestExecutionContext,
+  ) {
+    let key = match &ctx.downstream_graphql_request {
+      Some(gql_request) if gql_request.is_running_query() => self.cache_key(ctx, gql_request),
+      _ => return,
+    };
+    let cache_control = CacheControl::from_headers(&ctx.downstream_http_request.headers);
+
+    if cache_control.no_store {
+      return;
+    }
+
+    if !cache_control.no_cache {
+      if let Some(cached) = self.cached_response(&key) {
+        debug!("serving response from cache, key: {}", key);
+
+        let mut headers = HttpHeadersMap::new();
+
+        if let Some(content_type) = cached.content_type {
+          headers.insert(CONTENT_TYPE, content_type);
+        }
+
+        ctx.short_circuit(ConductorHttpResponse {
+          body: cached.body,
+          status: StatusCode::OK,
+          headers,
+        });
+
+        return;
+      }
+    }
+
+    ctx.ctx_insert(CACHE_KEY_CONTEXT_KEY, key);
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    if let Some(key) = ctx.ctx_get(CACHE_KEY_CONTEXT_KEY).and_then(Value::as_str) {
+      if is_cacheable_response(response) {
+        debug!("storing response in cache, key: {}", key);
+
+        self.insert(key, response);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    graphql::{GraphQLRequest, GraphQLResponse},
+    http::ConductorHttpRequest,
+    source::TestSource,
+  };
+
+  use super::*;
+
+  /// Runs a request through the plugin, and returns the response served from the cache, if any.
+  /// On a cache miss, `upstream_body` is used as the response.
+  async fn execute(
+    plugin: &ResponseCachePlugin,
+    operation: &str,
+    headers: &[(&str, &str)],
+    upstream_body: &str,
+  ) -> Option<Bytes> {
+    let mut headers_map = HttpHeadersMap::new();
+
+    for (name, value) in headers {
+      headers_map.append(*name, value.parse().unwrap());
+    }
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      headers: headers_map,
+      ..Default::default()
+    });
+    ctx.downstream_graphql_request = Some(
+      ParsedGraphQLRequest::create_and_parse(GraphQLRequest {
+        operation: operation.to_string(),
+        operation_name: None,
+        variables: None,
+        extensions: None,
+      })
+      .unwrap(),
+    );
+    plugin
+      .on_downstream_graphql_request(Arc::new(Box::new(TestSource::default())), &mut ctx)
+      .await;
+
+    if let Some(response) = ctx.short_circuit_response.take() {
+      return Some(response.body);
+    }
+
+    let mut response = ConductorHttpResponse {
+      body: Bytes::from(upstream_body.to_string()),
+      status: StatusCode::OK,
+      headers: HttpHeadersMap::new(),
+    };
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+
+    None
+  }
+
+  async fn plugin(config: ResponseCachePluginConfig) -> Box<ResponseCachePlugin> {
+    ResponseCachePlugin::create(config).await.unwrap()
+  }
+
+  static QUERY: &str = "query { me { id } }";
+  static RESPONSE: &str = r#"{"data":{"me":{"id":"1"}}}"#;
+
+  #[tokio::test]
+  async fn caches_queries() {
+    let plugin = plugin(Default::default()).await;
+
+    assert_eq!(execute(&plugin, QUERY, &[], RESPONSE).await, None);
+    assert_eq!(
+      execute(&plugin, "query {\n  me {\n    id\n  }\n}", &[], "").await,
+      Some(Bytes::from(RESPONSE))
+    );
+  }
+
+  #[tokio::test]
+  async fn does_not_cache_mutations() {
+    let plugin = plugin(Default::default()).await;
+    let mutation = "mutation { createUser { id } }";
+
+    assert_eq!(execute(&plugin, mutation, &[], RESPONSE).await, None);
+    assert_eq!(execute(&plugin, mutation, &[], RESPONSE).await, None);
+  }
+
+  #[tokio::test]
+  async fn does_not_cache_errors() {
+    let plugin = plugin(Default::default()).await;
+
+    assert_eq!(
+      execute(&plugin, QUERY, &[], r#"{"errors":[{"message":"oops"}]}"#).await,
+      None
+    );
+    assert_eq!(execute(&plugin, QUERY, &[], RESPONSE).await, None);
+  }
+
+  #[tokio::test]
+  async fn vary_headers() {
+    let plugin = plugin(ResponseCachePluginConfig {
+      vary_headers: vec!["Authorization".to_string()],
+      ..Default::default()
+    })
+    .await;
+
+    assert_eq!(
+      execute(&plugin, QUERY, &[("authorization", "a")], RESPONSE).await,
+      None
+    );
+    assert_eq!(
+      execute(&plugin, QUERY, &[("authorization", "b")], RESPONSE).await,
+      None
+    );
+    assert_eq!(
+      execute(&plugin, QUERY, &[("authorization", "a")], "").await,
+      Some(Bytes::from(RESPONSE))
+    );
+  }
+
+  #[tokio::test]
+  async fn cache_control() {
+    let plugin = plugin(Default::default()).await;
+    let other_response = r#"{"data":{"me":{"id":"2"}}}"#;
+
+    assert_eq!(
+      execute(&plugin, QUERY, &[("cache-control", "no-store")], RESPONSE).await,
+      None
+    );
+    assert_eq!(execute(&plugin, QUERY, &[], RESPONSE).await, None);
+    // `no-cache` skips the cached response, but the new response replaces it.
+    assert_eq!(
+      execute(
+        &plugin,
+        QUERY,
+        &[("cache-control", "no-cache")],
+        other_response
+      )
+      .await,
+      None
+    );
+    assert_eq!(
+      execute(&plugin, QUERY, &[], "").await,
+      Some(Bytes::from(other_response))
+    );
+  }
+
+  #[tokio::test]
+  async fn expires_after_ttl() {
+    let plugin = plugin(ResponseCachePluginConfig {
+      ttl: Duration::from_millis(50),
+      ..Default::default()
+    })
+    .await;
+
+    assert_eq!(execute(&plugin, QUERY, &[], RESPONSE).await, None);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(execute(&plugin, QUERY, &[], RESPONSE).await, None);
+  }
+}
@@ -0,0 +1,97 @@
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The `response_sampling` plugin captures a sample of the full response bodies returned for the endpoint, to help debugging intermittent issues with the upstream source.
+///
+/// Successful and errored responses (a non-2xx status code, or a GraphQL response with `errors`) are sampled separately for every source, and kept in an in-memory ring buffer per source. Sampling is deterministic: a rate of `0.25` captures every 4th response of the source.
+///
+/// The captured samples can be retrieved with a `GET` request to the configured `path` of the endpoint (for example: `GET /graphql/_response_samples`). The request must send the configured `secret` as a bearer token (`Authorization: Bearer <secret>`), or be authenticated by an authentication plugin (like `jwt_auth`) that runs before this plugin.
+///
+/// Before a JSON response body is captured, the values of all fields listed in `redact_fields` are replaced, at any depth of the response. Bodies that are not valid JSON can't be redacted, so only their status is captured.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+#[schemars(example = "response_sampling_example")]
+pub struct ResponseSamplingPluginConfig {
+  /// The path that exposes the captured samples, relative to the endpoint path.
+  #[serde(default = "default_path")]
+  pub path: String,
+  /// The rate of successful responses to capture, between `0` (none) and `1` (all).
+  #[serde(default = "default_success_rate")]
+  pub success_rate: f64,
+  /// The rate of errored responses to capture, between `0` (none) and `1` (all).
+  #[serde(default = "default_error_rate")]
+  pub error_rate: f64,
+  /// The maximum number of samples kept for every source. When the buffer is full, the oldest sample is dropped.
+  #[serde(default = "default_buffer_size")]
+  pub buffer_size: usize,
+  /// Names of JSON fields (case-insensitive) that should be redacted from the captured response bodies.
+  #[serde(default = "default_redact_fields")]
+  pub redact_fields: Vec<String>,
+  /// A secret that must be sent as a bearer token (`Authorization: Bearer <secret>`) to retrieve the captured samples.
+  ///
+  /// When not set, the request must be authenticated by an authentication plugin that runs before this plugin.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub secret: Option<String>,
+}
+
+impl Default for ResponseSamplingPluginConfig {
+  fn default() -> Self {
+    Self {
+      path: default_path(),
+      success_rate: default_success_rate(),
+      error_rate: default_error_rate(),
+      buffer_size: default_buffer_size(),
+      redact_fields: default_redact_fields(),
+      secret: None,
+    }
+  }
+}
+
+fn default_path() -> String {
+  "/_response_samples".to_string()
+}
+
+fn default_success_rate() -> f64 {
+  0.0
+}
+
+fn default_error_rate() -> f64 {
+  1.0
+}
+
+fn default_buffer_size() -> usize {
+  20
+}
+
+fn default_redact_fields() -> Vec<String> {
+  vec![
+    "password".to_string(),
+    "token".to_string(),
+    "accessToken".to_string(),
+    "refreshToken".to_string(),
+    "secret".to_string(),
+    "authorization".to_string(),
+  ]
+}
+
+fn response_sampling_example() -> JsonSchemaExample<ResponseSamplingPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Sample responses",
+      Some("This example captures all errored responses and 1% of the successful ones, keeping the last 50 samples, and redacts the `email` and `password` fields. The samples are only served to requests that send the secret as a bearer token."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "response_sampling".to_string(),
+    }),
+    example: ResponseSamplingPluginConfig {
+      success_rate: 0.01,
+      error_rate: 1.0,
+      buffer_size: 50,
+      redact_fields: vec!["email".to_string(), "password".to_string()],
+      secret: Some("my-secret".to_string()),
+      ..Default::default()
+    },
+  }
+}
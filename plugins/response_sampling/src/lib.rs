@@ -0,0 +1,5 @@
+mod config;
+mod plugin;
+
+pub use config::ResponseSamplingPluginConfig as Config;
+pub use plugin::ResponseSamplingPlugin as Plugin;
@@ -0,0 +1,422 @@
+use std::{
+  collections::{BTreeMap, VecDeque},
+  sync::{Arc, Mutex},
+};
+
+use conductor_common::{
+  admin_endpoint::AdminEndpoint,
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{ConductorHttpResponse, HeaderValue, HttpHeadersMap, StatusCode, CONTENT_TYPE},
+  plugin::{CreatablePlugin, Plugin, PluginError},
+  source::SourceRuntime,
+};
+use serde::Serialize;
+use serde_json::Value;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::ResponseSamplingPluginConfig;
+
+static SOURCE_CONTEXT_KEY: &str = "response_sampling:source";
+static REDACTED_VALUE: &str = "[REDACTED]";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseSample {
+  pub status: u16,
+  pub error: bool,
+  /// Milliseconds since the Unix epoch.
+  pub timestamp: u64,
+  /// The redacted response body. Bodies that are not valid JSON can't be redacted, and are not captured.
+  pub body: Option<Value>,
+}
+
+#[derive(Debug, Default)]
+struct SourceSamples {
+  samples: VecDeque<ResponseSample>,
+  success_count: u64,
+  error_count: u64,
+}
+
+impl SourceSamples {
+  /// Deterministic sampling: the response is captured every time the accumulated rate crosses a whole number.
+  fn should_sample(&mut self, error: bool, rate: f64) -> bool {
+    let count = match error {
+      true => &mut self.error_count,
+      false => &mut self.success_count,
+    };
+    let before = (*count as f64 * rate).floor();
+    *count += 1;
+
+    (*count as f64 * rate).floor() > before
+  }
+
+  fn push(&mut self, sample: ResponseSample, buffer_size: usize) {
+    if buffer_size == 0 {
+      return;
+    }
+
+    while self.samples.len() >= buffer_size {
+      self.samples.pop_front();
+    }
+
+    self.samples.push_back(sample);
+  }
+}
+
+/// Keyed by source name, so the sampling rate of a source doesn't depend on the traffic of the others.
+type SamplesStore = BTreeMap<String, SourceSamples>;
+
+#[derive(Debug)]
+pub struct ResponseSamplingPlugin {
+  config: ResponseSamplingPluginConfig,
+  endpoint: AdminEndpoint,
+  redact_fields: Vec<String>,
+  store: Mutex<SamplesStore>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for ResponseSamplingPlugin {
+  type Config = ResponseSamplingPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    for (name, rate) in [
+      ("success_rate", config.success_rate),
+      ("error_rate", config.error_rate),
+    ] {
+      if !(0.0..=1.0).contains(&rate) {
+        return Err(PluginError::InitError {
+          source: anyhow::anyhow!("{} must be between 0 and 1, got {}", name, rate),
+        });
+      }
+    }
+
+    Ok(Box::new(Self {
+      endpoint: AdminEndpoint::new(&config.path, config.secret.clone()),
+      redact_fields: config
+        .redact_fields
+        .iter()
+        .map(|field| field.to_lowercase())
+        .collect(),
+      config,
+      store: Mutex::new(SamplesStore::default()),
+    }))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for ResponseSamplingPlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    self.endpoint.handle(ctx, || self.render());
+  }
+
+  async fn on_downstream_graphql_request(
+    &self,
+    source_runtime: Arc<Box<dyn SourceRuntime>>,
+    ctx: &mut RequestExecutionContext,
+  ) {
+    ctx.ctx_insert(SOURCE_CONTEXT_KEY, source_runtime.name());
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    // Only responses of operations that were routed to a source are sampled.
+    let source = match ctx.ctx_get(SOURCE_CONTEXT_KEY).and_then(Value::as_str) {
+      Some(source) => source.to_string(),
+      None => return,
+    };
+
+    let body = serde_json::from_slice::<Value>(&response.body).ok();
+    let has_graphql_errors = body
+      .as_ref()
+      .and_then(|body| body.get("errors"))
+      .and_then(Value::as_array)
+      .is_some_and(|errors| !errors.is_empty());
+    let error = !response.status.is_success() || has_graphql_errors;
+    let rate = match error {
+      true => self.config.error_rate,
+      false => self.config.success_rate,
+    };
+
+    let mut store = match self.store.lock() {
+      Ok(store) => store,
+      Err(_) => return,
+    };
+    let source_samples = store.entry(source).or_default();
+
+    if !source_samples.should_sample(error, rate) {
+      return;
+    }
+
+    let body = body.map(|mut body| {
+      self.redact(&mut body);
+      body
+    });
+    let timestamp = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|duration| duration.as_millis() as u64)
+      .unwrap_or_default();
+
+    source_samples.push(
+      ResponseSample {
+        status: response.status.as_u16(),
+        error,
+        timestamp,
+        body,
+      },
+      self.config.buffer_size,
+    );
+  }
+}
+
+impl ResponseSamplingPlugin {
+  fn redact(&self, value: &mut Value) {
+    match value {
+      Value::Object(map) => {
+        for (key, value) in map.iter_mut() {
+          if self.redact_fields.contains(&key.to_lowercase()) {
+            *value = Value::String(REDACTED_VALUE.to_string());
+          } else {
+            self.redact(value);
+          }
+        }
+      }
+      Value::Array(values) => values.iter_mut().for_each(|value| self.redact(value)),
+      _ => {}
+    }
+  }
+
+  fn render(&self) -> ConductorHttpResponse {
+    let body = match self.store.lock() {
+      Ok(store) => serde_json::to_vec(
+        &store
+          .iter()
+          .map(|(source, source_samples)| (source, &source_samples.samples))
+          .collect::<BTreeMap<_, _>>(),
+      ),
+      Err(_) => {
+        return GraphQLResponse::new_error("failed to access captured samples")
+          .into_with_status_code(StatusCode::INTERNAL_SERVER_ERROR)
+      }
+    };
+
+    match body {
+      Ok(body) => {
+        let mut headers = HttpHeadersMap::new();
+        headers.append(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        ConductorHttpResponse {
+          body: body.into(),
+          status: StatusCode::OK,
+          headers,
+        }
+      }
+      Err(e) => GraphQLResponse::new_error(&format!("failed to serialize captured samples: {}", e))
+        .into_with_status_code(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    execute::ENDPOINT_CONTEXT_KEY,
+    http::{ConductorHttpRequest, Method},
+    source::TestSource,
+  };
+  use serde_json::json;
+
+  use super::*;
+
+  async fn plugin(config: ResponseSamplingPluginConfig) -> Box<ResponseSamplingPlugin> {
+    ResponseSamplingPlugin::create(config).await.unwrap()
+  }
+
+  async fn respond(plugin: &ResponseSamplingPlugin, status: StatusCode, body: Value) {
+    respond_raw(plugin, "test", status, body.to_string()).await;
+  }
+
+  async fn respond_raw(
+    plugin: &ResponseSamplingPlugin,
+    source: &'static str,
+    status: StatusCode,
+    body: String,
+  ) {
+    let mut ctx = RequestExecutionContext::new(Default::default());
+    plugin
+      .on_downstream_graphql_request(Arc::new(Box::new(TestSource(source))), &mut ctx)
+      .await;
+
+    let mut response = ConductorHttpResponse {
+      body: body.into(),
+      status,
+      headers: Default::default(),
+    };
+    plugin.on_downstream_http_response(&mut ctx, &mut response);
+  }
+
+  async fn retrieve(plugin: &ResponseSamplingPlugin) -> Value {
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::GET,
+      uri: "/graphql/_response_samples".to_string(),
+      ..Default::default()
+    });
+    ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, "/graphql");
+    ctx.mark_authenticated();
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    let response = ctx.short_circuit_response.expect("expected a response");
+    assert_eq!(response.status, StatusCode::OK);
+
+    serde_json::from_slice(&response.body).unwrap()
+  }
+
+  #[tokio::test]
+  async fn captures_redacted_samples() {
+    let plugin = plugin(ResponseSamplingPluginConfig {
+      success_rate: 1.0,
+      ..Default::default()
+    })
+    .await;
+
+    respond(
+      &plugin,
+      StatusCode::OK,
+      json!({ "data": { "user": { "name": "Dotan", "Password": "123", "sessions": [{ "token": "abc" }] } } }),
+    )
+    .await;
+    respond(
+      &plugin,
+      StatusCode::OK,
+      json!({ "errors": [{ "message": "failed" }] }),
+    )
+    .await;
+
+    let samples = retrieve(&plugin).await;
+    let samples = samples["test"].as_array().unwrap();
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[0]["error"], false);
+    assert_eq!(
+      samples[0]["body"],
+      json!({ "data": { "user": { "name": "Dotan", "Password": "[REDACTED]", "sessions": [{ "token": "[REDACTED]" }] } } })
+    );
+    assert_eq!(samples[1]["error"], true);
+    assert_eq!(samples[1]["status"], 200);
+  }
+
+  #[tokio::test]
+  async fn sampling_rates() {
+    let plugin = plugin(ResponseSamplingPluginConfig {
+      success_rate: 0.25,
+      error_rate: 0.0,
+      ..Default::default()
+    })
+    .await;
+
+    for _ in 0..8 {
+      respond(&plugin, StatusCode::OK, json!({ "data": {} })).await;
+      respond(&plugin, StatusCode::BAD_GATEWAY, json!({})).await;
+    }
+
+    let samples = retrieve(&plugin).await;
+    let samples = samples["test"].as_array().unwrap();
+    assert_eq!(samples.len(), 2);
+    assert!(samples.iter().all(|sample| sample["error"] == false));
+  }
+
+  #[tokio::test]
+  async fn ring_buffer_cap() {
+    let plugin = plugin(ResponseSamplingPluginConfig {
+      buffer_size: 3,
+      ..Default::default()
+    })
+    .await;
+
+    for i in 0..10 {
+      respond(
+        &plugin,
+        StatusCode::OK,
+        json!({ "errors": [{ "message": format!("error {}", i) }] }),
+      )
+      .await;
+    }
+
+    let samples = retrieve(&plugin).await;
+    let messages = samples["test"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|sample| sample["body"]["errors"][0]["message"].clone())
+      .collect::<Vec<_>>();
+    assert_eq!(messages, vec!["error 7", "error 8", "error 9"]);
+  }
+
+  #[tokio::test]
+  async fn sampling_rates_per_source() {
+    let plugin = plugin(ResponseSamplingPluginConfig {
+      success_rate: 0.5,
+      ..Default::default()
+    })
+    .await;
+
+    // With a counter shared by the sources, only the responses of "second" would be sampled.
+    for _ in 0..4 {
+      respond_raw(&plugin, "first", StatusCode::OK, "{}".to_string()).await;
+      respond_raw(&plugin, "second", StatusCode::OK, "{}".to_string()).await;
+    }
+
+    let samples = retrieve(&plugin).await;
+    assert_eq!(samples["first"].as_array().unwrap().len(), 2);
+    assert_eq!(samples["second"].as_array().unwrap().len(), 2);
+  }
+
+  #[tokio::test]
+  async fn drops_non_json_bodies() {
+    let plugin = plugin(Default::default()).await;
+
+    respond_raw(
+      &plugin,
+      "test",
+      StatusCode::BAD_GATEWAY,
+      "password=123".to_string(),
+    )
+    .await;
+
+    let samples = retrieve(&plugin).await;
+    let samples = samples["test"].as_array().unwrap();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0]["status"], 502);
+    assert_eq!(samples[0]["body"], Value::Null);
+  }
+
+  #[tokio::test]
+  async fn requires_credentials() {
+    let plugin = plugin(Default::default()).await;
+    respond(&plugin, StatusCode::BAD_GATEWAY, json!({})).await;
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::GET,
+      uri: "/graphql/_response_samples".to_string(),
+      ..Default::default()
+    });
+    ctx.ctx_insert(ENDPOINT_CONTEXT_KEY, "/graphql");
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    let response = ctx.short_circuit_response.expect("expected a response");
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+  }
+
+  #[tokio::test]
+  async fn invalid_rate() {
+    assert!(
+      ResponseSamplingPlugin::create(ResponseSamplingPluginConfig {
+        error_rate: 1.5,
+        ..Default::default()
+      })
+      .await
+      .is_err()
+    );
+  }
+}
@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use conductor_common::serde_utils::{
+  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType, LocalFileReference,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, JsonSchema)]
+#[schemars(example = "rhai_plugin_example_headers")]
+#[schemars(example = "rhai_plugin_example_short_circuit")]
+#[schemars(example = "rhai_plugin_example_file")]
+/// The `rhai` plugin allows you to customize the gateway behavior using small scripts written in [Rhai](https://rhai.rs/), without recompiling Conductor.
+///
+/// Scripts are compiled when the server starts, and executed in a sandboxed engine: scripts have no access to the file-system or the network, and their execution is bounded by the configured `limits`.
+///
+/// Like any other plugin, the `rhai` plugin can be configured globally or per endpoint, so each endpoint can have its own set of scripts.
+///
+/// ### Input/Output
+///
+/// Every hook exposes a `context` object-map: values stored in it are available to all other scripts executed for the same incoming request.
+///
+/// #### `on_downstream_http_request`
+///
+/// Executed when a downstream HTTP request is received to the gateway from the end-user. The script can access and modify the `request` object-map: `request.headers`, `request.body`, and read `request.method`, `request.uri` and `request.query_string`.
+///
+/// #### `on_upstream_http_request`
+///
+/// Executed when an HTTP request is about to be sent to the upstream GraphQL server. The script can access and modify the `request` object-map: `request.headers`, `request.body`, `request.method`, `request.uri` and `request.query_string`.
+///
+/// #### `on_downstream_http_response`
+///
+/// Executed before the response is sent to the end-user. The script can access and modify the `response` object-map: `response.status`, `response.headers` and `response.body`.
+///
+/// Header values are arrays of strings, one per value of the header (values that are not valid UTF-8 are left out), and scripts can set a header to an array or to a single string. Only the headers and body changed by the script are written back: other headers keep all of their values, and bodies that are not valid UTF-8 (for example, compressed bodies) are kept as is.
+///
+/// ### Short Circuit
+///
+/// Calling `short_circuit(status, message)` stops the execution, and responds to the end-user with a GraphQL error and the specified status code.
+///
+/// > In case of a runtime error (including exceeding the configured limits), an error will be returned to the end-user with a `500` status code, and the gateway will not continue with the execution.
+pub struct RhaiPluginConfig {
+  /// A script executed when a downstream HTTP request is received to the gateway from the end-user.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub on_downstream_http_request: Option<RhaiScriptReference>,
+  /// A script executed when an HTTP request is about to be sent to the upstream GraphQL server.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub on_upstream_http_request: Option<RhaiScriptReference>,
+  /// A script executed before the HTTP response is sent to the end-user.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub on_downstream_http_response: Option<RhaiScriptReference>,
+  /// Resource limits applied to every script execution.
+  #[serde(default)]
+  pub limits: RhaiScriptLimits,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(tag = "from")]
+pub enum RhaiScriptReference {
+  #[serde(rename = "inline")]
+  #[schemars(title = "inline")]
+  /// Inline string for a Rhai script.
+  Inline { content: String },
+  #[serde(rename = "file")]
+  #[schemars(title = "file")]
+  /// File reference to a Rhai script. The file is loaded when the server starts.
+  File { path: LocalFileReference },
+}
+
+impl RhaiScriptReference {
+  pub fn contents(&self) -> &String {
+    match self {
+      RhaiScriptReference::Inline { content } => content,
+      RhaiScriptReference::File { path } => &path.contents,
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct RhaiScriptLimits {
+  /// Maximum wall-clock time a single script execution may take.
+  #[serde(
+    deserialize_with = "humantime_serde::deserialize",
+    serialize_with = "humantime_serde::serialize",
+    default = "default_max_execution_time"
+  )]
+  #[schemars(with = "String")]
+  pub max_execution_time: Duration,
+  /// Maximum number of operations a single script execution may perform.
+  #[serde(default = "default_max_operations")]
+  pub max_operations: u64,
+  /// Maximum length (in bytes) of any string created by the script.
+  #[serde(default = "default_max_string_size")]
+  pub max_string_size: usize,
+  /// Maximum number of items in any array created by the script.
+  #[serde(default = "default_max_collection_size")]
+  pub max_array_size: usize,
+  /// Maximum number of properties in any object-map created by the script.
+  #[serde(default = "default_max_collection_size")]
+  pub max_map_size: usize,
+  /// Maximum depth of nested function calls.
+  #[serde(default = "default_max_call_levels")]
+  pub max_call_levels: usize,
+}
+
+impl Default for RhaiScriptLimits {
+  fn default() -> Self {
+    Self {
+      max_execution_time: default_max_execution_time(),
+      max_operations: default_max_operations(),
+      max_string_size: default_max_string_size(),
+      max_array_size: default_max_collection_size(),
+      max_map_size: default_max_collection_size(),
+      max_call_levels: default_max_call_levels(),
+    }
+  }
+}
+
+fn default_max_execution_time() -> Duration {
+  Duration::from_millis(50)
+}
+
+fn default_max_operations() -> u64 {
+  100_000
+}
+
+fn default_max_string_size() -> usize {
+  1024 * 1024
+}
+
+fn default_max_collection_size() -> usize {
+  10_000
+}
+
+fn default_max_call_levels() -> usize {
+  32
+}
+
+fn rhai_plugin_example_headers() -> JsonSchemaExample<RhaiPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Headers",
+      Some("This example adds a custom header to all responses, and forwards a header from the incoming request to the upstream."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "rhai".to_string(),
+    }),
+    example: RhaiPluginConfig {
+      on_downstream_http_request: Some(RhaiScriptReference::Inline {
+        content: r#"if "x-tenant" in request.headers { context.tenant = request.headers["x-tenant"][0]; }"#
+          .to_string(),
+      }),
+      on_upstream_http_request: Some(RhaiScriptReference::Inline {
+        content: r#"if "tenant" in context { request.headers["x-tenant"] = context.tenant; }"#
+          .to_string(),
+      }),
+      on_downstream_http_response: Some(RhaiScriptReference::Inline {
+        content: r#"response.headers["x-powered-by"] = "conductor";"#.to_string(),
+      }),
+      ..Default::default()
+    },
+  }
+}
+
+fn rhai_plugin_example_short_circuit() -> JsonSchemaExample<RhaiPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "Short Circuit",
+      Some("This example rejects all incoming requests that don't have the \"authorization\" header set."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "rhai".to_string(),
+    }),
+    example: RhaiPluginConfig {
+      on_downstream_http_request: Some(RhaiScriptReference::Inline {
+        content: r#"if !("authorization" in request.headers) { short_circuit(403, "Missing authorization header"); }"#
+          .to_string(),
+      }),
+      ..Default::default()
+    },
+  }
+}
+
+fn rhai_plugin_example_file() -> JsonSchemaExample<RhaiPluginConfig> {
+  JsonSchemaExample {
+    metadata: JsonSchemaExampleMetadata::new(
+      "File",
+      Some("Load a script from a '.rhai' file, with custom resource limits."),
+    ),
+    wrapper: Some(JsonSchemaExampleWrapperType::Plugin {
+      name: "rhai".to_string(),
+    }),
+    example: RhaiPluginConfig {
+      on_downstream_http_response: Some(RhaiScriptReference::File {
+        path: LocalFileReference {
+          contents: "".to_string(),
+          path: "my_script.rhai".to_string(),
+        },
+      }),
+      limits: RhaiScriptLimits {
+        max_execution_time: Duration::from_millis(10),
+        max_operations: 10_000,
+        ..Default::default()
+      },
+      ..Default::default()
+    },
+  }
+}
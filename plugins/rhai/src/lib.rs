@@ -0,0 +1,7 @@
+mod config;
+mod plugin;
+
+pub use config::RhaiPluginConfig as Config;
+pub use config::RhaiScriptLimits as Limits;
+pub use config::RhaiScriptReference as Script;
+pub use plugin::RhaiPlugin as Plugin;
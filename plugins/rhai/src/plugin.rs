@@ -0,0 +1,399 @@
+use std::{cell::Cell, str::FromStr, time::Duration};
+
+use conductor_common::{
+  execute::RequestExecutionContext,
+  graphql::GraphQLResponse,
+  http::{
+    Bytes, ConductorHttpRequest, ConductorHttpResponse, HeaderName, HeaderValue, HttpHeadersMap,
+    Method, StatusCode,
+  },
+  plugin::{CreatablePlugin, Plugin, PluginError},
+};
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Position, Scope, AST};
+use tracing::error;
+use web_time::Instant;
+
+use crate::config::{RhaiPluginConfig, RhaiScriptReference};
+
+static CONTEXT_KEY: &str = "rhai:context";
+static SCOPE_CONTEXT: &str = "context";
+static SCOPE_REQUEST: &str = "request";
+static SCOPE_RESPONSE: &str = "response";
+static SHORT_CIRCUIT_MARKER: &str = "__short_circuit";
+
+thread_local! {
+  // Scripts are executed synchronously, so the deadline of the current execution can be tracked per thread.
+  static EXECUTION_DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+#[derive(Debug)]
+pub struct RhaiPlugin {
+  engine: Engine,
+  max_execution_time: Duration,
+  on_downstream_http_request: Option<AST>,
+  on_upstream_http_request: Option<AST>,
+  on_downstream_http_response: Option<AST>,
+}
+
+#[derive(Debug)]
+enum ScriptError {
+  ShortCircuit(StatusCode, String),
+  Runtime(Box<EvalAltResult>),
+}
+
+impl ScriptError {
+  fn into_response(self, hook: &str) -> ConductorHttpResponse {
+    match self {
+      ScriptError::ShortCircuit(status, message) => {
+        GraphQLResponse::new_error(&message).into_with_status_code(status)
+      }
+      ScriptError::Runtime(e) => {
+        error!("rhai::{} runtime error: {}", hook, e);
+
+        GraphQLResponse::new_error("rhai runtime error")
+          .into_with_status_code(StatusCode::INTERNAL_SERVER_ERROR)
+      }
+    }
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl CreatablePlugin for RhaiPlugin {
+  type Config = RhaiPluginConfig;
+
+  async fn create(config: Self::Config) -> Result<Box<Self>, PluginError> {
+    let limits = &config.limits;
+    let mut engine = Engine::new();
+    engine
+      .set_max_operations(limits.max_operations)
+      .set_max_string_size(limits.max_string_size)
+      .set_max_array_size(limits.max_array_size)
+      .set_max_map_size(limits.max_map_size)
+      .set_max_call_levels(limits.max_call_levels);
+    engine.on_progress(|_| {
+      EXECUTION_DEADLINE.with(|deadline| match deadline.get() {
+        Some(deadline) if Instant::now() > deadline => {
+          Some(Dynamic::from("script exceeded the maximum execution time"))
+        }
+        _ => None,
+      })
+    });
+    engine.register_fn(
+      "short_circuit",
+      |status: i64, message: &str| -> Result<(), Box<EvalAltResult>> {
+        let mut value = Map::new();
+        value.insert(SHORT_CIRCUIT_MARKER.into(), true.into());
+        value.insert("status".into(), status.into());
+        value.insert("message".into(), message.into());
+
+        Err(Box::new(EvalAltResult::ErrorRuntime(
+          value.into(),
+          Position::NONE,
+        )))
+      },
+    );
+
+    let compile = |script: &Option<RhaiScriptReference>| -> Result<Option<AST>, PluginError> {
+      script
+        .as_ref()
+        .map(|script| {
+          engine
+            .compile(script.contents())
+            .map_err(|e| PluginError::InitError {
+              source: anyhow::anyhow!("failed to compile rhai script: {}", e),
+            })
+        })
+        .transpose()
+    };
+
+    let on_downstream_http_request = compile(&config.on_downstream_http_request)?;
+    let on_upstream_http_request = compile(&config.on_upstream_http_request)?;
+    let on_downstream_http_response = compile(&config.on_downstream_http_response)?;
+
+    Ok(Box::new(Self {
+      max_execution_time: config.limits.max_execution_time,
+      on_downstream_http_request,
+      on_upstream_http_request,
+      on_downstream_http_response,
+      engine,
+    }))
+  }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Plugin for RhaiPlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    if let Some(ast) = &self.on_downstream_http_request {
+      let mut scope = Scope::new();
+      scope.push(SCOPE_REQUEST, request_to_map(&ctx.downstream_http_request));
+
+      match self.run(ast, ctx, &mut scope) {
+        Ok(_) => {
+          if let Some(request) = scope.get_value::<Map>(SCOPE_REQUEST) {
+            apply_request_map(request, &mut ctx.downstream_http_request, false);
+          }
+        }
+        Err(e) => ctx.short_circuit(e.into_response("on_downstream_http_request")),
+      }
+    }
+  }
+
+  async fn on_upstream_http_request(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    req: &mut ConductorHttpRequest,
+  ) {
+    if let Some(ast) = &self.on_upstream_http_request {
+      let mut scope = Scope::new();
+      scope.push(SCOPE_REQUEST, request_to_map(req));
+
+      match self.run(ast, ctx, &mut scope) {
+        Ok(_) => {
+          if let Some(request) = scope.get_value::<Map>(SCOPE_REQUEST) {
+            apply_request_map(request, req, true);
+          }
+        }
+        Err(e) => ctx.short_circuit(e.into_response("on_upstream_http_request")),
+      }
+    }
+  }
+
+  fn on_downstream_http_response(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    response: &mut ConductorHttpResponse,
+  ) {
+    if let Some(ast) = &self.on_downstream_http_response {
+      let mut scope = Scope::new();
+      scope.push(SCOPE_RESPONSE, response_to_map(response));
+
+      match self.run(ast, ctx, &mut scope) {
+        Ok(_) => {
+          if let Some(map) = scope.get_value::<Map>(SCOPE_RESPONSE) {
+            apply_response_map(map, response);
+          }
+        }
+        // The response is already created at this point, so we replace it instead of short-circuiting.
+        Err(e) => *response = e.into_response("on_downstream_http_response"),
+      }
+    }
+  }
+}
+
+impl RhaiPlugin {
+  fn run(
+    &self,
+    ast: &AST,
+    ctx: &mut RequestExecutionContext,
+    scope: &mut Scope,
+  ) -> Result<(), ScriptError> {
+    let context = ctx
+      .ctx_get(CONTEXT_KEY)
+      .and_then(|value| rhai::serde::to_dynamic(value).ok())
+      .unwrap_or_else(|| Map::new().into());
+    scope.push_dynamic(SCOPE_CONTEXT, context);
+
+    EXECUTION_DEADLINE
+      .with(|deadline| deadline.set(Instant::now().checked_add(self.max_execution_time)));
+    let result = self.engine.run_ast_with_scope(scope, ast);
+    EXECUTION_DEADLINE.with(|deadline| deadline.set(None));
+
+    if let Err(e) = result {
+      let short_circuit = match e.as_ref() {
+        EvalAltResult::ErrorRuntime(value, _) => short_circuit_from_value(value),
+        _ => None,
+      };
+
+      return Err(match short_circuit {
+        Some((status, message)) => ScriptError::ShortCircuit(status, message),
+        None => ScriptError::Runtime(e),
+      });
+    }
+
+    if let Some(context) = scope.get_value::<Dynamic>(SCOPE_CONTEXT) {
+      match rhai::serde::from_dynamic::<serde_json::Value>(&context) {
+        Ok(value) => {
+          ctx.ctx_insert(CONTEXT_KEY, value);
+        }
+        Err(e) => error!("rhai: failed to store script context: {}", e),
+      }
+    }
+
+    Ok(())
+  }
+}
+
+fn short_circuit_from_value(value: &Dynamic) -> Option<(StatusCode, String)> {
+  let map = value.read_lock::<Map>()?;
+
+  if !map.contains_key(SHORT_CIRCUIT_MARKER) {
+    return None;
+  }
+
+  let status = map
+    .get("status")
+    .and_then(|v| v.as_int().ok())
+    .and_then(|v| u16::try_from(v).ok())
+    .and_then(|v| StatusCode::from_u16(v).ok())
+    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+  let message = map
+    .get("message")
+    .and_then(|v| v.clone().into_string().ok())
+    .unwrap_or_default();
+
+  Some((status, message))
+}
+
+/// Exposes every header as an array of its values, values that are not valid UTF-8 are left out.
+fn headers_to_map(headers: &HttpHeadersMap) -> Map {
+  headers
+    .keys()
+    .map(|name| {
+      let values = header_values(headers, name)
+        .into_iter()
+        .map(Dynamic::from)
+        .collect();
+
+      (name.as_str().into(), Dynamic::from_array(values))
+    })
+    .collect()
+}
+
+fn header_values(headers: &HttpHeadersMap, name: &HeaderName) -> Vec<String> {
+  headers
+    .get_all(name)
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+    .map(|value| value.to_string())
+    .collect()
+}
+
+/// Scripts can set a header to an array of values, or to a single value.
+fn script_header_values(value: &Dynamic) -> Vec<String> {
+  match value.read_lock::<rhai::Array>() {
+    Some(values) => values.iter().map(|value| value.to_string()).collect(),
+    None => vec![value.to_string()],
+  }
+}
+
+/// Writes back the headers changed by the script, so the other headers keep all of their values (including the values that are not valid UTF-8).
+fn apply_headers_map(map: &Map, headers: &mut HttpHeadersMap) {
+  let removed: Vec<HeaderName> = headers
+    .keys()
+    .filter(|name| !map.contains_key(name.as_str()))
+    .cloned()
+    .collect();
+
+  for name in removed {
+    headers.remove(name);
+  }
+
+  for (name, value) in map {
+    let name = match HeaderName::from_str(name.as_str()) {
+      Ok(name) => name,
+      Err(_) => {
+        error!("rhai: invalid header set by script, ignoring: {}", name);
+        continue;
+      }
+    };
+    let values = script_header_values(value);
+
+    if headers.contains_key(&name) && header_values(headers, &name) == values {
+      continue;
+    }
+
+    let values = values
+      .iter()
+      .map(|value| HeaderValue::from_str(value))
+      .collect::<Result<Vec<_>, _>>();
+
+    match values {
+      Ok(values) => {
+        headers.remove(&name);
+
+        for value in values {
+          headers.append(&name, value);
+        }
+      }
+      Err(_) => error!("rhai: invalid header set by script, ignoring: {}", name),
+    }
+  }
+}
+
+/// Writes back the body if the script changed it: bodies that are not valid UTF-8 are exposed lossily, so they're only replaced when the script sets a new body.
+fn apply_body(map: &mut Map, body: &mut Bytes) {
+  if let Some(new_body) = map.remove("body").and_then(|v| v.into_string().ok()) {
+    if new_body != String::from_utf8_lossy(&body[..]) {
+      *body = new_body.into();
+    }
+  }
+}
+
+fn request_to_map(req: &ConductorHttpRequest) -> Map {
+  let mut map = Map::new();
+  map.insert("method".into(), req.method.to_string().into());
+  map.insert("uri".into(), req.uri.clone().into());
+  map.insert("query_string".into(), req.query_string.clone().into());
+  map.insert("headers".into(), headers_to_map(&req.headers).into());
+  map.insert(
+    "body".into(),
+    String::from_utf8_lossy(&req.body).to_string().into(),
+  );
+
+  map
+}
+
+fn apply_request_map(mut map: Map, req: &mut ConductorHttpRequest, allow_routing_changes: bool) {
+  if let Some(headers) = map.remove("headers").and_then(|v| v.try_cast::<Map>()) {
+    apply_headers_map(&headers, &mut req.headers);
+  }
+
+  apply_body(&mut map, &mut req.body);
+
+  if allow_routing_changes {
+    if let Some(uri) = map.remove("uri").and_then(|v| v.into_string().ok()) {
+      req.uri = uri;
+    }
+
+    if let Some(query_string) = map
+      .remove("query_string")
+      .and_then(|v| v.into_string().ok())
+    {
+      req.query_string = query_string;
+    }
+
+    if let Some(method) = map.remove("method").and_then(|v| v.into_string().ok()) {
+      match Method::from_str(&method) {
+        Ok(method) => req.method = method,
+        Err(e) => error!("rhai: invalid method set by script, ignoring: {}", e),
+      }
+    }
+  }
+}
+
+fn response_to_map(response: &ConductorHttpResponse) -> Map {
+  let mut map = Map::new();
+  map.insert("status".into(), (response.status.as_u16() as i64).into());
+  map.insert("headers".into(), headers_to_map(&response.headers).into());
+  map.insert(
+    "body".into(),
+    String::from_utf8_lossy(&response.body).to_string().into(),
+  );
+
+  map
+}
+
+fn apply_response_map(mut map: Map, response: &mut ConductorHttpResponse) {
+  if let Some(status) = map.remove("status").and_then(|v| v.as_int().ok()) {
+    response.status = u16::try_from(status)
+      .ok()
+      .and_then(|v| StatusCode::from_u16(v).ok())
+      .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+  }
+
+  if let Some(headers) = map.remove("headers").and_then(|v| v.try_cast::<Map>()) {
+    apply_headers_map(&headers, &mut response.headers);
+  }
+
+  apply_body(&mut map, &mut response.body);
+}
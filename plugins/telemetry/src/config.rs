@@ -31,6 +31,13 @@ pub struct TelemetryPluginConfig {
   ///
   /// The telemtry data is scoped per endpoint, and you can specify multiple targets if you need to export stats to multiple backends.
   pub targets: Vec<TelemetryTarget>,
+  /// Propagates the [W3C Trace Context](https://www.w3.org/TR/trace-context/) between the client, Conductor and the upstream.
+  ///
+  /// When enabled, the `traceparent` header of incoming requests is used as the parent of the request trace, and a `traceparent` header is added to the requests sent to the upstream, so all spans are connected in the same trace.
+  ///
+  /// > The incoming trace is only adopted by the `otlp` and `zipkin` targets, other targets keep reporting the trace generated by Conductor.
+  #[serde(default)]
+  pub propagate_trace_context: bool,
 }
 
 fn default_service_name() -> String {
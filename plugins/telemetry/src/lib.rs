@@ -1,5 +1,6 @@
 mod config;
 mod plugin;
+mod propagation;
 
 #[cfg(target_arch = "wasm32")]
 pub mod wasm_reporter;
@@ -1,9 +1,15 @@
 use std::borrow::Cow;
 
 use crate::config::{TelemetryPluginConfig, TelemetryTarget};
+use crate::propagation::{TraceParent, TRACEPARENT_HEADER};
+use conductor_common::execute::RequestExecutionContext;
+use conductor_common::http::{ConductorHttpRequest, HeaderValue};
 use conductor_common::plugin::{CreatablePlugin, Plugin, PluginError};
 use conductor_tracing::minitrace_mgr::MinitraceManager;
+use conductor_tracing::otel_attrs::CONDUCTOR_REMOTE_PARENT;
 use conductor_tracing::reporters::TracingReporter;
+use minitrace::collector::SpanContext as MinitraceSpanContext;
+use minitrace::local::LocalSpan;
 use opentelemetry::trace::SpanKind;
 use opentelemetry::trace::TraceError;
 use opentelemetry::{InstrumentationLibrary, KeyValue};
@@ -25,6 +31,7 @@ impl CreatablePlugin for TelemetryPlugin {
 }
 
 static LIB_NAME: &str = "conductor";
+static REMOTE_PARENT_CONTEXT_KEY: &str = "telemetry:remote_parent";
 
 impl TelemetryPlugin {
   #[cfg(target_arch = "wasm32")]
@@ -224,4 +231,54 @@ impl TelemetryPlugin {
 }
 
 #[async_trait::async_trait(?Send)]
-impl Plugin for TelemetryPlugin {}
+impl Plugin for TelemetryPlugin {
+  async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
+    if !self.config.propagate_trace_context {
+      return;
+    }
+
+    let remote_parent = ctx
+      .downstream_http_request
+      .headers
+      .get(TRACEPARENT_HEADER)
+      .and_then(|value| value.to_str().ok())
+      .and_then(TraceParent::parse);
+
+    if let Some(remote_parent) = remote_parent {
+      // Picked up by the reporters when the trace is exported, see `adopt_remote_parents`.
+      let property = remote_parent.to_string();
+      LocalSpan::add_property(|| (CONDUCTOR_REMOTE_PARENT, property));
+      ctx.ctx_insert(REMOTE_PARENT_CONTEXT_KEY, remote_parent.to_string());
+    }
+  }
+
+  async fn on_upstream_http_request(
+    &self,
+    ctx: &mut RequestExecutionContext,
+    req: &mut ConductorHttpRequest,
+  ) {
+    if !self.config.propagate_trace_context {
+      return;
+    }
+
+    let current = match MinitraceSpanContext::current_local_parent() {
+      Some(current) => current,
+      None => return,
+    };
+    // The spans are exported with the trace id of the client, if it sent one.
+    let trace_id = ctx
+      .ctx_get(REMOTE_PARENT_CONTEXT_KEY)
+      .and_then(|value| value.as_str())
+      .and_then(TraceParent::parse)
+      .map(|remote_parent| remote_parent.trace_id)
+      .unwrap_or(current.trace_id.0);
+    let traceparent = TraceParent {
+      trace_id,
+      span_id: current.span_id.0,
+    };
+
+    if let Ok(value) = HeaderValue::from_str(&traceparent.to_string()) {
+      req.headers.insert(TRACEPARENT_HEADER, value);
+    }
+  }
+}
@@ -0,0 +1,153 @@
+use std::{collections::HashMap, fmt::Display};
+
+use conductor_tracing::otel_attrs::CONDUCTOR_REMOTE_PARENT;
+use minitrace::collector::{SpanId, SpanRecord, TraceId};
+
+pub static TRACEPARENT_HEADER: &str = "traceparent";
+
+/// The trace and span ids of a [W3C `traceparent` header](https://www.w3.org/TR/trace-context/#traceparent-header).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceParent {
+  pub trace_id: u128,
+  pub span_id: u64,
+}
+
+impl TraceParent {
+  pub fn parse(value: &str) -> Option<Self> {
+    let mut fields = value.trim().split('-');
+    let version = fields.next()?;
+    let trace_id = fields.next()?;
+    let span_id = fields.next()?;
+    let flags = fields.next()?;
+
+    // Version `00` has exactly four fields, future versions may append more. Version `ff` is invalid.
+    if version == "ff" || (version == "00" && fields.next().is_some()) {
+      return None;
+    }
+
+    let valid_lengths =
+      version.len() == 2 && trace_id.len() == 32 && span_id.len() == 16 && flags.len() == 2;
+    let valid_hex = [version, trace_id, span_id, flags].iter().all(|field| {
+      field
+        .bytes()
+        .all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f'))
+    });
+
+    if !valid_lengths || !valid_hex {
+      return None;
+    }
+
+    let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+    let span_id = u64::from_str_radix(span_id, 16).ok()?;
+
+    // All-zero ids are invalid.
+    match trace_id != 0 && span_id != 0 {
+      true => Some(Self { trace_id, span_id }),
+      false => None,
+    }
+  }
+}
+
+impl Display for TraceParent {
+  /// Formats the header value, always flagged as sampled.
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "00-{:032x}-{:016x}-01", self.trace_id, self.span_id)
+  }
+}
+
+/// Moves the traces that were started by a request carrying a `traceparent` header into the trace of the caller.
+///
+/// Conductor encodes the tenant in its own trace ids to route the spans to the right reporter, so the remote trace id is only applied when the spans are exported: every span of the trace takes the remote trace id, and the root span takes the remote span as its parent.
+pub fn adopt_remote_parents(spans: &mut [SpanRecord]) {
+  let remote_parents: HashMap<u128, TraceParent> = spans
+    .iter()
+    .filter_map(|span| {
+      span
+        .properties
+        .iter()
+        .find(|(key, _)| key == CONDUCTOR_REMOTE_PARENT)
+        .and_then(|(_, value)| TraceParent::parse(value))
+        .map(|parent| (span.trace_id.0, parent))
+    })
+    .collect();
+
+  if remote_parents.is_empty() {
+    return;
+  }
+
+  for span in spans.iter_mut() {
+    if let Some(parent) = remote_parents.get(&span.trace_id.0) {
+      if span.parent_id == SpanId::default() {
+        span.parent_id = SpanId(parent.span_id);
+      }
+
+      span.trace_id = TraceId(parent.trace_id);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  static TRACEPARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+  #[test]
+  fn parses_traceparent() {
+    let parent = TraceParent::parse(TRACEPARENT).unwrap();
+
+    assert_eq!(parent.trace_id, 0x0af7651916cd43dd8448eb211c80319c);
+    assert_eq!(parent.span_id, 0xb7ad6b7169203331);
+    assert_eq!(parent.to_string(), TRACEPARENT);
+    // Unknown flags and future versions are accepted.
+    assert!(
+      TraceParent::parse("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00").is_some()
+    );
+    assert!(TraceParent::parse(&format!("01{}-extra", &TRACEPARENT[2..])).is_some());
+  }
+
+  #[test]
+  fn rejects_invalid_traceparent() {
+    for value in [
+      "",
+      "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+      "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra",
+      "ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+      "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+      "00-0af7651916cd43dd8448eb211c8031-b7ad6b7169203331-01",
+      "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+      "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+      "00-+af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+    ] {
+      assert_eq!(TraceParent::parse(value), None, "{}", value);
+    }
+  }
+
+  #[test]
+  fn adopts_remote_parent() {
+    let span = |trace_id: u128, span_id: u64, parent_id: u64| SpanRecord {
+      trace_id: TraceId(trace_id),
+      span_id: SpanId(span_id),
+      parent_id: SpanId(parent_id),
+      ..Default::default()
+    };
+    let mut spans = vec![
+      span(1, 1, 0),
+      SpanRecord {
+        properties: vec![(CONDUCTOR_REMOTE_PARENT.into(), TRACEPARENT.into())],
+        ..span(1, 2, 1)
+      },
+      span(2, 3, 0),
+    ];
+
+    adopt_remote_parents(&mut spans);
+
+    assert_eq!(spans[0].trace_id.0, 0x0af7651916cd43dd8448eb211c80319c);
+    assert_eq!(spans[0].parent_id.0, 0xb7ad6b7169203331);
+    assert_eq!(spans[1].trace_id.0, 0x0af7651916cd43dd8448eb211c80319c);
+    assert_eq!(spans[1].parent_id.0, 1);
+    // Other traces are not affected.
+    assert_eq!(spans[2].trace_id.0, 2);
+    assert_eq!(spans[2].parent_id.0, 0);
+  }
+}
@@ -22,6 +22,8 @@ use opentelemetry_sdk::trace::SpanEvents;
 use opentelemetry_sdk::trace::SpanLinks;
 use opentelemetry_sdk::Resource;
 
+use crate::propagation::adopt_remote_parents;
+
 /// [OpenTelemetry](https://github.com/open-telemetry/opentelemetry-rust) reporter for `minitrace`.
 ///
 /// `OpenTelemetryReporter` exports trace records to remote agents that OpenTelemetry
@@ -104,7 +106,9 @@ impl OpenTelemetryReporter {
   }
 
   fn try_report(&mut self, spans: &[SpanRecord]) -> Result<(), Box<dyn std::error::Error>> {
-    let opentelemetry_spans = self.convert(spans);
+    let mut spans = spans.to_vec();
+    adopt_remote_parents(&mut spans);
+    let opentelemetry_spans = self.convert(&spans);
     futures::executor::block_on(self.opentelemetry_exporter.export(opentelemetry_spans))?;
     Ok(())
   }
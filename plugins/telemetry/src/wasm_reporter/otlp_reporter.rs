@@ -18,6 +18,8 @@ use opentelemetry_sdk::{
 use web_time::web::SystemTimeExt;
 use web_time::{Duration, UNIX_EPOCH};
 
+use crate::propagation::adopt_remote_parents;
+
 #[derive(Debug)]
 pub struct WasmTracingHttpClient;
 
@@ -121,7 +123,9 @@ impl WasmOtlpReporter {
   }
 
   async fn try_report(&mut self, spans: &[SpanRecord]) -> Result<(), Box<dyn std::error::Error>> {
-    let opentelemetry_spans = self.convert(spans);
+    let mut spans = spans.to_vec();
+    adopt_remote_parents(&mut spans);
+    let opentelemetry_spans = self.convert(&spans);
     self
       .opentelemetry_exporter
       .export(opentelemetry_spans)
@@ -1,8 +1,10 @@
 use conductor_common::serde_utils::{
-  JsonSchemaExample, JsonSchemaExampleMetadata, JsonSchemaExampleWrapperType, LocalFileReference,
+  deserialize_relative_path, JsonSchemaExample, JsonSchemaExampleMetadata,
+  JsonSchemaExampleWrapperType, LocalFileReference,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ApolloPersistedQueryManifest {
@@ -23,6 +25,8 @@ pub struct ApolloPersistedQueryManifestRecord {
 /// This plugin allows you to define a list of trusted GraphQL documents that can be executed by the gateway (also called **Persisted Operations**).
 ///
 /// For additional information, please refer to [Trusted Documents](https://benjie.dev/graphql/trusted-documents).
+///
+/// The document lookups are counted in the `conductor_trusted_documents_lookups_total` metric, labeled by `protocol` and `result` (`hit`, `miss` or `store_error`), and exposed by the `metrics` plugin. The lookups of the `remote` store are also counted in the `conductor_trusted_documents_remote_cache_total` metric, labeled by `store` URL and `result` (`hit` or `miss`).
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
 #[schemars(example = "trusted_documents_example_1")]
 #[schemars(example = "trusted_documents_example_2")]
@@ -35,8 +39,43 @@ pub struct TrustedDocumentsPluginConfig {
   pub protocols: Vec<TrustedDocumentsProtocolConfig>,
   /// By default, this plugin does not allow untrusted operations to be executed.
   /// This is a security measure to prevent accidental exposure of operations that are not persisted.
+  /// Requests without a trusted document ID are rejected with a `400 Bad Request` response, and requests with an unknown document ID with a `404 Not Found` response.
   #[serde(skip_serializing_if = "Option::is_none")]
   pub allow_untrusted: Option<bool>,
+  /// Defines which operation is executed when a request contains both a trusted document ID and an inline `query`.
+  /// By default, the trusted document is executed and the inline `query` is ignored.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub inline_query_precedence: Option<TrustedDocumentsInlineQueryPrecedence>,
+  /// When a request contains both a trusted document ID and an inline `query`, the SHA-256 hash of the inline `query` must match the document ID, otherwise the request is rejected.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub verify_inline_query_hash: Option<bool>,
+  /// If set to `true`, the documents loaded from the store are hashed and compared to the document ID they were requested with, and the request is rejected if they don't match.
+  /// Use it when the store is shared or not fully trusted. The document IDs must be SHA-256 hashes of the documents.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub verify_hash: Option<bool>,
+  /// If set to `true`, [automatic persisted queries](https://www.apollographql.com/docs/apollo-server/performance/apq/) are enabled: clients register an operation by sending it along with its SHA-256 hash, and send only the hash in the following requests.
+  /// A request with an unknown hash is answered with a `PersistedQueryNotFound` error, so the client retries with the full operation. Registered operations are kept in memory.
+  ///
+  /// Any client can register any operation, so this reduces the size of requests, but doesn't restrict the operations that can be executed: it requires `allow_untrusted` to be enabled, and can't be used with the `reject` `inline_query_precedence`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub automatic_persisted_queries: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema, Default)]
+pub enum TrustedDocumentsInlineQueryPrecedence {
+  /// Execute the trusted document from the store, and ignore the inline `query`.
+  #[serde(rename = "prefer_persisted")]
+  #[schemars(title = "prefer_persisted")]
+  #[default]
+  PreferPersisted,
+  /// Execute the inline `query`. Unless `allow_untrusted` is enabled, the inline `query` must be identical to the trusted document.
+  #[serde(rename = "prefer_inline")]
+  #[schemars(title = "prefer_inline")]
+  PreferInline,
+  /// Reject the request as ambiguous, with a `400 Bad Request` response.
+  #[serde(rename = "reject")]
+  #[schemars(title = "reject")]
+  Reject,
 }
 
 fn trusted_documents_example_1() -> JsonSchemaExample<TrustedDocumentsPluginConfig> {
@@ -54,6 +93,10 @@ fn trusted_documents_example_1() -> JsonSchemaExample<TrustedDocumentsPluginConf
                 format: TrustedDocumentsFileFormat::JsonKeyValue,
             },
             allow_untrusted: None,
+            inline_query_precedence: None,
+            verify_inline_query_hash: None,
+            verify_hash: None,
+            automatic_persisted_queries: None,
             protocols: vec![TrustedDocumentsProtocolConfig::DocumentId {
                 field_name: "documentId".to_string(),
             }],
@@ -76,6 +119,10 @@ fn trusted_documents_example_2() -> JsonSchemaExample<TrustedDocumentsPluginConf
                 format: TrustedDocumentsFileFormat::JsonKeyValue,
             },
             allow_untrusted: None,
+            inline_query_precedence: None,
+            verify_inline_query_hash: None,
+            verify_hash: None,
+            automatic_persisted_queries: None,
             protocols: vec![TrustedDocumentsProtocolConfig::HttpGet {
                 document_id_from: TrustedDocumentHttpGetParameterLocation::document_id_default(),
                 variables_from: TrustedDocumentHttpGetParameterLocation::variables_default(),
@@ -101,6 +148,54 @@ pub enum TrustedDocumentsPluginStoreConfig {
     /// The format and the expected structure of the loaded store file.
     format: TrustedDocumentsFileFormat,
   },
+  #[serde(rename = "directory")]
+  #[schemars(title = "directory")]
+  /// Directory-based store configuration. Each trusted document is stored in its own file, named `<hash>.graphql`.
+  /// Documents are read on demand and cached in memory, so documents can be added to the directory without restarting the gateway.
+  Directory {
+    /// A path to a local directory on the file-system. Relative to the location of the root configuration file.
+    #[serde(deserialize_with = "deserialize_relative_path")]
+    path: String,
+    /// If set to `true`, a cached document is read again when its file is modified, and evicted when its file is removed.
+    /// By default, a document is never read again once it's cached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    watch: Option<bool>,
+  },
+  #[serde(rename = "remote")]
+  #[schemars(title = "remote")]
+  /// Remote store configuration. Documents are fetched from an HTTP endpoint on demand, and the response body is used as the GraphQL document.
+  /// By default, a document is fetched with `GET {url}/{hash}`. A `404 Not Found` response means that the document doesn't exist.
+  ///
+  /// Fetched documents are cached in memory, and the least recently used document is evicted when the cache is full. A request to the remote store that doesn't complete within 10 seconds is treated as a store error.
+  Remote {
+    /// The base URL of the remote store, via HTTP/HTTPS.
+    url: String,
+    /// If set, documents are fetched with a `POST` request to `url` instead, with this template as the request body.
+    /// Every `{hash}` in the template is replaced with the document ID, for example: `{"id": "{hash}"}`.
+    /// The document ID is inserted as-is, so only IDs made of ASCII letters, digits, `-` and `_` are looked up, other IDs are treated as missing documents.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_body: Option<String>,
+    /// The maximum number of documents kept in memory. If not specified, up to 1000 documents are cached.
+    /// Document IDs that were not found are cached apart, up to the same number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_size: Option<usize>,
+    #[serde(
+      deserialize_with = "humantime_serde::deserialize",
+      serialize_with = "humantime_serde::serialize",
+      default
+    )]
+    #[schemars(with = "String")]
+    /// Duration after which a cached document is fetched again. If not specified, cached documents don't expire.
+    cache_ttl: Option<Duration>,
+    #[serde(
+      deserialize_with = "humantime_serde::deserialize",
+      serialize_with = "humantime_serde::serialize",
+      default
+    )]
+    #[schemars(with = "String")]
+    /// Duration during which a document ID that was not found in the remote store is not fetched again. If not specified, defaults to 30 seconds.
+    negative_cache_ttl: Option<Duration>,
+  },
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
@@ -108,9 +203,12 @@ pub enum TrustedDocumentsPluginStoreConfig {
 pub enum TrustedDocumentsProtocolConfig {
   /// This protocol is based on [Apollo's Persisted Query Extensions](https://www.apollographql.com/docs/kotlin/advanced/persisted-queries/#2-publish-operation-manifest).
   /// The GraphQL operation key is sent over `POST` and contains `extensions` field with the GraphQL document hash.
+  /// It can also be sent over `GET`, with the JSON-encoded `extensions` (and optionally `variables` and `operationName`) in the query string. Mutations are not allowed over `GET`.
   ///
   /// Example:
   /// `POST /graphql {"extensions": {"persistedQuery": {"version": 1, "sha256Hash": "123"}}`
+  ///
+  /// `GET /graphql?extensions=%7B%22persistedQuery%22%3A%7B%22version%22%3A1%2C%22sha256Hash%22%3A%22123%22%7D%7D`
   #[serde(rename = "apollo_manifest_extensions")]
   #[schemars(title = "apollo_manifest_extensions")]
   ApolloManifestExtensions,
@@ -126,6 +224,14 @@ pub enum TrustedDocumentsProtocolConfig {
     #[serde(default = "document_id_default_field_name")]
     field_name: String,
   },
+  /// This protocol is based on [Relay's persisted queries](https://relay.dev/docs/guides/persisted-queries/), where the document ID is sent in the `doc_id` field (or `id`, in older versions of Relay).
+  /// The document ID is read from the JSON body of a `POST` request, or from the query string of a `GET` request. Mutations are not allowed over `GET`.
+  ///
+  /// Example:
+  /// `POST /graphql {"doc_id": "123", "variables": {"code": "AF"}}`
+  #[serde(rename = "relay")]
+  #[schemars(title = "relay")]
+  Relay,
   /// This protocol is based on a HTTP `GET` request. You can customize where to fetch each one of the parameters from.
   /// Each request parameter can be obtained from a different source: query, path, or header.
   /// By defualt, all parameters are obtained from the query string.
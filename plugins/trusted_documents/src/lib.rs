@@ -1,9 +1,11 @@
 mod config;
+mod metrics;
 mod plugin;
 mod protocols;
 mod store;
 
 pub use config::TrustedDocumentsFileFormat as FileFormat;
+pub use config::TrustedDocumentsInlineQueryPrecedence as InlineQueryPrecedence;
 pub use config::TrustedDocumentsPluginConfig as Config;
 pub use config::TrustedDocumentsPluginStoreConfig as Store;
 pub use config::TrustedDocumentsProtocolConfig as Protocol;
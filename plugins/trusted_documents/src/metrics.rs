@@ -0,0 +1,43 @@
+use conductor_common::metrics::increment_counter;
+
+static LOOKUPS_COUNTER: &str = "conductor_trusted_documents_lookups_total";
+static LOOKUPS_COUNTER_HELP: &str = "The number of trusted document lookups.";
+static REMOTE_CACHE_COUNTER: &str = "conductor_trusted_documents_remote_cache_total";
+static REMOTE_CACHE_COUNTER_HELP: &str =
+  "The number of remote trusted documents store lookups, by whether they were served from the cache.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LookupOutcome {
+  Hit,
+  Miss,
+  StoreError,
+}
+
+impl LookupOutcome {
+  fn label(&self) -> &'static str {
+    match self {
+      LookupOutcome::Hit => "hit",
+      LookupOutcome::Miss => "miss",
+      LookupOutcome::StoreError => "store_error",
+    }
+  }
+}
+
+/// Counts a lookup in the `conductor_trusted_documents_lookups_total` counter, labeled with the name of the protocol that extracted the document ID.
+pub fn record_lookup(protocol: &'static str, outcome: LookupOutcome) {
+  increment_counter(
+    LOOKUPS_COUNTER,
+    LOOKUPS_COUNTER_HELP,
+    &[("protocol", protocol), ("result", outcome.label())],
+  );
+}
+
+/// Counts a lookup of the remote store at `url` in the `conductor_trusted_documents_remote_cache_total` counter.
+/// Document IDs that are known to be missing are cache hits too.
+pub fn record_remote_cache(url: &str, hit: bool) {
+  increment_counter(
+    REMOTE_CACHE_COUNTER,
+    REMOTE_CACHE_COUNTER_HELP,
+    &[("store", url), ("result", if hit { "hit" } else { "miss" })],
+  );
+}
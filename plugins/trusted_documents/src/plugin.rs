@@ -1,24 +1,34 @@
 use std::sync::Arc;
 
 use crate::{
+  metrics::{record_lookup, LookupOutcome},
   protocols::{
     apollo_manifest::ApolloManifestPersistedDocumentsProtocol,
     document_id::DocumentIdTrustedDocumentsProtocol, get_handler::TrustedDocumentsGetHandler,
+    relay::RelayTrustedDocumentsProtocol, DocumentHashAlgorithm, ExtractedTrustedDocument,
+  },
+  store::{
+    directory::TrustedDocumentsDirectoryStore, fs::TrustedDocumentsFilesystemStore,
+    memory::TrustedDocumentsMemoryStore, remote::TrustedDocumentsRemoteStore,
   },
-  store::fs::TrustedDocumentsFilesystemStore,
 };
 
 use super::{protocols::TrustedDocumentsProtocol, store::TrustedDocumentsStore};
 use crate::config::{
-  TrustedDocumentsPluginConfig, TrustedDocumentsPluginStoreConfig, TrustedDocumentsProtocolConfig,
+  TrustedDocumentsInlineQueryPrecedence, TrustedDocumentsPluginConfig,
+  TrustedDocumentsPluginStoreConfig, TrustedDocumentsProtocolConfig,
 };
 use conductor_common::{
   execute::RequestExecutionContext,
-  graphql::{ExtractGraphQLOperationError, GraphQLRequest, GraphQLResponse, ParsedGraphQLRequest},
-  http::StatusCode,
+  graphql::{
+    ExtractGraphQLOperationError, GraphQLError, GraphQLRequest, GraphQLResponse,
+    ParsedGraphQLRequest,
+  },
+  http::{ConductorHttpResponse, StatusCode},
   plugin::{CreatablePlugin, Plugin, PluginError},
   source::SourceRuntime,
 };
+use serde_json::{Map, Value};
 use tracing::{debug, error, info, warn};
 
 #[derive(Debug)]
@@ -26,12 +36,19 @@ pub struct TrustedDocumentsPlugin {
   config: TrustedDocumentsPluginConfig,
   incoming_message_handlers: Vec<Box<dyn TrustedDocumentsProtocol>>,
   store: Box<dyn TrustedDocumentsStore>,
+  /// The documents registered with automatic persisted queries, if enabled.
+  registered_documents: Option<TrustedDocumentsMemoryStore>,
 }
 
+/// Bounds the memory used by automatic persisted queries, as clients can register any operation.
+const MAX_REGISTERED_DOCUMENTS: usize = 10_000;
+
 #[derive(Debug, thiserror::Error)]
 pub enum TrustedDocumentsPluginError {
   #[error("failed to create store: {0}")]
   StoreCreationError(String),
+  #[error("invalid configuration: {0}")]
+  InvalidConfiguration(&'static str),
 }
 
 #[async_trait::async_trait(?Send)]
@@ -41,6 +58,25 @@ impl CreatablePlugin for TrustedDocumentsPlugin {
   async fn create(config: Self::Config) -> Result<Box<TrustedDocumentsPlugin>, PluginError> {
     debug!("creating trusted operations plugin");
 
+    // Any client can register any operation along with its hash, so the operations can't be restricted.
+    if config.automatic_persisted_queries == Some(true) {
+      let invalid_configuration = |reason| PluginError::InitError {
+        source: TrustedDocumentsPluginError::InvalidConfiguration(reason).into(),
+      };
+
+      if config.allow_untrusted != Some(true) {
+        return Err(invalid_configuration(
+          "automatic_persisted_queries requires allow_untrusted to be enabled",
+        ));
+      }
+
+      if config.inline_query_precedence == Some(TrustedDocumentsInlineQueryPrecedence::Reject) {
+        return Err(invalid_configuration(
+          "automatic_persisted_queries can't be used with the reject inline_query_precedence",
+        ));
+      }
+    }
+
     let store: Box<dyn TrustedDocumentsStore> = match &config.store {
       TrustedDocumentsPluginStoreConfig::File { file, format } => {
         let fs_store =
@@ -52,6 +88,29 @@ impl CreatablePlugin for TrustedDocumentsPlugin {
 
         Box::new(fs_store)
       }
+      TrustedDocumentsPluginStoreConfig::Directory { path, watch } => Box::new(
+        TrustedDocumentsDirectoryStore::new(path, watch.unwrap_or(false)),
+      ),
+      TrustedDocumentsPluginStoreConfig::Remote {
+        url,
+        post_body,
+        cache_size,
+        cache_ttl,
+        negative_cache_ttl,
+      } => {
+        let remote_store = TrustedDocumentsRemoteStore::new(
+          url,
+          post_body.clone(),
+          *cache_size,
+          *cache_ttl,
+          *negative_cache_ttl,
+        )
+        .map_err(|e| PluginError::InitError {
+          source: TrustedDocumentsPluginError::StoreCreationError(e.to_string()).into(),
+        })?;
+
+        Box::new(remote_store)
+      }
     };
 
     let incoming_message_handlers: Vec<Box<dyn TrustedDocumentsProtocol>> = config
@@ -71,6 +130,11 @@ impl CreatablePlugin for TrustedDocumentsPlugin {
                     Box::new(ApolloManifestPersistedDocumentsProtocol {})
                         as Box<dyn TrustedDocumentsProtocol>
                 }
+                TrustedDocumentsProtocolConfig::Relay => {
+                    debug!("adding trusted documents protocol of type relay");
+
+                    Box::new(RelayTrustedDocumentsProtocol {}) as Box<dyn TrustedDocumentsProtocol>
+                }
                 TrustedDocumentsProtocolConfig::HttpGet {
                     document_id_from,
                     variables_from,
@@ -90,14 +154,129 @@ impl CreatablePlugin for TrustedDocumentsPlugin {
             })
             .collect();
 
+    let registered_documents = config
+      .automatic_persisted_queries
+      .is_some_and(|v| v)
+      .then(|| TrustedDocumentsMemoryStore::new(MAX_REGISTERED_DOCUMENTS));
+
     Ok(Box::new(Self {
       config,
       store,
       incoming_message_handlers,
+      registered_documents,
     }))
   }
 }
 
+/// An error response with a machine-readable `code` extension, as used by Apollo clients to react to persisted query errors.
+fn coded_error(message: &str, code: &str) -> GraphQLResponse {
+  let mut error = GraphQLError::new(message);
+  error.extensions = Some(Map::from_iter([(
+    "code".to_string(),
+    Value::String(code.to_string()),
+  )]));
+
+  GraphQLResponse::new_errors(vec![error])
+}
+
+impl TrustedDocumentsPlugin {
+  /// Returns an error response when the inline query, sent along with a document ID, can't be accepted.
+  fn check_inline_query(
+    &self,
+    hash: &str,
+    inline_query: &str,
+    hash_algorithm: DocumentHashAlgorithm,
+  ) -> Option<ConductorHttpResponse> {
+    if self.config.inline_query_precedence == Some(TrustedDocumentsInlineQueryPrecedence::Reject) {
+      warn!("request contains both a trusted document id and an inline query, rejecting");
+
+      return Some(
+        GraphQLResponse::new_error(
+          "ambiguous request: both a trusted document id and an inline query were provided",
+        )
+        .into_with_status_code(StatusCode::BAD_REQUEST),
+      );
+    }
+
+    if self.config.verify_inline_query_hash == Some(true)
+      && !hash_algorithm.matches(inline_query, hash)
+    {
+      warn!(
+        "inline query hash {:?} does not match the trusted document id {:?}",
+        hash_algorithm.hash(inline_query),
+        hash
+      );
+
+      return Some(
+        GraphQLResponse::new_error(
+          "provided trusted document id does not match the inline query hash",
+        )
+        .into_with_status_code(StatusCode::BAD_REQUEST),
+      );
+    }
+
+    None
+  }
+
+  /// Looks up the document in the store, and then in the documents registered by clients.
+  /// A store error is treated like a missing document, so the request is rejected.
+  async fn find_document(&self, hash: &str, protocol: &'static str) -> Option<String> {
+    let document = match self.store.get_document(hash).await {
+      Ok(Some(document)) => Some(document),
+      Ok(None) => match &self.registered_documents {
+        Some(registered_documents) => registered_documents.get_document(hash).await.ok().flatten(),
+        None => None,
+      },
+      Err(e) => {
+        error!("failed to look up trusted document {:?}: {}", hash, e);
+        record_lookup(protocol, LookupOutcome::StoreError);
+
+        return None;
+      }
+    };
+
+    record_lookup(
+      protocol,
+      match document {
+        Some(_) => LookupOutcome::Hit,
+        None => LookupOutcome::Miss,
+      },
+    );
+
+    document
+  }
+
+  fn apply_document(
+    ctx: &mut RequestExecutionContext,
+    operation: String,
+    extracted: ExtractedTrustedDocument,
+  ) {
+    match ParsedGraphQLRequest::create_and_parse(GraphQLRequest {
+      operation,
+      operation_name: extracted.operation_name,
+      variables: extracted.variables,
+      extensions: extracted.extensions,
+    }) {
+      Ok(parsed) => {
+        debug!(
+          "extracted trusted document is valid, updating request context: {:?}",
+          parsed
+        );
+
+        ctx.downstream_graphql_request = Some(parsed);
+      }
+      Err(e) => {
+        warn!(
+          "failed to parse GraphQL request from a store object with key {:?}, error: {:?}",
+          extracted.hash, e
+        );
+
+        ctx.short_circuit(ExtractGraphQLOperationError::GraphQLParserError(e).into_response(None));
+      }
+    }
+  }
+}
+
 #[async_trait::async_trait(?Send)]
 impl Plugin for TrustedDocumentsPlugin {
   async fn on_downstream_http_request(&self, ctx: &mut RequestExecutionContext) {
@@ -105,6 +284,8 @@ impl Plugin for TrustedDocumentsPlugin {
       return;
     }
 
+    let mut has_document_id = false;
+
     for extractor in &self.incoming_message_handlers {
       debug!(
         "trying to extract trusted document from incoming request, extractor: {:?}",
@@ -115,39 +296,107 @@ impl Plugin for TrustedDocumentsPlugin {
           "extracted trusted document from incoming request: {:?}",
           extracted
         );
+        has_document_id = true;
 
-        if let Some(op) = self.store.get_document(&extracted.hash).await {
-          debug!("found trusted document with id {:?}", extracted.hash);
+        // With automatic persisted queries, a request with both the operation and its hash registers the operation for the following requests.
+        if let (Some(registered_documents), Some(inline_query)) =
+          (&self.registered_documents, &extracted.inline_query)
+        {
+          if !extractor
+            .document_hash_algorithm()
+            .matches(inline_query, &extracted.hash)
+          {
+            warn!(
+              "inline query does not match the hash {:?}, refusing to register it",
+              extracted.hash
+            );
 
-          match ParsedGraphQLRequest::create_and_parse(GraphQLRequest {
-            operation: op.clone(),
-            operation_name: extracted.operation_name,
-            variables: extracted.variables,
-            extensions: extracted.extensions,
-          }) {
-            Ok(parsed) => {
-              debug!(
-                "extracted trusted document is valid, updating request context: {:?}",
-                parsed
-              );
+            ctx.short_circuit(
+              coded_error(
+                "provided sha does not match query",
+                "PERSISTED_QUERY_HASH_MISMATCH",
+              )
+              .into_with_status_code(StatusCode::BAD_REQUEST),
+            );
+            return;
+          }
 
-              ctx.downstream_graphql_request = Some(parsed);
-              return;
-            }
-            Err(e) => {
+          registered_documents.register(&extracted.hash, inline_query);
+
+          let inline_query = inline_query.clone();
+          Self::apply_document(ctx, inline_query, extracted);
+          return;
+        }
+
+        if let Some(inline_query) = &extracted.inline_query {
+          if let Some(response) = self.check_inline_query(
+            &extracted.hash,
+            inline_query,
+            extractor.document_hash_algorithm(),
+          ) {
+            ctx.short_circuit(response);
+            return;
+          }
+
+          if self.config.inline_query_precedence.unwrap_or_default()
+            == TrustedDocumentsInlineQueryPrecedence::PreferInline
+          {
+            let document = self.find_document(&extracted.hash, extractor.name()).await;
+
+            if self.config.allow_untrusted != Some(true) && document.as_ref() != Some(inline_query)
+            {
               warn!(
-                "failed to parse GraphQL request from a store object with key {:?}, error: {:?}",
-                e, extracted.hash
+                "inline query does not match the trusted document with id {:?}",
+                extracted.hash
               );
 
               ctx.short_circuit(
-                ExtractGraphQLOperationError::GraphQLParserError(e).into_response(None),
+                GraphQLResponse::new_error("inline query does not match the trusted document")
+                  .into_with_status_code(StatusCode::BAD_REQUEST),
               );
               return;
             }
+
+            let inline_query = inline_query.clone();
+            Self::apply_document(ctx, inline_query, extracted);
+            return;
           }
+        }
+
+        if let Some(op) = self.find_document(&extracted.hash, extractor.name()).await {
+          debug!("found trusted document with id {:?}", extracted.hash);
+
+          // The store might be shared with other parties, so the document is checked against the id it's stored with.
+          if self.config.verify_hash == Some(true)
+            && !extractor
+              .document_hash_algorithm()
+              .matches(&op, &extracted.hash)
+          {
+            error!(
+              "trusted document with id {:?} does not match its hash",
+              extracted.hash
+            );
+
+            ctx.short_circuit(
+              GraphQLResponse::new_error("trusted document does not match its hash")
+                .into_with_status_code(StatusCode::INTERNAL_SERVER_ERROR),
+            );
+            return;
+          }
+
+          Self::apply_document(ctx, op, extracted);
+          return;
         } else {
           warn!("trusted document with id {:?} not found", extracted.hash);
+
+          // Clients using automatic persisted queries retry with the full operation when they get this error.
+          if self.registered_documents.is_some() {
+            ctx.short_circuit(
+              coded_error("PersistedQueryNotFound", "PERSISTED_QUERY_NOT_FOUND")
+                .into_with_status_code(StatusCode::OK),
+            );
+            return;
+          }
         }
       }
     }
@@ -155,10 +404,17 @@ impl Plugin for TrustedDocumentsPlugin {
     if self.config.allow_untrusted != Some(true) {
       error!("untrusted documentes are not allowed, short-circute with an error");
 
-      ctx.short_circuit(
-        GraphQLResponse::new_error("trusted documentnot found")
-          .into_with_status_code(StatusCode::NOT_FOUND),
-      );
+      // Requests that don't refer to a trusted document at all are arbitrary operations, and are reported as such.
+      match has_document_id {
+        true => ctx.short_circuit(
+          GraphQLResponse::new_error("trusted documentnot found")
+            .into_with_status_code(StatusCode::NOT_FOUND),
+        ),
+        false => ctx.short_circuit(
+          coded_error("persisted query required", "PERSISTED_QUERY_REQUIRED")
+            .into_with_status_code(StatusCode::BAD_REQUEST),
+        ),
+      }
 
       return;
     }
@@ -179,3 +435,402 @@ impl Plugin for TrustedDocumentsPlugin {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use conductor_common::{
+    http::{ConductorHttpRequest, Method},
+    metrics::counter_value,
+    serde_utils::LocalFileReference,
+  };
+  use serde_json::{json, Value};
+
+  use super::*;
+  use crate::config::TrustedDocumentsFileFormat;
+
+  static TRUSTED_QUERY: &str = "query { __typename }";
+  static INLINE_QUERY: &str = "query { me { id } }";
+
+  fn hash(query: &str) -> String {
+    DocumentHashAlgorithm::Sha256.hash(query)
+  }
+
+  fn config(
+    inline_query_precedence: Option<TrustedDocumentsInlineQueryPrecedence>,
+    verify_inline_query_hash: Option<bool>,
+  ) -> TrustedDocumentsPluginConfig {
+    TrustedDocumentsPluginConfig {
+      store: TrustedDocumentsPluginStoreConfig::File {
+        file: LocalFileReference {
+          path: "trusted_documents.json".to_string(),
+          contents: json!({ (hash(TRUSTED_QUERY)): TRUSTED_QUERY }).to_string(),
+        },
+        format: TrustedDocumentsFileFormat::JsonKeyValue,
+      },
+      protocols: vec![TrustedDocumentsProtocolConfig::ApolloManifestExtensions],
+      allow_untrusted: None,
+      inline_query_precedence,
+      verify_inline_query_hash,
+      verify_hash: None,
+      automatic_persisted_queries: None,
+    }
+  }
+
+  async fn plugin(
+    inline_query_precedence: Option<TrustedDocumentsInlineQueryPrecedence>,
+    verify_inline_query_hash: Option<bool>,
+  ) -> Box<TrustedDocumentsPlugin> {
+    TrustedDocumentsPlugin::create(config(inline_query_precedence, verify_inline_query_hash))
+      .await
+      .unwrap()
+  }
+
+  async fn execute(
+    plugin: &TrustedDocumentsPlugin,
+    hash: &str,
+    query: &str,
+  ) -> RequestExecutionContext {
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::POST,
+      body: json!({
+        "query": query,
+        "extensions": { "persistedQuery": { "version": 1, "sha256Hash": hash } }
+      })
+      .to_string()
+      .into(),
+      ..Default::default()
+    });
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    ctx
+  }
+
+  fn executed_operation(ctx: &RequestExecutionContext) -> Option<&str> {
+    ctx
+      .downstream_graphql_request
+      .as_ref()
+      .map(|request| request.request.operation.as_str())
+  }
+
+  fn error_message(ctx: &RequestExecutionContext) -> Value {
+    let response = ctx
+      .short_circuit_response
+      .as_ref()
+      .expect("expected a response");
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    serde_json::from_slice::<Value>(&response.body).unwrap()["errors"][0]["message"].clone()
+  }
+
+  #[tokio::test]
+  async fn apollo_get_request() {
+    let plugin = plugin(None, None).await;
+    let extensions =
+      json!({ "persistedQuery": { "version": 1, "sha256Hash": hash(TRUSTED_QUERY) } });
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::GET,
+      query_string: format!(
+        "extensions={}&variables={}&operationName=test",
+        urlencoding::encode(&extensions.to_string()),
+        urlencoding::encode(&json!({ "code": "AF" }).to_string())
+      ),
+      ..Default::default()
+    });
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    assert!(ctx.short_circuit_response.is_none());
+    assert_eq!(executed_operation(&ctx), Some(TRUSTED_QUERY));
+
+    let request = &ctx.downstream_graphql_request.unwrap().request;
+    assert_eq!(request.operation_name.as_deref(), Some("test"));
+    assert_eq!(
+      request.variables.as_ref().and_then(|v| v.get("code")),
+      Some(&json!("AF"))
+    );
+  }
+
+  #[tokio::test]
+  async fn relay_doc_id() {
+    let plugin = TrustedDocumentsPlugin::create(TrustedDocumentsPluginConfig {
+      protocols: vec![TrustedDocumentsProtocolConfig::Relay],
+      ..config(None, None)
+    })
+    .await
+    .unwrap();
+
+    for body in [
+      json!({ "doc_id": hash(TRUSTED_QUERY) }),
+      json!({ "id": hash(TRUSTED_QUERY) }),
+    ] {
+      let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+        method: Method::POST,
+        body: body.to_string().into(),
+        ..Default::default()
+      });
+      plugin.on_downstream_http_request(&mut ctx).await;
+
+      assert!(ctx.short_circuit_response.is_none());
+      assert_eq!(executed_operation(&ctx), Some(TRUSTED_QUERY));
+    }
+
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::GET,
+      query_string: format!("doc_id={}", hash(TRUSTED_QUERY)),
+      ..Default::default()
+    });
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    assert!(ctx.short_circuit_response.is_none());
+    assert_eq!(executed_operation(&ctx), Some(TRUSTED_QUERY));
+  }
+
+  #[tokio::test]
+  async fn persisted_query_required() {
+    let plugin = plugin(None, None).await;
+    let mut ctx = RequestExecutionContext::new(ConductorHttpRequest {
+      method: Method::POST,
+      body: json!({ "query": INLINE_QUERY }).to_string().into(),
+      ..Default::default()
+    });
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    assert_eq!(error_message(&ctx), "persisted query required");
+    let body = serde_json::from_slice::<Value>(&ctx.short_circuit_response.unwrap().body).unwrap();
+    assert_eq!(
+      body["errors"][0]["extensions"]["code"],
+      "PERSISTED_QUERY_REQUIRED"
+    );
+
+    // A document id that is not in the store is reported as not found.
+    let ctx = execute(&plugin, &hash(INLINE_QUERY), INLINE_QUERY).await;
+
+    assert_eq!(
+      ctx.short_circuit_response.map(|response| response.status),
+      Some(StatusCode::NOT_FOUND)
+    );
+  }
+
+  #[tokio::test]
+  async fn verify_hash() {
+    let mut config = config(None, None);
+    config.store = TrustedDocumentsPluginStoreConfig::File {
+      file: LocalFileReference {
+        path: "trusted_documents.json".to_string(),
+        contents: json!({
+          (hash(TRUSTED_QUERY)): TRUSTED_QUERY,
+          (hash(INLINE_QUERY)): TRUSTED_QUERY,
+        })
+        .to_string(),
+      },
+      format: TrustedDocumentsFileFormat::JsonKeyValue,
+    };
+    config.verify_hash = Some(true);
+    let plugin = TrustedDocumentsPlugin::create(config).await.unwrap();
+
+    let ctx = execute(&plugin, &hash(TRUSTED_QUERY), TRUSTED_QUERY).await;
+    assert_eq!(executed_operation(&ctx), Some(TRUSTED_QUERY));
+
+    let ctx = execute(&plugin, &hash(INLINE_QUERY), INLINE_QUERY).await;
+    assert_eq!(executed_operation(&ctx), None);
+    assert_eq!(
+      ctx.short_circuit_response.map(|response| response.status),
+      Some(StatusCode::INTERNAL_SERVER_ERROR)
+    );
+  }
+
+  #[tokio::test]
+  async fn automatic_persisted_queries() {
+    let plugin = TrustedDocumentsPlugin::create(TrustedDocumentsPluginConfig {
+      automatic_persisted_queries: Some(true),
+      allow_untrusted: Some(true),
+      ..config(None, None)
+    })
+    .await
+    .unwrap();
+    let hash_only = |hash: String| {
+      RequestExecutionContext::new(ConductorHttpRequest {
+        method: Method::POST,
+        body: json!({
+          "extensions": { "persistedQuery": { "version": 1, "sha256Hash": hash } }
+        })
+        .to_string()
+        .into(),
+        ..Default::default()
+      })
+    };
+
+    // The operation is not registered yet.
+    let mut ctx = hash_only(hash(INLINE_QUERY));
+    plugin.on_downstream_http_request(&mut ctx).await;
+
+    let response = ctx.short_circuit_response.unwrap();
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+      serde_json::from_slice::<Value>(&response.body).unwrap()["errors"][0]["extensions"]["code"],
+      "PERSISTED_QUERY_NOT_FOUND"
+    );
+
+    // The hash of a registered operation must match.
+    let ctx = execute(&plugin, &hash(TRUSTED_QUERY), INLINE_QUERY).await;
+    assert_eq!(error_message(&ctx), "provided sha does not match query");
+
+    let ctx = execute(&plugin, &hash(INLINE_QUERY), INLINE_QUERY).await;
+    assert_eq!(executed_operation(&ctx), Some(INLINE_QUERY));
+
+    // Registered operations are available by their hash.
+    let mut ctx = hash_only(hash(INLINE_QUERY));
+    plugin.on_downstream_http_request(&mut ctx).await;
+    assert_eq!(executed_operation(&ctx), Some(INLINE_QUERY));
+  }
+
+  #[tokio::test]
+  async fn automatic_persisted_queries_require_untrusted_operations() {
+    for config in [
+      TrustedDocumentsPluginConfig {
+        automatic_persisted_queries: Some(true),
+        ..config(None, None)
+      },
+      TrustedDocumentsPluginConfig {
+        automatic_persisted_queries: Some(true),
+        allow_untrusted: Some(true),
+        ..config(Some(TrustedDocumentsInlineQueryPrecedence::Reject), None)
+      },
+    ] {
+      assert!(TrustedDocumentsPlugin::create(config).await.is_err());
+    }
+  }
+
+  #[tokio::test]
+  async fn lookup_counters() {
+    // Counters are shared by the tests, the `document_id` protocol is only used here.
+    let plugin = TrustedDocumentsPlugin::create(TrustedDocumentsPluginConfig {
+      protocols: vec![
+        TrustedDocumentsProtocolConfig::ApolloManifestExtensions,
+        TrustedDocumentsProtocolConfig::DocumentId {
+          field_name: "documentId".to_string(),
+        },
+      ],
+      ..config(None, None)
+    })
+    .await
+    .unwrap();
+    let document_id = |id: String| {
+      RequestExecutionContext::new(ConductorHttpRequest {
+        method: Method::POST,
+        body: json!({ "documentId": id }).to_string().into(),
+        ..Default::default()
+      })
+    };
+
+    for id in [hash(TRUSTED_QUERY), hash(TRUSTED_QUERY), hash(INLINE_QUERY)] {
+      plugin
+        .on_downstream_http_request(&mut document_id(id))
+        .await;
+    }
+    execute(&plugin, &hash(TRUSTED_QUERY), TRUSTED_QUERY).await;
+
+    let lookups = |result: &str| {
+      counter_value(
+        "conductor_trusted_documents_lookups_total",
+        &[("protocol", "document_id"), ("result", result)],
+      )
+    };
+    assert_eq!(lookups("hit"), 2);
+    assert_eq!(lookups("miss"), 1);
+    assert_eq!(lookups("store_error"), 0);
+  }
+
+  #[tokio::test]
+  async fn lookup_counters_store_errors() {
+    let directory = std::env::temp_dir().join(format!(
+      "conductor_trusted_documents_{}_lookup_stats",
+      std::process::id()
+    ));
+    // A directory with the name of the document can't be read as a document.
+    std::fs::create_dir_all(directory.join(format!("{}.graphql", hash(TRUSTED_QUERY)))).unwrap();
+    let plugin = TrustedDocumentsPlugin::create(TrustedDocumentsPluginConfig {
+      store: TrustedDocumentsPluginStoreConfig::Directory {
+        path: directory.to_string_lossy().into_owned(),
+        watch: None,
+      },
+      ..config(None, None)
+    })
+    .await
+    .unwrap();
+
+    let ctx = execute(&plugin, &hash(TRUSTED_QUERY), TRUSTED_QUERY).await;
+    assert_eq!(executed_operation(&ctx), None);
+    assert_eq!(
+      counter_value(
+        "conductor_trusted_documents_lookups_total",
+        &[
+          ("protocol", "apollo_manifest_extensions"),
+          ("result", "store_error")
+        ],
+      ),
+      1
+    );
+
+    std::fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[tokio::test]
+  async fn prefer_persisted() {
+    let plugin = plugin(None, None).await;
+    let ctx = execute(&plugin, &hash(TRUSTED_QUERY), INLINE_QUERY).await;
+
+    assert!(ctx.short_circuit_response.is_none());
+    assert_eq!(executed_operation(&ctx), Some(TRUSTED_QUERY));
+  }
+
+  #[tokio::test]
+  async fn prefer_inline() {
+    let plugin = plugin(
+      Some(TrustedDocumentsInlineQueryPrecedence::PreferInline),
+      None,
+    )
+    .await;
+    let ctx = execute(&plugin, &hash(TRUSTED_QUERY), TRUSTED_QUERY).await;
+
+    assert!(ctx.short_circuit_response.is_none());
+    assert_eq!(executed_operation(&ctx), Some(TRUSTED_QUERY));
+
+    // Untrusted operations are not allowed, so the inline query must be the trusted document.
+    let ctx = execute(&plugin, &hash(TRUSTED_QUERY), INLINE_QUERY).await;
+
+    assert_eq!(executed_operation(&ctx), None);
+    assert_eq!(
+      error_message(&ctx),
+      "inline query does not match the trusted document"
+    );
+  }
+
+  #[tokio::test]
+  async fn reject_ambiguous() {
+    let plugin = plugin(Some(TrustedDocumentsInlineQueryPrecedence::Reject), None).await;
+    let ctx = execute(&plugin, &hash(TRUSTED_QUERY), TRUSTED_QUERY).await;
+
+    assert_eq!(executed_operation(&ctx), None);
+    assert_eq!(
+      error_message(&ctx),
+      "ambiguous request: both a trusted document id and an inline query were provided"
+    );
+  }
+
+  #[tokio::test]
+  async fn verify_inline_query_hash() {
+    let plugin = plugin(None, Some(true)).await;
+    let ctx = execute(&plugin, &hash(TRUSTED_QUERY), TRUSTED_QUERY).await;
+
+    assert!(ctx.short_circuit_response.is_none());
+    assert_eq!(executed_operation(&ctx), Some(TRUSTED_QUERY));
+
+    let ctx = execute(&plugin, &hash(TRUSTED_QUERY), INLINE_QUERY).await;
+
+    assert_eq!(executed_operation(&ctx), None);
+    assert_eq!(
+      error_message(&ctx),
+      "provided trusted document id does not match the inline query hash"
+    );
+  }
+}
@@ -4,7 +4,10 @@ use tracing::{debug, info};
 
 use super::{ExtractedTrustedDocument, TrustedDocumentsProtocol};
 use conductor_common::execute::RequestExecutionContext;
-use conductor_common::http::Method;
+use conductor_common::{
+  graphql::GraphQLResponse,
+  http::{parse_query_string, ConductorHttpResponse, Method, StatusCode},
+};
 
 #[derive(Debug)]
 pub struct ApolloManifestPersistedDocumentsProtocol;
@@ -15,6 +18,7 @@ struct ApolloPersistedOperationsIncomingMessage {
   variables: Option<Map<String, Value>>,
   #[serde(rename = "operationName")]
   operation_name: Option<String>,
+  query: Option<String>,
   extensions: Extensions,
 }
 
@@ -34,6 +38,10 @@ struct PersistedQuery {
 
 #[async_trait::async_trait(?Send)]
 impl TrustedDocumentsProtocol for ApolloManifestPersistedDocumentsProtocol {
+  fn name(&self) -> &'static str {
+    "apollo_manifest_extensions"
+  }
+
   async fn try_extraction(
     &self,
     ctx: &mut RequestExecutionContext,
@@ -55,10 +63,61 @@ impl TrustedDocumentsProtocol for ApolloManifestPersistedDocumentsProtocol {
           variables: message.variables,
           operation_name: message.operation_name,
           extensions: Some(message.extensions.other),
+          inline_query: message.query,
+        });
+      }
+    }
+
+    if ctx.downstream_http_request.method == Method::GET {
+      debug!("request http method is get, trying to extract from query string...");
+
+      // Clients send the JSON-encoded `extensions` and `variables` as URL-encoded query parameters.
+      let params = parse_query_string(&ctx.downstream_http_request.query_string);
+      let param = |name: &str| {
+        params
+          .get(name)
+          .and_then(|value| urlencoding::decode(value).ok())
+          .map(|value| value.into_owned())
+      };
+
+      if let Some(extensions) =
+        param("extensions").and_then(|value| serde_json::from_str::<Extensions>(&value).ok())
+      {
+        info!(
+          "succuessfully extracted incoming persisted operation from query string: {:?}",
+          extensions
+        );
+
+        return Some(ExtractedTrustedDocument {
+          hash: extensions.persisted_query.hash,
+          variables: param("variables").and_then(|value| serde_json::from_str(&value).ok()),
+          operation_name: param("operationName"),
+          extensions: Some(extensions.other),
+          inline_query: param("query"),
         });
       }
     }
 
     None
   }
+
+  fn should_prevent_execution(
+    &self,
+    ctx: &mut RequestExecutionContext,
+  ) -> Option<ConductorHttpResponse> {
+    if ctx.downstream_http_request.method == Method::GET {
+      if let Some(gql_req) = &ctx.downstream_graphql_request {
+        if gql_req.is_running_mutation() {
+          debug!("trying to execute mutation from the persisted operation, preventing because of GET request");
+
+          return Some(
+            GraphQLResponse::new_error("mutations are not allowed over GET")
+              .into_with_status_code(StatusCode::METHOD_NOT_ALLOWED),
+          );
+        }
+      }
+    }
+
+    None
+  }
 }
@@ -12,6 +12,10 @@ pub struct DocumentIdTrustedDocumentsProtocol {
 
 #[async_trait::async_trait(?Send)]
 impl TrustedDocumentsProtocol for DocumentIdTrustedDocumentsProtocol {
+  fn name(&self) -> &'static str {
+    "document_id"
+  }
+
   async fn try_extraction(
     &self,
     ctx: &mut RequestExecutionContext,
@@ -46,6 +50,10 @@ impl TrustedDocumentsProtocol for DocumentIdTrustedDocumentsProtocol {
               .get("extensions")
               .and_then(|v| v.as_object())
               .cloned(),
+            inline_query: root_object
+              .get("query")
+              .and_then(|v| v.as_str())
+              .map(|v| v.to_string()),
           });
         }
       }
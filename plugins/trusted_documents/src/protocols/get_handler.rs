@@ -98,6 +98,10 @@ impl TrustedDocumentsGetHandler {
 
 #[async_trait::async_trait(?Send)]
 impl TrustedDocumentsProtocol for TrustedDocumentsGetHandler {
+  fn name(&self) -> &'static str {
+    "http_get"
+  }
+
   async fn try_extraction(
     &self,
     ctx: &mut RequestExecutionContext,
@@ -115,6 +119,7 @@ impl TrustedDocumentsProtocol for TrustedDocumentsGetHandler {
             .and_then(|v| serde_json::from_str(&v).ok()),
           operation_name: self.maybe_operation_name(ctx),
           extensions: None,
+          inline_query: None,
         });
       }
     }
@@ -1,11 +1,13 @@
 pub mod apollo_manifest;
 pub mod document_id;
 pub mod get_handler;
+pub mod relay;
 
 use std::fmt::Debug;
 
 use conductor_common::http::ConductorHttpResponse;
 use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
 
 use conductor_common::execute::RequestExecutionContext;
 
@@ -15,14 +17,42 @@ pub struct ExtractedTrustedDocument {
   pub variables: Option<Map<String, Value>>,
   pub operation_name: Option<String>,
   pub extensions: Option<Map<String, Value>>,
+  /// The GraphQL operation sent along with the document ID, if any.
+  pub inline_query: Option<String>,
+}
+
+/// The digest used to compute the document ID of a GraphQL document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocumentHashAlgorithm {
+  /// A hex-encoded SHA-256 digest.
+  Sha256,
+}
+
+impl DocumentHashAlgorithm {
+  pub fn hash(&self, document: &str) -> String {
+    match self {
+      DocumentHashAlgorithm::Sha256 => format!("{:x}", Sha256::digest(document)),
+    }
+  }
+
+  /// Hex-encoded digests are compared case-insensitively.
+  pub fn matches(&self, document: &str, hash: &str) -> bool {
+    self.hash(document).eq_ignore_ascii_case(hash)
+  }
 }
 
 #[async_trait::async_trait(?Send)]
 pub trait TrustedDocumentsProtocol: Sync + Send + Debug {
+  /// The name of the protocol in the configuration, used to label metrics.
+  fn name(&self) -> &'static str;
   async fn try_extraction(
     &self,
     ctx: &mut RequestExecutionContext,
   ) -> Option<ExtractedTrustedDocument>;
+  /// The digest used by clients of this protocol to compute the document ID, when the document ID is a hash of the document.
+  fn document_hash_algorithm(&self) -> DocumentHashAlgorithm {
+    DocumentHashAlgorithm::Sha256
+  }
   fn should_prevent_execution(
     &self,
     _ctx: &mut RequestExecutionContext,
@@ -0,0 +1,109 @@
+use serde_json::{Map, Value};
+use tracing::{debug, info};
+
+use super::{ExtractedTrustedDocument, TrustedDocumentsProtocol};
+use conductor_common::execute::RequestExecutionContext;
+use conductor_common::{
+  graphql::GraphQLResponse,
+  http::{parse_query_string, ConductorHttpResponse, Method, StatusCode},
+};
+
+/// Relay clients send the document ID in `doc_id`, older versions use `id`.
+static DOCUMENT_ID_FIELDS: [&str; 2] = ["doc_id", "id"];
+
+#[derive(Debug)]
+pub struct RelayTrustedDocumentsProtocol;
+
+#[async_trait::async_trait(?Send)]
+impl TrustedDocumentsProtocol for RelayTrustedDocumentsProtocol {
+  fn name(&self) -> &'static str {
+    "relay"
+  }
+
+  async fn try_extraction(
+    &self,
+    ctx: &mut RequestExecutionContext,
+  ) -> Option<ExtractedTrustedDocument> {
+    if ctx.downstream_http_request.method == Method::POST {
+      debug!("request http method is post, trying to extract from body...");
+
+      if let Ok(Value::Object(root_object)) = ctx.downstream_http_request.json_body::<Value>() {
+        let doc_id = DOCUMENT_ID_FIELDS
+          .iter()
+          .find_map(|field| root_object.get(*field).and_then(|v| v.as_str()));
+
+        if let Some(doc_id) = doc_id {
+          info!("succuessfully extracted incoming relay persisted query from request");
+
+          return Some(ExtractedTrustedDocument {
+            hash: doc_id.to_string(),
+            variables: root_object
+              .get("variables")
+              .and_then(|v| v.as_object())
+              .cloned(),
+            operation_name: root_object
+              .get("operationName")
+              .and_then(|v| v.as_str())
+              .map(|v| v.to_string()),
+            extensions: root_object
+              .get("extensions")
+              .and_then(|v| v.as_object())
+              .cloned(),
+            inline_query: root_object
+              .get("query")
+              .and_then(|v| v.as_str())
+              .map(|v| v.to_string()),
+          });
+        }
+      }
+    }
+
+    if ctx.downstream_http_request.method == Method::GET {
+      debug!("request http method is get, trying to extract from query string...");
+
+      let params = parse_query_string(&ctx.downstream_http_request.query_string);
+      let param = |name: &str| {
+        params
+          .get(name)
+          .and_then(|value| urlencoding::decode(value).ok())
+          .map(|value| value.into_owned())
+      };
+
+      if let Some(doc_id) = DOCUMENT_ID_FIELDS.iter().find_map(|field| param(field)) {
+        info!("succuessfully extracted incoming relay persisted query from query string");
+
+        return Some(ExtractedTrustedDocument {
+          hash: doc_id,
+          variables: param("variables")
+            .and_then(|value| serde_json::from_str::<Map<String, Value>>(&value).ok()),
+          operation_name: param("operationName"),
+          extensions: param("extensions")
+            .and_then(|value| serde_json::from_str::<Map<String, Value>>(&value).ok()),
+          inline_query: None,
+        });
+      }
+    }
+
+    None
+  }
+
+  fn should_prevent_execution(
+    &self,
+    ctx: &mut RequestExecutionContext,
+  ) -> Option<ConductorHttpResponse> {
+    if ctx.downstream_http_request.method == Method::GET {
+      if let Some(gql_req) = &ctx.downstream_graphql_request {
+        if gql_req.is_running_mutation() {
+          debug!("trying to execute mutation from the persisted query, preventing because of GET request");
+
+          return Some(
+            GraphQLResponse::new_error("mutations are not allowed over GET")
+              .into_with_status_code(StatusCode::METHOD_NOT_ALLOWED),
+          );
+        }
+      }
+    }
+
+    None
+  }
+}
@@ -0,0 +1,194 @@
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+  sync::RwLock,
+  time::SystemTime,
+};
+use tracing::debug;
+
+use super::{is_valid_document_id, TrustedDocumentsStore, TrustedDocumentsStoreError};
+
+static DOCUMENT_FILE_EXTENSION: &str = "graphql";
+
+#[derive(Debug, Clone)]
+struct CachedDocument {
+  document: String,
+  /// The modification time of the file, at the time it was read.
+  modified: Option<SystemTime>,
+}
+
+/// Reads trusted documents from `<hash>.graphql` files in a directory, on demand.
+/// Documents are cached in memory once read. When `watch` is enabled, a cached document is read again if its file was modified or removed.
+#[derive(Debug)]
+pub struct TrustedDocumentsDirectoryStore {
+  directory: PathBuf,
+  watch: bool,
+  cache: RwLock<HashMap<String, CachedDocument>>,
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+  std::fs::metadata(path)
+    .and_then(|metadata| metadata.modified())
+    .ok()
+}
+
+#[async_trait::async_trait(?Send)]
+impl TrustedDocumentsStore for TrustedDocumentsDirectoryStore {
+  async fn has_document(&self, hash: &str) -> bool {
+    self
+      .get_document(hash)
+      .await
+      .is_ok_and(|document| document.is_some())
+  }
+
+  async fn get_document(&self, hash: &str) -> Result<Option<String>, TrustedDocumentsStoreError> {
+    if !is_valid_document_id(hash) {
+      debug!("document id {:?} can't be used as a file name", hash);
+
+      return Ok(None);
+    }
+
+    let path = self.document_path(hash);
+    let cached = self
+      .cache
+      .read()
+      .ok()
+      .and_then(|cache| cache.get(hash).cloned());
+
+    match cached {
+      Some(cached) if !self.watch || file_modified(&path) == cached.modified => {
+        Ok(Some(cached.document))
+      }
+      _ => self.read_document(hash, &path),
+    }
+  }
+}
+
+impl TrustedDocumentsDirectoryStore {
+  pub fn new(directory: &str, watch: bool) -> Self {
+    debug!(
+      "creating trusted documents store from the directory {:?}, watch: {}",
+      directory, watch
+    );
+
+    Self {
+      directory: PathBuf::from(directory),
+      watch,
+      cache: RwLock::new(HashMap::new()),
+    }
+  }
+
+  fn document_path(&self, hash: &str) -> PathBuf {
+    self
+      .directory
+      .join(hash)
+      .with_extension(DOCUMENT_FILE_EXTENSION)
+  }
+
+  fn read_document(
+    &self,
+    hash: &str,
+    path: &Path,
+  ) -> Result<Option<String>, TrustedDocumentsStoreError> {
+    let modified = file_modified(path);
+
+    match std::fs::read_to_string(path) {
+      Ok(document) => {
+        if let Ok(mut cache) = self.cache.write() {
+          cache.insert(
+            hash.to_string(),
+            CachedDocument {
+              document: document.clone(),
+              modified,
+            },
+          );
+        }
+
+        Ok(Some(document))
+      }
+      Err(e) => {
+        if let Ok(mut cache) = self.cache.write() {
+          cache.remove(hash);
+        }
+
+        match e.kind() {
+          std::io::ErrorKind::NotFound => Ok(None),
+          _ => Err(TrustedDocumentsStoreError::FileReadError(
+            path.to_path_buf(),
+            e,
+          )),
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use std::{fs, time::Duration};
+
+  fn documents_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!(
+      "conductor_trusted_documents_{}_{}",
+      std::process::id(),
+      name
+    ));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    directory
+  }
+
+  #[tokio::test]
+  async fn directory_store() {
+    let directory = documents_directory("read");
+    fs::write(directory.join("key1.graphql"), "query { __typename }").unwrap();
+    let store = TrustedDocumentsDirectoryStore::new(&directory.to_string_lossy(), false);
+
+    assert_eq!(
+      store.get_document("key1").await.unwrap(),
+      Some("query { __typename }".to_string())
+    );
+    assert!(!store.has_document("key2").await);
+    assert!(!store.has_document("../key1").await);
+
+    // Without `watch`, the cached document is used even if the file changes.
+    fs::remove_file(directory.join("key1.graphql")).unwrap();
+    assert!(store.has_document("key1").await);
+
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[tokio::test]
+  async fn directory_store_watch() {
+    let directory = documents_directory("watch");
+    let path = directory.join("key1.graphql");
+    fs::write(&path, "query { a }").unwrap();
+    let store = TrustedDocumentsDirectoryStore::new(&directory.to_string_lossy(), true);
+
+    assert_eq!(
+      store.get_document("key1").await.unwrap(),
+      Some("query { a }".to_string())
+    );
+
+    fs::write(&path, "query { b }").unwrap();
+    // Make sure the change is visible, even on file-systems with a coarse modification time.
+    fs::File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_modified(SystemTime::now() + Duration::from_secs(10))
+      .unwrap();
+
+    assert_eq!(
+      store.get_document("key1").await.unwrap(),
+      Some("query { b }".to_string())
+    );
+
+    fs::remove_file(&path).unwrap();
+    assert!(!store.has_document("key1").await);
+
+    fs::remove_dir_all(&directory).unwrap();
+  }
+}
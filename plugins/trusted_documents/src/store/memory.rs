@@ -0,0 +1,101 @@
+use std::{
+  collections::{HashMap, VecDeque},
+  sync::RwLock,
+};
+use tracing::debug;
+
+use super::{TrustedDocumentsStore, TrustedDocumentsStoreError};
+
+#[derive(Debug, Default)]
+struct RegisteredDocuments {
+  documents: HashMap<String, String>,
+  /// The hashes of the registered documents, oldest first.
+  order: VecDeque<String>,
+}
+
+/// Keeps the documents registered by clients at runtime (with automatic persisted queries) in memory.
+/// Once `capacity` documents are registered, the oldest document is evicted for every new one.
+#[derive(Debug)]
+pub struct TrustedDocumentsMemoryStore {
+  capacity: usize,
+  registered: RwLock<RegisteredDocuments>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl TrustedDocumentsStore for TrustedDocumentsMemoryStore {
+  async fn has_document(&self, hash: &str) -> bool {
+    self
+      .registered
+      .read()
+      .is_ok_and(|registered| registered.documents.contains_key(hash))
+  }
+
+  async fn get_document(&self, hash: &str) -> Result<Option<String>, TrustedDocumentsStoreError> {
+    Ok(
+      self
+        .registered
+        .read()
+        .ok()
+        .and_then(|registered| registered.documents.get(hash).cloned()),
+    )
+  }
+}
+
+impl TrustedDocumentsMemoryStore {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      capacity,
+      registered: RwLock::new(RegisteredDocuments::default()),
+    }
+  }
+
+  pub fn register(&self, hash: &str, document: &str) {
+    let mut registered = match self.registered.write() {
+      Ok(registered) => registered,
+      Err(_) => return,
+    };
+
+    if registered.documents.contains_key(hash) || self.capacity == 0 {
+      return;
+    }
+
+    while registered.documents.len() >= self.capacity {
+      match registered.order.pop_front() {
+        Some(oldest) => {
+          registered.documents.remove(&oldest);
+        }
+        None => break,
+      }
+    }
+
+    debug!("registering document with hash {:?}", hash);
+
+    registered
+      .documents
+      .insert(hash.to_string(), document.to_string());
+    registered.order.push_back(hash.to_string());
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn evicts_oldest_document() {
+    let store = TrustedDocumentsMemoryStore::new(2);
+    store.register("key1", "query { a }");
+    store.register("key2", "query { b }");
+    store.register("key3", "query { c }");
+
+    assert!(!store.has_document("key1").await);
+    assert_eq!(
+      store.get_document("key2").await.unwrap(),
+      Some("query { b }".to_string())
+    );
+    assert_eq!(
+      store.get_document("key3").await.unwrap(),
+      Some("query { c }".to_string())
+    );
+  }
+}
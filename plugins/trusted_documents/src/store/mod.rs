@@ -1,9 +1,31 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, path::PathBuf};
 
+use self::remote::RemoteStoreError;
+
+pub mod directory;
 pub mod fs;
+pub mod memory;
+pub mod remote;
 
 #[async_trait::async_trait(?Send)]
 pub trait TrustedDocumentsStore: Sync + Send + Debug {
   async fn has_document(&self, hash: &str) -> bool;
-  async fn get_document(&self, hash: &str) -> Option<&String>;
+  /// Returns `Ok(None)` if the store doesn't have the document, and an error if the store failed to look it up.
+  async fn get_document(&self, hash: &str) -> Result<Option<String>, TrustedDocumentsStoreError>;
+}
+
+/// Document IDs are used in file names, URLs and request bodies, so only IDs made of ASCII letters, digits, `-` and `_` are looked up.
+pub(crate) fn is_valid_document_id(hash: &str) -> bool {
+  !hash.is_empty()
+    && hash
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TrustedDocumentsStoreError {
+  #[error("failed to read trusted document from {0:?}: {1}")]
+  FileReadError(PathBuf, std::io::Error),
+  #[error(transparent)]
+  RemoteStoreError(#[from] RemoteStoreError),
 }
@@ -0,0 +1,393 @@
+use std::{sync::Mutex, time::Duration};
+
+use conductor_common::lru::LruMap;
+use reqwest::StatusCode;
+use tracing::debug;
+use web_time::Instant;
+
+use super::{is_valid_document_id, TrustedDocumentsStore, TrustedDocumentsStoreError};
+use crate::metrics::record_remote_cache;
+
+/// Used when the remote store configuration doesn't specify `cache_size`.
+pub const DEFAULT_CACHE_SIZE: usize = 1000;
+/// Used when the remote store configuration doesn't specify `negative_cache_ttl`.
+pub const DEFAULT_NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(30);
+/// The maximum time to wait for the remote store, so requests don't hang when it doesn't respond.
+#[cfg(not(target_arch = "wasm32"))]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, Debug)]
+pub enum RemoteStoreError {
+  #[error("failed to fetch document from remote store: {0}")]
+  NetworkError(reqwest::Error),
+  #[error("remote store responded with unexpected status code: {0}")]
+  UnexpectedStatusCode(StatusCode),
+}
+
+#[derive(Debug)]
+struct CachedDocument {
+  document: String,
+  expires_at: Option<Instant>,
+}
+
+/// Fetches trusted documents from an HTTP endpoint, and keeps the recently used documents in memory.
+/// Document IDs that the endpoint doesn't know are cached for `negative_cache_ttl`, so unknown IDs don't hit the endpoint on every request.
+/// They're cached apart from the documents, in a map of the same size, so a client sending many unknown IDs can't evict the documents.
+/// Failed fetches are not cached, and are reported as errors.
+/// The cache hits and misses are counted in the `conductor_trusted_documents_remote_cache_total` counter.
+#[derive(Debug)]
+pub struct TrustedDocumentsRemoteStore {
+  url: String,
+  post_body: Option<String>,
+  ttl: Option<Duration>,
+  negative_ttl: Duration,
+  client: reqwest::Client,
+  cached: Mutex<LruMap<CachedDocument>>,
+  /// The expiration of the document IDs that the remote store doesn't know.
+  missing: Mutex<LruMap<Instant>>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl TrustedDocumentsStore for TrustedDocumentsRemoteStore {
+  async fn has_document(&self, hash: &str) -> bool {
+    self
+      .get_document(hash)
+      .await
+      .is_ok_and(|document| document.is_some())
+  }
+
+  async fn get_document(&self, hash: &str) -> Result<Option<String>, TrustedDocumentsStoreError> {
+    // The document ID is inserted in the URL or in the request body, it can't be trusted to be escaped.
+    if !is_valid_document_id(hash) {
+      debug!("document id {:?} can't be sent to the remote store", hash);
+
+      return Ok(None);
+    }
+
+    if let Some(cached) = self.cached_document(hash) {
+      record_remote_cache(&self.url, true);
+
+      return Ok(cached);
+    }
+
+    record_remote_cache(&self.url, false);
+    debug!(
+      "trusted document {:?} is not cached, fetching from the remote store",
+      hash
+    );
+
+    let document = self.fetch_document(hash).await?;
+    self.insert(hash, document.clone());
+
+    Ok(document)
+  }
+}
+
+impl TrustedDocumentsRemoteStore {
+  pub fn new(
+    url: &str,
+    post_body: Option<String>,
+    capacity: Option<usize>,
+    ttl: Option<Duration>,
+    negative_ttl: Option<Duration>,
+  ) -> Result<Self, reqwest::Error> {
+    debug!(
+      "creating trusted documents store from the remote store {}",
+      url
+    );
+
+    let client = wasm_polyfills::create_http_client();
+    #[cfg(not(target_arch = "wasm32"))]
+    let client = client.timeout(REQUEST_TIMEOUT);
+
+    Ok(Self {
+      url: url.trim_end_matches('/').to_string(),
+      post_body,
+      ttl,
+      negative_ttl: negative_ttl.unwrap_or(DEFAULT_NEGATIVE_CACHE_TTL),
+      client: client.build()?,
+      cached: Mutex::new(LruMap::new(capacity.unwrap_or(DEFAULT_CACHE_SIZE))),
+      missing: Mutex::new(LruMap::new(capacity.unwrap_or(DEFAULT_CACHE_SIZE))),
+    })
+  }
+
+  /// `Some(None)` means the document is known to be missing from the remote store.
+  fn cached_document(&self, hash: &str) -> Option<Option<String>> {
+    let now = Instant::now();
+
+    if let Ok(mut cached) = self.cached.lock() {
+      match cached.get_mut(hash) {
+        Some(cached_document)
+          if cached_document
+            .expires_at
+            .map_or(true, |expires_at| expires_at > now) =>
+        {
+          return Some(Some(cached_document.document.clone()));
+        }
+        Some(_) => {
+          cached.remove(hash);
+        }
+        None => {}
+      }
+    }
+
+    let mut missing = self.missing.lock().ok()?;
+
+    match missing.get_mut(hash) {
+      Some(expires_at) if *expires_at > now => Some(None),
+      Some(_) => {
+        missing.remove(hash);
+        None
+      }
+      None => None,
+    }
+  }
+
+  fn insert(&self, hash: &str, document: Option<String>) {
+    match document {
+      Some(document) => {
+        if self.ttl.is_some_and(|ttl| ttl.is_zero()) {
+          return;
+        }
+
+        if let Ok(mut cached) = self.cached.lock() {
+          cached.insert(
+            hash.to_string(),
+            CachedDocument {
+              document,
+              expires_at: self.ttl.map(|ttl| Instant::now() + ttl),
+            },
+          );
+        }
+      }
+      None => {
+        if self.negative_ttl.is_zero() {
+          return;
+        }
+
+        if let Ok(mut missing) = self.missing.lock() {
+          missing.insert(hash.to_string(), Instant::now() + self.negative_ttl);
+        }
+      }
+    }
+  }
+
+  async fn fetch_document(&self, hash: &str) -> Result<Option<String>, RemoteStoreError> {
+    let request = match &self.post_body {
+      Some(post_body) => self
+        .client
+        .post(&self.url)
+        .body(post_body.replace("{hash}", hash)),
+      None => self
+        .client
+        .get(format!("{}/{}", self.url, urlencoding::encode(hash))),
+    };
+
+    let response = wasm_polyfills::call_async(request.send())
+      .await
+      .map_err(RemoteStoreError::NetworkError)?;
+
+    match response.status() {
+      StatusCode::NOT_FOUND => Ok(None),
+      status if status.is_success() => wasm_polyfills::call_async(response.text())
+        .await
+        .map(Some)
+        .map_err(RemoteStoreError::NetworkError),
+      status => Err(RemoteStoreError::UnexpectedStatusCode(status)),
+    }
+  }
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use conductor_common::metrics::counter_value;
+  use httpmock::{
+    Method::{GET, POST},
+    MockServer,
+  };
+
+  #[tokio::test]
+  async fn remote_store_get() {
+    let server = MockServer::start();
+    let found = server.mock(|when, then| {
+      when.method(GET).path("/cached/key1");
+      then.status(200).body("query { __typename }");
+    });
+    let missing = server.mock(|when, then| {
+      when.method(GET).path("/cached/key2");
+      then.status(404);
+    });
+    let store =
+      TrustedDocumentsRemoteStore::new(&server.url("/cached/"), None, None, None, None).unwrap();
+
+    for _ in 0..2 {
+      assert_eq!(
+        store.get_document("key1").await.unwrap(),
+        Some("query { __typename }".to_string())
+      );
+      assert!(!store.has_document("key2").await);
+    }
+
+    found.assert_hits(1);
+    missing.assert_hits(1);
+
+    // Mock servers are reused by the tests, the `/cached` path is only used here.
+    let url = server.url("/cached");
+    for result in ["hit", "miss"] {
+      assert_eq!(
+        counter_value(
+          "conductor_trusted_documents_remote_cache_total",
+          &[("store", &url), ("result", result)],
+        ),
+        2
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn remote_store_post_body() {
+    let server = MockServer::start();
+    let found = server.mock(|when, then| {
+      when
+        .method(POST)
+        .path("/documents")
+        .body(r#"{"id": "key1"}"#);
+      then.status(200).body("query { __typename }");
+    });
+    let store = TrustedDocumentsRemoteStore::new(
+      &server.url("/documents"),
+      Some(r#"{"id": "{hash}"}"#.to_string()),
+      None,
+      None,
+      None,
+    )
+    .unwrap();
+
+    assert_eq!(
+      store.get_document("key1").await.unwrap(),
+      Some("query { __typename }".to_string())
+    );
+    found.assert_hits(1);
+  }
+
+  #[tokio::test]
+  async fn remote_store_invalid_ids() {
+    let server = MockServer::start();
+    let any = server.mock(|when, then| {
+      when.method(POST).path("/documents");
+      then.status(200).body("query { __typename }");
+    });
+    let store = TrustedDocumentsRemoteStore::new(
+      &server.url("/documents"),
+      Some(r#"{"id": "{hash}"}"#.to_string()),
+      None,
+      None,
+      None,
+    )
+    .unwrap();
+
+    for hash in [r#"key1", "admin": "true"#, "../key1", ""] {
+      assert_eq!(store.get_document(hash).await.unwrap(), None);
+    }
+    any.assert_hits(0);
+  }
+
+  #[tokio::test]
+  async fn remote_store_errors_are_not_cached() {
+    let server = MockServer::start();
+    let failing = server.mock(|when, then| {
+      when.method(GET).path("/key1");
+      then.status(500);
+    });
+    let store = TrustedDocumentsRemoteStore::new(&server.url(""), None, None, None, None).unwrap();
+
+    for _ in 0..2 {
+      assert!(matches!(
+        store.get_document("key1").await,
+        Err(TrustedDocumentsStoreError::RemoteStoreError(
+          RemoteStoreError::UnexpectedStatusCode(StatusCode::INTERNAL_SERVER_ERROR)
+        ))
+      ));
+    }
+    failing.assert_hits(2);
+  }
+
+  #[tokio::test]
+  async fn remote_store_evicts_least_recently_used() {
+    let server = MockServer::start();
+    let documents = ["key1", "key2", "key3"].map(|hash| {
+      server.mock(|when, then| {
+        when.method(GET).path(format!("/{}", hash));
+        then.status(200).body(format!("query {{ {} }}", hash));
+      })
+    });
+    let store =
+      TrustedDocumentsRemoteStore::new(&server.url(""), None, Some(2), None, None).unwrap();
+
+    store.get_document("key1").await.unwrap();
+    store.get_document("key2").await.unwrap();
+    store.get_document("key1").await.unwrap();
+    // The cache is full, "key2" is the least recently used document.
+    store.get_document("key3").await.unwrap();
+    store.get_document("key1").await.unwrap();
+    store.get_document("key2").await.unwrap();
+
+    documents[0].assert_hits(1);
+    documents[1].assert_hits(2);
+    documents[2].assert_hits(1);
+  }
+
+  #[tokio::test]
+  async fn remote_store_unknown_ids_dont_evict_documents() {
+    let server = MockServer::start();
+    let found = server.mock(|when, then| {
+      when.method(GET).path("/key1");
+      then.status(200).body("query { __typename }");
+    });
+    let missing = server.mock(|when, then| {
+      when.method(GET).path_contains("/unknown");
+      then.status(404);
+    });
+    let store =
+      TrustedDocumentsRemoteStore::new(&server.url(""), None, Some(2), None, None).unwrap();
+
+    store.get_document("key1").await.unwrap();
+    for hash in ["unknown1", "unknown2", "unknown3"] {
+      assert_eq!(store.get_document(hash).await.unwrap(), None);
+    }
+    store.get_document("key1").await.unwrap();
+    found.assert_hits(1);
+    missing.assert_hits(3);
+
+    // The unknown IDs are bounded on their own, "unknown1" was evicted.
+    assert_eq!(store.get_document("unknown3").await.unwrap(), None);
+    assert_eq!(store.get_document("unknown1").await.unwrap(), None);
+    missing.assert_hits(4);
+  }
+
+  #[tokio::test]
+  async fn remote_store_cache_ttl() {
+    let server = MockServer::start();
+    let found = server.mock(|when, then| {
+      when.method(GET).path("/key1");
+      then.status(200).body("query { __typename }");
+    });
+    let store = TrustedDocumentsRemoteStore::new(
+      &server.url(""),
+      None,
+      None,
+      Some(Duration::from_millis(100)),
+      None,
+    )
+    .unwrap();
+
+    store.get_document("key1").await.unwrap();
+    store.get_document("key1").await.unwrap();
+    found.assert_hits(1);
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    store.get_document("key1").await.unwrap();
+    found.assert_hits(2);
+  }
+}
@@ -1,4 +1,5 @@
 export default {
   graphql: 'GraphQL Source',
   federation: 'Federation Source',
+  rest: 'REST Source',
 };
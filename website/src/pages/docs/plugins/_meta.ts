@@ -7,4 +7,23 @@ export default {
   'disable-introspection': 'Disable Introspection',
   'trusted-documents': 'Trusted Documents',
   'http-get': 'HTTP GET',
+  rhai: 'Rhai (Scripting)',
+  'last-errors': 'Last Errors',
+  'response-sampling': 'Response Sampling',
+  'max-errors': 'Max Errors',
+  'operation-allowlist': 'Operation Allowlist',
+  'omit-nulls': 'Omit Nulls',
+  'idempotency-key': 'Idempotency Key',
+  'request-sequence': 'Request Sequence',
+  'rate-limit': 'Rate Limit',
+  'response-cache': 'Response Cache',
+  'depth-limit': 'Depth Limit',
+  'cost-limit': 'Cost Limit',
+  'max-body-size': 'Max Body Size',
+  'request-id': 'Request ID',
+  metrics: 'Metrics',
+  'mask-errors': 'Mask Errors',
+  compression: 'Compression',
+  'header-propagation': 'Header Propagation',
+  'circuit-breaker': 'Circuit Breaker',
 };